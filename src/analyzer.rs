@@ -0,0 +1,155 @@
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// FFTの点数
+pub const FFT_SIZE: usize = 2048;
+
+/// 表示する最小レベル（dB）
+pub const MIN_DB: f32 = -100.0;
+
+/// 前のフレームとの平均をとる割合（表示のちらつきを抑える）
+const SMOOTHING: f32 = 0.5;
+
+/// 出力した直近のサンプル（モノラル、FFT_SIZE 個のリングバッファ）
+struct TapBuffer {
+    samples: Vec<f32>,
+    write_index: usize,
+}
+
+/// オーディオコールバックから解析用のサンプルを送る口（オーディオスレッドが保持する）
+#[derive(Clone)]
+pub struct AnalyzerTap {
+    buffer: Arc<Mutex<TapBuffer>>,
+    sample_rate: Arc<AtomicU32>,
+    channels: Arc<AtomicU32>,
+}
+
+impl AnalyzerTap {
+    pub fn new() -> Self {
+        Self {
+            buffer: Arc::new(Mutex::new(TapBuffer {
+                samples: vec![0.0; FFT_SIZE],
+                write_index: 0,
+            })),
+            sample_rate: Arc::new(AtomicU32::new(44100)),
+            channels: Arc::new(AtomicU32::new(1)),
+        }
+    }
+
+    /// ストリームのフォーマットを設定する（ストリーム構築時に呼ぶ）
+    pub fn set_format(&self, sample_rate: u32, channels: u16) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+        self.channels.store(channels.max(1) as u32, Ordering::Relaxed);
+    }
+
+    /// 出力したサンプルの最初のチャンネルを積む（GUIが読んでいる間は捨てる）
+    pub fn push(&self, data: &[f32]) {
+        let channels = self.channels.load(Ordering::Relaxed).max(1) as usize;
+        if let Ok(mut buffer) = self.buffer.try_lock() {
+            for frame in data.chunks(channels) {
+                let index = buffer.write_index;
+                buffer.samples[index] = frame[0];
+                buffer.write_index = (index + 1) % FFT_SIZE;
+            }
+        }
+    }
+
+    /// 直近の FFT_SIZE サンプルを古い順に取り出す
+    fn snapshot(&self) -> Option<Vec<f32>> {
+        let buffer = self.buffer.lock().ok()?;
+        let (newer, older) = buffer.samples.split_at(buffer.write_index);
+        Some(older.iter().chain(newer.iter()).copied().collect())
+    }
+}
+
+/// 出力音声のスペクトラムを計算する構造体（GUIスレッドが保持する）
+pub struct SpectrumAnalyzer {
+    tap: AnalyzerTap,
+    window: Vec<f32>,
+    spectrum: Vec<f32>, // ビンごとのレベル（dB）
+}
+
+impl SpectrumAnalyzer {
+    pub fn new() -> Self {
+        // ハン窓
+        let window = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / FFT_SIZE as f32).cos())
+            .collect();
+        Self {
+            tap: AnalyzerTap::new(),
+            window,
+            spectrum: vec![MIN_DB; FFT_SIZE / 2],
+        }
+    }
+
+    /// オーディオストリームに渡す口
+    pub fn tap(&self) -> AnalyzerTap {
+        self.tap.clone()
+    }
+
+    /// 解析しているストリームのサンプルレート
+    pub fn sample_rate(&self) -> f32 {
+        self.tap.sample_rate.load(Ordering::Relaxed) as f32
+    }
+
+    /// 直近のサンプルからスペクトラムを計算し直して返す（ビンごとのdB値）
+    pub fn update(&mut self) -> &[f32] {
+        let Some(samples) = self.tap.snapshot() else {
+            return &self.spectrum;
+        };
+
+        let mut re: Vec<f32> = samples.iter().zip(self.window.iter()).map(|(x, w)| x * w).collect();
+        let mut im = vec![0.0; FFT_SIZE];
+        fft(&mut re, &mut im);
+
+        // 窓の合計で正規化して、フルスケールの正弦波が 0dB になるようにする
+        let scale = 2.0 / self.window.iter().sum::<f32>();
+        for (bin, level) in self.spectrum.iter_mut().enumerate() {
+            let magnitude = (re[bin] * re[bin] + im[bin] * im[bin]).sqrt() * scale;
+            let db = (20.0 * magnitude.max(1.0e-10).log10()).max(MIN_DB);
+            *level = *level * SMOOTHING + db * (1.0 - SMOOTHING);
+        }
+        &self.spectrum
+    }
+}
+
+/// 基数2の反復型FFT（長さは2のべき乗）
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+
+    // ビット反転の並べ替え
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    // バタフライ演算
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
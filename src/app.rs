@@ -1,208 +1,3372 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
 use eframe::{egui, App};
-use cpal::Stream;
 use midir::MidiInputConnection;
+// ブラウザでは std::time::Instant が使えないので web_time を使う（ネイティブでは std と同じ）
+use web_time::{Duration, Instant};
 
-use crate::audio::play_sine_wave;
+use crate::analyzer::{MIN_DB, SpectrumAnalyzer};
+use synth_core::arpeggiator::{ArpPattern, Arpeggiator, MAX_ARP_OCTAVES, MAX_HUMANIZE_TIME};
+use crate::audio::{self, AudioBackend, AudioSettings, AudioSource, AudioStream, OutputDeviceInfo};
+use crate::config::{AppConfig, Theme};
+use synth_core::bitcrusher::{MAX_BITS, MAX_CRUSH_RATE, MIN_BITS, MIN_CRUSH_RATE};
+use synth_core::chord::ChordShape;
+use synth_core::chorus::MAX_CHORUS_VOICES;
+use synth_core::delay::{MAX_DELAY_TIME, MIN_DELAY_TIME};
+use synth_core::distortion::DistortionAlgorithm;
+use synth_core::envelope::EnvelopeTarget;
+use synth_core::euclid::MAX_EUCLID_STEPS;
+use synth_core::effects::EffectKind;
+use crate::envelope_editor::{envelope_curve_controls, envelope_editor};
+use synth_core::filter::{FilterMode, MAX_KEY_TRACK};
+use synth_core::phaser::{MAX_PHASER_STAGES, MIN_PHASER_STAGES};
+use crate::keyboard::KeyboardInput;
+use synth_core::logger::Logger;
+use synth_core::meter::note_name;
 use crate::midi::setup_midi_callback;
-use crate::unison::UnisonManager;
-use crate::oscillator::Waveform;
+use synth_core::midi_activity::{MidiActivity, MidiActivityKind};
+use synth_core::midi_config::MidiChannel;
+use synth_core::midi_map::{MidiMapManager, ParamId};
+use synth_core::midi_queue::MidiQueue;
+use synth_core::modmatrix::{ModDestination, ModSource};
+use synth_core::mod_monitor::{ModRange, MonitoredParam};
+use synth_core::lfo::{LfoRetrigger, LfoShape, LfoTarget, LfoVoiceMode};
+use synth_core::note::NoteHandler;
+use synth_core::params::SynthParams;
+use synth_core::part::{MAX_PART_VOLUME, MAX_PARTS, Part};
+use synth_core::pitch_bend::MAX_BEND_RANGE;
+use synth_core::randomize::PatchRandomizer;
+use synth_core::scale_lock::{PITCH_CLASS_NAMES, ScaleKind};
+use crate::recorder::{self, Recorder};
+use crate::session::{self, Session};
+use crate::xy_pad::xy_pad;
+use synth_core::render;
+use synth_core::smf::MidiFile;
+use synth_core::strum::{MAX_STRUM_TIME, StrumDirection};
+use synth_core::transport::{BEATS_PER_BAR, MAX_BPM, MAX_SWING, MIN_BPM, MIN_SWING, NoteDivision};
+use synth_core::transpose::MAX_TRANSPOSE;
+use synth_core::tuning::{MAX_A4, MAX_FINE_TUNE, MIN_A4};
+use synth_core::unison::{UnisonPhase, UnisonSpread, MAX_UNISON_COARSE, MAX_UNISON_VOICES};
+use synth_core::wavetable::WaveTable;
+use synth_core::zone::{KeyRange, LayerPatch, VelocityLayer, VelocitySwitchMode};
+use synth_core::velocity::VelocityCurve;
+use synth_core::voice::{GlideMode, GlideRate, MAX_GLIDE_TIME, MAX_MPE_BEND_RANGE, MAX_VOICES, NotePriority, StealPolicy, VoiceMode};
+use synth_core::preset::{self, PRESET_CATEGORIES, Preset, PresetEntry};
+use synth_core::program::ProgramNumber;
+use synth_core::oscillator::{
+    MAX_FM_INDEX, MAX_PULSE_WIDTH, MIN_PULSE_WIDTH, NoiseType, OscRouting, OscillatorMode, SubWaveform, Waveform,
+};
+
+/// MIDIポートを走査して自動接続する間隔
+const MIDI_PORT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 出力デバイスの抜き差しやデフォルトデバイスの切り替えを確認する間隔
+const AUDIO_DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 変調の範囲の表示を今の値へ縮める速さ（1フレームあたりの割合）
+const MOD_RING_DECAY: f32 = 0.05;
+
+/// 通知が来なくなってから変調の範囲の表示を消すまでの時間
+const MOD_RING_HOLD: Duration = Duration::from_millis(200);
+
+/// MIDIの受信を表示するランプを受信ごとに点灯させておく時間
+const MIDI_ACTIVITY_BLINK: Duration = Duration::from_millis(100);
+
+/// 画面下のお知らせを表示しておく時間
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+
+/// セッションを自動保存する間隔
+const SESSION_AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
 
 /// アプリの状態を表す構造体
 pub struct SynthApp {
     freq: f32, // 再生する周波数（Hz）
-    stream_handle: Option<Stream>, // 再生中のストリーム（再生停止に使う）
-    midi_connection: Option<MidiInputConnection<()>>, // MIDI接続ハンドル
+    stream_handle: Option<Box<dyn AudioStream>>, // 再生中のストリーム（再生停止に使う）
+    audio_wanted: bool, // ストリームを動かしておくか（止めていないのにストリームがない時は作り直す）
+    last_device_poll: Option<Instant>, // 最後に出力デバイスを確認した時刻
+    midi_connections: Vec<(String, MidiInputConnection<()>, Arc<MidiActivity>)>, // 接続中のMIDIポート名と接続ハンドルと受信の数
+    midi_lamps: HashMap<String, [(u32, Option<Instant>); MidiActivityKind::ALL.len()]>, // ポートごとの受信ランプ（前回見た受信の数と最後に増えた時刻）
     last_note: Option<u8>, // 最後に押されたノート番号
-    midi_freq: Arc<Mutex<f32>>, // MIDIから設定された周波数（スレッド間共有）
     current_freq: Arc<Mutex<f32>>, // 現在再生中の周波数（スレッド間共有）
     midi_ports: Vec<String>, // 利用可能なMIDIポートのリスト
-    selected_port: usize, // 選択されたMIDIポートのインデックス
-    unison_manager: Arc<UnisonManager>, // Unison設定の管理
+    last_port_poll: Option<Instant>, // 最後にMIDIポートを走査した時刻（ホットプラグの検出に使う）
+    params: SynthParams, // シンセの全パラメータ（スレッド間共有）
+    note_handler: NoteHandler, // ノートオン/オフの処理（MIDIとPCキーボードで共有）
+    midi_queue: MidiQueue, // MIDI入力からオーディオスレッドへ送るメッセージのキュー
+    keyboard: KeyboardInput, // PCキーボードからのノート入力
+    recorder: Recorder, // 出力音声のWAV録音
+    arpeggiator: Arpeggiator, // アルペジエーターのタイミングスレッド
+    parts: Vec<Part>, // MIDIチャンネルごとの追加のパート（音色とノートの状態）
+    part_arpeggiators: Vec<Arpeggiator>, // 追加のパートのアルペジエーターのタイミングスレッド
+    part_preset_names: [String; MAX_PARTS], // 追加のパートに読み込んだプリセットの名前
+    analyzer: SpectrumAnalyzer, // 出力音声のスペクトラム解析
+    logger: Logger, // オーディオ・MIDIコールバックからのログを出力するスレッド
+    audio_backends: Vec<Box<dyn AudioBackend>>, // このビルドで使えるオーディオ出力のバックエンド
+    audio_settings: AudioSettings, // 出力デバイス・サンプルレート・バッファサイズの設定
+    audio_devices: Vec<OutputDeviceInfo>, // 利用可能な出力デバイスのリスト
+    clip_hold_until: Option<Instant>, // クリップ表示を点灯し続ける期限
+    meter_peak: f32, // 表示中のピーク（リニア、ゆっくり下げる）
+    dsp_load: f32, // 表示中のDSP負荷（ゆっくり下げる）
+    mod_rings: [Option<(ModRange, Instant)>; MonitoredParam::ALL.len()], // スライダーに重ねる変調の範囲と最後に通知が来た時刻
+    presets: Vec<PresetEntry>, // プリセットディレクトリ内のプリセット（サブフォルダーを含む）
+    preset_scan: Option<JoinHandle<Vec<PresetEntry>>>, // プリセットディレクトリを走査中のスレッド
+    selected_preset: Option<usize>, // 選択されたプリセットのインデックス
+    preset_search: String, // プリセットブラウザーの検索文字列
+    preset_tag_filter: Option<&'static str>, // プリセットブラウザーで絞り込むタグ（None は全て）
+    favorites_only: bool, // お気に入りのプリセットだけを表示するか
+    favorite_presets: Vec<PathBuf>, // お気に入りにしたプリセットのパス
+    preset_tags: Vec<String>, // 今のプリセットのタグ（保存する時に書き込む）
+    current_preset_path: Option<PathBuf>, // 最後に読み込み・保存したプリセットのパス
+    preset_name: String, // Save As で使うプリセット名
+    randomizer: PatchRandomizer, // パッチのランダム生成と変化
+    mutate_amount: f32, // Mutate で変える大きさ（0.0-1.0）
+    ab_slots: [Option<Preset>; 2], // A/B比較用のパッチ（編集中のスロットは切り替えた時に保存する、None はまだ使っていない）
+    ab_active: usize, // 編集中のスロット（0: A、1: B）
+    custom_wavetable: Option<WaveTable>, // 読み込んだ単一周期WAVの波形テーブル
+    wavetable_path: String, // 読み込むWAVファイルのパス
+    midi_file_path: String, // WAVに書き出すMIDIファイルのパス
+    midi_render: Option<JoinHandle<Result<PathBuf, String>>>, // 書き出し中のレンダリングスレッド
+    midi_render_status: Option<String>, // 最後の書き出し結果
+    toast: Option<(String, Instant)>, // 画面下に表示中のお知らせと表示を始めた時刻
+    recovered_session: Option<Session>, // 前回異常終了した時に残っていたセッション（復元するか尋ねている間は自動保存しない）
+    last_autosave: Instant, // 最後にセッションの自動保存を確認した時刻
+    last_session_json: String, // 最後に自動保存したセッション（変わっていなければ書かない）
+    theme: Theme, // GUIの配色
+    window_size: Option<[f32; 2]>, // 現在のウィンドウの大きさ（終了時に保存する）
 }
 
 /// アプリのデフォルト初期値を定義（440Hz・再生停止中）
 impl Default for SynthApp {
     fn default() -> Self {
-        Self {
+        let current_freq = Arc::new(Mutex::new(0.0)); // 現在の周波数の初期値（音なし）
+        let params = SynthParams::new(); // パラメータの初期化
+        // 保存されているMIDI CCの割り当てを読み込む
+        if let Err(err) = params.midi_map.load() {
+            println!("Failed to load MIDI map: {}", err);
+        }
+        // 受信チャンネルと前回接続していたMIDIポートを読み込む
+        if let Err(err) = params.midi_config.load() {
+            println!("Failed to load MIDI config: {}", err);
+        }
+        // 前回終了時のオーディオ設定・配色・プリセットを読み込む
+        let config = AppConfig::load_or_default();
+        let note_handler = NoteHandler::new(Arc::clone(&current_freq), params.clone());
+        let audio_backends = audio::available_backends();
+        let arpeggiator = Arpeggiator::new(note_handler.clone(), Arc::clone(&params.arp), Arc::clone(&params.transport));
+        let parts: Vec<Part> = (0..MAX_PARTS).map(|_| Part::new(&params)).collect();
+        let part_arpeggiators = parts
+            .iter()
+            .map(|part| Arpeggiator::new(part.notes.clone(), Arc::clone(&part.params.arp), Arc::clone(&part.params.transport)))
+            .collect();
+
+        let mut app = Self {
             freq: 0.0,          // 初期周波数は0（音なし）
             stream_handle: None, // ストリームはまだ存在しない
-            midi_connection: None, // MIDI接続はまだ存在しない
+            audio_wanted: false, // 最初に音を出す時にストリームを開始する
+            last_device_poll: None,
+            midi_connections: Vec::new(), // MIDI接続はまだ存在しない
+            midi_lamps: HashMap::new(),
             last_note: None,     // 最後に押されたノートはまだない
-            midi_freq: Arc::new(Mutex::new(0.0)), // MIDI周波数の初期値（音なし）
-            current_freq: Arc::new(Mutex::new(0.0)), // 現在の周波数の初期値（音なし）
+            current_freq,
             midi_ports: Vec::new(), // MIDIポートのリストは空
-            selected_port: 0,    // デフォルトは最初のポート
-            unison_manager: Arc::new(UnisonManager::new()), // Unison設定の初期化
+            last_port_poll: None, // 最初のフレームで走査する
+            params,
+            note_handler,
+            midi_queue: MidiQueue::new(),
+            keyboard: KeyboardInput::default(),
+            recorder: Recorder::new(),
+            arpeggiator,
+            parts,
+            part_arpeggiators,
+            part_preset_names: std::array::from_fn(|_| String::from("Init")),
+            analyzer: SpectrumAnalyzer::new(),
+            logger: Logger::new(),
+            audio_devices: audio::find_backend(&audio_backends, &config.audio)
+                .map(|backend| backend.output_devices())
+                .unwrap_or_default(),
+            audio_settings: config.audio, // 前回のデバイスを使う（見つからなければデフォルトデバイス）
+            audio_backends,
+            clip_hold_until: None,
+            meter_peak: 0.0,
+            dsp_load: 0.0,
+            mod_rings: [None; MonitoredParam::ALL.len()],
+            presets: Vec::new(), // 起動後に別スレッドで走査する
+            preset_scan: None,
+            selected_preset: None, // プリセットはまだ選択されていない
+            preset_search: String::new(),
+            preset_tag_filter: None,
+            favorites_only: false,
+            favorite_presets: config.favorite_presets,
+            preset_tags: Vec::new(),
+            current_preset_path: None, // プリセットはまだ読み込まれていない
+            preset_name: String::from("New Preset"), // Save As のデフォルト名
+            randomizer: PatchRandomizer::from_time(),
+            mutate_amount: 0.1,
+            ab_slots: [None, None],
+            ab_active: 0,
+            custom_wavetable: None, // 波形テーブルはまだ読み込まれていない
+            wavetable_path: String::new(),
+            midi_file_path: String::new(),
+            midi_render: None,
+            midi_render_status: None,
+            toast: None,
+            recovered_session: Session::recover(),
+            last_autosave: Instant::now(),
+            last_session_json: String::new(),
+            theme: config.theme,
+            window_size: config.window_size,
+        };
+        // 前回最後に使ったプリセットを読み込む（消されていたら何もしない）
+        if let Some(path) = config.last_preset.filter(|path| path.exists()) {
+            let name = preset::preset_display_name(&path);
+            if let Some(skipped) = app.load_preset(path)
+                && !skipped.is_empty()
+            {
+                app.show_toast(with_skipped_fields(format!("Loaded preset: {}", name), &skipped));
+            }
         }
+        app.refresh_presets();
+        app
     }
 }
 
 /// eframe::App の実装（毎フレーム呼ばれる update 関数など）
 impl App for SynthApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // MIDIから設定された周波数を取得
-        if let Ok(midi_freq) = self.midi_freq.try_lock() {
-            self.freq = *midi_freq;
-            // 再生中の周波数も更新
-            if let Ok(mut current_freq) = self.current_freq.try_lock() {
-                *current_freq = self.freq;
+        // 再生中の周波数を表示用に取得（MIDI・PCキーボードで更新される）
+        if let Ok(current_freq) = self.current_freq.try_lock() {
+            self.freq = *current_freq;
+        }
+
+        // 配色が変わったら反映し、ウィンドウの大きさを覚えておく
+        if ctx.style().visuals.dark_mode != (self.theme == Theme::Dark) {
+            ctx.set_visuals(self.theme.visuals());
+        }
+        self.window_size = Some(ctx.input(|i| i.screen_rect().size().into()));
+
+        // オーディオスレッドから変調を受けたパラメータの範囲を受け取る
+        self.update_mod_rings();
+
+        // 異常終了しても音作りが失われないように、今の設定を自動保存する
+        self.autosave_session();
+
+        // PCキーボードからのノート入力（音が出るようにストリームを開始する）
+        if self.keyboard.handle_input(ctx, &self.note_handler) {
+            self.ensure_audio_stream();
+        }
+
+        // 前回接続していたMIDIポートが現れたら自動で接続する（起動直後と、以降は1秒ごとに走査）
+        if self.last_port_poll.is_none_or(|polled| polled.elapsed() >= MIDI_PORT_POLL_INTERVAL) {
+            self.last_port_poll = Some(Instant::now());
+            self.refresh_midi_ports();
+            self.reconnect_midi_ports();
+        }
+        ctx.request_repaint_after(MIDI_PORT_POLL_INTERVAL);
+
+        // 出力デバイスが抜かれたりデフォルトデバイスが変わったら、ストリームを作り直す
+        if self.last_device_poll.is_none_or(|polled| polled.elapsed() >= AUDIO_DEVICE_POLL_INTERVAL) {
+            self.last_device_poll = Some(Instant::now());
+            self.check_audio_device();
+        }
+
+        // MIDIのプログラムチェンジで選ばれたプリセットを読み込む
+        if let Some(number) = self.params.program.take() {
+            self.load_program(number);
+        }
+        // 追加のパートのチャンネルで届いたプログラムチェンジはそのパートに読み込む
+        let part_programs: Vec<_> = self
+            .parts
+            .iter()
+            .enumerate()
+            .filter_map(|(index, part)| part.params.program.take().map(|number| (index, number)))
+            .collect();
+        for (index, number) in part_programs {
+            self.load_part_program(index, number);
+        }
+
+        // ドロップされたプリセットをすぐに読み込む
+        let dropped_preset = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.path.clone())
+                .find(|path| path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| ext.eq_ignore_ascii_case("json")))
+        });
+        if let Some(path) = dropped_preset {
+            let name = preset::preset_display_name(&path);
+            if let Some(skipped) = self.load_preset(path) {
+                self.select_current_preset();
+                self.show_toast(with_skipped_fields(format!("Loaded preset: {}", name), &skipped));
+            } else {
+                self.show_toast(format!("Failed to load preset: {}", name));
+            }
+        }
+        // ドロップされたWAVファイルをオシレータAの波形として読み込む
+        let dropped_wav = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.path.clone())
+                .find(|path| path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| ext.eq_ignore_ascii_case("wav")))
+        });
+        if let Some(path) = dropped_wav {
+            let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            self.wavetable_path = path.display().to_string();
+            if self.load_wavetable() {
+                self.show_toast(format!("Loaded wavetable: {}", name));
+            } else {
+                self.show_toast(format!("Failed to load wavetable: {}", name));
             }
         }
+        // ドロップされたMIDIファイルは書き出し対象にする
+        let dropped_midi = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.path.clone())
+                .find(|path| {
+                    path.extension()
+                        .and_then(|ext| ext.to_str())
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("mid") || ext.eq_ignore_ascii_case("midi"))
+                })
+        });
+        if let Some(path) = dropped_midi {
+            self.midi_file_path = path.display().to_string();
+            let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            self.show_toast(format!("MIDI file ready to render: {}", name));
+        }
+
+        // 下端に出力レベルとボイスの状態を表示する
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+            self.status_ui(ui);
+        });
 
         // 中央パネルにGUIを描画する
         egui::CentralPanel::default().show(ctx, |ui| {
-            // タイトル見出し
-            ui.heading("🎹 Rust Synth");
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                // タイトル見出し
+                ui.heading("🎹 Rust Synth");
+
+                // GUIの配色
+                egui::ComboBox::from_label("Theme")
+                    .selected_text(self.theme.label())
+                    .show_ui(ui, |ui| {
+                        for theme in Theme::ALL {
+                            ui.selectable_value(&mut self.theme, theme, theme.label());
+                        }
+                    });
 
-            // MIDIポートの更新と選択UI
-            if ui.button("🔄 Refresh MIDI Ports").clicked() {
-                // MIDIポートのリストを更新
-                if let Ok(midi_in) = midir::MidiInput::new("rust_synth") {
-                    let ports = midi_in.ports();
-                    self.midi_ports.clear();
-                    for port in ports.iter() {
-                        if let Ok(port_name) = midi_in.port_name(port) {
-                            self.midi_ports.push(port_name);
+                // MIDIポートの更新と選択UI
+                if ui.button("🔄 Refresh MIDI Ports").clicked() {
+                    self.refresh_midi_ports();
+                }
+
+                // MIDIポートごとの接続チェックボックス（複数のポートを同時に接続して入力をまとめる）
+                // 接続中のポートには受信したメッセージの種類ごとのランプを並べる
+                let mut toggled = None;
+                for port_name in self.midi_ports.clone() {
+                    let mut connected = self.is_midi_port_connected(&port_name);
+                    ui.horizontal(|ui| {
+                        if ui.checkbox(&mut connected, &port_name).changed() {
+                            toggled = Some((port_name.clone(), connected));
+                        }
+                        self.midi_activity_ui(ui, &port_name);
+                    });
+                }
+                match toggled {
+                    Some((port_name, true)) => {
+                        self.connect_midi_port(&port_name);
+                        if self.is_midi_port_connected(&port_name) {
+                            self.params.midi_config.add_port(&port_name);
+                            self.params.midi_config.save_or_log();
                         }
                     }
-                    println!("Available MIDI ports:");
-                    for (i, name) in self.midi_ports.iter().enumerate() {
-                        println!("[{}] {}", i, name);
+                    Some((port_name, false)) => {
+                        self.disconnect_midi_port(&port_name);
+                        self.params.midi_config.remove_port(&port_name);
+                        self.params.midi_config.save_or_log();
                     }
+                    None => {}
                 }
-            }
 
-            // MIDIポート選択コンボボックス
-            if !self.midi_ports.is_empty() {
-                egui::ComboBox::from_label("MIDI Port")
-                    .selected_text(&self.midi_ports[self.selected_port])
+                // 受信するMIDIチャンネル（Omniは全チャンネル）
+                let mut midi_channel = self.params.midi_config.channel();
+                egui::ComboBox::from_label("MIDI Channel")
+                    .selected_text(midi_channel.label())
                     .show_ui(ui, |ui| {
-                        for (i, port_name) in self.midi_ports.iter().enumerate() {
-                            ui.selectable_value(&mut self.selected_port, i, port_name);
-                        }
-                    });
-            }
-
-            // MIDI接続ボタン
-            if ui.button("🔌 Connect MIDI").clicked() && self.midi_connection.is_none() {
-                if let Ok(mut midi_in) = midir::MidiInput::new("rust_synth") {
-                    midi_in.ignore(midir::Ignore::None);
-                    let ports = midi_in.ports();
-                    
-                    // 選択されたポートに接続を試みる
-                    if let Some(port) = ports.get(self.selected_port) {
-                        let port_name = midi_in.port_name(port).unwrap_or_else(|_| "Unknown".to_string());
-                        println!("Attempting to connect to MIDI port: {}", port_name);
-                        
-                        // MIDIコールバックをセットアップ
-                        let current_freq = Arc::clone(&self.current_freq);
-                        if let Ok(conn) = setup_midi_callback(midi_in, port, current_freq) {
-                            println!("MIDI connection established successfully");
-                            self.midi_connection = Some(conn);
-                            
-                            // オーディオストリームを開始（初期周波数は0で音なし）
-                            let stream = play_sine_wave(0.0, Arc::clone(&self.current_freq), Arc::clone(&self.unison_manager));
-                            self.stream_handle = Some(stream);
-                        } else {
-                            println!("Failed to establish MIDI connection");
+                        ui.selectable_value(&mut midi_channel, MidiChannel::Omni, "Omni");
+                        for channel in 0..16 {
+                            let option = MidiChannel::Channel(channel);
+                            ui.selectable_value(&mut midi_channel, option, option.label());
                         }
-                    } else {
-                        println!("Selected MIDI port not available");
-                    }
-                } else {
-                    println!("Failed to create MIDI input");
+                    });
+                if midi_channel != self.params.midi_config.channel() {
+                    self.params.midi_config.set_channel(midi_channel);
+                    self.params.midi_config.save_or_log();
+                    // 受信しなくなったチャンネルのノートが鳴り続けないようにリリースする
+                    self.note_handler.all_notes_off();
                 }
-            }
 
-            // MIDI切断ボタン
-            if ui.button("🔌 Disconnect MIDI").clicked() && self.midi_connection.is_some() {
-                // 音声ストリームを停止
-                self.stream_handle = None;
-                // MIDI接続を切断
-                self.midi_connection = None;
-                self.last_note = None;
-                // 周波数を0に設定
-                if let Ok(mut freq_lock) = self.current_freq.lock() {
-                    *freq_lock = 0.0;
+                // 全てのMIDI接続を切断するボタン
+                if ui.button("🔌 Disconnect All MIDI").clicked() && !self.midi_connections.is_empty() {
+                    // 音声ストリームを停止
+                    self.stream_handle = None;
+                    self.audio_wanted = false;
+                    // MIDI接続を切断
+                    self.midi_connections.clear();
+                    self.params.midi_config.clear_ports();
+                    self.params.midi_config.save_or_log();
+                    self.last_note = None;
+                    // 押されたままのノートを解放
+                    self.note_handler.all_notes_off();
                 }
-                if let Ok(mut freq_lock) = self.midi_freq.lock() {
-                    *freq_lock = 0.0;
+
+                // パニックボタン（ノートオフが届かず鳴り続ける音を即座に止める）
+                if ui.button("🛑 Panic").clicked() {
+                    self.note_handler.all_sound_off();
+                    self.last_note = None;
                 }
-                self.freq = 0.0;
-            }
 
-            // 波形選択UI
-            ui.separator();
-            ui.heading("Oscillator Settings");
+                // オーディオスレッドがMIDIラーンで割り当てたCCをここで保存する
+                if let Err(err) = self.params.midi_map.save_learned() {
+                    println!("Failed to save MIDI map: {}", err);
+                }
+                // MIDIラーン中の案内（次に動かしたCCが割り当てられる）
+                if let Some(param) = self.params.midi_map.learning() {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(egui::Color32::YELLOW, format!("MIDI Learn: move a knob for {}", param.label()));
+                        if ui.button("Cancel").clicked() {
+                            self.params.midi_map.cancel_learn();
+                        }
+                    });
+                    // MIDIスレッドで割り当てが終わったら表示を消すために再描画する
+                    ctx.request_repaint_after(Duration::from_millis(100));
+                }
+
+                // PCキーボード演奏の案内
+                ui.label(format!(
+                    "Keyboard: Z-M / Q-P to play, -/+ to change octave (C{})",
+                    self.keyboard.octave()
+                ));
+                if self.note_handler.sustain_pedal() {
+                    ui.label("Sustain: On");
+                }
+
+                // オーディオ設定UI
+                self.audio_settings_ui(ui);
+
+                // 録音UI
+                self.recorder_ui(ui);
+
+                // プリセットUI
+                self.preset_ui(ui);
+
+                // マルチティンバーUI（MIDIチャンネルごとの追加のパート）
+                self.parts_ui(ui);
+
+                // オシレータ設定UI
+                self.oscillator_ui(ui);
+
+                // ミキサーUI（音源ごとの音量と定位、マスター）
+                self.mixer_ui(ui);
+
+                // 発音モード設定UI
+                self.voice_ui(ui);
+
+                // キーボードスプリット・レイヤーUI
+                self.zone_ui(ui);
+
+                // Unison設定UI
+                ui.separator();
+                ui.heading("Unison Settings");
             
-            // 波形選択コンボボックス
-            let mut current_waveform = if let Ok(settings) = self.unison_manager.get_settings().lock() {
-                settings.waveform
-            } else {
-                Waveform::Sine
-            };
+                // Unisonボイス数のスライダー（1-8）
+                let mut voices = if let Ok(settings) = self.params.unison.get_settings().lock() {
+                    settings.voices
+                } else {
+                    1
+                };
+                let response = ui.add(egui::Slider::new(&mut voices, 1..=8).text("Unison Voices"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::UnisonVoices);
+                self.params.unison.set_voices(voices);
             
-            egui::ComboBox::from_label("Waveform")
-                .selected_text(format!("{:?}", current_waveform))
-                .show_ui(ui, |ui| {
-                    ui.selectable_value(&mut current_waveform, Waveform::Sine, "Sine");
-                    ui.selectable_value(&mut current_waveform, Waveform::Triangle, "Triangle");
-                    ui.selectable_value(&mut current_waveform, Waveform::Square, "Square");
-                    ui.selectable_value(&mut current_waveform, Waveform::Sawtooth, "Sawtooth");
+                // デチューン量のスライダー（粗い側は半音、細かい側は0から100セント）
+                let (mut coarse, mut detune) = if let Ok(settings) = self.params.unison.get_settings().lock() {
+                    (settings.detune_coarse, settings.detune)
+                } else {
+                    (0, 0.0)
+                };
+                let response = ui.add(egui::Slider::new(&mut coarse, 0..=MAX_UNISON_COARSE).text("Detune Coarse (semitones)"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::UnisonCoarse);
+                let response = ui.add(egui::Slider::new(&mut detune, 0.0..=100.0).text("Detune Fine (cents)"));
+                mod_ring(ui, &response, self.mod_rings[MonitoredParam::UnisonDetune.index()], 0.0..=100.0, false);
+                midi_learn(ui, response, &self.params.midi_map, ParamId::UnisonDetune);
+                self.params.unison.set_detune_coarse(coarse);
+                self.params.unison.set_detune(detune);
+
+                // ステレオの広がりのスライダー（0.0-1.0）
+                let mut width = if let Ok(settings) = self.params.unison.get_settings().lock() {
+                    settings.width
+                } else {
+                    0.0
+                };
+                let response = ui.add(egui::Slider::new(&mut width, 0.0..=1.0).text("Stereo Width"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::UnisonWidth);
+                self.params.unison.set_width(width);
+
+                // 中央と両側のボイスのバランス、発音開始時の位相
+                let (mut blend, mut phase) = if let Ok(settings) = self.params.unison.get_settings().lock() {
+                    (settings.blend, settings.phase)
+                } else {
+                    (0.5, UnisonPhase::Random)
+                };
+                let response = ui.add(egui::Slider::new(&mut blend, 0.0..=1.0).text("Blend (Center ↔ Sides)"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::UnisonBlend);
+                egui::ComboBox::from_label("Start Phase")
+                    .selected_text(format!("{:?}", phase))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut phase, UnisonPhase::Random, "Random");
+                        ui.selectable_value(&mut phase, UnisonPhase::Spread, "Spread");
+                    });
+                self.params.unison.set_blend(blend);
+                self.params.unison.set_phase(phase);
+
+                // デチューンの振り分け方と外側のボイスの音量の絞り
+                let (mut spread, mut taper) = if let Ok(settings) = self.params.unison.get_settings().lock() {
+                    (settings.spread, settings.taper)
+                } else {
+                    (UnisonSpread::Linear, 0.0)
+                };
+                egui::ComboBox::from_label("Detune Spread")
+                    .selected_text(match spread {
+                        UnisonSpread::Linear => "Linear",
+                        UnisonSpread::Exponential => "Exponential",
+                        UnisonSpread::SuperSaw => "Super Saw",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut spread, UnisonSpread::Linear, "Linear");
+                        ui.selectable_value(&mut spread, UnisonSpread::Exponential, "Exponential");
+                        ui.selectable_value(&mut spread, UnisonSpread::SuperSaw, "Super Saw");
+                    });
+                let response = ui.add(egui::Slider::new(&mut taper, 0.0..=1.0).text("Level Taper"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::UnisonTaper);
+                self.params.unison.set_spread(spread);
+                self.params.unison.set_taper(taper);
+
+                // エンベロープ設定UI
+                ui.separator();
+                ui.heading("Envelope Settings");
+
+                let mut env_params = self.params.envelope.get_params(EnvelopeTarget::Amp);
+                envelope_editor(ui, "amp_envelope", &mut env_params);
+                ui.horizontal(|ui| {
+                    let response = ui.add(egui::DragValue::new(&mut env_params.delay).clamp_range(0.0..=5.0).speed(0.01).prefix("Dly: ").suffix(" s"));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::AmpDelay);
+                    let response = ui.add(egui::DragValue::new(&mut env_params.attack).clamp_range(0.0..=5.0).speed(0.01).prefix("A: ").suffix(" s"));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::AmpAttack);
+                    let response = ui.add(egui::DragValue::new(&mut env_params.hold).clamp_range(0.0..=5.0).speed(0.01).prefix("H: ").suffix(" s"));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::AmpHold);
+                    let response = ui.add(egui::DragValue::new(&mut env_params.decay).clamp_range(0.0..=5.0).speed(0.01).prefix("D: ").suffix(" s"));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::AmpDecay);
+                    let response = ui.add(egui::DragValue::new(&mut env_params.sustain).clamp_range(0.0..=1.0).speed(0.01).prefix("S: "));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::AmpSustain);
+                    let response = ui.add(egui::DragValue::new(&mut env_params.release).clamp_range(0.0..=10.0).speed(0.01).prefix("R: ").suffix(" s"));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::AmpRelease);
+                    ui.checkbox(&mut env_params.looping, "Loop");
                 });
-            
-            self.unison_manager.set_waveform(current_waveform);
+                envelope_curve_controls(ui, &mut env_params);
+                let mut release_sensitivity = self
+                    .params
+                    .velocity
+                    .get_settings()
+                    .lock()
+                    .map(|settings| settings.release_sensitivity)
+                    .unwrap_or_default();
+                let response = ui.add(egui::Slider::new(&mut release_sensitivity, 0.0..=1.0).text("Release Velocity → Release"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::VelocityRelease);
+                self.params.velocity.set_release_sensitivity(release_sensitivity);
+                self.params.envelope.set_delay(EnvelopeTarget::Amp, env_params.delay);
+                self.params.envelope.set_attack(EnvelopeTarget::Amp, env_params.attack);
+                self.params.envelope.set_hold(EnvelopeTarget::Amp, env_params.hold);
+                self.params.envelope.set_decay(EnvelopeTarget::Amp, env_params.decay);
+                self.params.envelope.set_sustain(EnvelopeTarget::Amp, env_params.sustain);
+                self.params.envelope.set_release(EnvelopeTarget::Amp, env_params.release);
+                self.params.envelope.set_attack_curve(EnvelopeTarget::Amp, env_params.attack_curve);
+                self.params.envelope.set_decay_curve(EnvelopeTarget::Amp, env_params.decay_curve);
+                self.params.envelope.set_release_curve(EnvelopeTarget::Amp, env_params.release_curve);
+                self.params.envelope.set_looping(EnvelopeTarget::Amp, env_params.looping);
 
-            // Unison設定UI
-            ui.separator();
-            ui.heading("Unison Settings");
-            
-            // Unisonボイス数のスライダー（1-8）
-            let mut voices = if let Ok(settings) = self.unison_manager.get_settings().lock() {
-                settings.voices
-            } else {
-                1
-            };
-            ui.add(egui::Slider::new(&mut voices, 1..=8).text("Unison Voices"));
-            self.unison_manager.set_voices(voices);
-            
-            // デチューン量のスライダー（0から100セント）
-            let mut detune = if let Ok(settings) = self.unison_manager.get_settings().lock() {
-                settings.detune
-            } else {
-                0.0
-            };
-            ui.add(egui::Slider::new(&mut detune, 0.0..=100.0).text("Detune (cents)"));
-            self.unison_manager.set_detune(detune);
+                // ベロシティ設定UI
+                ui.separator();
+                ui.heading("Velocity");
 
-            // 周波数スライダー（100Hz〜1000Hz）を追加
-            ui.separator();
-            ui.add(
-                egui::Slider::new(&mut self.freq, 100.0..=1000.0)
-                    .text("Frequency (Hz)"),
-            );
-            // スライダーの値を現在の周波数に反映
-            if let Ok(mut current_freq) = self.current_freq.try_lock() {
-                *current_freq = self.freq;
-            }
+                let mut velocity_settings = if let Ok(settings) = self.params.velocity.get_settings().lock() {
+                    *settings
+                } else {
+                    Default::default()
+                };
+
+                egui::ComboBox::from_label("Velocity Curve")
+                    .selected_text(format!("{:?}", velocity_settings.curve))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut velocity_settings.curve, VelocityCurve::Linear, "Linear");
+                        ui.selectable_value(&mut velocity_settings.curve, VelocityCurve::Exponential, "Exponential");
+                        ui.selectable_value(&mut velocity_settings.curve, VelocityCurve::Fixed, "Fixed");
+                    });
+                let response = ui.add(egui::Slider::new(&mut velocity_settings.sensitivity, 0.0..=1.0).text("Velocity → Amp"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::VelocityAmp);
+                let response = ui.add(egui::Slider::new(&mut velocity_settings.attack_sensitivity, 0.0..=1.0).text("Velocity → Attack"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::VelocityAttack);
+
+                self.params.velocity.set_curve(velocity_settings.curve);
+                self.params.velocity.set_sensitivity(velocity_settings.sensitivity);
+                self.params.velocity.set_attack_sensitivity(velocity_settings.attack_sensitivity);
+
+                // フィルター設定UI
+                ui.separator();
+                ui.heading("Filter Settings");
+
+                let mut filter_settings = if let Ok(settings) = self.params.filter.get_settings().lock() {
+                    *settings
+                } else {
+                    Default::default()
+                };
+
+                ui.checkbox(&mut filter_settings.enabled, "Enable Filter");
+
+                // フィルターの種類の選択コンボボックス
+                egui::ComboBox::from_label("Filter Type")
+                    .selected_text(format!("{:?}", filter_settings.mode))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut filter_settings.mode, FilterMode::LowPass, "LowPass");
+                        ui.selectable_value(&mut filter_settings.mode, FilterMode::HighPass, "HighPass");
+                        ui.selectable_value(&mut filter_settings.mode, FilterMode::BandPass, "BandPass");
+                        ui.selectable_value(&mut filter_settings.mode, FilterMode::Notch, "Notch");
+                    });
+
+                // カットオフ（対数スケール）とレゾナンスのスライダー
+                let response = ui.add(
+                    egui::Slider::new(&mut filter_settings.cutoff, 20.0..=20000.0)
+                        .logarithmic(true)
+                        .text("Cutoff (Hz)"),
+                );
+                mod_ring(ui, &response, self.mod_rings[MonitoredParam::FilterCutoff.index()], 20.0..=20000.0, true);
+                midi_learn(ui, response, &self.params.midi_map, ParamId::FilterCutoff);
+                let response = ui.add(egui::Slider::new(&mut filter_settings.resonance, 0.0..=1.0).text("Resonance"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::FilterResonance);
+
+                // キートラッキング（100%でカットオフがノートの音程と同じだけ動く、C4で設定値のまま）
+                let mut key_track_percent = filter_settings.key_track * 100.0;
+                let response = ui.add(
+                    egui::Slider::new(&mut key_track_percent, 0.0..=MAX_KEY_TRACK * 100.0)
+                        .text("Key Track")
+                        .suffix(" %"),
+                );
+                midi_learn(ui, response, &self.params.midi_map, ParamId::FilterKeyTrack);
+                filter_settings.key_track = key_track_percent / 100.0;
+
+                // フィルターエンベロープ（音量エンベロープとは別のADSR）と量
+                ui.label("Filter Envelope");
+                let response = ui.add(egui::Slider::new(&mut filter_settings.env_amount, -1.0..=1.0).text("Env Amount"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::FilterEnvAmount);
+                let mut filter_env = self.params.envelope.get_params(EnvelopeTarget::Filter);
+                envelope_editor(ui, "filter_envelope", &mut filter_env);
+                ui.horizontal(|ui| {
+                    let response = ui.add(egui::DragValue::new(&mut filter_env.delay).clamp_range(0.0..=5.0).speed(0.01).prefix("Dly: ").suffix(" s"));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::FilterEnvDelay);
+                    let response = ui.add(egui::DragValue::new(&mut filter_env.attack).clamp_range(0.0..=5.0).speed(0.01).prefix("A: ").suffix(" s"));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::FilterEnvAttack);
+                    let response = ui.add(egui::DragValue::new(&mut filter_env.hold).clamp_range(0.0..=5.0).speed(0.01).prefix("H: ").suffix(" s"));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::FilterEnvHold);
+                    let response = ui.add(egui::DragValue::new(&mut filter_env.decay).clamp_range(0.0..=5.0).speed(0.01).prefix("D: ").suffix(" s"));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::FilterEnvDecay);
+                    let response = ui.add(egui::DragValue::new(&mut filter_env.sustain).clamp_range(0.0..=1.0).speed(0.01).prefix("S: "));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::FilterEnvSustain);
+                    let response = ui.add(egui::DragValue::new(&mut filter_env.release).clamp_range(0.0..=10.0).speed(0.01).prefix("R: ").suffix(" s"));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::FilterEnvRelease);
+                    ui.checkbox(&mut filter_env.looping, "Loop");
+                });
+                envelope_curve_controls(ui, &mut filter_env);
+
+                self.params.filter.set_enabled(filter_settings.enabled);
+                self.params.filter.set_mode(filter_settings.mode);
+                self.params.filter.set_cutoff(filter_settings.cutoff);
+                self.params.filter.set_resonance(filter_settings.resonance);
+                self.params.filter.set_env_amount(filter_settings.env_amount);
+                self.params.filter.set_key_track(filter_settings.key_track);
+                self.params.envelope.set_delay(EnvelopeTarget::Filter, filter_env.delay);
+                self.params.envelope.set_attack(EnvelopeTarget::Filter, filter_env.attack);
+                self.params.envelope.set_hold(EnvelopeTarget::Filter, filter_env.hold);
+                self.params.envelope.set_decay(EnvelopeTarget::Filter, filter_env.decay);
+                self.params.envelope.set_sustain(EnvelopeTarget::Filter, filter_env.sustain);
+                self.params.envelope.set_release(EnvelopeTarget::Filter, filter_env.release);
+                self.params.envelope.set_attack_curve(EnvelopeTarget::Filter, filter_env.attack_curve);
+                self.params.envelope.set_decay_curve(EnvelopeTarget::Filter, filter_env.decay_curve);
+                self.params.envelope.set_release_curve(EnvelopeTarget::Filter, filter_env.release_curve);
+                self.params.envelope.set_looping(EnvelopeTarget::Filter, filter_env.looping);
+
+                // LFO設定UI
+                ui.separator();
+                ui.heading("LFO Settings");
+
+                let mut lfo_settings = if let Ok(settings) = self.params.lfo.get_settings().lock() {
+                    *settings
+                } else {
+                    Default::default()
+                };
+
+                ui.checkbox(&mut lfo_settings.enabled, "Enable LFO");
+
+                // LFO波形の選択コンボボックス
+                egui::ComboBox::from_label("LFO Shape")
+                    .selected_text(format!("{:?}", lfo_settings.shape))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut lfo_settings.shape, LfoShape::Sine, "Sine");
+                        ui.selectable_value(&mut lfo_settings.shape, LfoShape::Triangle, "Triangle");
+                        ui.selectable_value(&mut lfo_settings.shape, LfoShape::Square, "Square");
+                        ui.selectable_value(&mut lfo_settings.shape, LfoShape::Sawtooth, "Sawtooth");
+                        ui.selectable_value(&mut lfo_settings.shape, LfoShape::SampleAndHold, "Sample & Hold");
+                        ui.selectable_value(&mut lfo_settings.shape, LfoShape::SmoothRandom, "Smooth Random");
+                    });
+
+                // モジュレーション先の選択コンボボックス
+                egui::ComboBox::from_label("LFO Target")
+                    .selected_text(match lfo_settings.target {
+                        LfoTarget::Pitch => "Pitch (Vibrato)",
+                        LfoTarget::Amplitude => "Amplitude (Tremolo)",
+                        LfoTarget::FilterCutoff => "Filter Cutoff",
+                        LfoTarget::PulseWidth => "Pulse Width (PWM)",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut lfo_settings.target, LfoTarget::Pitch, "Pitch (Vibrato)");
+                        ui.selectable_value(&mut lfo_settings.target, LfoTarget::Amplitude, "Amplitude (Tremolo)");
+                        ui.selectable_value(&mut lfo_settings.target, LfoTarget::FilterCutoff, "Filter Cutoff");
+                        ui.selectable_value(&mut lfo_settings.target, LfoTarget::PulseWidth, "Pulse Width (PWM)");
+                    });
+
+                ui.checkbox(&mut lfo_settings.sync, "Sync to Tempo");
+                if lfo_settings.sync {
+                    division_combo(ui, "LFO Division", &mut lfo_settings.division);
+                } else {
+                    let response = ui.add(
+                        egui::Slider::new(&mut lfo_settings.rate, 0.01..=20.0)
+                            .logarithmic(true)
+                            .text("LFO Rate (Hz)"),
+                    );
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::LfoRate);
+                }
+                let response = ui.add(egui::Slider::new(&mut lfo_settings.depth, 0.0..=1.0).text("LFO Depth"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::LfoDepth);
+                let response = ui.add(egui::Slider::new(&mut lfo_settings.slew, 0.0..=1.0).text("LFO Slew"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::LfoSlew);
 
-            // 現在の周波数をラベルとして表示
-            ui.label(format!("Current frequency: {:.1} Hz", self.freq));
+                // 全ボイスで共有するかボイスごとに持つか、ノートを弾いた時の位相の扱い
+                ui.horizontal(|ui| {
+                    ui.label("LFO Mode:");
+                    ui.selectable_value(&mut lfo_settings.voice_mode, LfoVoiceMode::Global, "Global");
+                    ui.selectable_value(&mut lfo_settings.voice_mode, LfoVoiceMode::PerVoice, "Per Voice");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Retrigger:");
+                    ui.selectable_value(&mut lfo_settings.retrigger, LfoRetrigger::FreeRun, "Free Run");
+                    ui.selectable_value(&mut lfo_settings.retrigger, LfoRetrigger::Key, "Key");
+                });
+                let response = ui.add(egui::Slider::new(&mut lfo_settings.phase_offset, 0.0..=1.0).text("LFO Phase Offset"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::LfoPhaseOffset);
+
+                self.params.lfo.set_enabled(lfo_settings.enabled);
+                self.params.lfo.set_shape(lfo_settings.shape);
+                self.params.lfo.set_target(lfo_settings.target);
+                self.params.lfo.set_rate(lfo_settings.rate);
+                self.params.lfo.set_sync(lfo_settings.sync);
+                self.params.lfo.set_division(lfo_settings.division);
+                self.params.lfo.set_depth(lfo_settings.depth);
+                self.params.lfo.set_slew(lfo_settings.slew);
+                self.params.lfo.set_voice_mode(lfo_settings.voice_mode);
+                self.params.lfo.set_retrigger(lfo_settings.retrigger);
+                self.params.lfo.set_phase_offset(lfo_settings.phase_offset);
+
+                // モジュレーションホイール（CC1）からビブラートの深さへの量
+                let response = ui.add(egui::Slider::new(&mut lfo_settings.mod_wheel_vibrato, 0.0..=1.0).text("Mod Wheel → Vibrato"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::ModWheelVibrato);
+                self.params.lfo.set_mod_wheel_vibrato(lfo_settings.mod_wheel_vibrato);
+
+                // ピッチベンド設定UI
+                ui.separator();
+                ui.heading("Pitch Bend");
+
+                let mut bend_range = if let Ok(settings) = self.params.pitch_bend.get_settings().lock() {
+                    settings.range
+                } else {
+                    2.0
+                };
+                let response = ui.add(
+                    egui::Slider::new(&mut bend_range, 0.0..=MAX_BEND_RANGE)
+                        .step_by(1.0)
+                        .text("Bend Range (semitones)"),
+                );
+                midi_learn(ui, response, &self.params.midi_map, ParamId::BendRange);
+                self.params.pitch_bend.set_range(bend_range);
+                ui.label(format!(
+                    "Current bend: {:+.2} semitones",
+                    self.params.pitch_bend.value() * bend_range
+                ));
+
+                // 周波数スライダー（100Hz〜1000Hz）を追加
+                ui.separator();
+                let response = ui.add(
+                    egui::Slider::new(&mut self.freq, 100.0..=1000.0)
+                        .clamp_to_range(false)
+                        .text("Frequency (Hz)"),
+                );
+                // スライダーを動かした時だけ最後に鳴らしたボイスに反映（MIDIの周波数を上書きしないように）
+                if response.changed() {
+                    self.note_handler.set_current_freq(self.freq);
+                }
+
+                // 現在の周波数をラベルとして表示
+                ui.label(format!("Current frequency: {:.1} Hz", self.freq));
+
+                // トランスポート（テンポとクロック）UI
+                self.transport_ui(ui);
+
+                // アルペジエーターUI
+                self.arp_ui(ui);
+
+                // スケールロックUI
+                self.scale_lock_ui(ui);
+
+                // コードモードUI
+                self.chord_ui(ui);
+
+                // ストラムUI
+                self.strum_ui(ui);
+
+                // モジュレーションマトリクスUI
+                self.mod_matrix_ui(ui);
+
+                // マクロツマミUI
+                self.macro_ui(ui);
+
+                // XYパッドUI
+                self.xy_pad_ui(ui);
+
+                // エフェクトUI（チェーンの並びと、各エフェクトの設定）
+                self.effects_ui(ui);
+                self.chorus_ui(ui);
+                self.reverb_ui(ui);
+                self.distortion_ui(ui);
+                self.bitcrusher_ui(ui);
+                self.phaser_ui(ui);
+                self.flanger_ui(ui);
+                self.delay_ui(ui);
+
+                // マスター出力UI
+                self.master_ui(ui);
+
+                // スペクトラム表示
+                self.spectrum_ui(ui);
+
+                // オーディオ・MIDIコールバックからのログ
+                self.log_ui(ui);
+            });
         });
+
+        // ファイルをドラッグしている間は、ドロップできるファイルを案内する
+        if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+            egui::Area::new("drop_hint")
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .interactable(false)
+                .show(ctx, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label("Drop a preset (.json), wavetable (.wav) or MIDI file (.mid)");
+                    });
+                });
+        }
+
+        // 前回異常終了していたら、自動保存したセッションを復元するか尋ねる
+        self.session_recovery_ui(ctx);
+
+        // ドロップしたファイルを読み込んだ結果などのお知らせ
+        self.toast_ui(ctx);
     }
 
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
-        // アプリケーション終了時のクリーンアップ
+        // 次の起動時に復元する設定を保存する
+        self.save_config();
+        // 正常に終了したので自動保存したセッションは消す（復元するか答えていなければ次の起動でもう一度尋ねる）
+        if self.recovered_session.is_none() {
+            session::clear();
+        }
+
+        // アプリケーション終了時のクリーンアップ（録音中ならファイルを書き出す）
+        self.recorder.stop();
+        self.arpeggiator.stop();
+        for arpeggiator in &mut self.part_arpeggiators {
+            arpeggiator.stop();
+        }
         self.stream_handle = None;
-        self.midi_connection = None;
+        self.audio_wanted = false;
+        self.midi_connections.clear();
         self.last_note = None;
-        if let Ok(mut freq_lock) = self.current_freq.lock() {
-            *freq_lock = 0.0;
+        self.note_handler.all_notes_off();
+        for part in &self.parts {
+            part.notes.all_notes_off();
         }
-        if let Ok(mut freq_lock) = self.midi_freq.lock() {
+        if let Ok(mut freq_lock) = self.current_freq.lock() {
             *freq_lock = 0.0;
         }
         self.freq = 0.0;
     }
-} 
\ No newline at end of file
+}
+
+impl SynthApp {
+    /// オーディオスレッドから変調の範囲を受け取り、表示中の範囲をゆっくり今の値へ縮める
+    ///
+    /// しばらく通知が来なければ（ボイスが鳴っていない、フィルターが無効など）表示を消す
+    fn update_mod_rings(&mut self) {
+        for param in MonitoredParam::ALL {
+            let ring = &mut self.mod_rings[param.index()];
+            match (self.params.mod_monitor.take(param), ring.as_mut()) {
+                (Some(range), Some((shown, reported))) => {
+                    shown.min = (shown.min + (range.value - shown.min) * MOD_RING_DECAY).min(range.min);
+                    shown.max = (shown.max + (range.value - shown.max) * MOD_RING_DECAY).max(range.max);
+                    shown.value = range.value;
+                    *reported = Instant::now();
+                }
+                (Some(range), None) => *ring = Some((range, Instant::now())),
+                (None, Some((_, reported))) if reported.elapsed() > MOD_RING_HOLD => *ring = None,
+                (None, _) => {}
+            }
+        }
+    }
+
+    /// 一定時間ごとに今の設定をセッションとして自動保存する（前回から変わっていなければ書かない）
+    fn autosave_session(&mut self) {
+        if self.recovered_session.is_some() || self.last_autosave.elapsed() < SESSION_AUTOSAVE_INTERVAL {
+            return;
+        }
+        self.last_autosave = Instant::now();
+        let mut preset = Preset::capture(&self.preset_name, &self.params);
+        preset.tags = self.preset_tags.clone();
+        let session = Session {
+            preset,
+            preset_path: self.current_preset_path.clone(),
+            parts: self.params.parts.get_settings().lock().map(|settings| *settings).unwrap_or_default(),
+            part_presets: self
+                .parts
+                .iter()
+                .zip(&self.part_preset_names)
+                .map(|(part, name)| Preset::capture(name, &part.params))
+                .collect(),
+        };
+        match session.to_json() {
+            Ok(json) if json == self.last_session_json => {}
+            Ok(json) => match session::save(&json) {
+                Ok(()) => self.last_session_json = json,
+                Err(err) => println!("Failed to auto-save session: {}", err),
+            },
+            Err(err) => println!("Failed to auto-save session: {}", err),
+        }
+    }
+
+    /// 前回異常終了していたら、自動保存したセッションを復元するか尋ねるウィンドウを表示する
+    fn session_recovery_ui(&mut self, ctx: &egui::Context) {
+        let Some(session) = &self.recovered_session else {
+            return;
+        };
+        let mut restore = None;
+        egui::Window::new("Restore Session?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("The synth did not exit cleanly last time.");
+                ui.label(format!("Restore the auto-saved sound \"{}\"?", session.preset.name));
+                ui.horizontal(|ui| {
+                    if ui.button("Restore").clicked() {
+                        restore = Some(true);
+                    }
+                    if ui.button("Discard").clicked() {
+                        restore = Some(false);
+                    }
+                });
+            });
+        match restore {
+            Some(true) => {
+                if let Some(session) = self.recovered_session.take() {
+                    self.restore_session(session);
+                }
+            }
+            Some(false) => {
+                self.recovered_session = None;
+                session::clear();
+            }
+            None => {}
+        }
+    }
+
+    /// 自動保存したセッションをシンセ設定に反映する
+    fn restore_session(&mut self, session: Session) {
+        // 発音モードやアルペジエーターが変わっても音が残らないように先に止める
+        self.note_handler.all_notes_off();
+        session.preset.apply(&self.params);
+        self.preset_name = session.preset.name;
+        self.preset_tags = session.preset.tags;
+        self.current_preset_path = session.preset_path;
+        self.params.parts.set_all(session.parts.parts);
+        for ((part, name), preset) in self.parts.iter().zip(&mut self.part_preset_names).zip(session.part_presets) {
+            part.notes.all_notes_off();
+            preset.apply(&part.params);
+            *name = preset.name;
+        }
+        self.select_current_preset();
+        self.show_toast("Restored the previous session".to_string());
+    }
+
+    /// 画面下にお知らせを表示する（前のお知らせは置き換える）
+    fn show_toast(&mut self, message: String) {
+        self.toast = Some((message, Instant::now()));
+    }
+
+    /// 表示中のお知らせを描画する（TOAST_DURATION が過ぎたら消す）
+    fn toast_ui(&mut self, ctx: &egui::Context) {
+        let Some((message, shown)) = &self.toast else {
+            return;
+        };
+        let remaining = TOAST_DURATION.saturating_sub(shown.elapsed());
+        if remaining.is_zero() {
+            self.toast = None;
+            return;
+        }
+        egui::Area::new("toast")
+            .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -48.0])
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(message.as_str());
+                });
+            });
+        ctx.request_repaint_after(remaining);
+    }
+
+    /// オーディオ設定・配色・プリセット・ウィンドウの大きさと、MIDIの設定を保存する
+    fn save_config(&self) {
+        let config = AppConfig {
+            audio: self.audio_settings.clone(),
+            theme: self.theme,
+            last_preset: self.current_preset_path.clone(),
+            favorite_presets: self.favorite_presets.clone(),
+            window_size: self.window_size,
+        };
+        if let Err(err) = config.save() {
+            println!("Failed to save config: {}", err);
+        }
+        self.params.midi_config.save_or_log();
+    }
+
+    /// MIDIポートのリストを更新する（ポートが変わった時だけログに出す）
+    fn refresh_midi_ports(&mut self) {
+        let Ok(midi_in) = midir::MidiInput::new("rust_synth") else {
+            return;
+        };
+        let ports: Vec<String> = midi_in
+            .ports()
+            .iter()
+            .filter_map(|port| midi_in.port_name(port).ok())
+            .collect();
+        if ports == self.midi_ports {
+            return;
+        }
+        self.midi_ports = ports;
+        println!("Available MIDI ports:");
+        for (i, name) in self.midi_ports.iter().enumerate() {
+            println!("[{}] {}", i, name);
+        }
+    }
+
+    /// 抜かれたポートの接続を外し、前回接続していたポートが見つかれば接続する
+    fn reconnect_midi_ports(&mut self) {
+        let available = &self.midi_ports;
+        self.midi_connections.retain(|(name, ..)| {
+            let present = available.contains(name);
+            if !present {
+                println!("MIDI port disconnected: {}", name);
+            }
+            present
+        });
+        for port_name in self.params.midi_config.ports() {
+            if self.midi_ports.contains(&port_name) && !self.is_midi_port_connected(&port_name) {
+                self.connect_midi_port(&port_name);
+            }
+        }
+    }
+
+    /// MIDIポートが接続中かどうか
+    fn is_midi_port_connected(&self, port_name: &str) -> bool {
+        self.midi_connections.iter().any(|(name, ..)| name == port_name)
+    }
+
+    /// 名前で指定したMIDIポートに接続する（接続中の他のポートはそのまま）
+    fn connect_midi_port(&mut self, port_name: &str) {
+        if self.is_midi_port_connected(port_name) {
+            return;
+        }
+        let Ok(mut midi_in) = midir::MidiInput::new("rust_synth") else {
+            println!("Failed to create MIDI input");
+            return;
+        };
+        midi_in.ignore(midir::Ignore::None);
+        let ports = midi_in.ports();
+        let port = ports
+            .iter()
+            .find(|port| midi_in.port_name(port).is_ok_and(|name| name == port_name))
+            .cloned();
+        let Some(port) = port else {
+            println!("Selected MIDI port not available");
+            return;
+        };
+        println!("Attempting to connect to MIDI port: {}", port_name);
+
+        // MIDIコールバックをセットアップ（全てのポートが同じキューに積み、オーディオスレッドで反映する）
+        let activity = Arc::new(MidiActivity::new());
+        if let Ok(conn) = setup_midi_callback(midi_in, &port, self.midi_queue.clone(), Arc::clone(&activity)) {
+            println!("MIDI connection established successfully");
+            self.midi_connections.push((port_name.to_string(), conn, activity));
+
+            // オーディオストリームを開始
+            self.ensure_audio_stream();
+        } else {
+            println!("Failed to establish MIDI connection");
+        }
+    }
+
+    /// 接続中のポートで受信したメッセージの種類ごとのランプを描画する（受信の数が増えたら少しの間点灯する）
+    fn midi_activity_ui(&mut self, ui: &mut egui::Ui, port_name: &str) {
+        let Some((_, _, activity)) = self.midi_connections.iter().find(|(name, ..)| name == port_name) else {
+            self.midi_lamps.remove(port_name);
+            return;
+        };
+        let lamps = self.midi_lamps.entry(port_name.to_string()).or_insert_with(|| {
+            MidiActivityKind::ALL.map(|kind| (activity.count(kind), None))
+        });
+        let now = Instant::now();
+        for (kind, (seen, lit_at)) in MidiActivityKind::ALL.into_iter().zip(lamps.iter_mut()) {
+            let count = activity.count(kind);
+            if count != *seen {
+                *seen = count;
+                *lit_at = Some(now);
+            }
+            let lit = lit_at.is_some_and(|at| now.duration_since(at) < MIDI_ACTIVITY_BLINK);
+            let color = if lit { egui::Color32::GREEN } else { egui::Color32::DARK_GRAY };
+            ui.colored_label(color, format!("● {}", kind.label()));
+        }
+        // 操作がなくても受信を見逃さないように、点灯時間の半分ごとに描画し直す
+        ui.ctx().request_repaint_after(MIDI_ACTIVITY_BLINK / 2);
+    }
+
+    /// 名前で指定したMIDIポートの接続を切断する
+    fn disconnect_midi_port(&mut self, port_name: &str) {
+        self.midi_connections.retain(|(name, ..)| name != port_name);
+        // 切断したポートから押されていたノートが鳴り続けないようにリリースする
+        self.last_note = None;
+        self.note_handler.all_notes_off();
+    }
+
+    /// コールバックから届いたログを折りたたみ表示する
+    fn log_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        egui::CollapsingHeader::new("Log").show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .id_source("log_scroll")
+                .max_height(150.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for line in self.logger.history() {
+                        ui.monospace(line);
+                    }
+                });
+        });
+    }
+
+    /// オーディオストリームが止まっていれば開始する
+    fn ensure_audio_stream(&mut self) {
+        self.audio_wanted = true;
+        if self.stream_handle.is_none() {
+            let source = AudioSource {
+                params: self.params.clone(),
+                notes: self.note_handler.clone(),
+                parts: self.parts.clone(),
+                midi_queue: self.midi_queue.clone(),
+                record_tap: self.recorder.tap(),
+                analyzer_tap: self.analyzer.tap(),
+                log: self.logger.tap(),
+                midi_log: self.logger.tap(),
+            };
+            let Some(backend) = audio::find_backend(&self.audio_backends, &self.audio_settings) else {
+                println!("No audio backend available");
+                return;
+            };
+            match backend.start(source, &self.audio_settings) {
+                Ok(stream) => self.stream_handle = Some(stream),
+                Err(err) => println!("Failed to start audio stream: {}", err),
+            }
+        }
+    }
+
+    /// 出力デバイスの変化を確認し、必要ならストリームを作り直す
+    ///
+    /// - ストリームのエラーでデバイスが使えなくなった
+    /// - デフォルトデバイスを使っていて、OSのデフォルトデバイスが変わった
+    /// - 選択したデバイスが抜かれていてデフォルトデバイスで鳴らしていたが、また挿された
+    /// - 作り直しに失敗していた（次の確認でもう一度試す）
+    fn check_audio_device(&mut self) {
+        let device_lost = self.params.meter.take_device_lost();
+        if !self.audio_wanted {
+            return;
+        }
+        let Some(backend) = audio::find_backend(&self.audio_backends, &self.audio_settings) else {
+            return;
+        };
+        let wanted_device = match &self.audio_settings.device_name {
+            Some(name) if backend.output_device_names().contains(name) => Some(name.clone()),
+            _ => backend.default_device_name(),
+        };
+        let current_device = self
+            .stream_handle
+            .as_ref()
+            .and_then(|stream| stream.device_name().map(str::to_string));
+        let device_changed = wanted_device.is_some() && current_device.is_some() && wanted_device != current_device;
+
+        if !device_lost && !device_changed && self.stream_handle.is_some() {
+            return;
+        }
+        if device_lost {
+            println!("Output device lost, restarting audio stream");
+        } else if device_changed {
+            println!(
+                "Output device changed to '{}', restarting audio stream",
+                wanted_device.as_deref().unwrap_or("Unknown")
+            );
+        }
+        self.audio_devices = backend.output_devices();
+        self.stream_handle = None;
+        self.params.meter.reset_stream_info();
+        self.ensure_audio_stream();
+    }
+
+    /// オーディオストリームが動いていれば、現在の設定で作り直す
+    fn restart_audio_stream(&mut self) {
+        if self.stream_handle.is_some() {
+            self.stream_handle = None;
+            self.params.meter.reset_stream_info();
+            self.ensure_audio_stream();
+        }
+    }
+
+    /// 出力デバイス・サンプルレート・バッファサイズの設定UIを描画する
+    fn audio_settings_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Audio Settings").show(ui, |ui| {
+            let mut settings = self.audio_settings.clone();
+            let backend = audio::find_backend(&self.audio_backends, &settings);
+
+            // バックエンドの選択（JACKなどを有効にしてビルドした時だけ複数になる）
+            if self.audio_backends.len() > 1 {
+                egui::ComboBox::from_label("Backend")
+                    .selected_text(backend.map_or("None", |backend| backend.name()))
+                    .show_ui(ui, |ui| {
+                        for candidate in &self.audio_backends {
+                            let name = candidate.name().to_string();
+                            let selected = backend.is_some_and(|backend| backend.name() == name);
+                            if ui.selectable_label(selected, &name).clicked() {
+                                settings.backend = Some(name);
+                            }
+                        }
+                    });
+            }
+
+            // JACK・Web Audio ではデバイスとフォーマットはサーバー・ブラウザが決める
+            if !backend.is_some_and(|backend| backend.is_configurable()) {
+                if let Some(backend) = backend {
+                    ui.label(format!("Device, sample rate and buffer size are set by {}", backend.name()));
+                }
+                self.latency_ui(ui, &settings);
+                self.apply_audio_settings(settings);
+                return;
+            }
+
+            if ui.button("🔄 Refresh Devices").clicked() {
+                self.audio_devices = backend.map(|backend| backend.output_devices()).unwrap_or_default();
+            }
+
+            // 出力デバイスの選択
+            egui::ComboBox::from_label("Output Device")
+                .selected_text(settings.device_name.clone().unwrap_or_else(|| "Default".to_string()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut settings.device_name, None, "Default");
+                    for device in &self.audio_devices {
+                        ui.selectable_value(&mut settings.device_name, Some(device.name.clone()), &device.name);
+                    }
+                });
+
+            // 選択中のデバイスが対応しているサンプルレートとバッファサイズ
+            let device_info = settings
+                .device_name
+                .as_ref()
+                .and_then(|name| self.audio_devices.iter().find(|device| &device.name == name));
+            let sample_rates = device_info
+                .map(|device| device.sample_rates.clone())
+                .unwrap_or_else(|| vec![44100, 48000, 96000]);
+            let buffer_sizes = device_info
+                .map(|device| device.buffer_sizes.clone())
+                .unwrap_or_else(|| vec![64, 128, 256, 512, 1024, 2048]);
+
+            // サンプルレートの選択
+            egui::ComboBox::from_label("Sample Rate")
+                .selected_text(match settings.sample_rate {
+                    Some(rate) => format!("{} Hz", rate),
+                    None => "Default".to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut settings.sample_rate, None, "Default");
+                    for rate in sample_rates {
+                        ui.selectable_value(&mut settings.sample_rate, Some(rate), format!("{} Hz", rate));
+                    }
+                });
+
+            // バッファサイズの選択
+            egui::ComboBox::from_label("Buffer Size")
+                .selected_text(match settings.buffer_size {
+                    Some(frames) => format!("{} frames", frames),
+                    None => "Default".to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut settings.buffer_size, None, "Default");
+                    for frames in buffer_sizes {
+                        ui.selectable_value(&mut settings.buffer_size, Some(frames), format!("{} frames", frames));
+                    }
+                });
+
+            self.latency_ui(ui, &settings);
+            self.apply_audio_settings(settings);
+        });
+    }
+
+    /// バッファの長さから計算した出力レイテンシーと、バックエンドが測ったレイテンシーを表示する
+    ///
+    /// ストリームが動いていれば実際のバッファの長さを使い、止まっていれば選択中の設定から計算する
+    fn latency_ui(&self, ui: &mut egui::Ui, settings: &AudioSettings) {
+        let buffer = self
+            .params
+            .meter
+            .buffer()
+            .filter(|_| self.stream_handle.is_some())
+            .or_else(|| settings.buffer_size.zip(settings.sample_rate));
+        match buffer {
+            Some((frames, sample_rate)) => ui.label(format!(
+                "Buffer latency: {:.1} ms ({} frames @ {} Hz)",
+                frames as f32 / sample_rate as f32 * 1000.0,
+                frames,
+                sample_rate
+            )),
+            None => ui.label("Buffer latency: depends on the device default"),
+        };
+        if let Some(latency) = self.params.meter.output_latency().filter(|_| self.stream_handle.is_some()) {
+            ui.label(format!("Measured output latency: {:.1} ms", latency * 1000.0));
+        }
+    }
+
+    /// 設定が変わったらストリームを作り直す
+    fn apply_audio_settings(&mut self, mut settings: AudioSettings) {
+        if settings == self.audio_settings {
+            return;
+        }
+        // バックエンドが変わったら、デバイスを探し直してデバイスの設定をデフォルトに戻す
+        if settings.backend != self.audio_settings.backend {
+            settings.device_name = None;
+            if let Some(backend) = audio::find_backend(&self.audio_backends, &settings) {
+                self.audio_devices = backend.output_devices();
+            }
+        }
+        // デバイスが変わったら、そのデバイスで使えないかもしれない設定はデフォルトに戻す
+        if settings.device_name != self.audio_settings.device_name {
+            settings.sample_rate = None;
+            settings.buffer_size = None;
+        }
+        self.audio_settings = settings;
+        self.restart_audio_stream();
+    }
+
+    /// オシレータA・B、ミックス、生成方式の設定UIを描画する
+    fn oscillator_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Oscillator Settings");
+
+        let mut waveform_a = if let Ok(settings) = self.params.unison.get_settings().lock() {
+            settings.waveform.clone()
+        } else {
+            Waveform::Sine
+        };
+        let mut osc_b = if let Ok(settings) = self.params.osc_b.get_settings().lock() {
+            settings.clone()
+        } else {
+            Default::default()
+        };
+
+        // オシレータAとBを横に並べる
+        ui.columns(2, |columns| {
+            columns[0].group(|ui| {
+                ui.label("Osc A");
+                waveform_combo(ui, "osc_a_waveform", &mut waveform_a, self.custom_wavetable.as_ref());
+            });
+            columns[1].group(|ui| {
+                ui.label("Osc B");
+                waveform_combo(ui, "osc_b_waveform", &mut osc_b.waveform, self.custom_wavetable.as_ref());
+                let response = ui.add(egui::Slider::new(&mut osc_b.octave, -3..=3).text("Octave"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::OscBOctave);
+                let response = ui.add(egui::Slider::new(&mut osc_b.semitone, -12..=12).text("Semi"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::OscBSemitone);
+                let response = ui.add(egui::Slider::new(&mut osc_b.fine, -100.0..=100.0).text("Fine (cents)"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::OscBFine);
+            });
+        });
+
+        // 単一周期WAVの読み込み（ウィンドウへのドロップでも読み込める）
+        ui.horizontal(|ui| {
+            ui.label("Custom WAV:");
+            ui.text_edit_singleline(&mut self.wavetable_path);
+            if ui.button("Load").clicked() {
+                self.load_wavetable();
+                // 読み込んだ波形を編集中の値にも反映する
+                if let Some(table) = &self.custom_wavetable {
+                    waveform_a = Waveform::Custom(table.clone());
+                }
+            }
+        });
+
+        // 矩形波のパルス幅（どちらかのオシレータが矩形波の時だけ表示する）
+        if waveform_a == Waveform::Square || osc_b.waveform == Waveform::Square {
+            let mut pulse_width = if let Ok(settings) = self.params.oscillator.get_settings().lock() {
+                settings.pulse_width
+            } else {
+                0.5
+            };
+            let response = ui.add(egui::Slider::new(&mut pulse_width, MIN_PULSE_WIDTH..=MAX_PULSE_WIDTH).text("Pulse Width"));
+            let ring = self.mod_rings[MonitoredParam::PulseWidth.index()];
+            mod_ring(ui, &response, ring, MIN_PULSE_WIDTH..=MAX_PULSE_WIDTH, false);
+            midi_learn(ui, response, &self.params.midi_map, ParamId::PulseWidth);
+            self.params.oscillator.set_pulse_width(pulse_width);
+        }
+
+        // AとBの組み合わせ方
+        egui::ComboBox::from_label("Routing")
+            .selected_text(match osc_b.routing {
+                OscRouting::Mix => "Mix",
+                OscRouting::Fm => "FM (B → A)",
+                OscRouting::RingMod => "Ring Mod (A × B)",
+                OscRouting::Sync => "Hard Sync (A → B)",
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut osc_b.routing, OscRouting::Mix, "Mix");
+                ui.selectable_value(&mut osc_b.routing, OscRouting::Fm, "FM (B → A)");
+                ui.selectable_value(&mut osc_b.routing, OscRouting::RingMod, "Ring Mod (A × B)");
+                ui.selectable_value(&mut osc_b.routing, OscRouting::Sync, "Hard Sync (A → B)");
+            });
+
+        match osc_b.routing {
+            // AとBの音量はミキサーで調整する
+            OscRouting::Mix => {}
+            OscRouting::Sync => {
+                // A↔B のミックス
+                let response = ui.add(egui::Slider::new(&mut osc_b.mix, 0.0..=1.0).text("Mix (A ↔ B)"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::OscBMix);
+            }
+            OscRouting::RingMod => {
+                // A↔A×B のミックス
+                let response = ui.add(egui::Slider::new(&mut osc_b.mix, 0.0..=1.0).text("Mix (A ↔ A×B)"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::OscBMix);
+            }
+            OscRouting::Fm => {
+                // モジュレーターの周波数比と変調指数、変調指数にかけるエンベロープ
+                let response = ui.add(egui::Slider::new(&mut osc_b.fm_ratio, 0.25..=16.0).logarithmic(true).text("FM Ratio"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::FmRatio);
+                let response = ui.add(egui::Slider::new(&mut osc_b.fm_index, 0.0..=MAX_FM_INDEX).text("FM Index"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::FmIndex);
+                ui.label("Modulator Envelope");
+                let mut mod_env = self.params.envelope.get_params(EnvelopeTarget::Modulator);
+                envelope_editor(ui, "mod_envelope", &mut mod_env);
+                ui.horizontal(|ui| {
+                    let response = ui.add(egui::DragValue::new(&mut mod_env.delay).clamp_range(0.0..=5.0).speed(0.01).prefix("Dly: ").suffix(" s"));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::ModEnvDelay);
+                    let response = ui.add(egui::DragValue::new(&mut mod_env.attack).clamp_range(0.0..=5.0).speed(0.01).prefix("A: ").suffix(" s"));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::ModEnvAttack);
+                    let response = ui.add(egui::DragValue::new(&mut mod_env.hold).clamp_range(0.0..=5.0).speed(0.01).prefix("H: ").suffix(" s"));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::ModEnvHold);
+                    let response = ui.add(egui::DragValue::new(&mut mod_env.decay).clamp_range(0.0..=5.0).speed(0.01).prefix("D: ").suffix(" s"));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::ModEnvDecay);
+                    let response = ui.add(egui::DragValue::new(&mut mod_env.sustain).clamp_range(0.0..=1.0).speed(0.01).prefix("S: "));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::ModEnvSustain);
+                    let response = ui.add(egui::DragValue::new(&mut mod_env.release).clamp_range(0.0..=10.0).speed(0.01).prefix("R: ").suffix(" s"));
+                    midi_learn(ui, response, &self.params.midi_map, ParamId::ModEnvRelease);
+                    ui.checkbox(&mut mod_env.looping, "Loop");
+                });
+                envelope_curve_controls(ui, &mut mod_env);
+                self.params.envelope.set_delay(EnvelopeTarget::Modulator, mod_env.delay);
+                self.params.envelope.set_attack(EnvelopeTarget::Modulator, mod_env.attack);
+                self.params.envelope.set_hold(EnvelopeTarget::Modulator, mod_env.hold);
+                self.params.envelope.set_decay(EnvelopeTarget::Modulator, mod_env.decay);
+                self.params.envelope.set_sustain(EnvelopeTarget::Modulator, mod_env.sustain);
+                self.params.envelope.set_release(EnvelopeTarget::Modulator, mod_env.release);
+                self.params.envelope.set_attack_curve(EnvelopeTarget::Modulator, mod_env.attack_curve);
+                self.params.envelope.set_decay_curve(EnvelopeTarget::Modulator, mod_env.decay_curve);
+                self.params.envelope.set_release_curve(EnvelopeTarget::Modulator, mod_env.release_curve);
+                self.params.envelope.set_looping(EnvelopeTarget::Modulator, mod_env.looping);
+            }
+        }
+
+        self.params.unison.set_waveform(waveform_a);
+        self.params.osc_b.set_waveform(osc_b.waveform);
+        self.params.osc_b.set_octave(osc_b.octave);
+        self.params.osc_b.set_semitone(osc_b.semitone);
+        self.params.osc_b.set_fine(osc_b.fine);
+        self.params.osc_b.set_mix(osc_b.mix);
+        self.params.osc_b.set_routing(osc_b.routing);
+        self.params.osc_b.set_fm_ratio(osc_b.fm_ratio);
+        self.params.osc_b.set_fm_index(osc_b.fm_index);
+
+        // サブオシレータ
+        let mut sub_osc = if let Ok(settings) = self.params.sub_osc.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+        ui.horizontal(|ui| {
+            ui.label("Sub");
+            egui::ComboBox::from_id_source("sub_osc_waveform")
+                .selected_text(format!("{:?}", sub_osc.waveform))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut sub_osc.waveform, SubWaveform::Sine, "Sine");
+                    ui.selectable_value(&mut sub_osc.waveform, SubWaveform::Square, "Square");
+                });
+            ui.selectable_value(&mut sub_osc.octave, 1, "-1 Oct");
+            ui.selectable_value(&mut sub_osc.octave, 2, "-2 Oct");
+        });
+        self.params.sub_osc.set_waveform(sub_osc.waveform);
+        self.params.sub_osc.set_octave(sub_osc.octave);
+
+        // ノイズ源
+        let mut noise = if let Ok(settings) = self.params.noise.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+        ui.horizontal(|ui| {
+            ui.label("Noise");
+            egui::ComboBox::from_id_source("noise_type")
+                .selected_text(format!("{:?}", noise.noise_type))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut noise.noise_type, NoiseType::White, "White");
+                    ui.selectable_value(&mut noise.noise_type, NoiseType::Pink, "Pink");
+                    ui.selectable_value(&mut noise.noise_type, NoiseType::Brown, "Brown");
+                });
+        });
+        self.params.noise.set_noise_type(noise.noise_type);
+
+        // ドリフト（ボイスごとにピッチと音量をゆっくり揺らしてアナログVCOの不安定さを真似る）
+        let mut drift = if let Ok(settings) = self.params.drift.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+        let response = ui.add(egui::Slider::new(&mut drift.amount, 0.0..=1.0).text("Analog Drift"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::Drift);
+        self.params.drift.set_amount(drift.amount);
+
+        // 生成方式・オーバーサンプリングなどの品質設定
+        egui::CollapsingHeader::new("Quality").show(ui, |ui| {
+            let mut osc_settings = if let Ok(settings) = self.params.oscillator.get_settings().lock() {
+                *settings
+            } else {
+                Default::default()
+            };
+
+            // 生成方式の選択コンボボックス（PolyBLEP / Table / Raw）
+            egui::ComboBox::from_label("Mode")
+                .selected_text(match osc_settings.mode {
+                    OscillatorMode::Raw => "Raw",
+                    OscillatorMode::PolyBlep => "PolyBLEP",
+                    OscillatorMode::Table => "Table",
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut osc_settings.mode, OscillatorMode::PolyBlep, "PolyBLEP");
+                    ui.selectable_value(&mut osc_settings.mode, OscillatorMode::Table, "Table");
+                    ui.selectable_value(&mut osc_settings.mode, OscillatorMode::Raw, "Raw");
+                });
+
+            // オーバーサンプリング倍率（2分の1ずつ間引くので2のべき乗から選ぶ、Tableモードでは使わない）
+            ui.add_enabled_ui(osc_settings.mode != OscillatorMode::Table, |ui| {
+                egui::ComboBox::from_label("Oversampling")
+                    .selected_text(format!("{}x", osc_settings.oversample_ratio))
+                    .show_ui(ui, |ui| {
+                        for ratio in [1, 2, 4, 8, 16] {
+                            ui.selectable_value(&mut osc_settings.oversample_ratio, ratio, format!("{}x", ratio));
+                        }
+                    });
+            });
+
+            // フィルター・スムージングのスライダー
+            ui.add(egui::Slider::new(&mut osc_settings.filter_alpha, 0.0..=0.5).text("Filter"));
+            ui.add(egui::Slider::new(&mut osc_settings.smoothing_strength, 0.0..=0.5).text("Smoothing"));
+
+            self.params.oscillator.set_mode(osc_settings.mode);
+            self.params.oscillator.set_oversample_ratio(osc_settings.oversample_ratio);
+            self.params.oscillator.set_filter_alpha(osc_settings.filter_alpha);
+            self.params.oscillator.set_smoothing_strength(osc_settings.smoothing_strength);
+        });
+    }
+
+    /// 発音モード・ノート優先順位・グライドの設定を描画する
+    fn voice_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Voice");
+
+        let mut voice_settings = if let Ok(settings) = self.params.voice.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+        let previous_mode = voice_settings.mode;
+
+        egui::ComboBox::from_label("Voice Mode")
+            .selected_text(format!("{:?}", voice_settings.mode))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut voice_settings.mode, VoiceMode::Poly, "Poly");
+                ui.selectable_value(&mut voice_settings.mode, VoiceMode::Mono, "Mono");
+                ui.selectable_value(&mut voice_settings.mode, VoiceMode::Legato, "Legato");
+                ui.selectable_value(&mut voice_settings.mode, VoiceMode::Paraphonic, "Paraphonic");
+            });
+
+        if voice_settings.is_mono() {
+            egui::ComboBox::from_label("Note Priority")
+                .selected_text(format!("{:?}", voice_settings.priority))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut voice_settings.priority, NotePriority::Last, "Last");
+                    ui.selectable_value(&mut voice_settings.priority, NotePriority::High, "High");
+                    ui.selectable_value(&mut voice_settings.priority, NotePriority::Low, "Low");
+                });
+            let response = ui.add(
+                egui::Slider::new(&mut voice_settings.glide_time, 0.0..=MAX_GLIDE_TIME)
+                    .logarithmic(true)
+                    .text(if voice_settings.glide_rate == GlideRate::ConstantRate { "Glide (s/oct)" } else { "Glide (s)" }),
+            );
+            midi_learn(ui, response, &self.params.midi_map, ParamId::GlideTime);
+            ui.horizontal(|ui| {
+                // 押したまま弾いた時だけグライドするか、毎回グライドするか
+                ui.selectable_value(&mut voice_settings.glide_mode, GlideMode::Always, "Always");
+                ui.selectable_value(&mut voice_settings.glide_mode, GlideMode::Fingered, "Fingered");
+                ui.separator();
+                // 一定時間で到達するか、一定の速さで動くか
+                ui.selectable_value(&mut voice_settings.glide_rate, GlideRate::ConstantTime, "Constant Time");
+                ui.selectable_value(&mut voice_settings.glide_rate, GlideRate::ConstantRate, "Constant Rate");
+            });
+        } else {
+            let response = ui.add(egui::Slider::new(&mut voice_settings.polyphony, 1..=MAX_VOICES as u8).text("Polyphony"));
+            midi_learn(ui, response, &self.params.midi_map, ParamId::Polyphony);
+            // 同時発音数を超えた時に奪うボイス
+            egui::ComboBox::from_label("Voice Stealing")
+                .selected_text(format!("{:?}", voice_settings.steal))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut voice_settings.steal, StealPolicy::Oldest, "Oldest");
+                    ui.selectable_value(&mut voice_settings.steal, StealPolicy::Newest, "Newest");
+                    ui.selectable_value(&mut voice_settings.steal, StealPolicy::Quietest, "Quietest");
+                    ui.selectable_value(&mut voice_settings.steal, StealPolicy::Lowest, "Lowest");
+                });
+        }
+
+        self.params.voice.set_mode(voice_settings.mode);
+        self.params.voice.set_priority(voice_settings.priority);
+        self.params.voice.set_polyphony(voice_settings.polyphony);
+        self.params.voice.set_steal(voice_settings.steal);
+        self.params.voice.set_glide_time(voice_settings.glide_time);
+        self.params.voice.set_glide_mode(voice_settings.glide_mode);
+        self.params.voice.set_glide_rate(voice_settings.glide_rate);
+
+        // MPE（チャンネル2〜16をノートごとに使うコントローラー向け）
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut voice_settings.mpe, "MPE Mode");
+            if voice_settings.mpe {
+                let response = ui.add(egui::Slider::new(&mut voice_settings.mpe_bend_range, 1.0..=MAX_MPE_BEND_RANGE).text("Note Bend Range (st)"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::MpeBendRange);
+            }
+        });
+        self.params.voice.set_mpe(voice_settings.mpe);
+        self.params.voice.set_mpe_bend_range(voice_settings.mpe_bend_range);
+
+        // モードを切り替えたら鳴っているノートをリリースする（ボイスの割り当て方が変わるため）
+        if voice_settings.mode != previous_mode {
+            self.note_handler.all_notes_off();
+        }
+    }
+
+    /// キーボードスプリットとレイヤーを描画する（鍵盤の範囲ごとに鳴らす音色を選ぶ）
+    ///
+    /// メインのレイヤーは各パネルの音色で鳴らし、追加のレイヤーはここで波形・フィルター・エンベロープを編集する。
+    /// どのレイヤーもベロシティで切り替える硬い音色を持てる（メインのレイヤーはゾーンを使わない時も使える）
+    fn zone_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Key Zones");
+
+        let mut zone_settings = if let Ok(settings) = self.params.zones.get_settings().lock() {
+            settings.clone()
+        } else {
+            Default::default()
+        };
+        let main_patch = LayerPatch::capture(&self.params);
+
+        ui.push_id("main_velocity_layer", |ui| {
+            velocity_layer_ui(ui, "Main", &mut zone_settings.main_velocity, &main_patch, self.custom_wavetable.as_ref());
+        });
+        ui.checkbox(&mut zone_settings.enabled, "Enable Split / Layers");
+        if zone_settings.enabled {
+            key_range_ui(ui, "Main", &mut zone_settings.main_range);
+            for (index, zone) in zone_settings.layers.iter_mut().enumerate() {
+                ui.push_id(("zone_layer", index), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut zone.enabled, format!("Layer {}", index + 1));
+                        // メインの今の音色をコピーして、そこから作り始められるようにする
+                        if ui.button("Copy Main Sound").clicked() {
+                            zone.patch = main_patch.clone();
+                        }
+                    });
+                    if !zone.enabled {
+                        return;
+                    }
+                    key_range_ui(ui, "Keys", &mut zone.range);
+                    layer_patch_ui(ui, &mut zone.patch, self.custom_wavetable.as_ref());
+                    velocity_layer_ui(ui, &format!("Layer {}", index + 1), &mut zone.velocity, &zone.patch, self.custom_wavetable.as_ref());
+                });
+            }
+        }
+
+        self.params.zones.set_enabled(zone_settings.enabled);
+        self.params.zones.set_main_range(zone_settings.main_range);
+        self.params.zones.set_main_velocity(zone_settings.main_velocity);
+        for (index, zone) in zone_settings.layers.into_iter().enumerate() {
+            self.params.zones.set_layer_enabled(index, zone.enabled);
+            self.params.zones.set_layer_range(index, zone.range);
+            self.params.zones.set_layer_patch(index, zone.patch);
+            self.params.zones.set_layer_velocity(index, zone.velocity);
+        }
+    }
+
+    /// MIDIチャンネルごとの追加のパート（マルチティンバー）を描画する
+    ///
+    /// メインのパートは各パネルで編集する音色で、追加のパートにはプリセットを読み込んで使う
+    fn parts_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Multi-Timbral Parts");
+        ui.weak("Part 1 is the main sound. Channels assigned to an enabled part are not sent to Part 1.");
+
+        let mut part_settings = if let Ok(settings) = self.params.parts.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+
+        let mut load = None;
+        for (index, part) in part_settings.parts.iter_mut().enumerate() {
+            ui.push_id(("multi_part", index), |ui| {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut part.enabled, format!("Part {}", index + 2));
+                    egui::ComboBox::from_id_source("part_channel")
+                        .selected_text(format!("Ch {}", part.channel + 1))
+                        .show_ui(ui, |ui| {
+                            for channel in 0..16 {
+                                ui.selectable_value(&mut part.channel, channel, format!("Ch {}", channel + 1));
+                            }
+                        });
+                    // プリセットブラウザーのリストから音色を選ぶ
+                    egui::ComboBox::from_id_source("part_preset")
+                        .selected_text(self.part_preset_names[index].as_str())
+                        .show_ui(ui, |ui| {
+                            for entry in &self.presets {
+                                if ui.selectable_label(false, entry.name.as_str()).clicked() {
+                                    load = Some((index, entry.path.clone()));
+                                }
+                            }
+                        });
+                });
+                if part.enabled {
+                    ui.add(egui::Slider::new(&mut part.volume, 0.0..=MAX_PART_VOLUME).text("Volume"));
+                    ui.add(egui::Slider::new(&mut part.pan, -1.0..=1.0).text("Pan"));
+                }
+            });
+        }
+        if let Some((index, path)) = load {
+            let name = preset::preset_display_name(&path);
+            match self.load_part_preset(index, path) {
+                Some(skipped) if !skipped.is_empty() => {
+                    self.show_toast(with_skipped_fields(format!("Loaded preset: {}", name), &skipped));
+                }
+                Some(_) => {}
+                None => self.show_toast(format!("Failed to load preset: {}", name)),
+            }
+        }
+
+        for (index, (part, settings)) in self.parts.iter().zip(part_settings.parts.iter()).enumerate() {
+            // 止めたパートは生成しなくなるのですぐに消音し、受信チャンネルを変えたパートのノートはリリースする
+            if let Ok(current) = self.params.parts.get_settings().lock() {
+                let current = current.parts[index];
+                if current.enabled && !settings.enabled {
+                    part.notes.all_sound_off();
+                } else if current.channel != settings.channel {
+                    part.notes.all_notes_off();
+                }
+            }
+            self.params.parts.set_enabled(index, settings.enabled);
+            self.params.parts.set_channel(index, settings.channel);
+            self.params.parts.set_volume(index, settings.volume);
+            self.params.parts.set_pan(index, settings.pan);
+        }
+    }
+
+    /// テンポとクロックの再生・停止を描画する
+    fn transport_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Transport");
+
+        let mut transport_settings = self.params.transport.settings();
+        ui.checkbox(&mut transport_settings.external_sync, "Follow MIDI Clock");
+        if transport_settings.external_sync {
+            // 外部同期中はテンポと再生・停止を受信したクロックに任せる
+            let state = if self.params.transport.is_playing() { "playing" } else { "stopped" };
+            ui.label(format!("MIDI Clock: {:.1} BPM ({})", self.params.transport.tempo(), state));
+        } else {
+            ui.horizontal(|ui| {
+                let response = ui.add(egui::Slider::new(&mut transport_settings.bpm, MIN_BPM..=MAX_BPM).text("BPM"));
+                midi_learn(ui, response, &self.params.midi_map, ParamId::Tempo);
+                let label = if transport_settings.running { "Stop" } else { "Run" };
+                if ui.button(label).clicked() {
+                    transport_settings.running = !transport_settings.running;
+                }
+                if ui.button("Rewind").clicked() {
+                    self.params.transport.rewind();
+                }
+            });
+        }
+
+        // スウィング（アルペジエーターとテンポ同期したLFOの区切りを、組の2つ目ごとに遅らせる）
+        ui.horizontal(|ui| {
+            let response = ui.add(egui::Slider::new(&mut transport_settings.swing, MIN_SWING..=MAX_SWING).text("Swing (%)"));
+            midi_learn(ui, response, &self.params.midi_map, ParamId::Swing);
+            division_combo(ui, "Swing Grid", &mut transport_settings.swing_division);
+        });
+
+        // 再生位置（小節.拍）
+        let position = self.params.transport.position();
+        let beat = position.floor() as u64;
+        ui.label(format!(
+            "Position: {}.{}",
+            beat / BEATS_PER_BAR as u64 + 1,
+            beat % BEATS_PER_BAR as u64 + 1
+        ));
+
+        self.params.transport.set_bpm(transport_settings.bpm);
+        self.params.transport.set_running(transport_settings.running);
+        self.params.transport.set_external_sync(transport_settings.external_sync);
+        self.params.transport.set_swing(transport_settings.swing);
+        self.params.transport.set_swing_division(transport_settings.swing_division);
+    }
+
+    /// アルペジエーターの設定を描画する
+    fn arp_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Arpeggiator");
+
+        let mut arp_settings = if let Ok(settings) = self.params.arp.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+        let was_enabled = arp_settings.enabled;
+
+        ui.checkbox(&mut arp_settings.enabled, "Enable Arpeggiator");
+        egui::ComboBox::from_label("Pattern")
+            .selected_text(format!("{:?}", arp_settings.pattern))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut arp_settings.pattern, ArpPattern::Up, "Up");
+                ui.selectable_value(&mut arp_settings.pattern, ArpPattern::Down, "Down");
+                ui.selectable_value(&mut arp_settings.pattern, ArpPattern::UpDown, "UpDown");
+                ui.selectable_value(&mut arp_settings.pattern, ArpPattern::Random, "Random");
+            });
+
+        ui.checkbox(&mut arp_settings.sync, "Sync to Tempo");
+        if arp_settings.sync {
+            division_combo(ui, "Division", &mut arp_settings.division);
+        } else {
+            let response = ui.add(egui::Slider::new(&mut arp_settings.rate, 0.5..=20.0).logarithmic(true).text("Rate (Hz)"));
+            midi_learn(ui, response, &self.params.midi_map, ParamId::ArpRate);
+        }
+        let response = ui.add(egui::Slider::new(&mut arp_settings.gate, 0.05..=1.0).text("Gate"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::ArpGate);
+        let response = ui.add(egui::Slider::new(&mut arp_settings.octaves, 1..=MAX_ARP_OCTAVES).text("Octaves"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::ArpOctaves);
+
+        // ヒューマナイズ（ステップごとにタイミングとベロシティを揺らす）
+        let response = ui.add(egui::Slider::new(&mut arp_settings.humanize_time, 0.0..=MAX_HUMANIZE_TIME).text("Humanize Timing (± ms)"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::ArpHumanizeTime);
+        let response = ui.add(egui::Slider::new(&mut arp_settings.humanize_velocity, 0.0..=1.0).text("Humanize Velocity (±)"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::ArpHumanizeVelocity);
+
+        // ユークリッドリズム（発音するステップを均等に散らし、休みのステップでは鳴らさない）
+        let rhythm = &mut arp_settings.rhythm;
+        ui.checkbox(&mut rhythm.enabled, "Euclidean Rhythm");
+        if rhythm.enabled {
+            ui.add(egui::Slider::new(&mut rhythm.steps, 1..=MAX_EUCLID_STEPS).text("Steps"));
+            let steps = rhythm.steps;
+            ui.add(egui::Slider::new(&mut rhythm.pulses, 0..=steps).text("Pulses"));
+            ui.add(egui::Slider::new(&mut rhythm.rotation, 0..=steps.saturating_sub(1)).text("Rotation"));
+            let pattern: String = rhythm.pattern().iter().map(|&pulse| if pulse { '●' } else { '○' }).collect();
+            ui.monospace(pattern);
+        }
+
+        self.params.arp.set_pattern(arp_settings.pattern);
+        self.params.arp.set_sync(arp_settings.sync);
+        self.params.arp.set_division(arp_settings.division);
+        self.params.arp.set_rate(arp_settings.rate);
+        self.params.arp.set_gate(arp_settings.gate);
+        self.params.arp.set_octaves(arp_settings.octaves);
+        self.params.arp.set_rhythm(arp_settings.rhythm);
+        self.params.arp.set_humanize_time(arp_settings.humanize_time);
+        self.params.arp.set_humanize_velocity(arp_settings.humanize_velocity);
+
+        // 切り替えた時は鳴っているノートを止める（鍵盤の受け渡し先が変わるため）
+        if arp_settings.enabled != was_enabled {
+            self.note_handler.all_notes_off();
+            self.params.arp.set_enabled(arp_settings.enabled);
+        }
+    }
+
+    /// スケールロックの設定を描画する（次に弾いたノートから反映される）
+    fn scale_lock_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Scale Lock");
+
+        let mut scale_settings = if let Ok(settings) = self.params.scale_lock.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+
+        ui.checkbox(&mut scale_settings.enabled, "Snap Notes to Scale");
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Root")
+                .selected_text(PITCH_CLASS_NAMES[scale_settings.root as usize % 12])
+                .show_ui(ui, |ui| {
+                    for (root, name) in PITCH_CLASS_NAMES.iter().enumerate() {
+                        ui.selectable_value(&mut scale_settings.root, root as u8, *name);
+                    }
+                });
+            egui::ComboBox::from_label("Scale")
+                .selected_text(scale_settings.scale.label())
+                .show_ui(ui, |ui| {
+                    for scale in ScaleKind::ALL {
+                        ui.selectable_value(&mut scale_settings.scale, scale, scale.label());
+                    }
+                });
+        });
+        // スケールに含まれる音（カスタムでは押して選ぶ、ルートからの半音ごと）
+        let mask = scale_settings.mask();
+        ui.horizontal(|ui| {
+            for degree in 0..12 {
+                let name = PITCH_CLASS_NAMES[(scale_settings.root as usize + degree) % 12];
+                let response = ui.add_enabled(
+                    scale_settings.scale == ScaleKind::Custom,
+                    egui::SelectableLabel::new(mask & 1 << degree != 0, name),
+                );
+                if response.clicked() {
+                    scale_settings.custom ^= 1 << degree;
+                }
+            }
+        });
+
+        self.params.scale_lock.set_enabled(scale_settings.enabled);
+        self.params.scale_lock.set_root(scale_settings.root);
+        self.params.scale_lock.set_scale(scale_settings.scale);
+        self.params.scale_lock.set_custom(scale_settings.custom);
+    }
+
+    /// コードモードの設定を描画する（一覧から選ぶか、Learn を押してから和音を弾いて覚えさせる）
+    fn chord_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Chord Mode");
+
+        let mut chord_settings = if let Ok(settings) = self.params.chord.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+
+        let learned_intervals = chord_settings.intervals;
+
+        ui.checkbox(&mut chord_settings.enabled, "Play Chord from One Note");
+        ui.horizontal(|ui| {
+            let shape = ChordShape::from_intervals(chord_settings.intervals);
+            egui::ComboBox::from_label("Chord")
+                .selected_text(shape.map_or("Learned", ChordShape::label))
+                .show_ui(ui, |ui| {
+                    for shape in ChordShape::ALL {
+                        ui.selectable_value(&mut chord_settings.intervals, shape.intervals(), shape.label());
+                    }
+                });
+            // 覚えている間は全ての鍵盤を離した時に押されていた和音を覚える
+            if self.params.chord.is_learning() {
+                if ui.button("Cancel Learn").clicked() {
+                    self.params.chord.cancel_learning();
+                }
+                ui.colored_label(egui::Color32::YELLOW, "Play a chord...");
+                ui.ctx().request_repaint_after(Duration::from_millis(100));
+            } else if ui.button("Learn").clicked() {
+                self.params.chord.start_learning();
+            }
+        });
+        let intervals: Vec<String> = chord_settings.interval_list().iter().map(u8::to_string).collect();
+        ui.label(format!("Intervals: {} st", intervals.join(" ")));
+
+        // 描画中に覚えたコードを上書きしないように、一覧から選んだ時だけ設定する
+        self.params.chord.set_enabled(chord_settings.enabled);
+        if chord_settings.intervals != learned_intervals {
+            self.params.chord.set_intervals(chord_settings.intervals);
+        }
+    }
+
+    /// ストラムの設定を描画する（同時に弾いたノートとコードモードのコードの鳴り始めをずらす）
+    fn strum_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Strum");
+
+        let mut strum_settings = if let Ok(settings) = self.params.strum.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+
+        ui.checkbox(&mut strum_settings.enabled, "Strum Chords");
+        let response = ui.add(egui::Slider::new(&mut strum_settings.time, 0.0..=MAX_STRUM_TIME).text("Strum Time (s/note)"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::StrumTime);
+        ui.horizontal(|ui| {
+            ui.label("Direction:");
+            ui.selectable_value(&mut strum_settings.direction, StrumDirection::Up, "Up (low → high)");
+            ui.selectable_value(&mut strum_settings.direction, StrumDirection::Down, "Down (high → low)");
+        });
+
+        self.params.strum.set_enabled(strum_settings.enabled);
+        self.params.strum.set_time(strum_settings.time);
+        self.params.strum.set_direction(strum_settings.direction);
+    }
+
+    /// マクロツマミと、それぞれの割り当て先を描画する
+    fn macro_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Macros");
+
+        let macro_settings = if let Ok(settings) = self.params.macros.get_settings().lock() {
+            settings.clone()
+        } else {
+            Default::default()
+        };
+
+        for (index, slot) in macro_settings.slots.iter().enumerate() {
+            let param = ParamId::Macro(index as u8);
+            let mut value = slot.value;
+            let response = ui.add(egui::Slider::new(&mut value, 0.0..=1.0).text(param.label()));
+            if response.changed() {
+                self.params.macros.set_value(index, value, &self.params);
+            }
+            midi_learn(ui, response, &self.params.midi_map, param);
+
+            egui::CollapsingHeader::new(format!("{} Assignments ({})", param.label(), slot.assignments.len()))
+                .id_source(("macro_assignments", index))
+                .show(ui, |ui| {
+                    for (assignment_index, assignment) in slot.assignments.iter().enumerate() {
+                        let mut assignment = *assignment;
+                        let mut removed = false;
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_source(("macro_param", index, assignment_index))
+                                .selected_text(assignment.param.label())
+                                .show_ui(ui, |ui| {
+                                    for candidate in ParamId::ALL {
+                                        ui.selectable_value(&mut assignment.param, candidate, candidate.label());
+                                    }
+                                });
+                            ui.add(egui::DragValue::new(&mut assignment.min).clamp_range(0.0..=1.0).speed(0.01).prefix("Min "));
+                            ui.add(egui::DragValue::new(&mut assignment.max).clamp_range(0.0..=1.0).speed(0.01).prefix("Max "));
+                            ui.checkbox(&mut assignment.inverted, "Invert");
+                            removed = ui.button("Remove").clicked();
+                        });
+                        if removed {
+                            self.params.macros.unassign(index, assignment_index);
+                            break;
+                        }
+                        self.params.macros.set_assignment(index, assignment_index, assignment);
+                    }
+
+                    // まだ割り当てていないパラメータだけ追加できる
+                    egui::ComboBox::from_id_source(("macro_assign", index))
+                        .selected_text("Assign parameter")
+                        .show_ui(ui, |ui| {
+                            for candidate in ParamId::ALL {
+                                if slot.assignments.iter().any(|assignment| assignment.param == candidate) {
+                                    continue;
+                                }
+                                if ui.selectable_label(false, candidate.label()).clicked() {
+                                    self.params.macros.assign(index, candidate);
+                                }
+                            }
+                        });
+                });
+        }
+    }
+
+    /// XYパッドと、横・縦の軸に割り当てるパラメータを描画する
+    fn xy_pad_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("XY Pad");
+
+        let mut xy_settings = if let Ok(settings) = self.params.xy_pad.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+
+        for (label, param) in [("X Axis", &mut xy_settings.x_param), ("Y Axis", &mut xy_settings.y_param)] {
+            egui::ComboBox::from_label(label)
+                .selected_text(param.label())
+                .show_ui(ui, |ui| {
+                    for candidate in ParamId::ALL {
+                        ui.selectable_value(param, candidate, candidate.label());
+                    }
+                });
+        }
+        self.params.xy_pad.set_params(xy_settings.x_param, xy_settings.y_param);
+
+        // パラメータへの反映はオーディオスレッドが滑らかに行う
+        if xy_pad(ui, &mut xy_settings.x, &mut xy_settings.y) {
+            self.params.xy_pad.set_position(xy_settings.x, xy_settings.y);
+        }
+    }
+
+    /// モジュレーションマトリクスのスロットを表形式で描画する
+    fn mod_matrix_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Mod Matrix");
+
+        let mut mod_settings = if let Ok(settings) = self.params.mod_matrix.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+
+        egui::Grid::new("mod_matrix_grid").striped(true).show(ui, |ui| {
+            ui.label("#");
+            ui.label("Source");
+            ui.label("Destination");
+            ui.label("Amount");
+            ui.end_row();
+
+            for (slot, route) in mod_settings.routes.iter_mut().enumerate() {
+                ui.label(format!("{}", slot + 1));
+                egui::ComboBox::from_id_source(("mod_source", slot))
+                    .selected_text(format!("{:?}", route.source))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut route.source, ModSource::None, "None");
+                        ui.selectable_value(&mut route.source, ModSource::Lfo, "Lfo");
+                        ui.selectable_value(&mut route.source, ModSource::Envelope, "Envelope");
+                        ui.selectable_value(&mut route.source, ModSource::Velocity, "Velocity");
+                        ui.selectable_value(&mut route.source, ModSource::ModWheel, "ModWheel");
+                        ui.selectable_value(&mut route.source, ModSource::Aftertouch, "Aftertouch");
+                        ui.selectable_value(&mut route.source, ModSource::PolyAftertouch, "PolyAftertouch");
+                        ui.selectable_value(&mut route.source, ModSource::Slide, "Slide (MPE)");
+                    });
+                egui::ComboBox::from_id_source(("mod_destination", slot))
+                    .selected_text(format!("{:?}", route.destination))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut route.destination, ModDestination::Pitch, "Pitch");
+                        ui.selectable_value(&mut route.destination, ModDestination::Amplitude, "Amplitude");
+                        ui.selectable_value(&mut route.destination, ModDestination::FilterCutoff, "FilterCutoff");
+                        ui.selectable_value(&mut route.destination, ModDestination::Detune, "Detune");
+                    });
+                ui.add(egui::Slider::new(&mut route.amount, -1.0..=1.0));
+                ui.end_row();
+            }
+        });
+
+        for (slot, route) in mod_settings.routes.iter().enumerate() {
+            self.params.mod_matrix.set_route(slot, *route);
+        }
+    }
+
+    /// エフェクトチェーンを描画する（上から順にかかる）
+    fn effects_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Effects");
+
+        let chain = self.params.effects.get_chain();
+        let count = chain.slots.len();
+        for (index, slot) in chain.slots.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{}. {}", index + 1, slot.kind.label()));
+                let mut bypassed = slot.bypassed;
+                if ui.checkbox(&mut bypassed, "Bypass").changed() {
+                    self.params.effects.set_bypassed(index, bypassed);
+                }
+                if ui.add_enabled(index > 0, egui::Button::new("↑")).clicked() {
+                    self.params.effects.move_slot(index, index - 1);
+                }
+                if ui.add_enabled(index + 1 < count, egui::Button::new("↓")).clicked() {
+                    self.params.effects.move_slot(index, index + 1);
+                }
+                if ui.button("Remove").clicked() {
+                    self.params.effects.remove(index);
+                }
+            });
+        }
+
+        // チェーンに入っていない種類だけ追加できる
+        let available: Vec<EffectKind> = EffectKind::ALL
+            .iter()
+            .copied()
+            .filter(|kind| !chain.slots.iter().any(|slot| slot.kind == *kind))
+            .collect();
+        if !available.is_empty() {
+            egui::ComboBox::from_id_source("add_effect")
+                .selected_text("Add effect")
+                .show_ui(ui, |ui| {
+                    for kind in available {
+                        if ui.selectable_label(false, kind.label()).clicked() {
+                            self.params.effects.add(kind);
+                        }
+                    }
+                });
+        }
+    }
+
+    /// コーラスの設定を描画する
+    fn chorus_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Chorus");
+
+        let mut chorus_settings = if let Ok(settings) = self.params.chorus.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+
+        let response = ui.add(egui::Slider::new(&mut chorus_settings.voices, 2..=MAX_CHORUS_VOICES).text("Voices"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::ChorusVoices);
+        let response = ui.add(egui::Slider::new(&mut chorus_settings.rate, 0.05..=5.0).logarithmic(true).text("Rate (Hz)"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::ChorusRate);
+        let response = ui.add(egui::Slider::new(&mut chorus_settings.depth, 0.0..=1.0).text("Depth"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::ChorusDepth);
+        let response = ui.add(egui::Slider::new(&mut chorus_settings.mix, 0.0..=1.0).text("Mix"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::ChorusMix);
+
+        self.params.chorus.set_voices(chorus_settings.voices);
+        self.params.chorus.set_rate(chorus_settings.rate);
+        self.params.chorus.set_depth(chorus_settings.depth);
+        self.params.chorus.set_mix(chorus_settings.mix);
+    }
+
+    /// リバーブの設定を描画する
+    fn reverb_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Reverb");
+
+        let mut reverb_settings = if let Ok(settings) = self.params.reverb.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+
+        let response = ui.add(egui::Slider::new(&mut reverb_settings.room_size, 0.0..=1.0).text("Room Size"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::ReverbRoomSize);
+        let response = ui.add(egui::Slider::new(&mut reverb_settings.damping, 0.0..=1.0).text("Damping"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::ReverbDamping);
+        let response = ui.add(egui::Slider::new(&mut reverb_settings.mix, 0.0..=1.0).text("Mix"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::ReverbMix);
+
+        self.params.reverb.set_room_size(reverb_settings.room_size);
+        self.params.reverb.set_damping(reverb_settings.damping);
+        self.params.reverb.set_mix(reverb_settings.mix);
+    }
+
+    /// ディストーションの設定を描画する
+    fn distortion_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Distortion");
+
+        let mut distortion_settings = if let Ok(settings) = self.params.distortion.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+
+        egui::ComboBox::from_label("Algorithm")
+            .selected_text(format!("{:?}", distortion_settings.algorithm))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut distortion_settings.algorithm, DistortionAlgorithm::SoftClip, "SoftClip");
+                ui.selectable_value(&mut distortion_settings.algorithm, DistortionAlgorithm::HardClip, "HardClip");
+                ui.selectable_value(&mut distortion_settings.algorithm, DistortionAlgorithm::Tanh, "Tanh");
+                ui.selectable_value(&mut distortion_settings.algorithm, DistortionAlgorithm::Foldback, "Foldback");
+            });
+        let response = ui.add(egui::Slider::new(&mut distortion_settings.drive, 0.0..=40.0).text("Drive (dB)"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::DistortionDrive);
+        let response = ui.add(egui::Slider::new(&mut distortion_settings.tone, 0.0..=1.0).text("Tone"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::DistortionTone);
+        let response = ui.add(egui::Slider::new(&mut distortion_settings.output, -24.0..=12.0).text("Output (dB)"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::DistortionOutput);
+
+        self.params.distortion.set_algorithm(distortion_settings.algorithm);
+        self.params.distortion.set_drive(distortion_settings.drive);
+        self.params.distortion.set_tone(distortion_settings.tone);
+        self.params.distortion.set_output(distortion_settings.output);
+    }
+
+    /// ビットクラッシャーの設定を描画する
+    fn bitcrusher_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Bitcrusher");
+
+        let mut bitcrusher_settings = if let Ok(settings) = self.params.bitcrusher.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+
+        let response = ui.add(egui::Slider::new(&mut bitcrusher_settings.bits, MIN_BITS..=MAX_BITS).text("Bits"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::BitcrusherBits);
+        let response = ui.add(
+            egui::Slider::new(&mut bitcrusher_settings.rate, MIN_CRUSH_RATE..=MAX_CRUSH_RATE)
+                .logarithmic(true)
+                .text("Rate (Hz)"),
+        );
+        midi_learn(ui, response, &self.params.midi_map, ParamId::BitcrusherRate);
+        ui.checkbox(&mut bitcrusher_settings.interpolate, "Interpolate");
+        let response = ui.add(egui::Slider::new(&mut bitcrusher_settings.mix, 0.0..=1.0).text("Mix"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::BitcrusherMix);
+
+        self.params.bitcrusher.set_bits(bitcrusher_settings.bits);
+        self.params.bitcrusher.set_rate(bitcrusher_settings.rate);
+        self.params.bitcrusher.set_interpolate(bitcrusher_settings.interpolate);
+        self.params.bitcrusher.set_mix(bitcrusher_settings.mix);
+    }
+
+    /// フェイザーの設定を描画する
+    fn phaser_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Phaser");
+
+        let mut phaser_settings = if let Ok(settings) = self.params.phaser.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+
+        let response = ui.add(egui::Slider::new(&mut phaser_settings.stages, MIN_PHASER_STAGES..=MAX_PHASER_STAGES).text("Stages"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::PhaserStages);
+        let response = ui.add(egui::Slider::new(&mut phaser_settings.rate, 0.05..=5.0).logarithmic(true).text("Rate (Hz)"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::PhaserRate);
+        let response = ui.add(egui::Slider::new(&mut phaser_settings.depth, 0.0..=1.0).text("Depth"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::PhaserDepth);
+        let response = ui.add(egui::Slider::new(&mut phaser_settings.feedback, -0.9..=0.9).text("Feedback"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::PhaserFeedback);
+        let response = ui.add(egui::Slider::new(&mut phaser_settings.mix, 0.0..=1.0).text("Mix"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::PhaserMix);
+
+        self.params.phaser.set_stages(phaser_settings.stages);
+        self.params.phaser.set_rate(phaser_settings.rate);
+        self.params.phaser.set_depth(phaser_settings.depth);
+        self.params.phaser.set_feedback(phaser_settings.feedback);
+        self.params.phaser.set_mix(phaser_settings.mix);
+    }
+
+    /// フランジャーの設定を描画する
+    fn flanger_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Flanger");
+
+        let mut flanger_settings = if let Ok(settings) = self.params.flanger.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+
+        let response = ui.add(egui::Slider::new(&mut flanger_settings.rate, 0.05..=5.0).logarithmic(true).text("Rate (Hz)"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::FlangerRate);
+        let response = ui.add(egui::Slider::new(&mut flanger_settings.depth, 0.0..=1.0).text("Depth"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::FlangerDepth);
+        let response = ui.add(egui::Slider::new(&mut flanger_settings.feedback, -0.95..=0.95).text("Feedback"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::FlangerFeedback);
+        let response = ui.add(egui::Slider::new(&mut flanger_settings.mix, 0.0..=1.0).text("Mix"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::FlangerMix);
+
+        self.params.flanger.set_rate(flanger_settings.rate);
+        self.params.flanger.set_depth(flanger_settings.depth);
+        self.params.flanger.set_feedback(flanger_settings.feedback);
+        self.params.flanger.set_mix(flanger_settings.mix);
+    }
+
+    /// ディレイの設定を描画する
+    fn delay_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Delay");
+
+        let mut delay_settings = if let Ok(settings) = self.params.delay.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+
+        ui.checkbox(&mut delay_settings.sync, "Sync to Tempo");
+        if delay_settings.sync {
+            division_combo(ui, "Delay Division", &mut delay_settings.division);
+        } else {
+            let response = ui.add(
+                egui::Slider::new(&mut delay_settings.time, MIN_DELAY_TIME..=MAX_DELAY_TIME)
+                    .logarithmic(true)
+                    .text("Time (ms)"),
+            );
+            midi_learn(ui, response, &self.params.midi_map, ParamId::DelayTime);
+        }
+        let response = ui.add(egui::Slider::new(&mut delay_settings.feedback, 0.0..=0.95).text("Feedback"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::DelayFeedback);
+        let response = ui.add(egui::Slider::new(&mut delay_settings.mix, 0.0..=1.0).text("Mix"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::DelayMix);
+
+        self.params.delay.set_sync(delay_settings.sync);
+        self.params.delay.set_division(delay_settings.division);
+        self.params.delay.set_time(delay_settings.time);
+        self.params.delay.set_feedback(delay_settings.feedback);
+        self.params.delay.set_mix(delay_settings.mix);
+    }
+
+    /// 音源ごとの音量と定位、マスターの音量と定位を描画する
+    fn mixer_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Mixer");
+
+        let mut mixer = if let Ok(settings) = self.params.mixer.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+        let mut sub_level = if let Ok(settings) = self.params.sub_osc.get_settings().lock() {
+            settings.level
+        } else {
+            0.0
+        };
+        let mut noise_level = if let Ok(settings) = self.params.noise.get_settings().lock() {
+            settings.level
+        } else {
+            0.0
+        };
+        let mut master_settings = if let Ok(settings) = self.params.master.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+        // AとBを組み合わせるルーティングでは、組み合わせた音をAのチャンネルに流す
+        let osc_b_mixed = if let Ok(settings) = self.params.osc_b.get_settings().lock() {
+            settings.routing == OscRouting::Mix
+        } else {
+            true
+        };
+
+        egui::Grid::new("mixer_grid").striped(true).show(ui, |ui| {
+            ui.label("Source");
+            ui.label("Level");
+            ui.label("Pan (L ↔ R)");
+            ui.end_row();
+
+            ui.label("Osc A");
+            let response = ui.add(egui::Slider::new(&mut mixer.osc_a.level, 0.0..=1.0));
+            midi_learn(ui, response, &self.params.midi_map, ParamId::OscALevel);
+            let response = ui.add(egui::Slider::new(&mut mixer.osc_a.pan, -1.0..=1.0));
+            midi_learn(ui, response, &self.params.midi_map, ParamId::OscAPan);
+            ui.end_row();
+
+            ui.label("Osc B");
+            let response = ui.add_enabled(osc_b_mixed, egui::Slider::new(&mut mixer.osc_b.level, 0.0..=1.0));
+            midi_learn(ui, response, &self.params.midi_map, ParamId::OscBLevel);
+            let response = ui.add_enabled(osc_b_mixed, egui::Slider::new(&mut mixer.osc_b.pan, -1.0..=1.0));
+            midi_learn(ui, response, &self.params.midi_map, ParamId::OscBPan);
+            ui.end_row();
+
+            ui.label("Sub");
+            let response = ui.add(egui::Slider::new(&mut sub_level, 0.0..=1.0));
+            midi_learn(ui, response, &self.params.midi_map, ParamId::SubLevel);
+            let response = ui.add(egui::Slider::new(&mut mixer.sub_pan, -1.0..=1.0));
+            midi_learn(ui, response, &self.params.midi_map, ParamId::SubPan);
+            ui.end_row();
+
+            ui.label("Noise");
+            let response = ui.add(egui::Slider::new(&mut noise_level, 0.0..=1.0));
+            midi_learn(ui, response, &self.params.midi_map, ParamId::NoiseLevel);
+            let response = ui.add(egui::Slider::new(&mut mixer.noise_pan, -1.0..=1.0));
+            midi_learn(ui, response, &self.params.midi_map, ParamId::NoisePan);
+            ui.end_row();
+
+            // マスター（音量はdB）
+            ui.strong("Master");
+            let response = ui.add(egui::Slider::new(&mut master_settings.gain_db, -60.0..=12.0).suffix(" dB"));
+            midi_learn(ui, response, &self.params.midi_map, ParamId::MasterVolume);
+            let response = ui.add(egui::Slider::new(&mut master_settings.pan, -1.0..=1.0));
+            midi_learn(ui, response, &self.params.midi_map, ParamId::MasterPan);
+            ui.end_row();
+        });
+
+        self.params.mixer.set_osc_a_level(mixer.osc_a.level);
+        self.params.mixer.set_osc_a_pan(mixer.osc_a.pan);
+        self.params.mixer.set_osc_b_level(mixer.osc_b.level);
+        self.params.mixer.set_osc_b_pan(mixer.osc_b.pan);
+        self.params.mixer.set_sub_pan(mixer.sub_pan);
+        self.params.mixer.set_noise_pan(mixer.noise_pan);
+        self.params.sub_osc.set_level(sub_level);
+        self.params.noise.set_level(noise_level);
+        self.params.master.set_gain_db(master_settings.gain_db);
+        self.params.master.set_pan(master_settings.pan);
+    }
+
+    /// リミッター・チューニング・トランスポーズ・コンプレッサーを描画する（音量と定位はミキサーにある）
+    fn master_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Master");
+
+        let mut master_settings = if let Ok(settings) = self.params.master.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+
+        ui.checkbox(&mut master_settings.limiter, "Soft Limiter");
+        self.params.master.set_limiter(master_settings.limiter);
+
+        // マスターチューニング（次に弾いたノートから反映される）
+        let mut tuning_settings = if let Ok(settings) = self.params.tuning.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+        let response = ui.add(egui::Slider::new(&mut tuning_settings.a4, MIN_A4..=MAX_A4).text("A4 (Hz)"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::MasterTune);
+        let response = ui.add(egui::Slider::new(&mut tuning_settings.fine, -MAX_FINE_TUNE..=MAX_FINE_TUNE).text("Fine Tune (cents)"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::FineTune);
+        self.params.tuning.set_a4(tuning_settings.a4);
+        self.params.tuning.set_fine(tuning_settings.fine);
+
+        // トランスポーズとオクターブシフト（押している鍵盤は押した時の高さのまま鳴らす）
+        let mut transpose = self.params.transpose.semitones();
+        ui.horizontal(|ui| {
+            if ui.button("Oct -").clicked() {
+                self.params.transpose.shift_octave(-1);
+            }
+            if ui.button("Oct +").clicked() {
+                self.params.transpose.shift_octave(1);
+            }
+            let response = ui.add(egui::Slider::new(&mut transpose, -MAX_TRANSPOSE..=MAX_TRANSPOSE).text("Transpose (st)"));
+            if response.changed() {
+                self.params.transpose.set_semitones(transpose);
+            }
+            midi_learn(ui, response, &self.params.midi_map, ParamId::Transpose);
+        });
+        let transpose = self.params.transpose.semitones();
+        ui.label(format!(
+            "Offset: {:+} st ({:+} oct {:+} st)",
+            transpose,
+            transpose / 12,
+            transpose % 12
+        ));
+
+        // マスターバスのコンプレッサー
+        let mut compressor_settings = if let Ok(settings) = self.params.compressor.get_settings().lock() {
+            *settings
+        } else {
+            Default::default()
+        };
+
+        ui.checkbox(&mut compressor_settings.enabled, "Compressor");
+        let response = ui.add(egui::Slider::new(&mut compressor_settings.threshold, -60.0..=0.0).text("Threshold (dB)"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::CompressorThreshold);
+        let response = ui.add(egui::Slider::new(&mut compressor_settings.ratio, 1.0..=20.0).logarithmic(true).text("Ratio"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::CompressorRatio);
+        let response = ui.add(egui::Slider::new(&mut compressor_settings.attack, 0.1..=100.0).logarithmic(true).text("Attack (ms)"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::CompressorAttack);
+        let response = ui.add(egui::Slider::new(&mut compressor_settings.release, 10.0..=1000.0).logarithmic(true).text("Release (ms)"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::CompressorRelease);
+        let response = ui.add(egui::Slider::new(&mut compressor_settings.makeup, 0.0..=24.0).text("Makeup (dB)"));
+        midi_learn(ui, response, &self.params.midi_map, ParamId::CompressorMakeup);
+
+        self.params.compressor.set_enabled(compressor_settings.enabled);
+        self.params.compressor.set_threshold(compressor_settings.threshold);
+        self.params.compressor.set_ratio(compressor_settings.ratio);
+        self.params.compressor.set_attack(compressor_settings.attack);
+        self.params.compressor.set_release(compressor_settings.release);
+        self.params.compressor.set_makeup(compressor_settings.makeup);
+
+        // ゲインリダクションのメーター（24dBで振り切る）
+        let reduction = self.params.compressor.gain_reduction();
+        ui.add(
+            egui::ProgressBar::new((reduction / 24.0).clamp(0.0, 1.0))
+                .desired_width(160.0)
+                .text(format!("GR {:.1} dB", reduction)),
+        );
+
+        // 再生中はゲインリダクションを更新し続ける
+        if self.stream_handle.is_some() {
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+        }
+    }
+
+    /// 出力レベル（ピーク/RMS）・クリップ・鳴っているボイスの数・押されているノートを1行で描画する
+    fn status_ui(&mut self, ui: &mut egui::Ui) {
+        // ピークは最大値を保持してからゆっくり下げる（1フレームごとに約0.5dB）
+        self.meter_peak = self.params.meter.take_peak().max(self.meter_peak * 0.94);
+        let rms = self.params.meter.rms();
+        self.dsp_load = self.params.meter.take_dsp_load().max(self.dsp_load * 0.94);
+        let to_db = |x: f32| 20.0 * x.max(1e-6).log10();
+
+        // クリップを検出したら1秒間点灯させる
+        if self.params.master.take_clip() {
+            self.clip_hold_until = Some(Instant::now() + Duration::from_secs(1));
+        }
+        let clipping = self.clip_hold_until.is_some_and(|until| Instant::now() < until);
+
+        ui.horizontal(|ui| {
+            // -60dB〜0dB をバーの長さにする
+            let fill = |db: f32| ((db + 60.0) / 60.0).clamp(0.0, 1.0);
+            ui.add(
+                egui::ProgressBar::new(fill(to_db(self.meter_peak)))
+                    .desired_width(120.0)
+                    .text(format!("Peak {:.1} dB", to_db(self.meter_peak))),
+            );
+            ui.add(
+                egui::ProgressBar::new(fill(to_db(rms)))
+                    .desired_width(120.0)
+                    .text(format!("RMS {:.1} dB", to_db(rms))),
+            );
+            let color = if clipping { egui::Color32::RED } else { egui::Color32::DARK_GRAY };
+            ui.colored_label(color, "● CLIP");
+
+            // DSP負荷（100%でバッファの生成が間に合わなくなる）とアンダーランの回数
+            ui.separator();
+            let color = if self.dsp_load > 0.8 { egui::Color32::RED } else { ui.visuals().text_color() };
+            ui.colored_label(color, format!("DSP {:.0}%", self.dsp_load * 100.0));
+            ui.label(format!("Xruns: {}", self.params.meter.xruns()));
+            if ui.small_button("Reset").clicked() {
+                self.params.meter.reset_xruns();
+            }
+
+            // オーディオ出力の状態（デバイスが使えない間は作り直しを待っている）
+            ui.separator();
+            match &self.stream_handle {
+                Some(stream) => ui.label(format!("Audio: {}", stream.device_name().unwrap_or("running"))),
+                None if self.audio_wanted => ui.colored_label(egui::Color32::YELLOW, "Audio: reconnecting..."),
+                None => ui.label("Audio: stopped"),
+            };
+
+            ui.separator();
+            ui.label(format!("Voices: {}", self.params.meter.active_voices()));
+            let notes: Vec<String> = self.params.meter.held_notes().into_iter().map(note_name).collect();
+            ui.label(if notes.is_empty() { "Notes: -".to_string() } else { format!("Notes: {}", notes.join(" ")) });
+        });
+
+        // 再生中はメーターを更新し続ける
+        if self.stream_handle.is_some() {
+            ui.ctx().request_repaint_after(Duration::from_millis(30));
+        }
+    }
+
+    /// 出力音声のスペクトラムを描画する（横軸は対数周波数、縦軸はdB）
+    fn spectrum_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Spectrum");
+
+        let sample_rate = self.analyzer.sample_rate();
+        let spectrum = self.analyzer.update();
+
+        let size = egui::vec2(ui.available_width(), 160.0);
+        let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+        let rect = response.rect;
+        painter.rect_filled(rect, 2.0, egui::Color32::from_gray(20));
+
+        let min_freq = 20.0f32;
+        let max_freq = sample_rate / 2.0;
+        let x_of = |freq: f32| rect.left() + rect.width() * (freq / min_freq).ln() / (max_freq / min_freq).ln();
+        let y_of = |db: f32| rect.top() + rect.height() * (db / MIN_DB).clamp(0.0, 1.0);
+
+        // 目盛り（100Hz・1kHz・10kHz と -20dB ごと）
+        let grid = egui::Stroke::new(1.0, egui::Color32::from_gray(50));
+        for freq in [100.0, 1000.0, 10000.0] {
+            if freq < max_freq {
+                let x = x_of(freq);
+                painter.line_segment([egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())], grid);
+            }
+        }
+        for db in [-20.0, -40.0, -60.0, -80.0] {
+            let y = y_of(db);
+            painter.line_segment([egui::pos2(rect.left(), y), egui::pos2(rect.right(), y)], grid);
+        }
+
+        let bin_width = sample_rate / (spectrum.len() * 2) as f32;
+        let points: Vec<egui::Pos2> = spectrum
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(bin, _)| *bin as f32 * bin_width >= min_freq)
+            .map(|(bin, &db)| egui::pos2(x_of(bin as f32 * bin_width), y_of(db)))
+            .collect();
+        painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, egui::Color32::LIGHT_GREEN)));
+
+        // 再生中はスペクトラムを更新し続ける
+        if self.stream_handle.is_some() {
+            ui.ctx().request_repaint_after(Duration::from_millis(33));
+        }
+    }
+
+    /// 録音ボタンと録音状態を描画する
+    fn recorder_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if self.recorder.is_recording() {
+                if ui.button("⏹ Stop Recording").clicked() {
+                    self.recorder.stop();
+                } else {
+                    let elapsed = self.recorder.elapsed().as_secs();
+                    ui.label(format!("🔴 REC {:02}:{:02}", elapsed / 60, elapsed % 60));
+                    // 経過時間を更新するために再描画を要求
+                    ui.ctx().request_repaint_after(Duration::from_millis(250));
+                }
+            } else if ui.button("⏺ Record").clicked() {
+                // 録音するにはストリームが動いている必要がある
+                self.ensure_audio_stream();
+                self.recorder.start();
+            }
+        });
+        if let Some(path) = self.recorder.current_path() {
+            ui.label(format!("Recording to {}", path.display()));
+        }
+
+        // MIDIファイルを今の音色でWAVに書き出す
+        ui.horizontal(|ui| {
+            ui.label("MIDI file:");
+            ui.text_edit_singleline(&mut self.midi_file_path);
+            let rendering = self.midi_render.is_some();
+            if ui.add_enabled(!rendering, egui::Button::new("📄 Render MIDI file…")).clicked() {
+                self.render_midi_file();
+            }
+        });
+        if let Some(handle) = self.midi_render.take_if(|handle| handle.is_finished()) {
+            self.midi_render_status = Some(match handle.join() {
+                Ok(Ok(path)) => format!("Rendered to {}", path.display()),
+                Ok(Err(err)) => err,
+                Err(_) => "Rendering failed".to_string(),
+            });
+        }
+        if self.midi_render.is_some() {
+            ui.label("Rendering…");
+            // 書き出しの完了を検出するために再描画を要求
+            ui.ctx().request_repaint_after(Duration::from_millis(250));
+        } else if let Some(status) = &self.midi_render_status {
+            ui.label(status.as_str());
+        }
+    }
+
+    /// MIDIファイルを今の音色でWAVに書き出す（別スレッドで実時間より速く生成する）
+    fn render_midi_file(&mut self) {
+        // 読み込めないファイルは書き出しを始める前にエラーを表示する
+        let midi_path = PathBuf::from(self.midi_file_path.trim());
+        if !midi_path.is_file() {
+            self.midi_render_status = Some(if self.midi_file_path.trim().is_empty() {
+                "Enter the path of a MIDI file or drop one on the window".to_string()
+            } else {
+                format!("MIDI file not found: {}", midi_path.display())
+            });
+            return;
+        }
+        let file = match MidiFile::load(&midi_path) {
+            Ok(file) => file,
+            Err(err) => {
+                self.midi_render_status = Some(format!("Failed to load {}: {}", midi_path.display(), err));
+                return;
+            }
+        };
+        let name = midi_path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("midi").to_string();
+        let wav_path = recorder::recordings_dir().join(format!("{}.wav", name));
+        let sample_rate = self.audio_settings.sample_rate.unwrap_or(44100);
+
+        // 演奏中の音に影響しないように、今の設定を複製したパラメータで生成する
+        let params = SynthParams::new();
+        Preset::capture(&name, &self.params).apply(&params);
+
+        self.midi_render_status = None;
+        let spawned = thread::Builder::new().name("midi-render".to_string()).spawn(move || {
+            let samples = render::render_midi_file(params, &file, sample_rate);
+            if let Some(parent) = wav_path.parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+            render::write_wav(&wav_path, &samples, sample_rate)
+                .map_err(|err| format!("Failed to write {}: {}", wav_path.display(), err))?;
+            println!("Rendered {} to {}", midi_path.display(), wav_path.display());
+            Ok(wav_path)
+        });
+        match spawned {
+            Ok(handle) => self.midi_render = Some(handle),
+            Err(err) => self.midi_render_status = Some(format!("Failed to start rendering: {}", err)),
+        }
+    }
+
+    /// プリセットブラウザーと保存UIを描画する
+    fn preset_ui(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Presets");
+
+        // 別スレッドでの走査が終わったらリストを入れ替える
+        if self.preset_scan.as_ref().is_some_and(|scan| scan.is_finished()) {
+            if let Some(Ok(presets)) = self.preset_scan.take().map(JoinHandle::join) {
+                self.presets = presets;
+                self.select_current_preset();
+            }
+        }
+        if self.preset_scan.is_some() {
+            ui.label("Scanning presets…");
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+        }
+
+        // 検索文字列・タグ・お気に入りで絞り込む
+        let search = ui
+            .horizontal(|ui| {
+                ui.label("🔍");
+                let search = ui.text_edit_singleline(&mut self.preset_search);
+                egui::ComboBox::from_id_source("preset_tag_filter")
+                    .selected_text(self.preset_tag_filter.unwrap_or("All Tags"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.preset_tag_filter, None, "All Tags");
+                        for tag in PRESET_CATEGORIES {
+                            ui.selectable_value(&mut self.preset_tag_filter, Some(tag), tag);
+                        }
+                    });
+                ui.toggle_value(&mut self.favorites_only, "★ Favorites");
+                search
+            })
+            .inner;
+        let visible: Vec<usize> = (0..self.presets.len())
+            .filter(|&i| {
+                let entry = &self.presets[i];
+                entry.matches(&self.preset_search, self.preset_tag_filter)
+                    && (!self.favorites_only || self.favorite_presets.contains(&entry.path))
+            })
+            .collect();
+
+        // 上下キーで絞り込んだリストの中を移動し、すぐに読み込んで試聴する
+        // （検索欄にいる時か、他に入力中のウィジェットがない時だけ）
+        let navigate = search.has_focus() || ui.memory(|memory| memory.focus().is_none());
+        let mut moved = false;
+        if navigate && !visible.is_empty() {
+            let (up, down) = ui.input(|input| (input.key_pressed(egui::Key::ArrowUp), input.key_pressed(egui::Key::ArrowDown)));
+            let position = self.selected_preset.and_then(|selected| visible.iter().position(|&i| i == selected));
+            let next = match (up, down, position) {
+                (true, _, Some(position)) => Some(position.saturating_sub(1)),
+                (_, true, Some(position)) => Some((position + 1).min(visible.len() - 1)),
+                (true, _, None) | (_, true, None) => Some(0),
+                _ => None,
+            };
+            if let Some(next) = next.filter(|&next| Some(next) != position) {
+                self.preview_preset(visible[next]);
+                moved = true;
+            }
+        }
+
+        // フォルダーごとに並べたリスト（クリックですぐに読み込み、☆でお気に入りにする）
+        // フォルダーにはMIDIのバンクセレクトで選ぶ時のバンク番号を添える
+        let banks = preset::preset_banks(&self.presets);
+        let mut clicked = None;
+        let mut toggled_favorite = None;
+        egui::ScrollArea::vertical()
+            .id_source("preset_browser")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                let mut folder = None;
+                for &i in &visible {
+                    let entry = &self.presets[i];
+                    if folder != Some(&entry.folder) {
+                        folder = Some(&entry.folder);
+                        if !entry.folder.is_empty() {
+                            ui.horizontal(|ui| {
+                                ui.label(egui::RichText::new(format!("📁 {}", entry.folder)).strong());
+                                if let Some(bank) = banks.iter().position(|&folder| folder == entry.folder) {
+                                    ui.weak(format!("Bank {}", bank));
+                                }
+                            });
+                        }
+                    }
+                    ui.horizontal(|ui| {
+                        let favorite = self.favorite_presets.contains(&entry.path);
+                        if ui.small_button(if favorite { "★" } else { "☆" }).clicked() {
+                            toggled_favorite = Some(entry.path.clone());
+                        }
+                        let selected = self.selected_preset == Some(i);
+                        let response = ui.selectable_label(selected, entry.name.as_str());
+                        if selected && moved {
+                            response.scroll_to_me(None);
+                        }
+                        if response.clicked() {
+                            clicked = Some(i);
+                        }
+                        if !entry.tags.is_empty() {
+                            ui.weak(entry.tags.join(", "));
+                        }
+                    });
+                }
+                if visible.is_empty() {
+                    ui.weak("No presets found");
+                }
+            });
+        if let Some(i) = clicked {
+            self.preview_preset(i);
+        }
+        if let Some(path) = toggled_favorite {
+            if let Some(position) = self.favorite_presets.iter().position(|favorite| *favorite == path) {
+                self.favorite_presets.remove(position);
+            } else {
+                self.favorite_presets.push(path);
+            }
+            self.save_config();
+        }
+
+        ui.horizontal(|ui| {
+            // 現在のプリセットに上書き保存（未保存なら Save As と同じ）
+            if ui.button("💾 Save").clicked() {
+                let path = self
+                    .current_preset_path
+                    .clone()
+                    .unwrap_or_else(|| preset::preset_path(&self.preset_name));
+                self.save_preset(path);
+            }
+
+            // プリセットのリストを更新
+            if ui.button("🔄 Rescan").clicked() {
+                self.refresh_presets();
+            }
+        });
+
+        // 名前を付けて保存
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.preset_name);
+            if ui.button("💾 Save As").clicked() && !self.preset_name.trim().is_empty() {
+                let path = preset::preset_path(self.preset_name.trim());
+                self.save_preset(path);
+            }
+        });
+
+        // 保存するプリセットのタグ
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Tags:");
+            for tag in PRESET_CATEGORIES {
+                let mut tagged = self.preset_tags.iter().any(|t| t == tag);
+                if ui.toggle_value(&mut tagged, tag).changed() {
+                    if tagged {
+                        self.preset_tags.push(tag.to_string());
+                    } else {
+                        self.preset_tags.retain(|t| t != tag);
+                    }
+                }
+            }
+        });
+
+        // パッチのランダム生成（オシレータ・Unison・エンベロープ・フィルター）
+        ui.horizontal(|ui| {
+            if ui.button("🎲 Randomize").clicked() {
+                self.randomize_patch(None);
+            }
+            if ui.button("Mutate").clicked() {
+                self.randomize_patch(Some(self.mutate_amount));
+            }
+            ui.add(egui::Slider::new(&mut self.mutate_amount, 0.01..=0.5).text("Amount"));
+        });
+
+        // A/B比較（2つのパッチを切り替えて聴き比べる）
+        ui.horizontal(|ui| {
+            for (slot, label) in ["A", "B"].into_iter().enumerate() {
+                if ui.selectable_label(self.ab_active == slot, label).clicked() && self.ab_active != slot {
+                    self.switch_ab_slot(slot);
+                }
+            }
+            if ui.button("Copy A→B").clicked() {
+                self.copy_ab_slot(0, 1);
+            }
+        });
+    }
+
+    /// 編集中のパッチを今のスロットに保存し、もう一方のスロットのパッチに切り替える
+    ///
+    /// まだ使っていないスロットに切り替えた時は、今のパッチをそのまま使う
+    fn switch_ab_slot(&mut self, slot: usize) {
+        let current = Preset::capture(&self.preset_name, &self.params);
+        let next = self.ab_slots[slot].take().unwrap_or_else(|| current.clone());
+        self.ab_slots[self.ab_active] = Some(current);
+        self.ab_active = slot;
+        // 発音モードやアルペジエーターが変わっても音が残らないように先に止める
+        self.note_handler.all_notes_off();
+        next.apply(&self.params);
+    }
+
+    /// スロットのパッチを別のスロットにコピーする（コピー先を編集中なら、すぐにそのパッチにする）
+    fn copy_ab_slot(&mut self, from: usize, to: usize) {
+        // 編集中のスロットと、まだ使っていないスロットは今のパッチを使う
+        let patch = match &self.ab_slots[from] {
+            Some(patch) if from != self.ab_active => patch.clone(),
+            _ => Preset::capture(&self.preset_name, &self.params),
+        };
+        if to == self.ab_active {
+            self.note_handler.all_notes_off();
+            patch.apply(&self.params);
+        } else {
+            self.ab_slots[to] = Some(patch);
+        }
+    }
+
+    /// 現在のパッチをランダムに作り直す（`mutate` が指定されたらその大きさだけ変える）
+    fn randomize_patch(&mut self, mutate: Option<f32>) {
+        let mut preset = Preset::capture(&self.preset_name, &self.params);
+        match mutate {
+            Some(amount) => self.randomizer.mutate(&mut preset, amount),
+            None => self.randomizer.randomize(&mut preset),
+        }
+        // 発音中のボイスが設定の変化で途切れないように先に止める
+        self.note_handler.all_notes_off();
+        preset.apply(&self.params);
+    }
+
+    /// プリセットを読み込んでシンセ設定に反映する（読み込めたら、読み込めずにデフォルトにした設定の名前を返す）
+    fn load_preset(&mut self, path: PathBuf) -> Option<Vec<String>> {
+        match Preset::load(&path) {
+            Ok((preset, skipped)) => {
+                // アルペジエーターや発音モードが変わっても音が残らないように先に止める
+                self.note_handler.all_notes_off();
+                preset.apply(&self.params);
+                println!("Loaded preset: {}", path.display());
+                print_skipped_fields(&skipped);
+                self.preset_name = preset::preset_display_name(&path);
+                self.preset_tags = preset.tags;
+                self.current_preset_path = Some(path);
+                Some(skipped)
+            }
+            Err(err) => {
+                println!("Failed to load preset {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+
+    /// 現在のシンセ設定をプリセットとして保存する
+    fn save_preset(&mut self, path: PathBuf) {
+        let name = preset::preset_display_name(&path);
+        let mut preset = Preset::capture(&name, &self.params);
+        preset.tags = self.preset_tags.clone();
+        match preset.save(&path) {
+            Ok(()) => {
+                println!("Saved preset: {}", path.display());
+                self.preset_name = name;
+                self.current_preset_path = Some(path);
+                self.refresh_presets();
+            }
+            Err(err) => {
+                println!("Failed to save preset {}: {}", path.display(), err);
+            }
+        }
+    }
+
+    /// 単一周期のWAVファイルを読み込んでオシレータAの波形にする（読み込めたら true）
+    fn load_wavetable(&mut self) -> bool {
+        let path = PathBuf::from(self.wavetable_path.trim());
+        match WaveTable::load_wav(&path) {
+            Ok(table) => {
+                self.custom_wavetable = Some(table.clone());
+                self.params.unison.set_waveform(Waveform::Custom(table));
+                println!("Loaded wavetable: {}", path.display());
+                true
+            }
+            Err(err) => {
+                println!("Failed to load wavetable {}: {}", path.display(), err);
+                false
+            }
+        }
+    }
+
+    /// バンクセレクトとプログラムチェンジで選ばれたプリセットを読み込む（バンクはフォルダーの並び順）
+    fn load_program(&mut self, number: ProgramNumber) {
+        let Some(entry) = preset::find_program(&self.presets, number.bank, number.program) else {
+            self.show_toast(format!("No preset for bank {} program {}", number.bank, number.program));
+            return;
+        };
+        let (path, name) = (entry.path.clone(), entry.name.clone());
+        if let Some(skipped) = self.load_preset(path) {
+            self.select_current_preset();
+            self.show_toast(with_skipped_fields(format!("Program {}:{}: {}", number.bank, number.program, name), &skipped));
+        } else {
+            self.show_toast(format!("Failed to load preset: {}", name));
+        }
+    }
+
+    /// 追加のパートにプリセットを読み込む
+    ///
+    /// アルペジエーターも各パートで動くが、テンポとクロックはメインのパートと共有する
+    fn load_part_preset(&mut self, index: usize, path: PathBuf) -> Option<Vec<String>> {
+        let part = self.parts.get(index)?;
+        match Preset::load(&path) {
+            Ok((preset, skipped)) => {
+                part.notes.all_notes_off();
+                preset.apply(&part.params);
+                println!("Loaded preset into part {}: {}", index + 2, path.display());
+                print_skipped_fields(&skipped);
+                self.part_preset_names[index] = preset::preset_display_name(&path);
+                Some(skipped)
+            }
+            Err(err) => {
+                println!("Failed to load preset {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+
+    /// 追加のパートのチャンネルで届いたプログラムチェンジのプリセットを、そのパートに読み込む
+    fn load_part_program(&mut self, index: usize, number: ProgramNumber) {
+        let Some(entry) = preset::find_program(&self.presets, number.bank, number.program) else {
+            self.show_toast(format!("No preset for bank {} program {}", number.bank, number.program));
+            return;
+        };
+        let (path, name) = (entry.path.clone(), entry.name.clone());
+        if let Some(skipped) = self.load_part_preset(index, path) {
+            let message = format!("Part {} program {}:{}: {}", index + 2, number.bank, number.program, name);
+            self.show_toast(with_skipped_fields(message, &skipped));
+        } else {
+            self.show_toast(format!("Failed to load preset: {}", name));
+        }
+    }
+
+    /// ブラウザーで選んだプリセットをすぐに読み込む
+    fn preview_preset(&mut self, index: usize) {
+        if let Some(entry) = self.presets.get(index) {
+            let (path, name) = (entry.path.clone(), entry.name.clone());
+            self.selected_preset = Some(index);
+            if let Some(skipped) = self.load_preset(path)
+                && !skipped.is_empty()
+            {
+                self.show_toast(with_skipped_fields(format!("Loaded preset: {}", name), &skipped));
+            }
+        }
+    }
+
+    /// プリセットディレクトリを別スレッドで再走査する（終わったら preset_ui でリストを入れ替える）
+    ///
+    /// 走査中に呼ばれたら前の走査の結果は捨てる（保存したばかりのプリセットを含めるため）
+    fn refresh_presets(&mut self) {
+        match thread::Builder::new().name("preset-scan".to_string()).spawn(preset::scan_presets) {
+            Ok(handle) => self.preset_scan = Some(handle),
+            Err(err) => println!("Failed to start preset scan: {}", err),
+        }
+    }
+
+    /// 現在のプリセットをリストから選択し直す
+    fn select_current_preset(&mut self) {
+        self.selected_preset = self
+            .current_preset_path
+            .as_ref()
+            .and_then(|current| self.presets.iter().position(|entry| entry.path == *current));
+    }
+}
+
+/// 読み込めずにデフォルトにしたプリセットの設定をログに出す
+fn print_skipped_fields(skipped: &[String]) {
+    if !skipped.is_empty() {
+        println!("Preset settings could not be read, using the defaults: {}", skipped.join(", "));
+    }
+}
+
+/// お知らせの文に、読み込めずにデフォルトにしたプリセットの設定を書き足す
+fn with_skipped_fields(message: String, skipped: &[String]) -> String {
+    if skipped.is_empty() {
+        message
+    } else {
+        format!("{} (defaults used for: {})", message, skipped.join(", "))
+    }
+}
+
+/// 波形選択コンボボックス
+///
+/// `custom` は読み込み済みの波形テーブル（ある時だけ Custom を選べる）
+fn waveform_combo(ui: &mut egui::Ui, id: &str, waveform: &mut Waveform, custom: Option<&WaveTable>) {
+    egui::ComboBox::from_id_source(id)
+        .selected_text(match waveform {
+            Waveform::Sine => "Sine",
+            Waveform::Triangle => "Triangle",
+            Waveform::Square => "Square",
+            Waveform::Sawtooth => "Sawtooth",
+            Waveform::Custom(_) => "Custom",
+        })
+        .show_ui(ui, |ui| {
+            ui.selectable_value(waveform, Waveform::Sine, "Sine");
+            ui.selectable_value(waveform, Waveform::Triangle, "Triangle");
+            ui.selectable_value(waveform, Waveform::Square, "Square");
+            ui.selectable_value(waveform, Waveform::Sawtooth, "Sawtooth");
+            if let Some(table) = custom {
+                ui.selectable_value(waveform, Waveform::Custom(table.clone()), "Custom");
+            }
+        });
+}
+
+/// レイヤーの音色（波形・Unison・パルス幅・フィルター・音量とフィルターのエンベロープ）を編集する
+fn layer_patch_ui(ui: &mut egui::Ui, patch: &mut LayerPatch, custom: Option<&WaveTable>) {
+    ui.horizontal(|ui| {
+        ui.label("Waveform");
+        waveform_combo(ui, "layer_waveform", &mut patch.unison.waveform, custom);
+    });
+    ui.add(egui::Slider::new(&mut patch.unison.voices, 1..=MAX_UNISON_VOICES as u8).text("Unison Voices"));
+    ui.add(egui::Slider::new(&mut patch.unison.detune, 0.0..=100.0).text("Detune Fine (cents)"));
+    ui.add(egui::Slider::new(&mut patch.oscillator.pulse_width, MIN_PULSE_WIDTH..=MAX_PULSE_WIDTH).text("Pulse Width"));
+
+    let filter = &mut patch.filter;
+    ui.checkbox(&mut filter.enabled, "Enable Filter");
+    if filter.enabled {
+        egui::ComboBox::from_label("Filter Type")
+            .selected_text(format!("{:?}", filter.mode))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut filter.mode, FilterMode::LowPass, "LowPass");
+                ui.selectable_value(&mut filter.mode, FilterMode::HighPass, "HighPass");
+                ui.selectable_value(&mut filter.mode, FilterMode::BandPass, "BandPass");
+                ui.selectable_value(&mut filter.mode, FilterMode::Notch, "Notch");
+            });
+        ui.add(egui::Slider::new(&mut filter.cutoff, 20.0..=20000.0).logarithmic(true).text("Cutoff (Hz)"));
+        ui.add(egui::Slider::new(&mut filter.resonance, 0.0..=1.0).text("Resonance"));
+        ui.add(egui::Slider::new(&mut filter.env_amount, -1.0..=1.0).text("Env Amount"));
+    }
+
+    // 音量とフィルターのエンベロープ
+    for (target, label) in [(EnvelopeTarget::Amp, "Amp"), (EnvelopeTarget::Filter, "Filter")] {
+        let env = &mut patch.envelope[target.index()];
+        ui.horizontal(|ui| {
+            ui.label(label);
+            ui.add(egui::DragValue::new(&mut env.attack).clamp_range(0.0..=5.0).speed(0.01).prefix("A: ").suffix(" s"));
+            ui.add(egui::DragValue::new(&mut env.decay).clamp_range(0.0..=5.0).speed(0.01).prefix("D: ").suffix(" s"));
+            ui.add(egui::DragValue::new(&mut env.sustain).clamp_range(0.0..=1.0).speed(0.01).prefix("S: "));
+            ui.add(egui::DragValue::new(&mut env.release).clamp_range(0.0..=10.0).speed(0.01).prefix("R: ").suffix(" s"));
+        });
+    }
+}
+
+/// レイヤーのベロシティで切り替える硬い音色を編集する（`soft` はコピー元にするレイヤーの柔らかい音色）
+fn velocity_layer_ui(ui: &mut egui::Ui, label: &str, velocity: &mut VelocityLayer, soft: &LayerPatch, custom: Option<&WaveTable>) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut velocity.enabled, format!("{} Velocity Layer", label));
+        if velocity.enabled && ui.button("Copy Soft Sound").clicked() {
+            velocity.hard = soft.clone();
+        }
+    });
+    if !velocity.enabled {
+        return;
+    }
+    ui.horizontal(|ui| {
+        ui.selectable_value(&mut velocity.mode, VelocitySwitchMode::Switch, "Switch");
+        ui.selectable_value(&mut velocity.mode, VelocitySwitchMode::Crossfade, "Crossfade");
+    });
+    ui.add(egui::Slider::new(&mut velocity.threshold, 1..=127).text("Hard Velocity"));
+    if velocity.mode == VelocitySwitchMode::Crossfade {
+        ui.add(egui::Slider::new(&mut velocity.fade, 1..=127).text("Crossfade Width"));
+    }
+    ui.push_id("hard_patch", |ui| {
+        ui.label("Hard Sound");
+        layer_patch_ui(ui, &mut velocity.hard, custom);
+    });
+}
+
+/// 鍵盤の範囲（下端と上端のノート）のスライダー
+fn key_range_ui(ui: &mut egui::Ui, label: &str, range: &mut KeyRange) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.add(egui::Slider::new(&mut range.low, 0..=127).custom_formatter(|note, _| note_name(note as u8)).text("Low"));
+        ui.add(egui::Slider::new(&mut range.high, 0..=127).custom_formatter(|note, _| note_name(note as u8)).text("High"));
+    });
+}
+
+/// テンポ同期する音符の長さを選ぶコンボボックス
+fn division_combo(ui: &mut egui::Ui, label: &str, division: &mut NoteDivision) {
+    egui::ComboBox::from_label(label)
+        .selected_text(division.label())
+        .show_ui(ui, |ui| {
+            for option in NoteDivision::ALL {
+                ui.selectable_value(division, option, option.label());
+            }
+        });
+}
+
+/// 変調を受けているスライダーに、変調の範囲の帯と今の値の輪を重ねる（範囲がほとんどない時は描かない）
+fn mod_ring(
+    ui: &egui::Ui,
+    response: &egui::Response,
+    ring: Option<(ModRange, Instant)>,
+    range: RangeInclusive<f32>,
+    logarithmic: bool,
+) {
+    let Some((shown, _)) = ring else {
+        return;
+    };
+    // スライダーのレール（ラベルや数値の欄を除いた左側）での位置
+    let rail = egui::Rect::from_min_size(response.rect.min, egui::vec2(ui.spacing().slider_width, response.rect.height()));
+    let (start, end) = (*range.start(), *range.end());
+    let to_x = |value: f32| {
+        let t = if logarithmic {
+            (value.max(start).ln() - start.ln()) / (end.ln() - start.ln())
+        } else {
+            (value - start) / (end - start)
+        };
+        rail.left() + t.clamp(0.0, 1.0) * rail.width()
+    };
+    let (low, high) = (to_x(shown.min), to_x(shown.max));
+    if high - low < 1.0 {
+        return;
+    }
+    let color = ui.visuals().selection.stroke.color;
+    let y = rail.center().y;
+    ui.painter().rect_filled(
+        egui::Rect::from_x_y_ranges(low..=high, y - 2.0..=y + 2.0),
+        2.0,
+        color.gamma_multiply(0.5),
+    );
+    ui.painter()
+        .circle_stroke(egui::pos2(to_x(shown.value), y), rail.height() * 0.3, egui::Stroke::new(1.5, color));
+}
+
+/// スライダーに右クリックでMIDIラーンのメニューを付ける（ラーン中は枠を表示する）
+fn midi_learn(ui: &egui::Ui, response: egui::Response, midi_map: &MidiMapManager, param: ParamId) {
+    if midi_map.learning() == Some(param) {
+        ui.painter().rect_stroke(response.rect, 2.0, egui::Stroke::new(1.0, egui::Color32::YELLOW));
+    }
+    let controller = midi_map.controller_for(param);
+    response.context_menu(|ui| {
+        if let Some(cc) = controller {
+            ui.label(format!("Mapped to CC{}", cc));
+        }
+        if ui.button("MIDI Learn").clicked() {
+            midi_map.start_learn(param);
+            ui.close_menu();
+        }
+        if controller.is_some() && ui.button("Clear MIDI Mapping").clicked() {
+            if let Err(err) = midi_map.unbind(param) {
+                println!("Failed to save MIDI map: {}", err);
+            }
+            ui.close_menu();
+        }
+    });
+}
@@ -1,81 +1,418 @@
-use std::sync::{Arc, Mutex};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
 
-use crate::unison::{UnisonManager, generate_unison};
+use crate::analyzer::AnalyzerTap;
+use crate::recorder::RecordTap;
+use synth_core::engine::{DEFAULT_MAX_BLOCK_FRAMES, SynthEngine};
+use synth_core::logger::{LogEvent, LogTap};
+use synth_core::midi_message::handle_midi_message;
+use synth_core::midi_queue::{MidiQueue, MidiScheduler};
+use synth_core::note::NoteHandler;
+use synth_core::params::SynthParams;
+use synth_core::part::{Part, PartMixer};
+
+/// 選択できるサンプルレートの候補
+const SAMPLE_RATE_CANDIDATES: [u32; 8] = [22050, 32000, 44100, 48000, 88200, 96000, 176400, 192000];
+
+/// 選択できるバッファサイズの候補（フレーム数）
+const BUFFER_SIZE_CANDIDATES: [u32; 8] = [32, 64, 128, 256, 512, 1024, 2048, 4096];
+
+/// 前のコールバックからの間隔がバッファの長さのこの倍数を超えたら、出力が途切れたものとしてアンダーランに数える
+const XRUN_GAP_RATIO: f32 = 2.0;
+
+/// オーディオ出力の設定（None はデバイスのデフォルトを使う）
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    /// バックエンド名（None は最初に見つかったバックエンド）
+    pub backend: Option<String>,
+    /// 出力デバイス名
+    pub device_name: Option<String>,
+    /// サンプルレート（Hz）
+    pub sample_rate: Option<u32>,
+    /// バッファサイズ（フレーム数）
+    pub buffer_size: Option<u32>,
+}
+
+/// 出力デバイスの情報（設定UIで使う）
+pub struct OutputDeviceInfo {
+    /// デバイス名
+    pub name: String,
+    /// 対応しているサンプルレート
+    pub sample_rates: Vec<u32>,
+    /// 対応しているバッファサイズ
+    pub buffer_sizes: Vec<u32>,
+}
+
+/// ストリームに渡す音声の生成元（パラメータとMIDI入力のキュー、録音・スペクトラム表示・ログの口）
+pub struct AudioSource {
+    pub params: SynthParams,
+    pub notes: NoteHandler,
+    pub parts: Vec<Part>, // MIDIチャンネルごとの追加のパート
+    pub midi_queue: MidiQueue,
+    pub record_tap: RecordTap,
+    pub analyzer_tap: AnalyzerTap,
+    pub log: LogTap,
+    pub midi_log: LogTap,
+}
+
+impl AudioSource {
+    /// ストリームのフォーマットが決まったら、出力バッファを埋めるコールバックとログの口に分ける
+    ///
+    /// `max_block_frames` は1回のバッファの最大フレーム数（生成に使うバッファをオーディオスレッドの外で確保する）
+    pub fn into_callback(self, sample_rate: u32, channels: u16, max_block_frames: usize) -> (AudioCallback, LogTap) {
+        // 録音用にストリームのフォーマットを伝える
+        self.record_tap.set_format(sample_rate, channels);
+        self.analyzer_tap.set_format(sample_rate, channels);
+
+        // 音声生成の状態はエンジンが保持し、オーディオスレッドに渡す
+        let callback = AudioCallback {
+            engine: SynthEngine::new(self.params.clone(), sample_rate as f32, max_block_frames),
+            parts: PartMixer::new(self.parts, &self.params.parts, sample_rate as f32, channels as usize, max_block_frames),
+            channels: channels.max(1) as usize,
+            sample_rate: sample_rate as f32,
+            params: self.params,
+            notes: self.notes.realtime(),
+            midi: self.midi_queue.scheduler(),
+            midi_log: self.midi_log,
+            record_tap: self.record_tap,
+            analyzer_tap: self.analyzer_tap,
+        };
+        (callback, self.log)
+    }
+}
+
+/// オーディオスレッドで出力バッファを埋める（どのバックエンドでも共通）
+pub struct AudioCallback {
+    engine: SynthEngine,
+    parts: PartMixer, // 追加のパートの生成と、メインのパートへの混ぜ込み
+    channels: usize,
+    sample_rate: f32,
+    params: SynthParams,
+    notes: NoteHandler,
+    midi: MidiScheduler, // MIDI入力のメッセージ（バッファ内の位置つきで取り出す）
+    midi_log: LogTap,
+    record_tap: RecordTap,
+    analyzer_tap: AnalyzerTap,
+}
+
+impl AudioCallback {
+    /// インターリーブされた出力バッファを生成する
+    ///
+    /// 届いたMIDIメッセージの位置でバッファを区切り、メッセージを反映してから続きを生成する
+    /// （ノートオン/オフがバッファサイズに関係なくサンプル単位の位置で鳴る）
+    pub fn process(&mut self, data: &mut [f32]) {
+        let now = Instant::now();
+        let channels = self.channels;
+        let frames = data.len() / channels;
+        let mut start = 0;
+        while let Some((offset, event)) = self.midi.next_event(now, frames, self.sample_rate) {
+            let offset = offset.max(start);
+            if offset > start {
+                self.render(&mut data[start * channels..offset * channels]);
+                start = offset;
+            }
+            // 追加のパートに割り当てたチャンネルのメッセージはそのパートだけが受け取る
+            let message = event.message();
+            let midi_log = &mut self.midi_log;
+            let applied = match self.parts.route(message) {
+                Some(part) => handle_midi_message(message, &part.notes, &part.params, &mut |event| midi_log.push(event)),
+                None => handle_midi_message(message, &self.notes, &self.params, &mut |event| midi_log.push(event)),
+            };
+            // ノートの状態を他のスレッドが使っていれば待たずに戻し、次のバッファで反映する
+            if !applied {
+                self.midi.defer(event);
+                break;
+            }
+        }
+        self.render(&mut data[start * channels..]);
+
+        // 出力したサンプルをそのまま録音し、スペクトラム表示にも送る
+        self.record_tap.push(data);
+        self.analyzer_tap.push(data);
+
+        // バッファの長さに対して生成にかかった時間をDSP負荷として通知する
+        self.params.meter.report_buffer(frames, self.sample_rate as u32);
+        if frames > 0 {
+            let buffer_duration = frames as f32 / self.sample_rate;
+            self.params.meter.report_load(now.elapsed().as_secs_f32() / buffer_duration);
+        }
+    }
+
+    /// メインのパートを生成し、鳴らしている追加のパートを混ぜる
+    fn render(&mut self, data: &mut [f32]) {
+        self.engine.process(data, self.channels);
+        self.parts.process(data, self.channels);
+    }
+}
+
+/// 再生中のストリーム（ドロップすると止まる）
+pub trait AudioStream {
+    /// 出力しているデバイスの名前（デフォルトデバイスの切り替えを検出するのに使う、分からなければ None）
+    fn device_name(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// cpalのストリームと、出力しているデバイスの名前
+struct CpalStream {
+    _stream: cpal::Stream,
+    device_name: String,
+}
+
+impl AudioStream for CpalStream {
+    fn device_name(&self) -> Option<&str> {
+        Some(&self.device_name)
+    }
+}
+
+/// オーディオ出力のバックエンド（cpal・Web Audio などを差し替えられるようにする）
+pub trait AudioBackend {
+    /// 設定UIに表示する名前
+    fn name(&self) -> &'static str;
+
+    /// デバイス・サンプルレート・バッファサイズを選べるか（JACKなどはサーバーの設定に従う）
+    fn is_configurable(&self) -> bool {
+        true
+    }
+
+    /// 利用可能な出力デバイスとその対応設定を列挙する
+    fn output_devices(&self) -> Vec<OutputDeviceInfo>;
+
+    /// 利用可能な出力デバイスの名前だけを列挙する（デバイスの抜き差しの検出用、対応設定は調べない）
+    fn output_device_names(&self) -> Vec<String> {
+        self.output_devices().into_iter().map(|device| device.name).collect()
+    }
+
+    /// 今のデフォルトの出力デバイスの名前（デバイスを選べないバックエンドでは None）
+    fn default_device_name(&self) -> Option<String> {
+        None
+    }
+
+    /// 出力ストリームを開始する
+    fn start(&self, source: AudioSource, settings: &AudioSettings) -> Result<Box<dyn AudioStream>, String>;
+}
+
+/// このビルドで使えるバックエンド（先頭がデフォルト）
+pub fn available_backends() -> Vec<Box<dyn AudioBackend>> {
+    let mut backends: Vec<Box<dyn AudioBackend>> = Vec::new();
+    #[cfg(target_arch = "wasm32")]
+    backends.push(Box::new(crate::web_audio::WebAudioBackend));
+    #[cfg(not(target_arch = "wasm32"))]
+    backends.push(Box::new(CpalBackend));
+    #[cfg(all(target_os = "linux", feature = "jack"))]
+    backends.push(Box::new(crate::jack_audio::JackBackend));
+    backends
+}
+
+/// 設定で選ばれたバックエンドを探す（見つからない場合はデフォルト）
+pub fn find_backend<'a>(backends: &'a [Box<dyn AudioBackend>], settings: &AudioSettings) -> Option<&'a dyn AudioBackend> {
+    let selected = settings
+        .backend
+        .as_ref()
+        .and_then(|name| backends.iter().find(|backend| backend.name() == name));
+    selected.or(backends.first()).map(|backend| backend.as_ref())
+}
+
+/// cpal（各OSのオーディオAPI）で出力するバックエンド
+pub struct CpalBackend;
+
+impl AudioBackend for CpalBackend {
+    fn name(&self) -> &'static str {
+        "System (cpal)"
+    }
+
+    fn output_devices(&self) -> Vec<OutputDeviceInfo> {
+        list_output_devices()
+    }
+
+    fn output_device_names(&self) -> Vec<String> {
+        cpal::default_host()
+            .output_devices()
+            .map(|devices| devices.filter_map(|device| device.name().ok()).collect())
+            .unwrap_or_default()
+    }
+
+    fn default_device_name(&self) -> Option<String> {
+        cpal::default_host().default_output_device().and_then(|device| device.name().ok())
+    }
+
+    fn start(&self, source: AudioSource, settings: &AudioSettings) -> Result<Box<dyn AudioStream>, String> {
+        Ok(Box::new(play_sine_wave(source, settings)?))
+    }
+}
+
+/// 利用可能な出力デバイスとその対応設定を列挙する
+fn list_output_devices() -> Vec<OutputDeviceInfo> {
+    let host = cpal::default_host();
+    let mut infos = Vec::new();
+
+    let devices = match host.output_devices() {
+        Ok(devices) => devices,
+        Err(err) => {
+            println!("Failed to enumerate output devices: {}", err);
+            return infos;
+        }
+    };
+
+    for device in devices {
+        let name = match device.name() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+
+        let mut sample_rates = Vec::new();
+        let mut buffer_sizes = Vec::new();
+        if let Ok(configs) = device.supported_output_configs() {
+            for range in configs.filter(|range| range.sample_format() == cpal::SampleFormat::F32) {
+                for rate in SAMPLE_RATE_CANDIDATES {
+                    if range.min_sample_rate().0 <= rate && rate <= range.max_sample_rate().0 && !sample_rates.contains(&rate) {
+                        sample_rates.push(rate);
+                    }
+                }
+                for size in BUFFER_SIZE_CANDIDATES {
+                    let supported = match range.buffer_size() {
+                        cpal::SupportedBufferSize::Range { min, max } => *min <= size && size <= *max,
+                        cpal::SupportedBufferSize::Unknown => true,
+                    };
+                    if supported && !buffer_sizes.contains(&size) {
+                        buffer_sizes.push(size);
+                    }
+                }
+            }
+        }
+        sample_rates.sort();
+        buffer_sizes.sort();
+
+        infos.push(OutputDeviceInfo {
+            name,
+            sample_rates,
+            buffer_sizes,
+        });
+    }
+
+    infos
+}
+
+/// 設定に合う出力デバイスを探す（見つからない場合はデフォルトデバイス）
+fn find_output_device(host: &cpal::Host, settings: &AudioSettings) -> Option<cpal::Device> {
+    if let Some(name) = &settings.device_name {
+        if let Ok(mut devices) = host.output_devices() {
+            if let Some(device) = devices.find(|device| device.name().ok().as_ref() == Some(name)) {
+                return Some(device);
+            }
+        }
+        println!("Output device '{}' not found, using default device", name);
+    }
+    host.default_output_device()
+}
+
+/// 設定に合う出力フォーマットを探す（見つからない場合はデフォルトのフォーマット）
+fn find_output_config(
+    device: &cpal::Device,
+    settings: &AudioSettings,
+) -> Result<cpal::SupportedStreamConfig, String> {
+    let default_config = device
+        .default_output_config()
+        .map_err(|err| format!("Failed to get default output config: {}", err))?;
+
+    if let Some(rate) = settings.sample_rate {
+        if let Ok(configs) = device.supported_output_configs() {
+            // デフォルトと同じチャンネル数の f32 フォーマットを優先する
+            let mut candidates: Vec<_> = configs
+                .filter(|range| range.sample_format() == cpal::SampleFormat::F32)
+                .filter(|range| range.min_sample_rate().0 <= rate && rate <= range.max_sample_rate().0)
+                .collect();
+            candidates.sort_by_key(|range| range.channels() != default_config.channels());
+            if let Some(range) = candidates.into_iter().next() {
+                return Ok(range.with_sample_rate(cpal::SampleRate(rate)));
+            }
+        }
+        println!("Sample rate {}Hz not supported, using default config", rate);
+    }
+
+    Ok(default_config)
+}
 
 /// サイン波を生成してスピーカーから再生する関数
-pub fn play_sine_wave(
-    initial_freq: f32,
-    current_freq: Arc<Mutex<f32>>,
-    unison_manager: Arc<UnisonManager>,
-) -> cpal::Stream {
+fn play_sine_wave(source: AudioSource, audio_settings: &AudioSettings) -> Result<CpalStream, String> {
     // デフォルトのホストを取得
     let host = cpal::default_host();
-    // デフォルトの出力デバイスを取得
-    let device = host.default_output_device().expect("No output device available");
-    // デフォルトの出力フォーマットを取得
-    let config = device.default_output_config().expect("Failed to get default output config");
-    println!("Starting audio stream at {}Hz", config.sample_rate().0);
-
-    // 時間変数（サンプル数として保持）
-    let mut t = 0u64;
+    // 出力デバイスを取得（選択されていなければデフォルト）
+    let device = find_output_device(&host, audio_settings).ok_or("No output device available")?;
+    // 出力フォーマットを取得（選択されていなければデフォルト）
+    let config = find_output_config(&device, audio_settings)?;
+    let mut stream_config: cpal::StreamConfig = config.config();
+    if let Some(frames) = audio_settings.buffer_size {
+        stream_config.buffer_size = cpal::BufferSize::Fixed(frames);
+    }
+    let device_name = device.name().unwrap_or_else(|_| "Unknown".to_string());
+    println!(
+        "Starting audio stream on '{}' at {}Hz ({} channels, buffer {:?})",
+        device_name,
+        config.sample_rate().0,
+        config.channels(),
+        stream_config.buffer_size,
+    );
+
+    let meter = source.params.meter.clone();
+    let latency_meter = source.params.meter.clone();
+    // バッファサイズを指定した時はその大きさ、デバイスに任せる時は既定の大きさでバッファを確保する
+    let max_block_frames = match stream_config.buffer_size {
+        cpal::BufferSize::Fixed(frames) => frames as usize,
+        cpal::BufferSize::Default => DEFAULT_MAX_BLOCK_FRAMES,
+    };
+    let (mut callback, mut log) = source.into_callback(config.sample_rate().0, config.channels(), max_block_frames);
     let sample_rate = config.sample_rate().0 as f32;
+    let channels = config.channels().max(1) as usize;
+    let mut last_callback: Option<(cpal::StreamInstant, usize)> = None; // 前のコールバックの時刻とフレーム数
 
     // オーディオストリームを構築
     let stream = match config.sample_format() {
         cpal::SampleFormat::F32 => device.build_output_stream(
-            &config.into(),
-            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                // 現在の周波数を取得
-                let freq = if let Ok(freq_lock) = current_freq.try_lock() {
-                    *freq_lock
-                } else {
-                    initial_freq
-                };
-
-                // 周波数が0の場合は無音を出力
-                if freq <= 0.0 {
-                    for sample in data.iter_mut() {
-                        *sample = 0.0;
-                    }
-                    return;
+            &stream_config,
+            move |data: &mut [f32], info: &cpal::OutputCallbackInfo| {
+                callback.process(data);
+                // コールバックの時刻から、このバッファが実際に再生される時刻までの時間
+                let timestamp = info.timestamp();
+                if let Some(latency) = timestamp.playback.duration_since(&timestamp.callback) {
+                    latency_meter.report_output_latency(latency.as_secs_f32());
                 }
-
-                // Unison設定を取得
-                let unison_settings = if let Ok(settings) = unison_manager.get_settings().try_lock() {
-                    *settings
-                } else {
-                    return;
-                };
-
-                // 各サンプルを生成
-                for sample in data.iter_mut() {
-                    // 時間を秒単位に変換（浮動小数点の精度を考慮）
-                    let t_seconds = (t as f32) / sample_rate;
-                    
-                    // Unison音声を生成
-                    *sample = generate_unison(
-                        freq,
-                        unison_settings,
-                        t_seconds,
-                        sample_rate,
-                    );
-                    
-                    // 時間を進める（サンプル数として）
-                    t = t.wrapping_add(1);
+                // 前のバッファを出し切るより大きく遅れて呼ばれたら、その間は出力が途切れている
+                if let Some((previous, frames)) = last_callback
+                    && let Some(gap) = timestamp.callback.duration_since(&previous)
+                    && gap.as_secs_f32() > frames as f32 / sample_rate * XRUN_GAP_RATIO
+                {
+                    latency_meter.report_xrun();
                 }
+                last_callback = Some((timestamp.callback, data.len() / channels));
             },
             move |err| {
-                eprintln!("Error in output stream: {}", err);
+                // エラーのコールバックもオーディオスレッドから呼ばれることがあるのでログスレッドに送る
+                // デバイスが抜かれた時はGUIがストリームを作り直す。それ以外のエラーはアンダーランとは限らないので記録だけする
+                log.push(LogEvent::StreamError(match err {
+                    cpal::StreamError::DeviceNotAvailable => {
+                        meter.report_device_lost();
+                        "device not available"
+                    }
+                    _ => "backend specific error",
+                }));
             },
             None,
         ),
-        _ => panic!("Unsupported sample format"),
+        format => return Err(format!("Unsupported sample format: {:?}", format)),
     }
-    .expect("Failed to build output stream");
+    .map_err(|err| format!("Failed to build output stream: {}", err))?;
 
     // ストリームを開始
-    stream.play().expect("Failed to start output stream");
-
     stream
-} 
\ No newline at end of file
+        .play()
+        .map_err(|err| format!("Failed to start output stream: {}", err))?;
+
+    Ok(CpalStream {
+        _stream: stream,
+        device_name,
+    })
+}
+
@@ -0,0 +1,93 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::audio::AudioSettings;
+
+/// GUIの配色
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    /// 選択できる配色
+    pub const ALL: [Theme; 2] = [Theme::Dark, Theme::Light];
+
+    /// 表示名
+    pub fn label(&self) -> &'static str {
+        match self {
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+        }
+    }
+
+    /// eguiの配色
+    pub fn visuals(&self) -> egui::Visuals {
+        match self {
+            Theme::Dark => egui::Visuals::dark(),
+            Theme::Light => egui::Visuals::light(),
+        }
+    }
+}
+
+/// アプリの設定（終了時に保存し、次の起動時に復元する）
+///
+/// MIDIポートと受信チャンネルは midi_config.json に保存する（接続を切り替えた時にも保存するため）
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// オーディオ出力のバックエンド・デバイス・サンプルレート・バッファサイズ
+    pub audio: AudioSettings,
+    /// GUIの配色
+    pub theme: Theme,
+    /// 最後に読み込み・保存したプリセットのパス
+    pub last_preset: Option<PathBuf>,
+    /// お気に入りにしたプリセットのパス
+    pub favorite_presets: Vec<PathBuf>,
+    /// ウィンドウの大きさ（論理ピクセル）
+    pub window_size: Option<[f32; 2]>,
+}
+
+impl AppConfig {
+    /// 設定をファイルから読み込む（ファイルがなければデフォルト）
+    pub fn load() -> io::Result<Self> {
+        let path = config_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// 設定を読み込み、失敗したらログに出してデフォルトを使う
+    pub fn load_or_default() -> Self {
+        Self::load().unwrap_or_else(|err| {
+            println!("Failed to load config: {}", err);
+            Self::default()
+        })
+    }
+
+    /// 設定をファイルに保存する
+    pub fn save(&self) -> io::Result<()> {
+        let path = config_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// アプリの設定を保存するファイルのパス
+pub fn config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("rust_synth")
+        .join("config.json")
+}
@@ -0,0 +1,113 @@
+use eframe::egui;
+
+use synth_core::envelope::{EnvelopeParams, shape_curve};
+
+/// ディレイ・アタック・ホールド・ディケイの最大時間（秒、EnvelopeManager の範囲と同じ）
+const MAX_STAGE_TIME: f32 = 5.0;
+
+/// リリースの最大時間（秒）
+const MAX_RELEASE_TIME: f32 = 10.0;
+
+/// 曲線を描く時の1段階あたりの分割数
+const CURVE_STEPS: usize = 24;
+
+/// ドラッグできる点の半径
+const HANDLE_RADIUS: f32 = 5.0;
+
+/// DAHDSRの曲線を描き、折れ点をドラッグして編集できるエディター
+///
+/// 横軸は段階ごとに同じ幅を割り当て、時間の平方根で位置を決める（短い時間も操作しやすいように）。
+/// 曲線は Envelope::update と同じく、段階ごとの曲線の設定（shape_curve）で描く。
+/// ループモードではディケイの終わりからアタックの始まりへ戻る線を重ねる。
+pub fn envelope_editor(ui: &mut egui::Ui, id: &str, params: &mut EnvelopeParams) {
+    let size = egui::vec2(ui.available_width(), 120.0);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, egui::Color32::from_gray(20));
+
+    let inner = rect.shrink(HANDLE_RADIUS + 1.0);
+    let segment = inner.width() / 6.0;
+    let width_of = |time: f32, max: f32| segment * (time.clamp(0.0, max) / max).sqrt();
+    let time_of = |width: f32, max: f32| max * (width / segment).clamp(0.0, 1.0).powi(2);
+    let y_of = |level: f32| inner.bottom() - inner.height() * level.clamp(0.0, 1.0);
+
+    // 折れ点の位置
+    let start = egui::pos2(inner.left(), y_of(0.0));
+    let delay_end = egui::pos2(start.x + width_of(params.delay, MAX_STAGE_TIME), start.y);
+    let attack_end = egui::pos2(delay_end.x + width_of(params.attack, MAX_STAGE_TIME), y_of(1.0));
+    let hold_end = egui::pos2(attack_end.x + width_of(params.hold, MAX_STAGE_TIME), attack_end.y);
+    let decay_end = egui::pos2(hold_end.x + width_of(params.decay, MAX_STAGE_TIME), y_of(params.sustain));
+    let sustain_end = egui::pos2(decay_end.x + segment, decay_end.y);
+    let release_end = egui::pos2(sustain_end.x + width_of(params.release, MAX_RELEASE_TIME), y_of(0.0));
+
+    // 曲線（各段階を曲線の設定で補間）
+    let mut points = Vec::with_capacity(CURVE_STEPS * 3 + 3);
+    points.push(start);
+    push_curve(&mut points, delay_end, attack_end, params.attack_curve);
+    push_curve(&mut points, hold_end, decay_end, params.decay_curve);
+    points.push(sustain_end);
+    push_curve(&mut points, sustain_end, release_end, params.release_curve);
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, egui::Color32::LIGHT_BLUE)));
+    if params.looping {
+        painter.extend(egui::Shape::dashed_line(
+            &[decay_end, egui::pos2(delay_end.x, decay_end.y)],
+            egui::Stroke::new(1.0, egui::Color32::GRAY),
+            4.0,
+            3.0,
+        ));
+    }
+
+    // 折れ点のドラッグ
+    let handles = [delay_end, attack_end, hold_end, decay_end, sustain_end, release_end];
+    for (i, &pos) in handles.iter().enumerate() {
+        let handle_rect = egui::Rect::from_center_size(pos, egui::vec2(HANDLE_RADIUS * 3.0, HANDLE_RADIUS * 3.0));
+        let response = ui.interact(handle_rect, egui::Id::new((id, i)), egui::Sense::drag());
+        let pointer = response.interact_pointer_pos();
+
+        if response.dragged() {
+            if let Some(pointer) = pointer {
+                match i {
+                    0 => params.delay = time_of(pointer.x - start.x, MAX_STAGE_TIME),
+                    1 => params.attack = time_of(pointer.x - delay_end.x, MAX_STAGE_TIME),
+                    2 => params.hold = time_of(pointer.x - attack_end.x, MAX_STAGE_TIME),
+                    3 => {
+                        params.decay = time_of(pointer.x - hold_end.x, MAX_STAGE_TIME);
+                        params.sustain = ((inner.bottom() - pointer.y) / inner.height()).clamp(0.0, 1.0);
+                    }
+                    4 => params.sustain = ((inner.bottom() - pointer.y) / inner.height()).clamp(0.0, 1.0),
+                    _ => params.release = time_of(pointer.x - sustain_end.x, MAX_RELEASE_TIME),
+                }
+            }
+        }
+
+        let color = if response.hovered() || response.dragged() {
+            egui::Color32::WHITE
+        } else {
+            egui::Color32::LIGHT_BLUE
+        };
+        painter.circle_filled(pos, HANDLE_RADIUS, color);
+    }
+}
+
+/// 段階ごとの曲線（-1.0-1.0）を編集する行（0で smoothstep、正で最初に速く動く）
+pub fn envelope_curve_controls(ui: &mut egui::Ui, params: &mut EnvelopeParams) {
+    ui.horizontal(|ui| {
+        ui.label("Curve");
+        for (value, prefix) in [
+            (&mut params.attack_curve, "A: "),
+            (&mut params.decay_curve, "D: "),
+            (&mut params.release_curve, "R: "),
+        ] {
+            ui.add(egui::DragValue::new(value).clamp_range(-1.0..=1.0).speed(0.01).prefix(prefix));
+        }
+    });
+}
+
+/// 2点の間を段階の曲線で結ぶ点を追加する
+fn push_curve(points: &mut Vec<egui::Pos2>, from: egui::Pos2, to: egui::Pos2, curve: f32) {
+    for step in 0..=CURVE_STEPS {
+        let u = step as f32 / CURVE_STEPS as f32;
+        let shaped = shape_curve(u, curve);
+        points.push(egui::pos2(from.x + (to.x - from.x) * u, from.y + (to.y - from.y) * shaped));
+    }
+}
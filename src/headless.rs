@@ -0,0 +1,186 @@
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use synth_core::arpeggiator::Arpeggiator;
+use synth_core::logger::Logger;
+use synth_core::midi_activity::MidiActivity;
+use synth_core::midi_queue::MidiQueue;
+use synth_core::note::NoteHandler;
+use synth_core::params::SynthParams;
+use synth_core::preset::Preset;
+use synth_core::render;
+use synth_core::smf::MidiFile;
+
+use crate::analyzer::SpectrumAnalyzer;
+use crate::audio::{self, AudioSettings, AudioSource};
+use crate::midi::setup_midi_callback;
+use crate::recorder::Recorder;
+
+/// コマンドラインの使い方
+pub const USAGE: &str = "\
+Usage:
+  rust_synth_gui --headless --midi <PORT> [--preset <FILE>] [--backend <NAME>]
+  rust_synth_gui --headless --render <OUT.wav> [--note <0-127>] [--velocity <1-127>]
+                 [--duration <SEC>] [--sample-rate <HZ>] [--preset <FILE>]
+  rust_synth_gui --headless --render <OUT.wav> --midi-file <IN.mid> [--sample-rate <HZ>] [--preset <FILE>]";
+
+/// GUIを使わずに動かす時の設定
+struct HeadlessOptions {
+    preset: Option<PathBuf>,    // 読み込むプリセット
+    midi_port: Option<String>,  // 演奏に使うMIDIポート（名前の一部でもよい）
+    backend: Option<String>,    // 演奏に使うオーディオバックエンド（名前の一部でもよい）
+    render: Option<PathBuf>,    // 書き出すWAVファイル
+    midi_file: Option<PathBuf>, // 書き出すMIDIファイル（指定しなければノートを1つ鳴らす）
+    note: u8,                   // 書き出すノート番号
+    velocity: u8,               // 書き出すノートのベロシティ
+    duration: f32,              // ノートを押している時間（秒）
+    sample_rate: u32,           // 書き出すサンプルレート
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        Self {
+            preset: None,
+            midi_port: None,
+            backend: None,
+            render: None,
+            midi_file: None,
+            note: 69, // A4
+            velocity: 100,
+            duration: 1.0,
+            sample_rate: 44100,
+        }
+    }
+}
+
+impl HeadlessOptions {
+    /// コマンドライン引数を解釈する（`--headless` 自体は無視する）
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--headless" {
+                continue;
+            }
+            let value = args.next().ok_or_else(|| format!("Missing value for {}", arg))?;
+            match arg.as_str() {
+                "--preset" => options.preset = Some(PathBuf::from(value)),
+                "--midi" => options.midi_port = Some(value.clone()),
+                "--backend" => options.backend = Some(value.clone()),
+                "--render" => options.render = Some(PathBuf::from(value)),
+                "--midi-file" => options.midi_file = Some(PathBuf::from(value)),
+                "--note" => options.note = parse_value::<u8>(arg, value)?.min(127),
+                "--velocity" => options.velocity = parse_value::<u8>(arg, value)?.clamp(1, 127),
+                "--duration" => options.duration = parse_value::<f32>(arg, value)?.max(0.0),
+                "--sample-rate" => options.sample_rate = parse_value::<u32>(arg, value)?.max(1),
+                _ => return Err(format!("Unknown option: {}", arg)),
+            }
+        }
+        if options.midi_port.is_some() == options.render.is_some() {
+            return Err("Specify either --midi or --render".to_string());
+        }
+        if options.midi_file.is_some() && options.render.is_none() {
+            return Err("--midi-file requires --render".to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// 数値の引数を解釈する
+fn parse_value<T: std::str::FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid value for {}: {}", arg, value))
+}
+
+/// GUIを使わずにシンセを動かす（MIDIで演奏するか、ノートをWAVに書き出す）
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = HeadlessOptions::parse(args)?;
+
+    let params = SynthParams::new();
+    if let Some(path) = &options.preset {
+        let (preset, skipped) =
+            Preset::load(path).map_err(|err| format!("Failed to load preset {}: {}", path.display(), err))?;
+        preset.apply(&params);
+        println!("Loaded preset: {}", path.display());
+        if !skipped.is_empty() {
+            println!("Preset settings could not be read, using the defaults: {}", skipped.join(", "));
+        }
+    }
+
+    if let Some(path) = &options.render {
+        render_to_wav(&options, params, path)
+    } else if let Some(port_name) = &options.midi_port {
+        play_midi(params, port_name, options.backend.as_deref())
+    } else {
+        Ok(())
+    }
+}
+
+/// MIDIファイル、またはノートを1つ鳴らした音をWAVファイルに書き出す
+fn render_to_wav(options: &HeadlessOptions, params: SynthParams, path: &Path) -> Result<(), String> {
+    let samples = if let Some(midi_path) = &options.midi_file {
+        let file = MidiFile::load(midi_path).map_err(|err| format!("Failed to load {}: {}", midi_path.display(), err))?;
+        render::render_midi_file(params, &file, options.sample_rate)
+    } else {
+        render::render_note(params, options.note, options.velocity, options.duration, options.sample_rate)
+    };
+    render::write_wav(path, &samples, options.sample_rate)
+        .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+    println!(
+        "Rendered {:.2}s to {}",
+        samples.len() as f32 / render::RENDER_CHANNELS as f32 / options.sample_rate as f32,
+        path.display(),
+    );
+    Ok(())
+}
+
+/// MIDIポートからの演奏を選んだバックエンドのデフォルトの出力で鳴らす（Enterで終了）
+fn play_midi(params: SynthParams, port_name: &str, backend_name: Option<&str>) -> Result<(), String> {
+    let backends = audio::available_backends();
+    let backend = match backend_name {
+        Some(name) => backends
+            .iter()
+            .find(|backend| backend.name().to_lowercase().contains(&name.to_lowercase()))
+            .ok_or_else(|| format!("Audio backend '{}' not available", name))?,
+        None => backends.first().ok_or("No audio backend available")?,
+    };
+
+    let mut midi_in = midir::MidiInput::new("rust_synth").map_err(|err| format!("Failed to create MIDI input: {}", err))?;
+    midi_in.ignore(midir::Ignore::None);
+    let ports = midi_in.ports();
+    let port = ports
+        .iter()
+        .find(|port| midi_in.port_name(port).is_ok_and(|name| name.contains(port_name)))
+        .cloned()
+        .ok_or_else(|| format!("MIDI port '{}' not found", port_name))?;
+
+    let notes = NoteHandler::new(Arc::new(Mutex::new(0.0)), params.clone());
+    let mut arpeggiator = Arpeggiator::new(notes.clone(), Arc::clone(&params.arp), Arc::clone(&params.transport));
+    let logger = Logger::new();
+    // 録音とスペクトラム表示は使わないが、ストリームに渡す口として用意する
+    let recorder = Recorder::new();
+    let analyzer = SpectrumAnalyzer::new();
+
+    let midi_queue = MidiQueue::new();
+
+    let source = AudioSource {
+        params,
+        notes,
+        parts: Vec::new(),
+        midi_queue: midi_queue.clone(),
+        record_tap: recorder.tap(),
+        analyzer_tap: analyzer.tap(),
+        log: logger.tap(),
+        midi_log: logger.tap(),
+    };
+    let _stream = backend.start(source, &AudioSettings::default())?;
+    // 受信ランプはないので数えるだけで読まない
+    let _connection = setup_midi_callback(midi_in, &port, midi_queue, Arc::new(MidiActivity::new()))
+        .map_err(|err| format!("Failed to connect MIDI port: {}", err))?;
+    println!("Listening on MIDI port '{}'. Press Enter to quit.", port_name);
+
+    let mut line = String::new();
+    let _ = std::io::stdin().lock().read_line(&mut line);
+    arpeggiator.stop();
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use std::sync::{Arc, Mutex};
+
+use jack::{
+    AudioOut, Client, ClientOptions, Control, Frames, NotificationHandler, Port, PortFlags, PortSpec, ProcessHandler,
+    ProcessScope,
+};
+use synth_core::logger::{LogEvent, LogTap};
+use synth_core::meter::MeterManager;
+
+use crate::audio::{AudioBackend, AudioCallback, AudioSettings, AudioSource, AudioStream, OutputDeviceInfo};
+
+/// JACKのグラフに表示されるクライアント名
+const CLIENT_NAME: &str = "rust_synth";
+
+/// 出力ポート名（左・右）
+const PORT_NAMES: [&str; 2] = ["out_left", "out_right"];
+
+/// JACK（PipeWireのJACK互換を含む）で出力するバックエンド
+///
+/// サンプルレートとバッファサイズはサーバーの設定に従う。
+/// 起動時にシステムの再生ポートへ自動で接続するが、他のアプリへのルーティングはパッチベイで変えられる。
+pub struct JackBackend;
+
+impl AudioBackend for JackBackend {
+    fn name(&self) -> &'static str {
+        "JACK"
+    }
+
+    fn is_configurable(&self) -> bool {
+        false
+    }
+
+    fn output_devices(&self) -> Vec<OutputDeviceInfo> {
+        // 出力先はJACKのグラフで接続するので、デバイスは選ばない
+        Vec::new()
+    }
+
+    fn start(&self, source: AudioSource, _settings: &AudioSettings) -> Result<Box<dyn AudioStream>, String> {
+        // サーバーが動いていない場合は勝手に起動しない
+        let (client, _status) = Client::new(CLIENT_NAME, ClientOptions::NO_START_SERVER)
+            .map_err(|err| format!("Failed to connect to JACK server: {}", err))?;
+        let [left_name, right_name] = PORT_NAMES;
+        let left = client
+            .register_port(left_name, AudioOut::default())
+            .map_err(|err| format!("Failed to register JACK port: {}", err))?;
+        let right = client
+            .register_port(right_name, AudioOut::default())
+            .map_err(|err| format!("Failed to register JACK port: {}", err))?;
+        println!(
+            "Starting JACK client '{}' at {}Hz (buffer {} frames)",
+            client.name(),
+            client.sample_rate(),
+            client.buffer_size(),
+        );
+
+        let meter = source.params.meter.clone();
+        let (callback, log) =
+            source.into_callback(client.sample_rate() as u32, PORT_NAMES.len() as u16, client.buffer_size() as usize);
+        // ログの口はプロセスコールバックと通知のスレッドで共有する（プロセスコールバックでは待たずに諦める）
+        let log = Arc::new(Mutex::new(log));
+        let process = JackProcess {
+            callback,
+            buffer: vec![0.0; client.buffer_size() as usize * PORT_NAMES.len()],
+            left,
+            right,
+            meter: meter.clone(),
+            log: Arc::clone(&log),
+            size_mismatch: false,
+        };
+        let notifications = JackNotifications { meter, log };
+        let active = client
+            .activate_async(notifications, process)
+            .map_err(|err| format!("Failed to activate JACK client: {}", err))?;
+
+        // システムの再生ポートに自動で接続する（接続できなくてもストリームは動かす）
+        let client = active.as_client();
+        let playback = client.ports(None, Some(AudioOut::default().jack_port_type()), PortFlags::IS_INPUT | PortFlags::IS_PHYSICAL);
+        for (port_name, destination) in PORT_NAMES.iter().zip(playback.iter()) {
+            let output = format!("{}:{}", client.name(), port_name);
+            if let Err(err) = client.connect_ports_by_name(&output, destination) {
+                println!("Failed to connect {} to {}: {}", output, destination, err);
+            }
+        }
+
+        Ok(Box::new(JackStream { _client: active }))
+    }
+}
+
+/// JACKのプロセスコールバック（エンジンの出力を左右のポートに分ける）
+struct JackProcess {
+    callback: AudioCallback,
+    buffer: Vec<f32>, // 左右交互のバッファ
+    left: Port<AudioOut>,
+    right: Port<AudioOut>,
+    meter: Arc<MeterManager>,
+    log: Arc<Mutex<LogTap>>,
+    size_mismatch: bool, // バッファサイズが合わないことをログに出したか（続く間は出し直さない）
+}
+
+impl ProcessHandler for JackProcess {
+    fn process(&mut self, _client: &Client, scope: &ProcessScope) -> Control {
+        let frames = scope.n_frames() as usize;
+        let left = self.left.as_mut_slice(scope);
+        let right = self.right.as_mut_slice(scope);
+        let buffer = self
+            .buffer
+            .get_mut(..frames * PORT_NAMES.len())
+            .filter(|_| left.len() == frames && right.len() == frames);
+        let Some(buffer) = buffer else {
+            // 前のサイクルの内容が鳴り続けないように無音にして、ドロップアウトとして数える
+            left.fill(0.0);
+            right.fill(0.0);
+            self.meter.report_xrun();
+            if !self.size_mismatch {
+                self.size_mismatch = true;
+                if let Ok(mut log) = self.log.try_lock() {
+                    log.push(LogEvent::StreamError("JACK buffer size mismatch"));
+                }
+            }
+            return Control::Continue;
+        };
+        self.size_mismatch = false;
+        self.callback.process(buffer);
+
+        for ((frame, left), right) in buffer.chunks(PORT_NAMES.len()).zip(left.iter_mut()).zip(right.iter_mut()) {
+            *left = frame[0];
+            *right = frame[1];
+        }
+        Control::Continue
+    }
+
+    fn buffer_size(&mut self, _client: &Client, size: Frames) -> Control {
+        // バッファサイズの変更はプロセスコールバックの外で通知されるので、ここで確保し直す
+        self.buffer.resize(size as usize * PORT_NAMES.len(), 0.0);
+        Control::Continue
+    }
+}
+
+/// JACKサーバーからの通知（アンダーランをメーターとログに伝える）
+struct JackNotifications {
+    meter: Arc<MeterManager>,
+    log: Arc<Mutex<LogTap>>,
+}
+
+impl NotificationHandler for JackNotifications {
+    fn xrun(&mut self, _client: &Client) -> Control {
+        self.meter.report_xrun();
+        if let Ok(mut log) = self.log.lock() {
+            log.push(LogEvent::StreamError("JACK xrun"));
+        }
+        Control::Continue
+    }
+}
+
+/// 動作中のJACKクライアント（ドロップすると停止してグラフから外れる）
+struct JackStream {
+    _client: jack::AsyncClient<JackNotifications, JackProcess>,
+}
+
+impl AudioStream for JackStream {}
@@ -0,0 +1,130 @@
+use eframe::egui::{self, Key};
+
+use synth_core::note::NoteHandler;
+
+/// PCキーボードのキーと半音オフセットの対応（DAWでよく使われる配列）
+const KEY_MAP: [(Key, u8); 29] = [
+    // 下段：Z=C, S=C#, X=D, ... M=B
+    (Key::Z, 0),
+    (Key::S, 1),
+    (Key::X, 2),
+    (Key::D, 3),
+    (Key::C, 4),
+    (Key::V, 5),
+    (Key::G, 6),
+    (Key::B, 7),
+    (Key::H, 8),
+    (Key::N, 9),
+    (Key::J, 10),
+    (Key::M, 11),
+    // 上段：Q=C（1オクターブ上）, 2=C#, W=D, ... P=E
+    (Key::Q, 12),
+    (Key::Num2, 13),
+    (Key::W, 14),
+    (Key::Num3, 15),
+    (Key::E, 16),
+    (Key::R, 17),
+    (Key::Num5, 18),
+    (Key::T, 19),
+    (Key::Num6, 20),
+    (Key::Y, 21),
+    (Key::Num7, 22),
+    (Key::U, 23),
+    (Key::I, 24),
+    (Key::Num9, 25),
+    (Key::O, 26),
+    (Key::Num0, 27),
+    (Key::P, 28),
+];
+
+/// オクターブの範囲
+const MIN_OCTAVE: i32 = 0;
+const MAX_OCTAVE: i32 = 8;
+
+/// PCキーボードからのノート入力を管理する構造体
+pub struct KeyboardInput {
+    octave: i32,            // 下段Zキーのオクターブ（4ならC4 = ノート60）
+    pressed: Vec<(Key, u8)>, // 押されているキーと発音したノート番号
+}
+
+impl Default for KeyboardInput {
+    fn default() -> Self {
+        Self {
+            octave: 4,
+            pressed: Vec::new(),
+        }
+    }
+}
+
+impl KeyboardInput {
+    /// 現在のオクターブを取得する
+    pub fn octave(&self) -> i32 {
+        self.octave
+    }
+
+    /// キー入力を処理してノートオン/オフを送る（ノートオンがあれば true を返す）
+    pub fn handle_input(&mut self, ctx: &egui::Context, notes: &NoteHandler) -> bool {
+        // テキスト入力中はキーボード演奏を無効にする
+        if ctx.wants_keyboard_input() {
+            return false;
+        }
+
+        // 今フレームのキーイベントを集める
+        let events: Vec<(Key, bool)> = ctx.input(|i| {
+            i.events
+                .iter()
+                .filter_map(|event| match event {
+                    egui::Event::Key {
+                        key,
+                        pressed,
+                        repeat: false,
+                        modifiers,
+                        ..
+                    } if !modifiers.command && !modifiers.ctrl && !modifiers.alt => Some((*key, *pressed)),
+                    _ => None,
+                })
+                .collect()
+        });
+
+        let mut note_on = false;
+        for (key, pressed) in events {
+            match (key, pressed) {
+                // オクターブ切り替え（-/+）
+                (Key::Minus, true) => {
+                    self.octave = (self.octave - 1).max(MIN_OCTAVE);
+                    println!("Keyboard octave: {}", self.octave);
+                }
+                (Key::PlusEquals, true) => {
+                    self.octave = (self.octave + 1).min(MAX_OCTAVE);
+                    println!("Keyboard octave: {}", self.octave);
+                }
+                (key, true) => {
+                    if self.pressed.iter().any(|(k, _)| *k == key) {
+                        continue;
+                    }
+                    if let Some(note) = self.key_to_note(key) {
+                        self.pressed.push((key, note));
+                        notes.note_on(note, 100);
+                        note_on = true;
+                    }
+                }
+                (key, false) => {
+                    // 押した時のノートをリリースする（オクターブ変更後も正しく止まるように）
+                    if let Some(index) = self.pressed.iter().position(|(k, _)| *k == key) {
+                        let (_, note) = self.pressed.remove(index);
+                        notes.note_off(note);
+                    }
+                }
+            }
+        }
+
+        note_on
+    }
+
+    /// キーをMIDIノート番号に変換する
+    fn key_to_note(&self, key: Key) -> Option<u8> {
+        let (_, offset) = KEY_MAP.iter().find(|(k, _)| *k == key)?;
+        let note = (self.octave + 1) * 12 + *offset as i32;
+        u8::try_from(note).ok().filter(|&n| n <= 127)
+    }
+}
@@ -1,8 +1,19 @@
+mod analyzer;
 mod app;
 mod audio;
+mod config;
+mod envelope_editor;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+#[cfg(all(target_os = "linux", feature = "jack"))]
+mod jack_audio;
+mod keyboard;
 mod midi;
-mod unison;
-mod oscillator;
+mod recorder;
+mod session;
+#[cfg(target_arch = "wasm32")]
+mod web_audio;
+mod xy_pad;
 
 // 標準ライブラリから、円周率（PI）を使用
 use std::f32::consts::PI;
@@ -19,14 +30,30 @@ use eframe::{egui, App};
 // MIDI関連のインポート
 use midir::{MidiInput, MidiInputConnection};
 
+// MIDIノート番号から周波数への変換
+use synth_core::tuning::{Tuning, TuningSettings};
+
+#[cfg(not(target_arch = "wasm32"))]
 use eframe::NativeOptions;
 
 /// アプリケーションのエントリーポイント（GUIの初期化）
+#[cfg(not(target_arch = "wasm32"))]
 fn main() -> Result<(), eframe::Error> {
-    // ウィンドウ設定を定義（タイトルとウィンドウサイズ）
+    // --headless が指定されたらGUIを使わずに動かす
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--headless") {
+        if let Err(err) = headless::run(&args) {
+            eprintln!("{}\n\n{}", err, headless::USAGE);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // ウィンドウ設定を定義（タイトルとウィンドウサイズ、前回終了時の大きさがあればそれを使う）
+    let window_size = config::AppConfig::load_or_default().window_size.unwrap_or([480.0, 720.0]);
     let options = NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([300.0, 200.0])  // ウィンドウの初期サイズ
+            .with_inner_size(window_size)     // ウィンドウの初期サイズ
             .with_title("Rust Synth"),        // ウィンドウタイトル
         ..Default::default()
     };
@@ -39,6 +66,23 @@ fn main() -> Result<(), eframe::Error> {
     )
 }
 
+/// ブラウザでのエントリーポイント（ページ内のキャンバスにGUIを描画する）
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    wasm_bindgen_futures::spawn_local(async {
+        let result = eframe::WebRunner::new()
+            .start(
+                "the_canvas_id", // index.html のキャンバスのID
+                eframe::WebOptions::default(),
+                Box::new(|_cc| Box::new(app::SynthApp::default())),
+            )
+            .await;
+        if let Err(err) = result {
+            web_sys::console::error_2(&"Failed to start app:".into(), &err);
+        }
+    });
+}
+
 /// アプリの状態を表す構造体
 struct SynthApp {
     freq: f32, // 再生する周波数（Hz）
@@ -144,7 +188,7 @@ impl App for SynthApp {
                                     0x90 => { // Note On
                                         if velocity > 0 { // ベロシティ > 0
                                             // 周波数を更新（MIDIノート番号から周波数に変換）
-                                            let freq = 440.0 * 2.0f32.powf((note as f32 - 69.0) / 12.0);
+                                            let freq = TuningSettings::default().note_to_freq(note);
                                             println!("Note On: note={}, freq={:.2}Hz", note, freq);
                                             
                                             if let Ok(mut freq_lock) = current_freq.lock() {
@@ -235,8 +279,10 @@ fn play_sine_wave(_freq: f32, current_freq: Arc<Mutex<f32>>) -> cpal::Stream {
     // 出力デバイスの設定（例：44100Hz, f32型など）を取得
     let config = device.default_output_config().unwrap();
     let sample_rate = config.sample_rate().0 as f32;
+    // 1フレームあたりのチャンネル数（ステレオなら2、バッファは左右交互に並ぶ）
+    let channels = config.channels().max(1) as usize;
 
-    println!("Audio stream started with sample rate: {}Hz", sample_rate);
+    println!("Audio stream started with sample rate: {}Hz ({} channels)", sample_rate, channels);
 
     // 音の時間位置を追跡するための変数を作成（スレッド安全）
     let t = Arc::new(Mutex::new(0.0_f32));
@@ -259,12 +305,14 @@ fn play_sine_wave(_freq: f32, current_freq: Arc<Mutex<f32>>) -> cpal::Stream {
                     440.0 // デフォルト周波数
                 };
 
-                // 出力バッファにサンプルを書き込む
-                for sample in data.iter_mut() {
+                // 出力バッファにフレーム単位で書き込む（1フレームに1サンプルを生成し、全チャンネルに同じ値を書く）
+                for frame in data.chunks_mut(channels) {
                     // サイン波の式 sin(2πft)
                     let value = (2.0 * PI * freq * *t).sin() * 0.2; // 0.2 = 音量
-                    *sample = value; // バッファに書き込む
-                    *t += 1.0 / sample_rate; // 時間を進める
+                    for sample in frame.iter_mut() {
+                        *sample = value; // バッファに書き込む
+                    }
+                    *t += 1.0 / sample_rate; // 時間を進める（チャンネル数に関係なく1フレームごと）
                 }
             },
             move |err| {
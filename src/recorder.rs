@@ -0,0 +1,240 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use ringbuf::{HeapConsumer, HeapProducer, HeapRb};
+
+/// リングバッファの容量（サンプル数、48kHzステレオで約2.7秒分）
+const RING_CAPACITY: usize = 1 << 18;
+
+/// 書き込みスレッドへの命令
+enum RecorderCommand {
+    Start(PathBuf, hound::WavSpec), // 指定したファイルへの録音を開始
+    Stop,                           // 録音を終了してファイルを閉じる
+}
+
+/// オーディオコールバックから録音用のサンプルを送る口（オーディオスレッドが保持する）
+#[derive(Clone)]
+pub struct RecordTap {
+    producer: Arc<Mutex<HeapProducer<f32>>>, // オーディオスレッドだけが使うので try_lock は常に成功する
+    recording: Arc<AtomicBool>,
+    sample_rate: Arc<AtomicU32>,
+    channels: Arc<AtomicU32>,
+}
+
+impl RecordTap {
+    /// ストリームのフォーマットを設定する（ストリーム構築時に呼ぶ）
+    pub fn set_format(&self, sample_rate: u32, channels: u16) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+        self.channels.store(channels as u32, Ordering::Relaxed);
+    }
+
+    /// 出力したサンプルをリングバッファに積む（ブロックしない）
+    pub fn push(&self, data: &[f32]) {
+        if !self.recording.load(Ordering::Relaxed) {
+            return;
+        }
+        if let Ok(mut producer) = self.producer.try_lock() {
+            // 溢れた分は捨てる（オーディオスレッドを待たせない）
+            producer.push_slice(data);
+        }
+    }
+}
+
+/// WAV録音を管理する構造体
+pub struct Recorder {
+    tap: RecordTap,
+    command_tx: Option<Sender<RecorderCommand>>,
+    writer_thread: Option<JoinHandle<()>>,
+    started_at: Option<Instant>,
+    current_path: Option<PathBuf>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        let (producer, consumer) = HeapRb::<f32>::new(RING_CAPACITY).split();
+        let (command_tx, command_rx) = mpsc::channel();
+
+        // ファイル書き込みは別スレッドで行う（スレッドを作れない環境では録音できない）
+        let writer_thread = thread::Builder::new()
+            .name("recorder".to_string())
+            .spawn(move || writer_loop(consumer, command_rx))
+            .inspect_err(|err| println!("Failed to start recorder thread: {}", err))
+            .ok();
+
+        Self {
+            tap: RecordTap {
+                producer: Arc::new(Mutex::new(producer)),
+                recording: Arc::new(AtomicBool::new(false)),
+                sample_rate: Arc::new(AtomicU32::new(44100)),
+                channels: Arc::new(AtomicU32::new(2)),
+            },
+            command_tx: Some(command_tx),
+            writer_thread,
+            started_at: None,
+            current_path: None,
+        }
+    }
+
+    /// オーディオコールバックに渡す録音口を取得する
+    pub fn tap(&self) -> RecordTap {
+        self.tap.clone()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.tap.recording.load(Ordering::Relaxed)
+    }
+
+    /// 録音開始からの経過時間
+    pub fn elapsed(&self) -> Duration {
+        self.started_at.map(|start| start.elapsed()).unwrap_or_default()
+    }
+
+    /// 録音中のファイルのパス
+    pub fn current_path(&self) -> Option<&PathBuf> {
+        self.current_path.as_ref()
+    }
+
+    /// タイムスタンプ付きのWAVファイルへの録音を開始する
+    pub fn start(&mut self) {
+        if self.is_recording() {
+            return;
+        }
+
+        let file_name = format!("rust_synth_{}.wav", chrono::Local::now().format("%Y%m%d_%H%M%S"));
+        let path = recordings_dir().join(file_name);
+        let spec = hound::WavSpec {
+            channels: self.tap.channels.load(Ordering::Relaxed) as u16,
+            sample_rate: self.tap.sample_rate.load(Ordering::Relaxed),
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+
+        if let Some(tx) = &self.command_tx {
+            if tx.send(RecorderCommand::Start(path.clone(), spec)).is_ok() {
+                // 書き込みスレッドに命令を送ってからサンプルを流し始める
+                self.tap.recording.store(true, Ordering::Relaxed);
+                self.started_at = Some(Instant::now());
+                self.current_path = Some(path);
+            }
+        }
+    }
+
+    /// 録音を停止してファイルを書き出す
+    pub fn stop(&mut self) {
+        if !self.is_recording() {
+            return;
+        }
+
+        // サンプルの送信を止めてから、残りを書き出すよう命令する
+        self.tap.recording.store(false, Ordering::Relaxed);
+        if let Some(tx) = &self.command_tx {
+            let _ = tx.send(RecorderCommand::Stop);
+        }
+        self.started_at = None;
+        self.current_path = None;
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.stop();
+        // 送信側を閉じると書き込みスレッドが終了する
+        self.command_tx = None;
+        if let Some(handle) = self.writer_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// 録音ファイルの保存ディレクトリを取得する
+pub fn recordings_dir() -> PathBuf {
+    dirs::audio_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("rust_synth")
+}
+
+/// 書き込みスレッドの本体：リングバッファからサンプルを取り出してWAVに書き込む
+fn writer_loop(mut consumer: HeapConsumer<f32>, command_rx: Receiver<RecorderCommand>) {
+    let mut writer: Option<(PathBuf, hound::WavWriter<std::io::BufWriter<std::fs::File>>)> = None;
+    let mut buffer = vec![0.0f32; 4096];
+
+    loop {
+        // 命令を処理する
+        match command_rx.try_recv() {
+            Ok(RecorderCommand::Start(path, spec)) => {
+                finish_file(&mut writer, &mut consumer, &mut buffer);
+                consumer.clear();
+                if let Some(parent) = path.parent() {
+                    let _ = std::fs::create_dir_all(parent);
+                }
+                match hound::WavWriter::create(&path, spec) {
+                    Ok(wav) => {
+                        println!("Recording to {}", path.display());
+                        writer = Some((path, wav));
+                    }
+                    Err(err) => println!("Failed to create WAV file {}: {}", path.display(), err),
+                }
+            }
+            Ok(RecorderCommand::Stop) => {
+                finish_file(&mut writer, &mut consumer, &mut buffer);
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => {
+                finish_file(&mut writer, &mut consumer, &mut buffer);
+                break;
+            }
+        }
+
+        // 録音中ならリングバッファの中身を書き込む
+        let written = match writer.as_mut() {
+            Some((_, wav)) => write_pending(wav, &mut consumer, &mut buffer),
+            None => 0,
+        };
+
+        if written == 0 {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+/// リングバッファに溜まっているサンプルを書き込み、書き込んだ数を返す
+fn write_pending(
+    wav: &mut hound::WavWriter<std::io::BufWriter<std::fs::File>>,
+    consumer: &mut HeapConsumer<f32>,
+    buffer: &mut [f32],
+) -> usize {
+    let mut total = 0;
+    loop {
+        let count = consumer.pop_slice(buffer);
+        if count == 0 {
+            break;
+        }
+        for &sample in &buffer[..count] {
+            if wav.write_sample(sample).is_err() {
+                return total;
+            }
+        }
+        total += count;
+    }
+    total
+}
+
+/// 残りのサンプルを書き込んでファイルを閉じる
+fn finish_file(
+    writer: &mut Option<(PathBuf, hound::WavWriter<std::io::BufWriter<std::fs::File>>)>,
+    consumer: &mut HeapConsumer<f32>,
+    buffer: &mut [f32],
+) {
+    if let Some((path, mut wav)) = writer.take() {
+        write_pending(&mut wav, consumer, buffer);
+        match wav.finalize() {
+            Ok(()) => println!("Saved recording: {}", path.display()),
+            Err(err) => println!("Failed to finalize WAV file {}: {}", path.display(), err),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use synth_core::part::MultiTimbralSettings;
+use synth_core::preset::Preset;
+
+/// 自動保存したセッション（正常に終了した時は消すので、起動時に残っていたら前回は異常終了している）
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// シンセの全設定
+    pub preset: Preset,
+    /// 最後に読み込み・保存したプリセットのパス
+    pub preset_path: Option<PathBuf>,
+    /// 追加のパートのチャンネル・音量・定位
+    pub parts: MultiTimbralSettings,
+    /// 追加のパートの音色（パートの順）
+    pub part_presets: Vec<Preset>,
+}
+
+impl Session {
+    /// 前回のセッションが残っていれば読み込む（読み込めなければログに出して捨てる）
+    pub fn recover() -> Option<Self> {
+        let path = session_path();
+        if !path.exists() {
+            return None;
+        }
+        let session = fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|json| serde_json::from_str::<Session>(&json).map_err(|err| err.to_string()));
+        match session {
+            Ok(session) => Some(session),
+            Err(err) => {
+                println!("Failed to recover session: {}", err);
+                clear();
+                None
+            }
+        }
+    }
+
+    /// JSONにする（前回と同じなら書かずに済ませるために、書き込みとは分けてある）
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// JSONにしたセッションをファイルに書き込む（書き込み中に落ちても前回の内容が壊れないように、一時ファイルから置き換える）
+pub fn save(json: &str) -> io::Result<()> {
+    let path = session_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, json)?;
+    fs::rename(&temp, &path)
+}
+
+/// 自動保存したセッションを消す（正常に終了した時と、復元しないことにした時に呼ぶ）
+pub fn clear() {
+    let path = session_path();
+    if path.exists() {
+        if let Err(err) = fs::remove_file(&path) {
+            println!("Failed to remove session {}: {}", path.display(), err);
+        }
+    }
+}
+
+/// 自動保存したセッションのファイルのパス
+pub fn session_path() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("rust_synth")
+        .join("session.json")
+}
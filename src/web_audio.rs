@@ -0,0 +1,251 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    AudioContext, AudioWorkletNode, AudioWorkletNodeOptions, Blob, BlobPropertyBag, Event, MessageEvent, MidiAccess, MidiInput,
+    MidiMessageEvent, Url,
+};
+
+use synth_core::midi_queue::MidiQueue;
+
+use crate::audio::{AudioBackend, AudioCallback, AudioSettings, AudioSource, AudioStream, OutputDeviceInfo};
+
+/// メインスレッドで一度に生成するフレーム数
+const BLOCK_SIZE: usize = 256;
+
+/// AudioWorklet側で先読みしておくブロック数（多いほど途切れにくいが遅延が増える）
+const QUEUE_BLOCKS: u32 = 4;
+
+/// 出力のチャンネル数（ステレオ）
+const CHANNELS: u16 = 2;
+
+/// AudioWorkletに登録するプロセッサ名
+const PROCESSOR_NAME: &str = "rust-synth";
+
+/// AudioWorkletで動かすプロセッサ
+///
+/// メインスレッドから届いたブロック（左右交互）を順に出力し、
+/// 先読みが足りなくなったら次のブロックを要求する
+const PROCESSOR_SOURCE: &str = r#"
+class RustSynthProcessor extends AudioWorkletProcessor {
+  constructor(options) {
+    super();
+    this.queueBlocks = options.processorOptions.queueBlocks;
+    this.queue = [];
+    this.offset = 0;
+    this.requested = 0;
+    this.port.onmessage = (event) => {
+      this.queue.push(event.data);
+      this.requested -= 1;
+    };
+  }
+
+  process(inputs, outputs) {
+    const output = outputs[0];
+    for (let i = 0; i < output[0].length; i++) {
+      const block = this.queue[0];
+      for (let channel = 0; channel < output.length; channel++) {
+        output[channel][i] = block ? block[this.offset * 2 + Math.min(channel, 1)] : 0;
+      }
+      if (block) {
+        this.offset += 1;
+        if (this.offset * 2 >= block.length) {
+          this.queue.shift();
+          this.offset = 0;
+        }
+      }
+    }
+    while (this.queue.length + this.requested < this.queueBlocks) {
+      this.port.postMessage(null);
+      this.requested += 1;
+    }
+    return true;
+  }
+}
+
+registerProcessor("rust-synth", RustSynthProcessor);
+"#;
+
+/// ブラウザのWeb Audio（AudioWorklet）で出力するバックエンド
+///
+/// wasm32ではオーディオスレッドでRustのコードを動かせないので、
+/// メインスレッドでエンジンを回し、生成したブロックをAudioWorkletに送る。
+/// MIDI入力はWeb MIDIで受け取り、全ての入力ポートのメッセージを同じキューに積む
+pub struct WebAudioBackend;
+
+impl AudioBackend for WebAudioBackend {
+    fn name(&self) -> &'static str {
+        "Web Audio"
+    }
+
+    fn is_configurable(&self) -> bool {
+        false
+    }
+
+    fn output_devices(&self) -> Vec<OutputDeviceInfo> {
+        // ブラウザでは出力先を選べないので、既定の出力だけを使う
+        Vec::new()
+    }
+
+    fn start(&self, source: AudioSource, _settings: &AudioSettings) -> Result<Box<dyn AudioStream>, String> {
+        let context = AudioContext::new().map_err(|err| format!("Failed to create AudioContext: {:?}", err))?;
+        let midi_queue = source.midi_queue.clone();
+        let (callback, _log) = source.into_callback(context.sample_rate() as u32, CHANNELS, BLOCK_SIZE);
+
+        // AudioWorkletの読み込みは非同期なので、終わったらストリームに持たせる
+        let worklet = Rc::new(RefCell::new(None));
+        let pending = Rc::clone(&worklet);
+        let worklet_context = context.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            match connect_worklet(&worklet_context, callback).await {
+                Ok(connected) => *pending.borrow_mut() = Some(connected),
+                Err(err) => web_sys::console::error_2(&"Failed to start audio worklet:".into(), &err),
+            }
+        });
+
+        // MIDIアクセスの許可も非同期なので、許可されたらストリームに持たせる（許可されなければMIDIなしで鳴らす）
+        let midi = Rc::new(RefCell::new(None));
+        let pending = Rc::clone(&midi);
+        wasm_bindgen_futures::spawn_local(async move {
+            match connect_midi(midi_queue).await {
+                // 許可される前にストリームが止められていたら、ハンドラを外して捨てる
+                Ok(connected) if Rc::strong_count(&pending) == 1 => connected.detach(),
+                Ok(connected) => *pending.borrow_mut() = Some(connected),
+                Err(err) => web_sys::console::warn_2(&"Web MIDI is not available:".into(), &err),
+            }
+        });
+
+        // ブラウザの自動再生制限で止まっている場合に備えて再開する（ユーザー操作から呼ばれる）
+        let _ = context.resume();
+        Ok(Box::new(WebAudioStream { context, worklet, midi }))
+    }
+}
+
+/// 接続したAudioWorkletNodeと、ブロックを生成するメッセージハンドラ
+struct Worklet {
+    node: AudioWorkletNode,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+/// Web MIDIのアクセスと、入力ポートのメッセージをキューに積むハンドラ
+struct WebMidi {
+    access: MidiAccess,
+    _on_message: Closure<dyn FnMut(MidiMessageEvent)>,
+    _on_state_change: Closure<dyn FnMut(Event)>,
+}
+
+impl WebMidi {
+    /// 入力ポートとポートの接続の変化のハンドラを外す
+    fn detach(self) {
+        self.access.set_onstatechange(None);
+        Self::set_handler(&self.access, None);
+    }
+
+    /// 全ての入力ポートのハンドラを設定する（`None` で外す）
+    fn set_handler(access: &MidiAccess, handler: Option<&js_sys::Function>) {
+        // 入力ポートの一覧は [ID, ポート] の組を返すMapと同じ形
+        let Ok(Some(entries)) = js_sys::try_iter(&access.inputs()) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            if let Ok(input) = js_sys::Array::from(&entry).get(1).dyn_into::<MidiInput>() {
+                input.set_onmidimessage(handler);
+            }
+        }
+    }
+}
+
+/// 再生中のWeb Audioの出力（ドロップするとコンテキストを閉じ、MIDI入力のハンドラを外す）
+struct WebAudioStream {
+    context: AudioContext,
+    worklet: Rc<RefCell<Option<Worklet>>>,
+    midi: Rc<RefCell<Option<WebMidi>>>,
+}
+
+impl AudioStream for WebAudioStream {}
+
+impl Drop for WebAudioStream {
+    fn drop(&mut self) {
+        if let Some(worklet) = self.worklet.borrow_mut().take() {
+            if let Ok(port) = worklet.node.port() {
+                port.set_onmessage(None);
+            }
+            let _ = worklet.node.disconnect();
+        }
+        if let Some(midi) = self.midi.borrow_mut().take() {
+            midi.detach();
+        }
+        let _ = self.context.close();
+    }
+}
+
+/// プロセッサを読み込んでAudioWorkletNodeを出力につなぐ
+async fn connect_worklet(context: &AudioContext, mut callback: AudioCallback) -> Result<Worklet, JsValue> {
+    // プロセッサのソースをBlob URLにしてAudioWorkletに読み込ませる
+    let parts = js_sys::Array::of1(&JsValue::from_str(PROCESSOR_SOURCE));
+    let mut blob_options = BlobPropertyBag::new();
+    blob_options.type_("text/javascript");
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &blob_options)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+    JsFuture::from(context.audio_worklet()?.add_module(&url)?).await?;
+    Url::revoke_object_url(&url)?;
+
+    let processor_options = js_sys::Object::new();
+    js_sys::Reflect::set(&processor_options, &"queueBlocks".into(), &QUEUE_BLOCKS.into())?;
+    let mut options = AudioWorkletNodeOptions::new();
+    options
+        .number_of_inputs(0)
+        .output_channel_count(&js_sys::Array::of1(&(CHANNELS as u32).into()))
+        .processor_options(Some(&processor_options));
+    let node = AudioWorkletNode::new_with_options(context, PROCESSOR_NAME, &options)?;
+    node.connect_with_audio_node(&context.destination())?;
+
+    // プロセッサから要求が来るたびに1ブロック生成して送り返す（バッファの所有権ごと渡す）
+    let port = node.port()?;
+    let reply_port = port.clone();
+    let mut buffer = vec![0.0f32; BLOCK_SIZE * CHANNELS as usize];
+    let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |_event: MessageEvent| {
+        callback.process(&mut buffer);
+        let block = js_sys::Float32Array::from(buffer.as_slice());
+        let _ = reply_port.post_message_with_transferable(&block, &js_sys::Array::of1(&block.buffer()));
+    });
+    port.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+    Ok(Worklet {
+        node,
+        _on_message: on_message,
+    })
+}
+
+/// Web MIDIのアクセスを要求し、全ての入力ポートのメッセージをキューに積む
+///
+/// 後から接続されたポートにも同じハンドラを設定する
+async fn connect_midi(queue: MidiQueue) -> Result<WebMidi, JsValue> {
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?;
+    let access: MidiAccess = JsFuture::from(window.navigator().request_midi_access()?).await?.dyn_into()?;
+
+    // メッセージは受信時刻をつけて積むだけにし、エンジンがブロック内の位置で反映する
+    let on_message = Closure::<dyn FnMut(MidiMessageEvent)>::new(move |event: MidiMessageEvent| {
+        if let Ok(data) = event.data() {
+            queue.push(&data);
+        }
+    });
+    WebMidi::set_handler(&access, Some(on_message.as_ref().unchecked_ref()));
+
+    // ポートの接続が変わったら、新しい入力ポートにもハンドラを設定する
+    let handler: js_sys::Function = on_message.as_ref().unchecked_ref::<js_sys::Function>().clone();
+    let state_access = access.clone();
+    let on_state_change = Closure::<dyn FnMut(Event)>::new(move |_event: Event| {
+        WebMidi::set_handler(&state_access, Some(&handler));
+    });
+    access.set_onstatechange(Some(on_state_change.as_ref().unchecked_ref()));
+
+    Ok(WebMidi {
+        access,
+        _on_message: on_message,
+        _on_state_change: on_state_change,
+    })
+}
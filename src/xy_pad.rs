@@ -0,0 +1,39 @@
+use eframe::egui;
+
+/// パッドの大きさ（正方形の一辺）
+const PAD_SIZE: f32 = 200.0;
+/// 位置を示す点の半径
+const HANDLE_RADIUS: f32 = 6.0;
+
+/// XYパッド（ドラッグで横・縦の位置を 0.0〜1.0 で動かす、下が0）
+///
+/// 位置が変わったら true を返す
+pub fn xy_pad(ui: &mut egui::Ui, x: &mut f32, y: &mut f32) -> bool {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(PAD_SIZE, PAD_SIZE), egui::Sense::click_and_drag());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, egui::Color32::from_gray(20));
+
+    let inner = rect.shrink(HANDLE_RADIUS);
+    let mut changed = false;
+    if response.dragged() || response.clicked() {
+        if let Some(pointer) = response.interact_pointer_pos() {
+            *x = ((pointer.x - inner.left()) / inner.width()).clamp(0.0, 1.0);
+            *y = ((inner.bottom() - pointer.y) / inner.height()).clamp(0.0, 1.0);
+            changed = true;
+        }
+    }
+
+    // 十字線と現在位置
+    let pos = egui::pos2(inner.left() + inner.width() * *x, inner.bottom() - inner.height() * *y);
+    let stroke = egui::Stroke::new(1.0, egui::Color32::DARK_GRAY);
+    painter.line_segment([egui::pos2(rect.left(), pos.y), egui::pos2(rect.right(), pos.y)], stroke);
+    painter.line_segment([egui::pos2(pos.x, rect.top()), egui::pos2(pos.x, rect.bottom())], stroke);
+    let color = if response.hovered() || response.dragged() {
+        egui::Color32::WHITE
+    } else {
+        egui::Color32::LIGHT_BLUE
+    };
+    painter.circle_filled(pos, HANDLE_RADIUS, color);
+
+    changed
+}
@@ -0,0 +1,102 @@
+//! Unisonオシレータのスカラー版とSIMD版の速さを比べるベンチマーク
+//!
+//! `cargo bench -p synth-core --bench unison` で実行する（8ボイスのポリフォニー × 8ボイスのUnison）
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use synth_core::mixer::MixerSettings;
+use synth_core::oscillator::{OscBSettings, OscillatorMode, OscillatorSettings, Waveform};
+use synth_core::unison::{MAX_UNISON_VOICES, UnisonOscillator, UnisonSettings};
+
+/// 同時に鳴らすボイスの数
+const POLYPHONY: usize = 8;
+/// 1回の計測で生成する秒数
+const SECONDS: usize = 2;
+const SAMPLE_RATE: f32 = 48000.0;
+
+/// どちらの実装で生成するか
+#[derive(Clone, Copy)]
+enum Kernel {
+    Scalar,
+    Simd,
+}
+
+/// POLYPHONY ボイス分を SECONDS 秒生成するのにかかった時間を測る
+fn run(
+    kernel: Kernel,
+    settings: &UnisonSettings,
+    osc_settings: &OscillatorSettings,
+    osc_b: &OscBSettings,
+    mixer: &MixerSettings,
+) -> Duration {
+    let mut oscillators: Vec<UnisonOscillator> = (0..POLYPHONY).map(|i| UnisonOscillator::new(i as u32 + 1)).collect();
+    for oscillator in oscillators.iter_mut() {
+        oscillator.reset(settings);
+    }
+
+    let start = Instant::now();
+    let mut sum = 0.0;
+    for frame in 0..SAMPLE_RATE as usize * SECONDS {
+        for (voice, oscillator) in oscillators.iter_mut().enumerate() {
+            let freq = 110.0 * (1.0 + voice as f32 * 0.25) + (frame % 7) as f32 * 0.01;
+            let (left, right) = match kernel {
+                Kernel::Scalar => oscillator.next_scalar(freq, settings, SAMPLE_RATE, osc_settings, osc_b, mixer),
+                Kernel::Simd => oscillator.next_simd(freq, settings, SAMPLE_RATE, osc_settings, osc_b, mixer),
+            };
+            sum += left + right;
+        }
+    }
+    black_box(sum);
+    start.elapsed()
+}
+
+fn main() {
+    let osc_settings = OscillatorSettings {
+        mode: OscillatorMode::PolyBlep,
+        ..Default::default()
+    };
+    let cases = [
+        ("Saw, A only", Waveform::Sawtooth, 0.0),
+        ("Square, A+B", Waveform::Square, 1.0),
+        ("Sine, A+B", Waveform::Sine, 1.0),
+    ];
+
+    println!(
+        "{} voices x {} unison, {} s at {} Hz",
+        POLYPHONY, MAX_UNISON_VOICES, SECONDS, SAMPLE_RATE
+    );
+    for (name, waveform, level_b) in cases {
+        let settings = UnisonSettings {
+            voices: MAX_UNISON_VOICES as u8,
+            detune: 25.0,
+            waveform: waveform.clone(),
+            width: 1.0,
+            ..Default::default()
+        };
+        let osc_b = OscBSettings {
+            waveform,
+            fine: 7.0,
+            ..Default::default()
+        };
+        let mut mixer = MixerSettings::default();
+        mixer.osc_b.level = level_b;
+
+        // 1回目はキャッシュなどが温まっていないので、どちらの実装も1回ずつ捨てる
+        run(Kernel::Scalar, &settings, &osc_settings, &osc_b, &mixer);
+        run(Kernel::Simd, &settings, &osc_settings, &osc_b, &mixer);
+        let scalar = run(Kernel::Scalar, &settings, &osc_settings, &osc_b, &mixer);
+        let simd = run(Kernel::Simd, &settings, &osc_settings, &osc_b, &mixer);
+
+        let realtime = Duration::from_secs(SECONDS as u64).as_secs_f64();
+        println!(
+            "{:<12} scalar {:>8.1} ms ({:>5.1}% of realtime)  simd {:>8.1} ms ({:>5.1}%)  x{:.2}",
+            name,
+            scalar.as_secs_f64() * 1000.0,
+            scalar.as_secs_f64() / realtime * 100.0,
+            simd.as_secs_f64() * 1000.0,
+            simd.as_secs_f64() / realtime * 100.0,
+            scalar.as_secs_f64() / simd.as_secs_f64(),
+        );
+    }
+}
@@ -0,0 +1,384 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::euclid::EuclideanRhythm;
+use crate::lock::lock_or_try;
+use crate::note::NoteHandler;
+use crate::transport::{MAX_BPM, MIN_BPM, NoteDivision, TransportManager};
+
+/// タイミングスレッドの待ち時間（ステップの揺れはこの程度に収まる）
+const TICK: Duration = Duration::from_millis(1);
+
+/// アルペジエーターの最大オクターブ数
+pub const MAX_ARP_OCTAVES: u8 = 4;
+
+/// ヒューマナイズでタイミングを揺らす最大の幅（ms、±）
+pub const MAX_HUMANIZE_TIME: f32 = 50.0;
+
+/// アルペジオのパターン
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ArpPattern {
+    Up,     // 低い音から順に
+    Down,   // 高い音から順に
+    UpDown, // 上がって下がる（両端は繰り返さない）
+    Random, // ランダム
+}
+
+impl Default for ArpPattern {
+    fn default() -> Self {
+        Self::Up
+    }
+}
+
+/// アルペジエーターの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArpSettings {
+    /// アルペジエーターを有効にするかどうか
+    pub enabled: bool,
+    /// パターン
+    pub pattern: ArpPattern,
+    /// トランスポートのテンポに同期するかどうか（false なら rate で動く）
+    pub sync: bool,
+    /// 同期しない時の速さ（Hz、0.5-20）
+    pub rate: f32,
+    /// 同期する時の音符の長さ
+    pub division: NoteDivision,
+    /// ゲートの長さ（ステップに対する割合、0.05-1.0）
+    pub gate: f32,
+    /// オクターブ数（1-4）
+    pub octaves: u8,
+    /// 発音するステップを選ぶユークリッドリズム（休みのステップでは次のノートに進まない）
+    pub rhythm: EuclideanRhythm,
+    /// ヒューマナイズ：ステップごとのタイミングの揺れ（ms、±、0-50）
+    pub humanize_time: f32,
+    /// ヒューマナイズ：ステップごとのベロシティの揺れ（弾いたベロシティに対する割合、±、0.0-1.0）
+    pub humanize_velocity: f32,
+}
+
+impl Default for ArpSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            pattern: ArpPattern::Up,
+            sync: true,
+            rate: 8.0,
+            division: NoteDivision::Sixteenth,
+            gate: 0.5,
+            octaves: 1,
+            rhythm: EuclideanRhythm::default(),
+            humanize_time: 0.0,
+            humanize_velocity: 0.0,
+        }
+    }
+}
+
+impl ArpSettings {
+    /// テンポ（BPM）での1ステップの長さ
+    pub fn step_duration(&self, bpm: f32) -> Duration {
+        let seconds = if self.sync {
+            self.division.seconds(bpm)
+        } else {
+            1.0 / self.rate.clamp(0.5, 20.0)
+        };
+        Duration::from_secs_f32(seconds)
+    }
+
+    /// 押されているノート（音の低い順）をオクターブ数だけ広げた並びを作る
+    pub fn sequence(&self, held: &[(u8, u8)]) -> Vec<(u8, u8)> {
+        let mut sequence = Vec::new();
+        for octave in 0..self.octaves.clamp(1, MAX_ARP_OCTAVES) {
+            for &(note, velocity) in held {
+                let shifted = note as u32 + octave as u32 * 12;
+                if shifted <= 127 {
+                    sequence.push((shifted as u8, velocity));
+                }
+            }
+        }
+        sequence
+    }
+}
+
+/// アルペジエーターの設定と押されている鍵盤を管理する構造体
+pub struct ArpManager {
+    settings: Arc<Mutex<ArpSettings>>,
+    held: Arc<Mutex<Vec<(u8, u8)>>>, // 押されている鍵盤（ノート番号, ベロシティ、全ての鍵盤の分を確保しておく）
+}
+
+impl ArpManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(ArpSettings::default())),
+            // 鍵盤はオーディオスレッドのMIDI処理からも押されるので、追加でアロケーションしないように確保しておく
+            held: Arc::new(Mutex::new(Vec::with_capacity(128))),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<ArpSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.enabled = enabled;
+        }
+    }
+
+    pub fn set_pattern(&self, pattern: ArpPattern) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.pattern = pattern;
+        }
+    }
+
+    pub fn set_sync(&self, sync: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.sync = sync;
+        }
+    }
+
+    pub fn set_rate(&self, rate: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.rate = rate.clamp(0.5, 20.0);
+        }
+    }
+
+    pub fn set_division(&self, division: NoteDivision) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.division = division;
+        }
+    }
+
+    pub fn set_gate(&self, gate: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.gate = gate.clamp(0.05, 1.0);
+        }
+    }
+
+    pub fn set_octaves(&self, octaves: u8) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.octaves = octaves.clamp(1, MAX_ARP_OCTAVES);
+        }
+    }
+
+    pub fn set_rhythm(&self, rhythm: EuclideanRhythm) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.rhythm = rhythm.clamped();
+        }
+    }
+
+    pub fn set_humanize_time(&self, time: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.humanize_time = time.clamp(0.0, MAX_HUMANIZE_TIME);
+        }
+    }
+
+    pub fn set_humanize_velocity(&self, amount: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.humanize_velocity = amount.clamp(0.0, 1.0);
+        }
+    }
+
+    /// 押されている鍵盤をロックする（`wait` が false なら待たずに試し、他のスレッドがロックしていれば None）
+    pub fn lock_keys(&self, wait: bool) -> Option<ArpKeys<'_>> {
+        let enabled = lock_or_try(&self.settings, wait)?.enabled;
+        let held = lock_or_try(&self.held, wait)?;
+        Some(ArpKeys { enabled, held })
+    }
+
+    fn held_notes(&self) -> Vec<(u8, u8)> {
+        self.held.lock().map(|held| held.clone()).unwrap_or_default()
+    }
+
+    fn settings(&self) -> ArpSettings {
+        self.settings.lock().map(|settings| *settings).unwrap_or_default()
+    }
+}
+
+/// 押されている鍵盤をロックしている間の操作（`ArpManager::lock_keys` で作る）
+pub struct ArpKeys<'a> {
+    enabled: bool, // ロックした時にアルペジエーターが有効だったか
+    held: MutexGuard<'a, Vec<(u8, u8)>>,
+}
+
+impl ArpKeys<'_> {
+    /// アルペジエーターが有効かどうか
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// 鍵盤が押された（音の低い順に並べておく）
+    pub fn key_down(&mut self, note: u8, velocity: u8) {
+        self.held.retain(|&(n, _)| n != note);
+        let index = self.held.partition_point(|&(n, _)| n < note);
+        self.held.insert(index, (note, velocity));
+    }
+
+    /// 鍵盤が離された
+    pub fn key_up(&mut self, note: u8) {
+        self.held.retain(|&(n, _)| n != note);
+    }
+
+    /// 全ての鍵盤を離す
+    pub fn clear(&mut self) {
+        self.held.clear();
+    }
+}
+
+/// アルペジエーターのタイミングスレッド
+///
+/// 押されている鍵盤からステップごとにノートを選び、NoteHandler を通して発音する。
+/// テンポ同期中はトランスポートの再生位置が音符の区切りを越えるごとにステップを進める
+/// （再生位置はオーディオのブロックごとに更新されるので、揺れはバッファサイズ程度になる）
+pub struct Arpeggiator {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Arpeggiator {
+    pub fn new(notes: NoteHandler, arp: Arc<ArpManager>, transport: Arc<TransportManager>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        // スレッドを作れない環境ではアルペジエーターは動かない
+        let handle = thread::Builder::new()
+            .name("arpeggiator".to_string())
+            .spawn(move || run(notes, arp, transport, thread_stop))
+            .inspect_err(|err| println!("Failed to start arpeggiator thread: {}", err))
+            .ok();
+        Self { stop, handle }
+    }
+
+    /// タイミングスレッドを止める（鳴らしているノートは解放される）
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Arpeggiator {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// タイミングスレッドの本体
+fn run(notes: NoteHandler, arp: Arc<ArpManager>, transport: Arc<TransportManager>, stop: Arc<AtomicBool>) {
+    let mut playing: Option<u8> = None;
+    let mut step_index = 0usize;
+    let mut rhythm_index = 0usize; // テンポ同期していない時のリズムのステップ
+    let mut next_step = Instant::now();
+    let mut clock_step: Option<i64> = None; // テンポ同期中に最後に鳴らした音符の区切り
+    let mut gate_off = Instant::now();
+    let mut rng_state = 0x2545_F491u32;
+
+    while !stop.load(Ordering::Relaxed) {
+        let settings = arp.settings();
+        let held = arp.held_notes();
+
+        // 無効か鍵盤が押されていなければ止めて、次に押された時に先頭から始める
+        if !settings.enabled || held.is_empty() {
+            if let Some(note) = playing.take() {
+                notes.stop_note(note);
+            }
+            step_index = 0;
+            rhythm_index = 0;
+            next_step = Instant::now();
+            clock_step = None;
+            thread::sleep(TICK);
+            continue;
+        }
+
+        // トランスポートが止まっている間はテンポだけに合わせて自分のタイマーで進める
+        // （音符の区切りはスウィングをかけた再生位置で数える）
+        let tempo = transport.tempo();
+        let current_clock_step = (settings.sync && transport.is_playing())
+            .then(|| (transport.swung_position() / settings.division.beats() as f64).floor() as i64);
+        let now = Instant::now();
+        let due = match current_clock_step {
+            Some(step) => clock_step != Some(step),
+            None => now >= next_step,
+        };
+        if due {
+            if let Some(note) = playing.take() {
+                notes.stop_note(note);
+            }
+
+            // リズムの位置はテンポ同期中は音符の区切りから数える（小節の頭とリズムの頭が揃う）
+            let rhythm_step = current_clock_step.map_or(rhythm_index, |step| step.max(0) as usize);
+            rhythm_index = rhythm_index.wrapping_add(1);
+            let pulse = settings.rhythm.is_pulse(rhythm_step);
+
+            let sequence = settings.sequence(&held);
+            let index = match settings.pattern {
+                ArpPattern::Up => step_index % sequence.len(),
+                ArpPattern::Down => sequence.len() - 1 - step_index % sequence.len(),
+                ArpPattern::UpDown => {
+                    let period = (sequence.len() * 2).saturating_sub(2).max(1);
+                    let position = step_index % period;
+                    if position < sequence.len() { position } else { period - position }
+                }
+                ArpPattern::Random => xorshift(&mut rng_state) as usize % sequence.len(),
+            };
+            // 休みのステップでは鳴らさず、次に鳴らすノートも進めない
+            // ヒューマナイズではタイミングの揺れの幅だけ全てのステップを遅らせ、その前後に揺らして鳴らし始める
+            // （エンジンがサンプル単位で鳴り始めを遅らせるので、早める方向にも揺らせる）
+            let humanize_time = settings.humanize_time.clamp(0.0, MAX_HUMANIZE_TIME) / 1000.0;
+            let onset_delay = humanize_time * (1.0 + random_bipolar(&mut rng_state));
+            if pulse {
+                step_index = step_index.wrapping_add(1);
+                let (note, velocity) = sequence[index];
+                let variation = 1.0 + settings.humanize_velocity.clamp(0.0, 1.0) * random_bipolar(&mut rng_state);
+                let velocity = (velocity as f32 * variation).round().clamp(1.0, 127.0) as u8;
+                notes.start_note_delayed(note, velocity, onset_delay);
+                playing = Some(note);
+            }
+
+            // 次のステップとゲートを閉じる時刻を決める（遅れが大きい場合は今から数え直す）
+            // テンポ同期中は自分のタイマーで進める時もスウィングに合わせて区切りの長さを伸び縮みさせる
+            let step = if settings.sync {
+                let transport_settings = transport.settings();
+                let division = settings.division.beats() as f64;
+                let beats = transport_settings.unswung((rhythm_step + 1) as f64 * division)
+                    - transport_settings.unswung(rhythm_step as f64 * division);
+                Duration::from_secs_f64(beats * 60.0 / tempo.clamp(MIN_BPM, MAX_BPM) as f64)
+            } else {
+                settings.step_duration(tempo)
+            };
+            clock_step = current_clock_step;
+            gate_off = now + step.mul_f32(settings.gate.clamp(0.05, 1.0)) + Duration::from_secs_f32(onset_delay);
+            next_step += step;
+            if next_step < now {
+                next_step = now + step;
+            }
+        } else if settings.gate < 1.0
+            && now >= gate_off
+            && let Some(note) = playing.take()
+        {
+            notes.stop_note(note);
+        }
+
+        thread::sleep(TICK);
+    }
+
+    if let Some(note) = playing.take() {
+        notes.stop_note(note);
+    }
+}
+
+/// xorshift32 で次の乱数を作る
+fn xorshift(state: &mut u32) -> u32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state
+}
+
+/// -1.0〜1.0 の一様な乱数
+fn random_bipolar(state: &mut u32) -> f32 {
+    xorshift(state) as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
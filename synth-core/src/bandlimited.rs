@@ -0,0 +1,121 @@
+use std::f32::consts::PI;
+use std::sync::OnceLock;
+
+/// 1周期のテーブルのサンプル数（2のべき乗、位相をマスクで折り返す）
+const TABLE_SIZE: usize = 2048;
+/// オクターブごとのテーブルの数（1つ目は TABLE_SIZE / 2 個の倍音、1つ上がるごとに半分にして最後は基音だけ）
+const OCTAVES: usize = 11;
+
+/// 帯域制限したテーブルを用意している波形
+#[derive(Clone, Copy)]
+pub enum TableWaveform {
+    Sine,
+    Triangle,
+    Sawtooth,
+}
+
+/// 波形ごと・オクターブごとの帯域制限したテーブル
+struct BandLimitedTables {
+    sine: Vec<f32>,          // サインは倍音がないので1つだけ
+    triangle: Vec<Vec<f32>>, // オクターブごとの三角波
+    sawtooth: Vec<Vec<f32>>, // オクターブごとのノコギリ波（矩形波もこれを2つ重ねて作る）
+}
+
+impl BandLimitedTables {
+    fn new() -> Self {
+        let sine: Vec<f32> = (0..TABLE_SIZE).map(|i| (2.0 * PI * i as f32 / TABLE_SIZE as f32).sin()).collect();
+        let triangle = (0..OCTAVES)
+            .map(|octave| {
+                // 1 - 4|p - 0.5| = -(8/π²) Σ cos(2πhp) / h²（奇数次のみ）
+                render_additive(&sine, max_harmonics(octave), |h| {
+                    if h % 2 == 1 { Some((-8.0 / (PI * PI * (h * h) as f32), true)) } else { None }
+                })
+            })
+            .collect();
+        let sawtooth = (0..OCTAVES)
+            .map(|octave| {
+                // 2p - 1 = -(2/π) Σ sin(2πhp) / h
+                render_additive(&sine, max_harmonics(octave), |h| Some((-2.0 / (PI * h as f32), false)))
+            })
+            .collect();
+        Self { sine, triangle, sawtooth }
+    }
+}
+
+/// オクターブ番号のテーブルに入れる倍音の数
+fn max_harmonics(octave: usize) -> usize {
+    (TABLE_SIZE / 2) >> octave
+}
+
+/// 倍音を足し合わせて1周期のテーブルを作る
+///
+/// `coefficient` は倍音の次数から（振幅, cos かどうか）を返す（None はその倍音を含まない）
+fn render_additive(sine: &[f32], harmonics: usize, coefficient: impl Fn(usize) -> Option<(f32, bool)>) -> Vec<f32> {
+    let mut table = vec![0.0; TABLE_SIZE];
+    for h in 1..=harmonics {
+        let Some((amplitude, cosine)) = coefficient(h) else {
+            continue;
+        };
+        // cos は sin を 1/4 周期ずらして読む
+        let offset = if cosine { TABLE_SIZE / 4 } else { 0 };
+        for (i, value) in table.iter_mut().enumerate() {
+            *value += amplitude * sine[(h * i + offset) & (TABLE_SIZE - 1)];
+        }
+    }
+    table
+}
+
+fn tables() -> &'static BandLimitedTables {
+    static TABLES: OnceLock<BandLimitedTables> = OnceLock::new();
+    TABLES.get_or_init(BandLimitedTables::new)
+}
+
+/// テーブルを作っておく（初回の読み出しでオーディオスレッドが待たないように、起動時に呼ぶ）
+pub fn prepare() {
+    tables();
+}
+
+/// 1サンプルあたりの位相の進みから、折り返しが起きない一番倍音の多いオクターブを選ぶ
+fn octave_for(phase_inc: f32) -> usize {
+    // 倍音の数 × phase_inc が 0.5 以下になるオクターブ = ceil(log2(phase_inc × TABLE_SIZE))
+    let x = phase_inc.abs() * TABLE_SIZE as f32;
+    if x.is_nan() || x <= 1.0 {
+        return 0;
+    }
+    // 指数部が floor(log2(x))、仮数部が0でなければ1つ上げる
+    let bits = x.to_bits();
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127;
+    let ceil = exponent + i32::from(bits & 0x7f_ffff != 0);
+    (ceil.max(0) as usize).min(OCTAVES - 1)
+}
+
+/// テーブルを線形補間で読む（`phase` は -1.0 以上）
+fn read(table: &[f32], phase: f32) -> f32 {
+    // 1周期足して正にしてから整数に切り捨てる（はみ出した周期はマスクで折り返す）
+    let position = ((phase + 1.0) * TABLE_SIZE as f32).max(0.0);
+    let index = position as usize;
+    let frac = position - index as f32;
+    let a = table[index & (TABLE_SIZE - 1)];
+    let b = table[(index + 1) & (TABLE_SIZE - 1)];
+    a + (b - a) * frac
+}
+
+/// 帯域制限した波形の値（-1.0〜1.0）を読む
+///
+/// `phase_inc` は1サンプルあたりの位相の進み（周波数 / サンプルレート）で、これでオクターブのテーブルを選ぶ
+pub fn sample(waveform: TableWaveform, phase: f32, phase_inc: f32) -> f32 {
+    let tables = tables();
+    match waveform {
+        TableWaveform::Sine => read(&tables.sine, phase),
+        TableWaveform::Triangle => read(&tables.triangle[octave_for(phase_inc)], phase),
+        TableWaveform::Sawtooth => read(&tables.sawtooth[octave_for(phase_inc)], phase),
+    }
+}
+
+/// 帯域制限したパルス波（位相がパルス幅より前なら+1）を読む
+///
+/// ノコギリ波をパルス幅だけずらして引くと、段差が2つのパルス波になる
+pub fn pulse(phase: f32, phase_inc: f32, pulse_width: f32) -> f32 {
+    let table = &tables().sawtooth[octave_for(phase_inc)];
+    read(table, phase - pulse_width) - read(table, phase) + 2.0 * pulse_width - 1.0
+}
@@ -0,0 +1,124 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+/// ビット深度の範囲
+pub const MIN_BITS: u8 = 4;
+pub const MAX_BITS: u8 = 16;
+
+/// 間引いた後のサンプルレートの範囲（Hz）
+pub const MIN_CRUSH_RATE: f32 = 500.0;
+pub const MAX_CRUSH_RATE: f32 = 48000.0;
+
+/// ビットクラッシャーの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BitcrusherSettings {
+    /// ビット深度（4-16、小さいほど量子化ノイズが増える）
+    pub bits: u8,
+    /// 間引いた後のサンプルレート（Hz、500-48000）
+    pub rate: f32,
+    /// 保持したサンプルの間を直線で結ぶかどうか（オフで階段状になる）
+    pub interpolate: bool,
+    /// ウェットの割合（0.0-1.0）
+    pub mix: f32,
+}
+
+impl Default for BitcrusherSettings {
+    fn default() -> Self {
+        Self {
+            bits: 8,
+            rate: 11025.0,
+            interpolate: false,
+            mix: 1.0,
+        }
+    }
+}
+
+/// ビット深度とサンプルレートを落とすビットクラッシャー（オーディオスレッドが所有する）
+pub struct Bitcrusher {
+    phase: f32,    // 次のサンプルを取り込むまでの位置（0.0-1.0）
+    held: f32,     // 保持しているサンプル
+    previous: f32, // 1つ前に保持していたサンプル（補間用）
+    sample_rate: f32,
+}
+
+impl Bitcrusher {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            phase: 1.0,
+            held: 0.0,
+            previous: 0.0,
+            sample_rate,
+        }
+    }
+
+    /// 保持しているサンプルをクリアする
+    pub fn reset(&mut self) {
+        self.phase = 1.0;
+        self.held = 0.0;
+        self.previous = 0.0;
+    }
+
+    /// 1サンプル処理する
+    pub fn process(&mut self, input: f32, settings: &BitcrusherSettings) -> f32 {
+        // 間引いたレートで入力を量子化して取り込む
+        if self.phase >= 1.0 {
+            self.phase = self.phase.fract();
+            let levels = (1u32 << (settings.bits.clamp(MIN_BITS, MAX_BITS) - 1)) as f32;
+            self.previous = self.held;
+            self.held = (input.clamp(-1.0, 1.0) * levels).round() / levels;
+        }
+        let wet = if settings.interpolate {
+            self.previous + (self.held - self.previous) * self.phase
+        } else {
+            self.held
+        };
+        // 元のサンプルレートより高くしても変化はないので、そこで止める
+        self.phase += settings.rate.clamp(MIN_CRUSH_RATE, MAX_CRUSH_RATE).min(self.sample_rate) / self.sample_rate;
+
+        let mix = settings.mix.clamp(0.0, 1.0);
+        input * (1.0 - mix) + wet * mix
+    }
+}
+
+/// ビットクラッシャーの設定を管理する構造体
+pub struct BitcrusherManager {
+    settings: Arc<Mutex<BitcrusherSettings>>,
+}
+
+impl BitcrusherManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(BitcrusherSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<BitcrusherSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_bits(&self, bits: u8) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.bits = bits.clamp(MIN_BITS, MAX_BITS);
+        }
+    }
+
+    pub fn set_rate(&self, rate: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.rate = rate.clamp(MIN_CRUSH_RATE, MAX_CRUSH_RATE);
+        }
+    }
+
+    pub fn set_interpolate(&self, interpolate: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.interpolate = interpolate;
+        }
+    }
+
+    pub fn set_mix(&self, mix: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.mix = mix.clamp(0.0, 1.0);
+        }
+    }
+}
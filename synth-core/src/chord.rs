@@ -0,0 +1,268 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::lock::lock_or_try;
+
+/// コードに入れられる一番高い音（ルートからの半音）
+pub const MAX_CHORD_INTERVAL: u8 = 24;
+
+/// 1つのルートから鳴らせるノートの最大数
+pub const MAX_CHORD_NOTES: usize = MAX_CHORD_INTERVAL as usize + 1;
+
+/// 1つの鍵盤で鳴らすノート（固定長なのでオーディオスレッドでもアロケーションしない）
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct ChordNotes {
+    notes: [u8; MAX_CHORD_NOTES],
+    len: u8,
+}
+
+impl ChordNotes {
+    /// 1つのノートだけ
+    pub fn single(note: u8) -> Self {
+        let mut notes = Self::default();
+        notes.push(note);
+        notes
+    }
+
+    /// ノートを加える（既に入っているノートと、入りきらないノートは加えない）
+    pub fn push(&mut self, note: u8) {
+        if !self.contains(&note) && (self.len as usize) < MAX_CHORD_NOTES {
+            self.notes[self.len as usize] = note;
+            self.len += 1;
+        }
+    }
+
+    /// 全てのノートを外す
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl std::ops::Deref for ChordNotes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.notes[..self.len as usize]
+    }
+}
+
+/// 一覧から選べるコードの形
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChordShape {
+    Major,     // メジャートライアド
+    Minor,     // マイナートライアド
+    Dominant7, // セブンス
+    Major7,    // メジャーセブンス
+    Minor7,    // マイナーセブンス
+    Sus2,      // サスツー
+    Sus4,      // サスフォー
+    Power,     // パワーコード（ルートと5度とオクターブ）
+    Octave,    // オクターブ
+}
+
+impl ChordShape {
+    /// 全ての形（GUIの並び順）
+    pub const ALL: [Self; 9] = [
+        Self::Major,
+        Self::Minor,
+        Self::Dominant7,
+        Self::Major7,
+        Self::Minor7,
+        Self::Sus2,
+        Self::Sus4,
+        Self::Power,
+        Self::Octave,
+    ];
+
+    /// 表示名
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Major => "Major",
+            Self::Minor => "Minor",
+            Self::Dominant7 => "7th",
+            Self::Major7 => "Maj7",
+            Self::Minor7 => "Min7",
+            Self::Sus2 => "Sus2",
+            Self::Sus4 => "Sus4",
+            Self::Power => "Power",
+            Self::Octave => "Octave",
+        }
+    }
+
+    /// ルートからの半音ごとにコードに含まれるかを表すビット列（ビット0がルート）
+    pub fn intervals(self) -> u32 {
+        let degrees: &[u8] = match self {
+            Self::Major => &[0, 4, 7],
+            Self::Minor => &[0, 3, 7],
+            Self::Dominant7 => &[0, 4, 7, 10],
+            Self::Major7 => &[0, 4, 7, 11],
+            Self::Minor7 => &[0, 3, 7, 10],
+            Self::Sus2 => &[0, 2, 7],
+            Self::Sus4 => &[0, 5, 7],
+            Self::Power => &[0, 7, 12],
+            Self::Octave => &[0, 12],
+        };
+        degrees.iter().fold(0, |mask, &degree| mask | 1 << degree)
+    }
+
+    /// ビット列と同じ形（一覧にない形なら None）
+    pub fn from_intervals(intervals: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|shape| shape.intervals() == intervals)
+    }
+}
+
+/// コードモードの設定を表す構造体
+#[derive(Clone, Copy)]
+pub struct ChordSettings {
+    /// 1つのノートでコードを鳴らすか
+    pub enabled: bool,
+    /// コードの音（ルートからの半音ごとのビット列、ビット0がルート）
+    pub intervals: u32,
+}
+
+impl Default for ChordSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            intervals: ChordShape::Major.intervals(),
+        }
+    }
+}
+
+impl ChordSettings {
+    /// コードの音のルートからの半音（低い順）
+    pub fn interval_list(&self) -> Vec<u8> {
+        (0..=MAX_CHORD_INTERVAL).filter(|&degree| self.intervals & 1 << degree != 0).collect()
+    }
+
+    /// ルートのノートから鳴らすノート（低い順、無効ならルートだけ、127を超える音は鳴らさない）
+    pub fn notes(&self, root: u8) -> ChordNotes {
+        if !self.enabled || self.intervals == 0 {
+            return ChordNotes::single(root);
+        }
+        let mut notes = ChordNotes::default();
+        (0..=MAX_CHORD_INTERVAL)
+            .filter(|&degree| self.intervals & 1 << degree != 0)
+            .filter_map(|interval| root.checked_add(interval).filter(|&note| note <= 127))
+            .for_each(|note| notes.push(note));
+        notes
+    }
+}
+
+/// 弾いた和音からコードを覚える途中の状態
+#[derive(Default)]
+struct ChordLearn {
+    notes: ChordNotes, // 覚える間に押されたノート（コードに入れられる数まで）
+}
+
+/// コードモードの設定を管理する構造体
+///
+/// トランスポーズやスケールロックと同じく演奏の設定なので、プリセットには含めない
+pub struct ChordManager {
+    settings: Arc<Mutex<ChordSettings>>,
+    learn: Mutex<Option<ChordLearn>>, // 和音を弾いて覚えている間だけ Some
+}
+
+impl ChordManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(ChordSettings::default())),
+            learn: Mutex::new(None),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<ChordSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.enabled = enabled;
+        }
+    }
+
+    pub fn set_intervals(&self, intervals: u32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.intervals = chord_intervals(intervals);
+        }
+    }
+
+    /// 和音を弾いてコードを覚え始める（鍵盤を全て離した時に覚える）
+    pub fn start_learning(&self) {
+        if let Ok(mut learn) = self.learn.lock() {
+            *learn = Some(ChordLearn::default());
+        }
+    }
+
+    /// コードを覚えるのをやめる（覚えかけの和音は捨てる）
+    pub fn cancel_learning(&self) {
+        if let Ok(mut learn) = self.learn.lock() {
+            *learn = None;
+        }
+    }
+
+    /// コードを覚えている途中かどうか
+    pub fn is_learning(&self) -> bool {
+        self.learn.lock().is_ok_and(|learn| learn.is_some())
+    }
+
+    /// 設定と覚えている途中の和音をロックする（`wait` が false なら待たずに試し、他のスレッドがロックしていれば None）
+    pub fn lock(&self, wait: bool) -> Option<ChordKeys<'_>> {
+        let settings = lock_or_try(&self.settings, wait)?;
+        let learn = lock_or_try(&self.learn, wait)?;
+        Some(ChordKeys { settings, learn })
+    }
+}
+
+/// 範囲外の音を外し、ルートを加えたコードの音
+fn chord_intervals(intervals: u32) -> u32 {
+    // ルートは常に鳴らす
+    intervals & ((1 << (MAX_CHORD_INTERVAL + 1)) - 1) | 1
+}
+
+/// 設定と覚えている途中の和音をロックしている間の操作（`ChordManager::lock` で作る）
+pub struct ChordKeys<'a> {
+    settings: MutexGuard<'a, ChordSettings>,
+    learn: MutexGuard<'a, Option<ChordLearn>>,
+}
+
+impl ChordKeys<'_> {
+    /// 覚えている途中なら押されたノートを加える（覚えている途中なら true、その間はコードにせずに鳴らす）
+    pub fn learn_key_down(&mut self, note: u8) -> bool {
+        let Some(learn) = self.learn.as_mut() else {
+            return false;
+        };
+        learn.notes.push(note);
+        true
+    }
+
+    /// 覚えている途中で鍵盤が離されたら、押されたノートの一番低い音をルートとしてコードを覚える
+    ///
+    /// `still_held` は離された後もまだ押されている鍵盤があるかどうか（全て離されるまで待つ）
+    pub fn learn_key_up(&mut self, still_held: bool) {
+        if still_held {
+            return;
+        }
+        let learned = match self.learn.take() {
+            Some(learned) if !learned.notes.is_empty() => learned,
+            other => {
+                *self.learn = other;
+                return;
+            }
+        };
+        let Some(&root) = learned.notes.iter().min() else {
+            return;
+        };
+        let intervals = learned
+            .notes
+            .iter()
+            .map(|&note| note - root)
+            .filter(|&interval| interval <= MAX_CHORD_INTERVAL)
+            .fold(0, |mask, interval| mask | 1 << interval);
+        self.settings.intervals = chord_intervals(intervals);
+    }
+
+    /// ルートのノートから鳴らすノート（低い順）
+    pub fn notes(&self, root: u8) -> ChordNotes {
+        self.settings.notes(root)
+    }
+}
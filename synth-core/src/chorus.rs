@@ -0,0 +1,140 @@
+use std::f32::consts::TAU;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+/// ディレイの中心時間（秒）
+const BASE_DELAY: f32 = 0.015;
+
+/// 深さ1.0の時の変調幅（秒、中心から±）
+const MAX_MOD_DEPTH: f32 = 0.005;
+
+/// コーラスの最大ボイス数
+pub const MAX_CHORUS_VOICES: u8 = 3;
+
+/// コーラスの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChorusSettings {
+    /// コーラスを有効にするかどうか（旧形式のプリセット用、エンジンはエフェクトチェーンのバイパスを使う）
+    pub enabled: bool,
+    /// 変調ディレイの数（2-3）
+    pub voices: u8,
+    /// 変調の速さ（Hz、0.05-5.0）
+    pub rate: f32,
+    /// 変調の深さ（0.0-1.0）
+    pub depth: f32,
+    /// ウェットの割合（0.0-1.0）
+    pub mix: f32,
+}
+
+impl Default for ChorusSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            voices: 3,
+            rate: 0.8,
+            depth: 0.5,
+            mix: 0.5,
+        }
+    }
+}
+
+/// 変調ディレイを重ねるコーラス（オーディオスレッドが所有する）
+pub struct Chorus {
+    buffer: Vec<f32>,
+    write_index: usize,
+    phase: f32, // 変調LFOの位相（0.0-1.0）
+    sample_rate: f32,
+}
+
+impl Chorus {
+    /// サンプルレートに合わせて遅延バッファを確保する
+    ///
+    /// `phase_offset` は変調LFOの初期位相（0.0-1.0、左右で変えると広がりが出る）
+    pub fn new(sample_rate: f32, phase_offset: f32) -> Self {
+        let size = ((BASE_DELAY + MAX_MOD_DEPTH) * sample_rate) as usize + 2;
+        Self {
+            buffer: vec![0.0; size],
+            write_index: 0,
+            phase: phase_offset.fract(),
+            sample_rate,
+        }
+    }
+
+    /// 遅延バッファをクリアする
+    pub fn reset(&mut self) {
+        self.buffer.iter_mut().for_each(|x| *x = 0.0);
+    }
+
+    /// 1サンプル処理する
+    pub fn process(&mut self, input: f32, settings: &ChorusSettings) -> f32 {
+        let len = self.buffer.len();
+        self.buffer[self.write_index] = input;
+
+        // ボイスごとにLFOの位相をずらして遅延時間を揺らす
+        let voices = settings.voices.clamp(1, MAX_CHORUS_VOICES);
+        let depth = settings.depth.clamp(0.0, 1.0) * MAX_MOD_DEPTH;
+        let mut wet = 0.0;
+        for i in 0..voices {
+            let phase = self.phase + i as f32 / voices as f32;
+            let delay = (BASE_DELAY + depth * (TAU * phase).sin()) * self.sample_rate;
+
+            // 線形補間で遅延位置のサンプルを読む
+            let read = self.write_index as f32 - delay + len as f32;
+            let index = read.floor() as usize;
+            let frac = read - read.floor();
+            let a = self.buffer[index % len];
+            let b = self.buffer[(index + 1) % len];
+            wet += a + (b - a) * frac;
+        }
+        wet /= voices as f32;
+
+        self.write_index = (self.write_index + 1) % len;
+        self.phase = (self.phase + settings.rate.max(0.0) / self.sample_rate).fract();
+
+        let mix = settings.mix.clamp(0.0, 1.0);
+        input * (1.0 - mix) + wet * mix
+    }
+}
+
+/// コーラスの設定を管理する構造体
+pub struct ChorusManager {
+    settings: Arc<Mutex<ChorusSettings>>,
+}
+
+impl ChorusManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(ChorusSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<ChorusSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_voices(&self, voices: u8) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.voices = voices.clamp(2, MAX_CHORUS_VOICES);
+        }
+    }
+
+    pub fn set_rate(&self, rate: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.rate = rate.clamp(0.05, 5.0);
+        }
+    }
+
+    pub fn set_depth(&self, depth: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.depth = depth.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_mix(&self, mix: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.mix = mix.clamp(0.0, 1.0);
+        }
+    }
+}
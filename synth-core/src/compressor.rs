@@ -0,0 +1,182 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+/// ニーの幅（dB、しきい値の前後でこの幅だけ滑らかに圧縮を始める）
+const KNEE_WIDTH: f32 = 6.0;
+
+/// コンプレッサーの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressorSettings {
+    /// コンプレッサーを有効にするかどうか
+    pub enabled: bool,
+    /// しきい値（dB、-60〜0）
+    pub threshold: f32,
+    /// 圧縮比（1.0〜20.0、20で実質リミッター）
+    pub ratio: f32,
+    /// アタック時間（ミリ秒、0.1〜100）
+    pub attack: f32,
+    /// リリース時間（ミリ秒、10〜1000）
+    pub release: f32,
+    /// メイクアップゲイン（dB、0〜24）
+    pub makeup: f32,
+}
+
+impl Default for CompressorSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: -12.0,
+            ratio: 4.0,
+            attack: 5.0,
+            release: 100.0,
+            makeup: 0.0,
+        }
+    }
+}
+
+impl CompressorSettings {
+    /// 入力レベル（dB）に対するゲインリダクション量（dB、正の値）
+    fn gain_reduction(&self, level_db: f32) -> f32 {
+        let over = level_db - self.threshold;
+        let slope = 1.0 - 1.0 / self.ratio.max(1.0);
+        if over <= -KNEE_WIDTH * 0.5 {
+            0.0
+        } else if over < KNEE_WIDTH * 0.5 {
+            // ニーの中は2次曲線でつなぐ
+            slope * (over + KNEE_WIDTH * 0.5).powi(2) / (2.0 * KNEE_WIDTH)
+        } else {
+            slope * over
+        }
+    }
+}
+
+/// 左右をリンクしたマスターバスのコンプレッサー（オーディオスレッドが所有する）
+pub struct Compressor {
+    settings: CompressorSettings,
+    reduction: f32,      // 現在のゲインリダクション（dB）
+    attack_coeff: f32,   // ゲインリダクションを増やす時の追従係数
+    release_coeff: f32,  // ゲインリダクションを戻す時の追従係数
+    makeup: f32,         // メイクアップゲインの倍率
+    peak_reduction: f32, // ブロック内で最大のゲインリダクション（メーター用）
+    sample_rate: f32,
+}
+
+impl Compressor {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut compressor = Self {
+            settings: CompressorSettings::default(),
+            reduction: 0.0,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+            makeup: 1.0,
+            peak_reduction: 0.0,
+            sample_rate,
+        };
+        compressor.set_params(&CompressorSettings::default());
+        compressor
+    }
+
+    /// 設定から係数を計算する（バッファごとに1回呼ぶ）
+    pub fn set_params(&mut self, settings: &CompressorSettings) {
+        self.settings = *settings;
+        let coeff = |ms: f32| (-1.0 / (ms * 0.001 * self.sample_rate)).exp();
+        self.attack_coeff = coeff(settings.attack.clamp(0.1, 100.0));
+        self.release_coeff = coeff(settings.release.clamp(10.0, 1000.0));
+        self.makeup = 10.0f32.powf(settings.makeup.clamp(0.0, 24.0) / 20.0);
+    }
+
+    /// ゲインリダクションをクリアする
+    pub fn reset(&mut self) {
+        self.reduction = 0.0;
+        self.peak_reduction = 0.0;
+    }
+
+    /// 左右1組のサンプルを処理する（無効の場合はそのまま返す）
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if !self.settings.enabled {
+            return (left, right);
+        }
+        // 左右の大きい方のピークでゲインリダクションを決める（定位が揺れないように）
+        let peak = left.abs().max(right.abs()).max(1e-6);
+        let target = self.settings.gain_reduction(20.0 * peak.log10());
+        let coeff = if target > self.reduction { self.attack_coeff } else { self.release_coeff };
+        self.reduction = target + (self.reduction - target) * coeff;
+        self.peak_reduction = self.peak_reduction.max(self.reduction);
+
+        let gain = 10.0f32.powf(-self.reduction / 20.0) * self.makeup;
+        (left * gain, right * gain)
+    }
+
+    /// 前回呼んでからの最大のゲインリダクション（dB）を取得してリセットする
+    pub fn take_peak_reduction(&mut self) -> f32 {
+        std::mem::take(&mut self.peak_reduction)
+    }
+}
+
+/// コンプレッサーの設定を管理する構造体
+pub struct CompressorManager {
+    settings: Arc<Mutex<CompressorSettings>>,
+    gain_reduction: Arc<AtomicU32>, // オーディオスレッドが書くゲインリダクション（dB、f32のビット列）
+}
+
+impl CompressorManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(CompressorSettings::default())),
+            gain_reduction: Arc::new(AtomicU32::new(0.0f32.to_bits())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<CompressorSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.enabled = enabled;
+        }
+    }
+
+    pub fn set_threshold(&self, threshold: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.threshold = threshold.clamp(-60.0, 0.0);
+        }
+    }
+
+    pub fn set_ratio(&self, ratio: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.ratio = ratio.clamp(1.0, 20.0);
+        }
+    }
+
+    pub fn set_attack(&self, attack: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.attack = attack.clamp(0.1, 100.0);
+        }
+    }
+
+    pub fn set_release(&self, release: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.release = release.clamp(10.0, 1000.0);
+        }
+    }
+
+    pub fn set_makeup(&self, makeup: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.makeup = makeup.clamp(0.0, 24.0);
+        }
+    }
+
+    /// ブロックごとのゲインリダクションを通知する（オーディオスレッドから呼ぶ）
+    pub fn report_gain_reduction(&self, reduction: f32) {
+        self.gain_reduction.store(reduction.to_bits(), Ordering::Relaxed);
+    }
+
+    /// 最後に通知されたゲインリダクション（dB、GUIのメーター用）
+    pub fn gain_reduction(&self) -> f32 {
+        f32::from_bits(self.gain_reduction.load(Ordering::Relaxed))
+    }
+}
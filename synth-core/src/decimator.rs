@@ -0,0 +1,121 @@
+use std::f32::consts::PI;
+use std::sync::OnceLock;
+
+/// ハーフバンドフィルターのタップ数（奇数、中央以外の1つおきの係数は0になる）
+const HALF_BAND_TAPS: usize = 31;
+/// フィルターの中央のタップ
+const HALF_BAND_CENTER: usize = HALF_BAND_TAPS / 2;
+/// 入力の履歴の長さ（タップ数以上の2のべき乗、位置をマスクで折り返す）
+const HISTORY_LEN: usize = 32;
+/// 2分の1に間引く段の最大数（2^4 = 16倍まで）
+pub const MAX_DECIMATION_STAGES: usize = 4;
+/// オーバーサンプリング倍率の最大値
+pub const MAX_OVERSAMPLE_RATIO: u32 = 1 << MAX_DECIMATION_STAGES;
+
+/// ハーフバンドフィルターの係数（ブラックマン窓をかけた sinc、カットオフは入力のナイキストの半分）
+fn half_band_coefficients() -> &'static [f32; HALF_BAND_TAPS] {
+    static COEFFICIENTS: OnceLock<[f32; HALF_BAND_TAPS]> = OnceLock::new();
+    COEFFICIENTS.get_or_init(|| {
+        let mut coefficients = [0.0; HALF_BAND_TAPS];
+        let last = (HALF_BAND_TAPS - 1) as f32;
+        for (i, coefficient) in coefficients.iter_mut().enumerate() {
+            let x = i as f32 - HALF_BAND_CENTER as f32;
+            let sinc = if x == 0.0 { 0.5 } else { (0.5 * PI * x).sin() / (PI * x) };
+            let t = i as f32 / last;
+            let window = 0.42 - 0.5 * (2.0 * PI * t).cos() + 0.08 * (4.0 * PI * t).cos();
+            *coefficient = sinc * window;
+        }
+        // 直流の利得を1にする
+        let sum: f32 = coefficients.iter().sum();
+        for coefficient in coefficients.iter_mut() {
+            *coefficient /= sum;
+        }
+        coefficients
+    })
+}
+
+/// オーバーサンプリング倍率を使える値（1〜16の2のべき乗）に丸める
+pub fn oversample_ratio(ratio: u32) -> u32 {
+    ratio.clamp(1, MAX_OVERSAMPLE_RATIO).next_power_of_two()
+}
+
+/// 2サンプルを受け取って1サンプルを出すハーフバンドの間引きフィルター
+#[derive(Clone, Copy)]
+struct HalfBandStage {
+    history: [f32; HISTORY_LEN], // 入力の履歴（リングバッファ）
+    pos: usize,                  // 次に書き込む位置
+}
+
+impl HalfBandStage {
+    fn new() -> Self {
+        Self {
+            history: [0.0; HISTORY_LEN],
+            pos: 0,
+        }
+    }
+
+    fn push(&mut self, input: f32) {
+        self.history[self.pos] = input;
+        self.pos = (self.pos + 1) & (HISTORY_LEN - 1);
+    }
+
+    /// 2サンプルを入力して、半分のレートで1サンプルを出力する
+    fn process(&mut self, first: f32, second: f32) -> f32 {
+        self.push(first);
+        self.push(second);
+
+        // 係数が0のタップは飛ばす（端から1つおきのタップと中央だけを計算する）
+        let coefficients = half_band_coefficients();
+        let newest = self.pos + HISTORY_LEN - 1;
+        let mut sum = coefficients[HALF_BAND_CENTER] * self.history[(newest - HALF_BAND_CENTER) & (HISTORY_LEN - 1)];
+        for tap in (0..HALF_BAND_TAPS).step_by(2) {
+            sum += coefficients[tap] * self.history[(newest - tap) & (HISTORY_LEN - 1)];
+        }
+        sum
+    }
+}
+
+/// オーバーサンプリングした波形を出力のサンプルレートに戻す間引きフィルター（オシレータごとに保持する）
+///
+/// ハーフバンドフィルターで2分の1ずつ間引くのを倍率の分だけ重ねるので、
+/// 出力のナイキスト周波数より上の成分は折り返す前に取り除かれる
+#[derive(Clone, Copy)]
+pub struct Decimator {
+    stages: [HalfBandStage; MAX_DECIMATION_STAGES],
+}
+
+impl Decimator {
+    pub fn new() -> Self {
+        Self {
+            stages: [HalfBandStage::new(); MAX_DECIMATION_STAGES],
+        }
+    }
+
+    /// 履歴を消す（発音開始時に呼ぶ）
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// オーバーサンプリングした1サンプル分の波形から1サンプルを作る
+    ///
+    /// `samples` の長さは2のべき乗（倍率）で、中身は作業用に書き換える
+    pub fn process(&mut self, samples: &mut [f32]) -> f32 {
+        let mut len = samples.len();
+        for stage in self.stages.iter_mut() {
+            if len < 2 {
+                break;
+            }
+            len /= 2;
+            for i in 0..len {
+                samples[i] = stage.process(samples[2 * i], samples[2 * i + 1]);
+            }
+        }
+        samples.first().copied().unwrap_or(0.0)
+    }
+}
+
+impl Default for Decimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,154 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::denormal::flush_denormal;
+use crate::smoother::smoothing_coefficient;
+use crate::transport::NoteDivision;
+
+/// ディレイタイムの範囲（ミリ秒、テンポ同期した長さもこの範囲に収める）
+pub const MIN_DELAY_TIME: f32 = 1.0;
+pub const MAX_DELAY_TIME: f32 = 2000.0;
+
+/// ディレイタイムを変えた時に追従する時定数（秒、急に変えてもクリックが出ないように）
+const TIME_SMOOTHING: f32 = 0.05;
+
+/// ディレイの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DelaySettings {
+    /// ディレイタイム（ミリ秒、1-2000、テンポ同期しない時）
+    pub time: f32,
+    /// テンポに同期するかどうか（true なら division の長さにする）
+    pub sync: bool,
+    /// テンポ同期する時の長さ
+    pub division: NoteDivision,
+    /// フィードバック（0.0-0.95）
+    pub feedback: f32,
+    /// ウェットの割合（0.0-1.0）
+    pub mix: f32,
+}
+
+impl Default for DelaySettings {
+    fn default() -> Self {
+        Self {
+            time: 375.0,
+            sync: true,
+            division: NoteDivision::EighthDotted,
+            feedback: 0.4,
+            mix: 0.3,
+        }
+    }
+}
+
+impl DelaySettings {
+    /// テンポ（BPM）でのディレイタイム（ミリ秒）
+    pub fn time_at(&self, bpm: f32) -> f32 {
+        let time = if self.sync { self.division.seconds(bpm) * 1000.0 } else { self.time };
+        time.clamp(MIN_DELAY_TIME, MAX_DELAY_TIME)
+    }
+}
+
+/// フィードバックつきのディレイ（オーディオスレッドが所有する）
+pub struct Delay {
+    buffer: Vec<f32>,
+    write_index: usize,
+    delay: f32,  // 現在のディレイタイム（サンプル、目標値に追従させる）
+    target: f32, // 目標のディレイタイム（サンプル）
+    smoothing: f32,
+    sample_rate: f32,
+}
+
+impl Delay {
+    /// サンプルレートに合わせて遅延バッファを確保する
+    pub fn new(sample_rate: f32) -> Self {
+        let size = (MAX_DELAY_TIME * 0.001 * sample_rate) as usize + 2;
+        let delay = DelaySettings::default().time * 0.001 * sample_rate;
+        Self {
+            buffer: vec![0.0; size],
+            write_index: 0,
+            delay,
+            target: delay,
+            smoothing: smoothing_coefficient(TIME_SMOOTHING, sample_rate),
+            sample_rate,
+        }
+    }
+
+    /// ディレイタイム（ミリ秒）を設定する（バッファごとに1回呼ぶ）
+    pub fn set_time(&mut self, time: f32) {
+        self.target = time.clamp(MIN_DELAY_TIME, MAX_DELAY_TIME) * 0.001 * self.sample_rate;
+    }
+
+    /// 遅延バッファをクリアする
+    pub fn reset(&mut self) {
+        self.buffer.iter_mut().for_each(|x| *x = 0.0);
+        self.delay = self.target;
+    }
+
+    /// 1サンプル処理する
+    pub fn process(&mut self, input: f32, settings: &DelaySettings) -> f32 {
+        let len = self.buffer.len();
+        self.delay += (self.target - self.delay) * self.smoothing;
+
+        // 線形補間で遅延位置のサンプルを読む
+        let read = self.write_index as f32 - self.delay.clamp(1.0, (len - 2) as f32) + len as f32;
+        let index = read.floor() as usize;
+        let frac = read - read.floor();
+        let a = self.buffer[index % len];
+        let b = self.buffer[(index + 1) % len];
+        let wet = a + (b - a) * frac;
+
+        self.buffer[self.write_index] = flush_denormal(input + wet * settings.feedback.clamp(0.0, 0.95));
+        self.write_index = (self.write_index + 1) % len;
+
+        let mix = settings.mix.clamp(0.0, 1.0);
+        input * (1.0 - mix) + wet * mix
+    }
+}
+
+/// ディレイの設定を管理する構造体
+pub struct DelayManager {
+    settings: Arc<Mutex<DelaySettings>>,
+}
+
+impl DelayManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(DelaySettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<DelaySettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_time(&self, time: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.time = time.clamp(MIN_DELAY_TIME, MAX_DELAY_TIME);
+        }
+    }
+
+    pub fn set_sync(&self, sync: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.sync = sync;
+        }
+    }
+
+    pub fn set_division(&self, division: NoteDivision) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.division = division;
+        }
+    }
+
+    pub fn set_feedback(&self, feedback: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.feedback = feedback.clamp(0.0, 0.95);
+        }
+    }
+
+    pub fn set_mix(&self, mix: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.mix = mix.clamp(0.0, 1.0);
+        }
+    }
+}
@@ -0,0 +1,11 @@
+/// これより小さい値は0とみなす（-300dB、聞こえる音には影響しない）
+const DENORMAL_THRESHOLD: f32 = 1e-15;
+
+/// 非正規化数になりそうな小さな値を0にする
+///
+/// フィードバックのあるフィルターやエンベロープの減衰は0に近づくだけで0にならないので、
+/// 非正規化数の演算でCPU負荷が跳ね上がらないように状態を書き戻す時に通す
+#[inline]
+pub fn flush_denormal(x: f32) -> f32 {
+    if x.abs() < DENORMAL_THRESHOLD { 0.0 } else { x }
+}
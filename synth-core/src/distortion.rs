@@ -0,0 +1,199 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::filter::{FilterMode, FilterSettings, StateVariableFilter};
+
+/// オーバーサンプリングの倍率（波形を歪ませる処理だけこのレートで行う）
+const OVERSAMPLING: usize = 4;
+
+/// 折り返し防止フィルターのカットオフ（元のサンプルレートに対する割合）
+const ANTI_ALIAS_CUTOFF: f32 = 0.45;
+
+/// トーンのカットオフの範囲（Hz、0.0で暗く、1.0でほぼ素通し）
+const TONE_MIN_CUTOFF: f32 = 800.0;
+const TONE_MAX_CUTOFF: f32 = 20000.0;
+
+/// 歪ませ方の種類を表す列挙型
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum DistortionAlgorithm {
+    SoftClip, // 3次式のソフトクリップ
+    HardClip, // ±1で切り取る
+    Tanh,     // tanhによる飽和
+    Foldback, // ±1を超えた分を折り返す
+}
+
+impl Default for DistortionAlgorithm {
+    fn default() -> Self {
+        Self::SoftClip
+    }
+}
+
+impl DistortionAlgorithm {
+    /// 1サンプルを歪ませる
+    fn shape(self, x: f32) -> f32 {
+        match self {
+            DistortionAlgorithm::SoftClip => {
+                let x = x.clamp(-1.0, 1.0);
+                1.5 * (x - x * x * x / 3.0)
+            }
+            DistortionAlgorithm::HardClip => x.clamp(-1.0, 1.0),
+            DistortionAlgorithm::Tanh => x.tanh(),
+            // 周期4の三角波で折り返す（±1の範囲では入力と同じ）
+            DistortionAlgorithm::Foldback => 1.0 - 4.0 * (((x + 1.0) / 4.0).rem_euclid(1.0) - 0.5).abs(),
+        }
+    }
+}
+
+/// ディストーションの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DistortionSettings {
+    /// 歪ませ方
+    pub algorithm: DistortionAlgorithm,
+    /// 入力の増幅量（dB、0-40）
+    pub drive: f32,
+    /// トーン（0.0-1.0、小さいほど高域を削る）
+    pub tone: f32,
+    /// 出力の音量（dB、-24-12）
+    pub output: f32,
+}
+
+impl Default for DistortionSettings {
+    fn default() -> Self {
+        Self {
+            algorithm: DistortionAlgorithm::SoftClip,
+            drive: 12.0,
+            tone: 0.8,
+            output: -6.0,
+        }
+    }
+}
+
+impl DistortionSettings {
+    /// トーンのカットオフ周波数（対数スケール）
+    pub fn tone_cutoff(&self) -> f32 {
+        TONE_MIN_CUTOFF * (TONE_MAX_CUTOFF / TONE_MIN_CUTOFF).powf(self.tone.clamp(0.0, 1.0))
+    }
+}
+
+/// オーバーサンプリングして歪ませるディストーション（オーディオスレッドが所有する）
+///
+/// 0を挟んで4倍に伸ばし、ローパスで補間してから歪ませ、
+/// もう一度ローパスをかけて間引く（歪みで生じた高調波がナイキスト周波数で折り返さないように）
+pub struct Distortion {
+    upsample: [StateVariableFilter; 2],   // 補間用のローパス（2段で4次）
+    downsample: [StateVariableFilter; 2], // 間引く前のローパス（2段で4次）
+    tone: StateVariableFilter,
+    algorithm: DistortionAlgorithm,
+    drive: f32,  // 入力の倍率
+    output: f32, // 出力の倍率
+    sample_rate: f32,
+}
+
+impl Distortion {
+    pub fn new(sample_rate: f32) -> Self {
+        let oversampled_rate = sample_rate * OVERSAMPLING as f32;
+        let anti_alias = FilterSettings {
+            enabled: true,
+            mode: FilterMode::LowPass,
+            cutoff: sample_rate * ANTI_ALIAS_CUTOFF,
+            resonance: 0.0,
+            env_amount: 0.0,
+            key_track: 0.0,
+        };
+        let filter = || {
+            let mut filter = StateVariableFilter::new();
+            filter.set_params(&anti_alias, oversampled_rate);
+            filter
+        };
+        let mut distortion = Self {
+            upsample: [filter(), filter()],
+            downsample: [filter(), filter()],
+            tone: StateVariableFilter::new(),
+            algorithm: DistortionAlgorithm::SoftClip,
+            drive: 1.0,
+            output: 1.0,
+            sample_rate,
+        };
+        distortion.set_params(&DistortionSettings::default());
+        distortion
+    }
+
+    /// 設定から倍率とトーンの係数を計算する（バッファごとに1回呼ぶ）
+    pub fn set_params(&mut self, settings: &DistortionSettings) {
+        self.algorithm = settings.algorithm;
+        self.drive = 10.0f32.powf(settings.drive.clamp(0.0, 40.0) / 20.0);
+        self.output = 10.0f32.powf(settings.output.clamp(-24.0, 12.0) / 20.0);
+        let tone = FilterSettings {
+            enabled: true,
+            mode: FilterMode::LowPass,
+            cutoff: settings.tone_cutoff(),
+            resonance: 0.0,
+            env_amount: 0.0,
+            key_track: 0.0,
+        };
+        self.tone.set_params(&tone, self.sample_rate);
+    }
+
+    /// フィルターの状態をクリアする
+    pub fn reset(&mut self) {
+        self.upsample.iter_mut().for_each(StateVariableFilter::reset);
+        self.downsample.iter_mut().for_each(StateVariableFilter::reset);
+        self.tone.reset();
+    }
+
+    /// 1サンプル処理する
+    pub fn process(&mut self, input: f32) -> f32 {
+        let mut output = 0.0;
+        for i in 0..OVERSAMPLING {
+            // 0を挟んだ分の音量を倍率で補う
+            let stuffed = if i == 0 { input * OVERSAMPLING as f32 } else { 0.0 };
+            let upsampled = self.upsample.iter_mut().fold(stuffed, |x, filter| filter.process(x));
+            let shaped = self.algorithm.shape(upsampled * self.drive);
+            output = self.downsample.iter_mut().fold(shaped, |x, filter| filter.process(x));
+        }
+        self.tone.process(output) * self.output
+    }
+}
+
+/// ディストーションの設定を管理する構造体
+pub struct DistortionManager {
+    settings: Arc<Mutex<DistortionSettings>>,
+}
+
+impl DistortionManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(DistortionSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<DistortionSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_algorithm(&self, algorithm: DistortionAlgorithm) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.algorithm = algorithm;
+        }
+    }
+
+    pub fn set_drive(&self, drive: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.drive = drive.clamp(0.0, 40.0);
+        }
+    }
+
+    pub fn set_tone(&self, tone: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.tone = tone.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_output(&self, output: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.output = output.clamp(-24.0, 12.0);
+        }
+    }
+}
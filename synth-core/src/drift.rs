@@ -0,0 +1,96 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+/// 揺れの量が1.0の時のピッチのずれの最大（セント）
+pub const MAX_DRIFT_CENTS: f32 = 12.0;
+/// 揺れの量が1.0の時の音量のずれの最大（倍率、±6%）
+const MAX_DRIFT_GAIN: f32 = 0.06;
+/// 揺れの量が1.0の時の発音開始時の位相のずれの最大（周期）
+const MAX_DRIFT_PHASE: f32 = 0.25;
+/// ランダムウォークが中心へ戻る速さ（1/秒、大きいほど揺れが速い）
+const DRIFT_RETURN_RATE: f32 = 0.5;
+/// ランダムウォークの1秒あたりの揺れの大きさ（中心へ戻る速さと合わせて標準偏差が約0.5になる）
+const DRIFT_NOISE: f32 = 0.5;
+
+/// アナログVCOの不安定さ（ドリフト）の設定を表す構造体
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DriftSettings {
+    /// 揺れの量（0.0で揺れなし、1.0で最大）
+    pub amount: f32,
+}
+
+/// ボイスごとのピッチと音量の揺れ（オーディオスレッドがボイスごとに保持する）
+///
+/// ピッチと音量はそれぞれ -1.0〜1.0 の範囲に収まるランダムウォークで、ゆっくり中心へ戻りながら揺れる
+pub struct Drift {
+    pitch: f32,     // ピッチの揺れの位置（-1.0〜1.0）
+    gain: f32,      // 音量の揺れの位置（-1.0〜1.0）
+    rng_state: u32, // 乱数の状態（xorshift）
+}
+
+impl Drift {
+    /// ボイスごとに異なる種を渡して作る（0は使えないので1に置き換える）
+    pub fn new(seed: u32) -> Self {
+        Self {
+            pitch: 0.0,
+            gain: 0.0,
+            rng_state: seed.wrapping_mul(0x2545_F491).max(1),
+        }
+    }
+
+    /// 1サンプル分揺れを進めて、（周波数の倍率, 音量の倍率）を返す
+    pub fn next(&mut self, settings: &DriftSettings, sample_rate: f32) -> (f32, f32) {
+        let amount = settings.amount.clamp(0.0, 1.0);
+        if amount <= 0.0 {
+            return (1.0, 1.0);
+        }
+        let dt = 1.0 / sample_rate;
+        // 一様乱数（-1〜1）を √3 倍して分散を1にする
+        let noise = DRIFT_NOISE * dt.sqrt() * 3.0f32.sqrt();
+        let pitch_step = self.next_random() * noise;
+        let gain_step = self.next_random() * noise;
+        self.pitch = (self.pitch * (1.0 - DRIFT_RETURN_RATE * dt) + pitch_step).clamp(-1.0, 1.0);
+        self.gain = (self.gain * (1.0 - DRIFT_RETURN_RATE * dt) + gain_step).clamp(-1.0, 1.0);
+
+        let cents = self.pitch * MAX_DRIFT_CENTS * amount;
+        (2.0f32.powf(cents / 1200.0), 1.0 + self.gain * MAX_DRIFT_GAIN * amount)
+    }
+
+    /// 発音開始時の位相のずれ（周期）を決める
+    pub fn phase_offset(&mut self, settings: &DriftSettings) -> f32 {
+        self.next_random() * MAX_DRIFT_PHASE * settings.amount.clamp(0.0, 1.0)
+    }
+
+    /// -1.0〜1.0 の乱数を生成する
+    fn next_random(&mut self) -> f32 {
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 17;
+        self.rng_state ^= self.rng_state << 5;
+        self.rng_state as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+}
+
+/// ドリフトの設定を管理する構造体
+pub struct DriftManager {
+    settings: Arc<Mutex<DriftSettings>>,
+}
+
+impl DriftManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(DriftSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<DriftSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_amount(&self, amount: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.amount = amount.clamp(0.0, 1.0);
+        }
+    }
+}
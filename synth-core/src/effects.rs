@@ -0,0 +1,502 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bitcrusher::{Bitcrusher, BitcrusherSettings};
+use crate::chorus::{Chorus, ChorusSettings};
+use crate::delay::{Delay, DelaySettings};
+use crate::distortion::Distortion;
+use crate::flanger::{Flanger, FlangerSettings};
+use crate::params::SynthParams;
+use crate::phaser::{Phaser, PhaserSettings};
+use crate::reverb::{Reverb, ReverbSettings, STEREO_SPREAD};
+
+/// チェーンに並べられるエフェクトの最大数
+pub const MAX_EFFECTS: usize = 8;
+
+/// 1段あたりのビット数（チェーンを AtomicU64 に詰めるため）
+const SLOT_BITS: u32 = 8;
+
+/// エフェクトの種類
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum EffectKind {
+    Chorus,     // コーラス
+    Reverb,     // リバーブ
+    Distortion, // ディストーション
+    Bitcrusher, // ビットクラッシャー
+    Phaser,     // フェイザー
+    Flanger,    // フランジャー
+    Delay,      // ディレイ
+}
+
+impl EffectKind {
+    /// 全ての種類（エンジンが持つエフェクトの並び順）
+    pub const ALL: [EffectKind; 7] = [
+        EffectKind::Chorus,
+        EffectKind::Reverb,
+        EffectKind::Distortion,
+        EffectKind::Bitcrusher,
+        EffectKind::Phaser,
+        EffectKind::Flanger,
+        EffectKind::Delay,
+    ];
+
+    pub fn index(self) -> usize {
+        match self {
+            EffectKind::Chorus => 0,
+            EffectKind::Reverb => 1,
+            EffectKind::Distortion => 2,
+            EffectKind::Bitcrusher => 3,
+            EffectKind::Phaser => 4,
+            EffectKind::Flanger => 5,
+            EffectKind::Delay => 6,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            EffectKind::Chorus => "Chorus",
+            EffectKind::Reverb => "Reverb",
+            EffectKind::Distortion => "Distortion",
+            EffectKind::Bitcrusher => "Bitcrusher",
+            EffectKind::Phaser => "Phaser",
+            EffectKind::Flanger => "Flanger",
+            EffectKind::Delay => "Delay",
+        }
+    }
+
+    fn from_index(index: usize) -> Option<Self> {
+        Self::ALL.get(index).copied()
+    }
+}
+
+/// チェーンの1段（エフェクトの種類とバイパス）
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct EffectSlot {
+    pub kind: EffectKind,
+    /// バイパス中は処理せずに素通しする
+    pub bypassed: bool,
+}
+
+/// エフェクトチェーン（先頭から順にかける、同じ種類は1つまで）
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct EffectChain {
+    pub slots: Vec<EffectSlot>,
+}
+
+impl EffectChain {
+    /// 以前のコーラス→リバーブ固定の並びを、それぞれの有効/無効から作る（旧形式のプリセット用）
+    pub fn legacy(chorus: &ChorusSettings, reverb: &ReverbSettings) -> Self {
+        Self {
+            slots: vec![
+                EffectSlot {
+                    kind: EffectKind::Chorus,
+                    bypassed: !chorus.enabled,
+                },
+                EffectSlot {
+                    kind: EffectKind::Reverb,
+                    bypassed: !reverb.enabled,
+                },
+            ],
+        }
+    }
+
+    /// 種類がチェーンに入っていて、バイパスされていないかどうか
+    pub fn is_active(&self, kind: EffectKind) -> bool {
+        self.slots.iter().any(|slot| slot.kind == kind && !slot.bypassed)
+    }
+
+    /// 1段を8ビット（種類の番号+1 と バイパスのビット）にしてまとめる（0は空き）
+    fn encode(&self) -> u64 {
+        self.slots.iter().take(MAX_EFFECTS).enumerate().fold(0, |bits, (i, slot)| {
+            let value = ((slot.kind.index() as u64 + 1) << 1) | slot.bypassed as u64;
+            bits | (value << (i as u32 * SLOT_BITS))
+        })
+    }
+}
+
+/// オーディオスレッドで読むチェーン（アロケーションしないように固定長で持つ）
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct ChainState {
+    bits: u64,
+}
+
+impl ChainState {
+    /// 先頭から順に、バイパスされていないエフェクトの種類
+    pub fn active(self) -> impl Iterator<Item = EffectKind> {
+        (0..MAX_EFFECTS).filter_map(move |i| {
+            let value = (self.bits >> (i as u32 * SLOT_BITS)) & ((1 << SLOT_BITS) - 1);
+            if value == 0 || value & 1 == 1 {
+                return None;
+            }
+            EffectKind::from_index((value >> 1) as usize - 1)
+        })
+    }
+
+    /// 有効なエフェクトがあるかどうか
+    pub fn has_active(self) -> bool {
+        self.active().next().is_some()
+    }
+
+    /// 種類が有効かどうか
+    pub fn is_active(self, kind: EffectKind) -> bool {
+        self.active().any(|active| active == kind)
+    }
+}
+
+/// エフェクトチェーンを管理する構造体
+///
+/// 並びとバイパスは AtomicU64 に詰めて渡すので、オーディオスレッドはロックせずに読める。
+/// 各エフェクトのパラメータはそれぞれのマネージャー（ChorusManager など）が持つ
+pub struct EffectsManager {
+    chain: Mutex<EffectChain>, // GUIとプリセットで編集する並び
+    state: AtomicU64,          // オーディオスレッドに渡す並び
+}
+
+impl EffectsManager {
+    pub fn new() -> Self {
+        let chain = EffectChain::legacy(&ChorusSettings::default(), &ReverbSettings::default());
+        let state = AtomicU64::new(chain.encode());
+        Self {
+            chain: Mutex::new(chain),
+            state,
+        }
+    }
+
+    /// 現在のチェーン（GUI・プリセット用）
+    pub fn get_chain(&self) -> EffectChain {
+        self.chain.lock().map(|chain| chain.clone()).unwrap_or_default()
+    }
+
+    /// チェーンを置き換える（同じ種類の2つ目以降と、最大数を超えた分は捨てる）
+    pub fn set_chain(&self, chain: EffectChain) {
+        let mut slots: Vec<EffectSlot> = Vec::with_capacity(MAX_EFFECTS);
+        for slot in chain.slots {
+            if slots.len() < MAX_EFFECTS && !slots.iter().any(|existing| existing.kind == slot.kind) {
+                slots.push(slot);
+            }
+        }
+        let chain = EffectChain { slots };
+        self.state.store(chain.encode(), Ordering::Release);
+        if let Ok(mut current) = self.chain.lock() {
+            *current = chain;
+        }
+    }
+
+    /// チェーンの末尾にエフェクトを追加する
+    pub fn add(&self, kind: EffectKind) {
+        let mut chain = self.get_chain();
+        chain.slots.push(EffectSlot { kind, bypassed: false });
+        self.set_chain(chain);
+    }
+
+    /// チェーンの index 番目のエフェクトを取り除く
+    pub fn remove(&self, index: usize) {
+        let mut chain = self.get_chain();
+        if index < chain.slots.len() {
+            chain.slots.remove(index);
+            self.set_chain(chain);
+        }
+    }
+
+    /// チェーンの from 番目のエフェクトを to 番目に移す
+    pub fn move_slot(&self, from: usize, to: usize) {
+        let mut chain = self.get_chain();
+        if from < chain.slots.len() && to < chain.slots.len() {
+            let slot = chain.slots.remove(from);
+            chain.slots.insert(to, slot);
+            self.set_chain(chain);
+        }
+    }
+
+    /// チェーンの index 番目のエフェクトのバイパスを切り替える
+    pub fn set_bypassed(&self, index: usize, bypassed: bool) {
+        let mut chain = self.get_chain();
+        if let Some(slot) = chain.slots.get_mut(index) {
+            slot.bypassed = bypassed;
+            self.set_chain(chain);
+        }
+    }
+
+    /// オーディオスレッドがブロックの先頭で読む
+    pub fn state(&self) -> ChainState {
+        ChainState {
+            bits: self.state.load(Ordering::Acquire),
+        }
+    }
+}
+
+/// オーディオスレッドで動かすエフェクト（左右交互のステレオバッファを処理する）
+pub trait Effect: Send {
+    /// ブロックの先頭で共有パラメータを読む（ロックできない場合は前回の値を使う）
+    fn update(&mut self, params: &SynthParams);
+
+    /// バッファをその場で処理する
+    fn process(&mut self, buffer: &mut [f32]);
+
+    /// 遅延バッファや残響を消す（バイパスやチェーンから外した時に呼ぶ）
+    fn reset(&mut self);
+}
+
+/// 種類ごとのエフェクトを作る（遅延バッファを確保するのでオーディオスレッドの外で呼ぶ）
+pub fn create_effect(kind: EffectKind, sample_rate: f32) -> Box<dyn Effect> {
+    match kind {
+        EffectKind::Chorus => Box::new(ChorusEffect::new(sample_rate)),
+        EffectKind::Reverb => Box::new(ReverbEffect::new(sample_rate)),
+        EffectKind::Distortion => Box::new(DistortionEffect::new(sample_rate)),
+        EffectKind::Bitcrusher => Box::new(BitcrusherEffect::new(sample_rate)),
+        EffectKind::Phaser => Box::new(PhaserEffect::new(sample_rate)),
+        EffectKind::Flanger => Box::new(FlangerEffect::new(sample_rate)),
+        EffectKind::Delay => Box::new(DelayEffect::new(sample_rate)),
+    }
+}
+
+/// ステレオのコーラス（左右で変調の位相をずらして広がりを出す）
+struct ChorusEffect {
+    choruses: [Chorus; 2],
+    settings: ChorusSettings,
+}
+
+impl ChorusEffect {
+    fn new(sample_rate: f32) -> Self {
+        Self {
+            choruses: [Chorus::new(sample_rate, 0.0), Chorus::new(sample_rate, 0.25)],
+            settings: ChorusSettings::default(),
+        }
+    }
+}
+
+impl Effect for ChorusEffect {
+    fn update(&mut self, params: &SynthParams) {
+        if let Ok(settings) = params.chorus.get_settings().try_lock() {
+            self.settings = *settings;
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        for frame in buffer.chunks_mut(2) {
+            for (value, chorus) in frame.iter_mut().zip(self.choruses.iter_mut()) {
+                *value = chorus.process(*value, &self.settings);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.choruses.iter_mut().for_each(Chorus::reset);
+    }
+}
+
+/// ステレオのリバーブ（右チャンネルの遅延時間を伸ばして広がりを出す）
+struct ReverbEffect {
+    reverbs: [Reverb; 2],
+}
+
+impl ReverbEffect {
+    fn new(sample_rate: f32) -> Self {
+        Self {
+            reverbs: [Reverb::new(sample_rate, 0), Reverb::new(sample_rate, STEREO_SPREAD)],
+        }
+    }
+}
+
+impl Effect for ReverbEffect {
+    fn update(&mut self, params: &SynthParams) {
+        if let Ok(settings) = params.reverb.get_settings().try_lock() {
+            for reverb in self.reverbs.iter_mut() {
+                reverb.set_params(&settings);
+            }
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        for frame in buffer.chunks_mut(2) {
+            for (value, reverb) in frame.iter_mut().zip(self.reverbs.iter_mut()) {
+                *value = reverb.process(*value);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.reverbs.iter_mut().for_each(Reverb::reset);
+    }
+}
+
+/// ステレオのディストーション
+struct DistortionEffect {
+    distortions: [Distortion; 2],
+}
+
+impl DistortionEffect {
+    fn new(sample_rate: f32) -> Self {
+        Self {
+            distortions: [Distortion::new(sample_rate), Distortion::new(sample_rate)],
+        }
+    }
+}
+
+impl Effect for DistortionEffect {
+    fn update(&mut self, params: &SynthParams) {
+        if let Ok(settings) = params.distortion.get_settings().try_lock() {
+            for distortion in self.distortions.iter_mut() {
+                distortion.set_params(&settings);
+            }
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        for frame in buffer.chunks_mut(2) {
+            for (value, distortion) in frame.iter_mut().zip(self.distortions.iter_mut()) {
+                *value = distortion.process(*value);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.distortions.iter_mut().for_each(Distortion::reset);
+    }
+}
+
+/// ステレオのビットクラッシャー
+struct BitcrusherEffect {
+    crushers: [Bitcrusher; 2],
+    settings: BitcrusherSettings,
+}
+
+impl BitcrusherEffect {
+    fn new(sample_rate: f32) -> Self {
+        Self {
+            crushers: [Bitcrusher::new(sample_rate), Bitcrusher::new(sample_rate)],
+            settings: BitcrusherSettings::default(),
+        }
+    }
+}
+
+impl Effect for BitcrusherEffect {
+    fn update(&mut self, params: &SynthParams) {
+        if let Ok(settings) = params.bitcrusher.get_settings().try_lock() {
+            self.settings = *settings;
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        for frame in buffer.chunks_mut(2) {
+            for (value, crusher) in frame.iter_mut().zip(self.crushers.iter_mut()) {
+                *value = crusher.process(*value, &self.settings);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.crushers.iter_mut().for_each(Bitcrusher::reset);
+    }
+}
+
+/// ステレオのフェイザー（左右でスイープの位相をずらして広がりを出す）
+struct PhaserEffect {
+    phasers: [Phaser; 2],
+    settings: PhaserSettings,
+}
+
+impl PhaserEffect {
+    fn new(sample_rate: f32) -> Self {
+        Self {
+            phasers: [Phaser::new(sample_rate, 0.0), Phaser::new(sample_rate, 0.25)],
+            settings: PhaserSettings::default(),
+        }
+    }
+}
+
+impl Effect for PhaserEffect {
+    fn update(&mut self, params: &SynthParams) {
+        if let Ok(settings) = params.phaser.get_settings().try_lock() {
+            self.settings = *settings;
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        for frame in buffer.chunks_mut(2) {
+            for (value, phaser) in frame.iter_mut().zip(self.phasers.iter_mut()) {
+                *value = phaser.process(*value, &self.settings);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.phasers.iter_mut().for_each(Phaser::reset);
+    }
+}
+
+/// ステレオのフランジャー（左右で変調の位相をずらして広がりを出す）
+struct FlangerEffect {
+    flangers: [Flanger; 2],
+    settings: FlangerSettings,
+}
+
+impl FlangerEffect {
+    fn new(sample_rate: f32) -> Self {
+        Self {
+            flangers: [Flanger::new(sample_rate, 0.0), Flanger::new(sample_rate, 0.25)],
+            settings: FlangerSettings::default(),
+        }
+    }
+}
+
+impl Effect for FlangerEffect {
+    fn update(&mut self, params: &SynthParams) {
+        if let Ok(settings) = params.flanger.get_settings().try_lock() {
+            self.settings = *settings;
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        for frame in buffer.chunks_mut(2) {
+            for (value, flanger) in frame.iter_mut().zip(self.flangers.iter_mut()) {
+                *value = flanger.process(*value, &self.settings);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.flangers.iter_mut().for_each(Flanger::reset);
+    }
+}
+
+/// ステレオのディレイ（テンポ同期する時はトランスポートのテンポを使う、外部同期中は測ったテンポ）
+struct DelayEffect {
+    delays: [Delay; 2],
+    settings: DelaySettings,
+}
+
+impl DelayEffect {
+    fn new(sample_rate: f32) -> Self {
+        Self {
+            delays: [Delay::new(sample_rate), Delay::new(sample_rate)],
+            settings: DelaySettings::default(),
+        }
+    }
+}
+
+impl Effect for DelayEffect {
+    fn update(&mut self, params: &SynthParams) {
+        if let Ok(settings) = params.delay.get_settings().try_lock() {
+            self.settings = *settings;
+        }
+        let time = self.settings.time_at(params.transport.tempo());
+        for delay in self.delays.iter_mut() {
+            delay.set_time(time);
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        for frame in buffer.chunks_mut(2) {
+            for (value, delay) in frame.iter_mut().zip(self.delays.iter_mut()) {
+                *value = delay.process(*value, &self.settings);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.delays.iter_mut().for_each(Delay::reset);
+    }
+}
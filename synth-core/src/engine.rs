@@ -0,0 +1,785 @@
+use crate::bandlimited;
+use crate::compressor::Compressor;
+use crate::drift::{Drift, DriftSettings};
+use crate::effects::{ChainState, Effect, EffectKind, create_effect};
+use crate::envelope::{EnvelopeParams, EnvelopeTarget};
+use crate::filter::{FilterSettings, StereoFilter};
+use crate::lfo::{Lfo, LfoRetrigger, LfoSettings, LfoVoiceMode};
+use crate::master::{DcBlocker, MasterSettings};
+use crate::mixer::MixerSettings;
+use crate::mod_monitor::{ModRange, MonitoredParam};
+use crate::modmatrix::{ModControllers, ModMatrixSettings, ModSources};
+use crate::oscillator::{NoiseGenerator, NoiseSettings, OscBSettings, OscillatorSettings, SubOscSettings};
+use crate::params::SynthParams;
+use crate::smoother::{PARAM_SMOOTHING_TIME, Smoother, smoothing_coefficient};
+use crate::transport::TransportClock;
+use crate::unison::{UnisonOscillator, UnisonSettings};
+use crate::voice::{MAX_VOICES, PARAPHONIC_GATE, Voice, VoicePlayer, VoiceSettings};
+use crate::xy_pad::XyPadFollower;
+use crate::zone::{LayerPatch, ZoneSettings};
+
+use std::sync::{Arc, Mutex};
+
+/// ストリームのバッファサイズが分からない時に、1回で生成できるようにしておくフレーム数
+pub const DEFAULT_MAX_BLOCK_FRAMES: usize = 4096;
+
+/// シンセの音声生成エンジン（オーディオスレッドが保持して、バッファごとに呼ぶ）
+///
+/// 共有パラメータは `try_lock` で読み、ロックできない場合は前回の値を使う。
+/// オーディオデバイスに依存しないので、オフラインのレンダリングやテストにもそのまま使える。
+pub struct SynthEngine {
+    params: SynthParams,
+    sample_rate: f32,
+
+    // ボイスの割り当て（ロックできない場合は前回の割り当てを使う）
+    // グライド・音量・エンベロープ・オシレータの位相はボイスごとにエンジンが保持する
+    voice_slots: Arc<Mutex<[Option<Voice>; MAX_VOICES]>>,
+    voices: [Option<Voice>; MAX_VOICES],
+    voice_settings: VoiceSettings,
+    players: Vec<VoicePlayer>,
+    oscillators: Vec<UnisonOscillator>,
+
+    // フィルターの状態（左右のチャンネルごと）
+    filters: Vec<StereoFilter>, // ボイスごとのフィルター（キートラッキングとフィルターエンベロープはボイスごとにかける）
+    filter_settings: FilterSettings,
+
+    // パラフォニックモードで全ボイスが共有するエンベロープとフィルター
+    shared_player: VoicePlayer, // 共有のエンベロープ（最初のノートで始まり、全てのノートを離すとリリースする）
+    shared_age: u64,            // 共有のエンベロープをトリガーしたノートの割り当て順
+    shared_held: bool,          // 前のバッファで押されているノートがあったか
+    shared_filter: StereoFilter,
+
+    // Unison・オシレータ設定
+    unison_settings: UnisonSettings,
+    osc_settings: OscillatorSettings,
+    osc_b_settings: OscBSettings,
+    sub_osc_settings: SubOscSettings,
+
+    // ノイズ源（乱数とフィルターの状態はボイスごとに持つ）
+    noise_settings: NoiseSettings,
+    noises: Vec<NoiseGenerator>,
+
+    // ボイスごとのドリフト（ピッチと音量の揺れ）
+    drift_settings: DriftSettings,
+    drifts: Vec<Drift>,
+
+    // 音源ごとの音量と定位（ボイスごとにかけてから足し合わせる）
+    mixer_settings: MixerSettings,
+
+    // エンベロープのパラメータ（状態はボイスごとに VoicePlayer が持つ）
+    envelope_params: [EnvelopeParams; 3],
+
+    // キーボードスプリット・レイヤーの音色（ボイスのレイヤーに合わせてオシレータ・フィルター・エンベロープを選ぶ）
+    zone_settings: ZoneSettings,
+
+    // LFOの状態は毎サンプル進める（ボイスごとのモードでは voice_lfos を使う）
+    lfo: Lfo,
+    lfo_settings: LfoSettings,
+    voice_lfos: Vec<Lfo>,
+
+    // トランスポートのクロック（再生位置をサンプル単位で数える）
+    clock: TransportClock,
+
+    // モジュレーションマトリクス（ブロックの先頭で評価する）
+    mod_settings: ModMatrixSettings,
+    mod_controllers: ModControllers,
+    last_lfo_value: f32,
+
+    // エフェクト（種類ごとに1つずつ持ち、チェーンの並び順にかける）
+    effects: Vec<Box<dyn Effect>>,
+    effect_chain: ChainState,
+    effect_buffer: Vec<f32>, // エフェクトに渡す左右交互のバッファ
+
+    // マスター出力設定（エフェクトの後にコンプレッサー→マスター音量→リミッターの順にかける）
+    master_settings: MasterSettings,
+    dc_blockers: [DcBlocker; 2],
+    compressor: Compressor,
+
+    // ピッチベンドとボイスの音量の追従
+    bend_ratio: Smoother,
+    gain_smoothing: f32,
+
+    // スライダーで動かすパラメータ（ジッパーノイズを防ぐ）
+    detune: Smoother,
+    cutoff: Smoother,
+    resonance: Smoother,
+    amplitude: Smoother,
+    master_gain: Smoother,
+
+    // XYパッドの位置の追従（割り当てたパラメータをブロックごとに動かす）
+    xy_pad: XyPadFollower,
+}
+
+impl SynthEngine {
+    /// `max_block_frames` はストリームの1回のバッファの最大フレーム数（エフェクトのバッファをこの大きさで確保する）
+    pub fn new(params: SynthParams, sample_rate: f32, max_block_frames: usize) -> Self {
+        // 帯域制限テーブルはオーディオスレッドで初めて読む前に作っておく
+        bandlimited::prepare();
+
+        Self {
+            voice_slots: params.voice.get_voices(),
+            voices: [None; MAX_VOICES],
+            voice_settings: VoiceSettings::default(),
+            players: (0..MAX_VOICES).map(|_| VoicePlayer::new()).collect(),
+            oscillators: (0..MAX_VOICES).map(|i| UnisonOscillator::new(i as u32 + 1)).collect(),
+
+            filters: (0..MAX_VOICES).map(|_| StereoFilter::new()).collect(),
+            filter_settings: FilterSettings::default(),
+
+            shared_player: VoicePlayer::new(),
+            shared_age: 0,
+            shared_held: false,
+            shared_filter: StereoFilter::new(),
+
+            unison_settings: UnisonSettings::default(),
+            osc_settings: OscillatorSettings::default(),
+            osc_b_settings: OscBSettings::default(),
+            sub_osc_settings: SubOscSettings::default(),
+
+            noise_settings: NoiseSettings::default(),
+            noises: (0..MAX_VOICES).map(|i| NoiseGenerator::new(i as u32 + 1)).collect(),
+
+            drift_settings: DriftSettings::default(),
+            drifts: (0..MAX_VOICES).map(|i| Drift::new(i as u32 + 1)).collect(),
+
+            mixer_settings: MixerSettings::default(),
+
+            envelope_params: [EnvelopeParams::default(); 3],
+
+            zone_settings: ZoneSettings::default(),
+
+            lfo: Lfo::new(),
+            lfo_settings: LfoSettings::default(),
+            voice_lfos: (0..MAX_VOICES).map(|i| Lfo::with_seed(0x1234_5678 ^ (i as u32 + 1))).collect(),
+
+            clock: TransportClock::new(),
+
+            mod_settings: ModMatrixSettings::default(),
+            mod_controllers: ModControllers::default(),
+            last_lfo_value: 0.0,
+
+            // 遅延バッファの確保はここで済ませる（チェーンの編集ではアロケーションしない）
+            effects: EffectKind::ALL.iter().map(|&kind| create_effect(kind, sample_rate)).collect(),
+            effect_chain: ChainState::default(),
+            effect_buffer: vec![0.0; max_block_frames.max(1) * 2],
+
+            master_settings: MasterSettings::default(),
+            dc_blockers: [DcBlocker::new(sample_rate), DcBlocker::new(sample_rate)],
+            compressor: Compressor::new(sample_rate),
+
+            // ピッチベンド（目標値に向かって滑らかに追従させる、時定数5ms）
+            bend_ratio: Smoother::new(1.0, 0.005, sample_rate),
+            // ボイスの音量も同じ時定数で追従させる（ボイスを止めたり奪った時のクリックを防ぐ）
+            gain_smoothing: smoothing_coefficient(0.005, sample_rate),
+
+            detune: Smoother::new(0.0, PARAM_SMOOTHING_TIME, sample_rate),
+            cutoff: Smoother::new(FilterSettings::default().cutoff, PARAM_SMOOTHING_TIME, sample_rate),
+            resonance: Smoother::new(0.0, PARAM_SMOOTHING_TIME, sample_rate),
+            amplitude: Smoother::new(1.0, PARAM_SMOOTHING_TIME, sample_rate),
+            master_gain: Smoother::new(1.0, PARAM_SMOOTHING_TIME, sample_rate),
+
+            xy_pad: XyPadFollower::new(sample_rate),
+
+            params,
+            sample_rate,
+        }
+    }
+
+    /// 鳴っているボイスがあるかどうか（エンベロープのリリースが終わると false になる）
+    pub fn is_active(&self) -> bool {
+        self.players.iter().any(|player| player.is_audible())
+    }
+
+    /// インターリーブされた出力バッファを生成する
+    ///
+    /// `channels` は1フレームあたりのチャンネル数（モノラルでは左右を平均し、3チャンネル目以降は無音にする）。
+    /// 作成時に決めた最大フレーム数より大きいバッファは、その大きさずつに分けて生成する。
+    /// 奪われたボイスのフェードアウトが終わるフレームでも区切り、新しいノートを次のバッファまで待たせずに鳴らす
+    pub fn process(&mut self, data: &mut [f32], channels: usize) {
+        let channels = channels.max(1);
+        let block_len = self.effect_buffer.len() / 2 * channels;
+        let mut start = 0;
+        while data.len() - start >= channels {
+            let end = (start + block_len).min(data.len());
+            start += self.process_block(&mut data[start..end], channels) * channels;
+        }
+    }
+
+    /// 最大フレーム数までのバッファを生成し、生成したフレーム数を返す
+    ///
+    /// 奪われたボイスのフェードアウトがバッファの途中で終わる場合は、そこまでだけを生成する
+    fn process_block(&mut self, data: &mut [f32], channels: usize) -> usize {
+        let sample_rate = self.sample_rate;
+        let SynthEngine {
+            params,
+            voice_slots,
+            voices,
+            voice_settings,
+            players,
+            oscillators,
+            filters,
+            filter_settings,
+            shared_player,
+            shared_age,
+            shared_held,
+            shared_filter,
+            unison_settings,
+            osc_settings,
+            osc_b_settings,
+            sub_osc_settings,
+            noise_settings,
+            noises,
+            drift_settings,
+            drifts,
+            mixer_settings,
+            envelope_params,
+            zone_settings,
+            lfo,
+            lfo_settings,
+            voice_lfos,
+            clock,
+            mod_settings,
+            mod_controllers,
+            last_lfo_value,
+            effects,
+            effect_chain,
+            effect_buffer,
+            master_settings,
+            dc_blockers,
+            compressor,
+            bend_ratio,
+            gain_smoothing,
+            detune,
+            cutoff,
+            resonance,
+            amplitude,
+            master_gain,
+            xy_pad,
+            ..
+        } = self;
+        let gain_smoothing = *gain_smoothing;
+
+        // ボイスの割り当てを取得
+        if let Ok(slots) = voice_slots.try_lock() {
+            *voices = *slots;
+        }
+        // 奪われたボイスのフェードアウトがこのバッファの途中で終わるなら、そこまでを生成する
+        // （残りは次の呼び出しで、奪ったノートを鳴らし始めてから生成する）
+        let steal_fade_step = VoicePlayer::steal_fade_step(sample_rate);
+        let frames = data.len() / channels;
+        let frames = players
+            .iter()
+            .zip(voices.iter())
+            .filter_map(|(player, voice)| player.steal_fade_frames(voice.as_ref(), steal_fade_step))
+            .min()
+            .map_or(frames, |fade_frames| fade_frames.clamp(1, frames));
+        let data = &mut data[..frames * channels];
+
+        // XYパッドの位置を割り当てたパラメータに反映する（この後に読む設定に間に合うように先に行う）
+        xy_pad.update(params, frames);
+
+        // ストラムで遅らせたボイスの待ち時間はこのバッファの先頭のフレームから数える
+        let frame = params.strum.frame();
+
+        // 発音モード・エンベロープのパラメータを取得
+        if let Ok(settings) = params.voice.get_settings().try_lock() {
+            *voice_settings = *settings;
+        }
+        if let Ok(settings) = params.envelope.get_settings().try_lock() {
+            *envelope_params = *settings;
+        }
+        if let Ok(settings) = params.zones.get_settings().try_lock() {
+            zone_settings.clone_from(&settings);
+        }
+        // Unison設定は発音開始時の位相を決めるのにも使うのでここで取得する
+        if let Ok(settings) = params.unison.get_settings().try_lock() {
+            unison_settings.clone_from(&settings);
+        }
+        // ノートごとのソース（ポリフォニックアフタータッチ・MPE）はボイスごとの周波数と音量に反映する
+        // （VoicePlayerが目標値に滑らかに追従させる）
+        if let Ok(settings) = params.mod_matrix.get_settings().try_lock() {
+            *mod_settings = *settings;
+        }
+        // ドリフトの量は発音開始時の位相のずれにも使うのでここで取得する
+        if let Ok(settings) = params.drift.get_settings().try_lock() {
+            *drift_settings = *settings;
+        }
+        // LFO設定はノートを弾いた時の位相の合わせ直しにも使うのでここで取得する（ロックできない場合は前回の設定を使う）
+        if let Ok(settings) = params.lfo.get_settings().try_lock() {
+            *lfo_settings = *settings;
+        }
+        let per_voice_lfo = lfo_settings.voice_mode == LfoVoiceMode::PerVoice;
+        // パラフォニックモードでは、押されていなかった所から弾いたノートで共有のエンベロープを始め、全てのノートを離したらリリースする
+        let paraphonic = voice_settings.is_paraphonic();
+        let any_held = voices.iter().flatten().any(|voice| !voice.released);
+        let newest_voice = voices.iter().flatten().max_by_key(|voice| voice.age).copied();
+        if any_held && !*shared_held {
+            *shared_age = newest_voice.map_or(0, |voice| voice.age);
+        }
+        *shared_held = any_held;
+        let shared_voice = newest_voice.filter(|_| paraphonic).map(|voice| Voice {
+            age: *shared_age,
+            released: !any_held,
+            ..voice
+        });
+        let shared_active = shared_player.trigger(shared_voice.as_ref(), envelope_params, 0.0).is_some();
+
+        // ボイスごとのエンベロープをトリガーする（レベルはフレームごとに進める）
+        // エンベロープが終わったボイスは解放されたものとして扱う
+        // パラフォニックモードではボイスごとのエンベロープはゲートにして、共有のエンベロープが終わるまで鳴らす
+        // （他のノートが押されている間に離したノートだけを止める）
+        // 追加のレイヤーのボイスやベロシティで硬い音色を混ぜるボイスは、そのボイスの音色のエンベロープを使う
+        let main_params = if paraphonic { &[PARAPHONIC_GATE; 3] } else { &*envelope_params };
+        let main_patch = LayerPatch {
+            unison: unison_settings.clone(),
+            oscillator: *osc_settings,
+            filter: *filter_settings,
+            envelope: *envelope_params,
+        };
+        let voice_states = players.iter_mut().zip(oscillators.iter_mut()).zip(drifts.iter_mut()).zip(filters.iter_mut());
+        let voice_states = voice_states.zip(voice_lfos.iter_mut()).enumerate();
+        for ((index, ((((player, oscillator), drift), filter), voice_lfo)), voice) in voice_states.zip(voices.iter()) {
+            let voice = match voice {
+                Some(voice) if paraphonic => shared_active.then_some(Voice {
+                    released: voice.released && any_held,
+                    ..*voice
+                }),
+                voice => *voice,
+            };
+            // ストラムで遅らせたボイスはこのバッファの先頭から鳴り始めまでエンベロープで待つ
+            let onset_delay = voice.map_or(0.0, |voice| voice.onset.saturating_sub(frame) as f32 / sample_rate);
+            let patch = voice.and_then(|voice| zone_settings.voice_patch(voice.layer, voice.velocity, &main_patch));
+            let voice_params = match &patch {
+                Some(patch) if !paraphonic => &patch.envelope,
+                _ => main_params,
+            };
+            let voice_unison = patch.as_ref().map_or(&*unison_settings, |patch| &patch.unison);
+            let voice = player.trigger(voice.as_ref(), voice_params, onset_delay).map(|voice| {
+                let per_note = mod_settings.evaluate_per_note(voice.pressure, voice.slide);
+                Voice {
+                    freq: voice.freq * per_note.pitch_ratio() * 2.0f32.powf(voice.bend / 12.0),
+                    gain: voice.gain * per_note.amplitude_gain(),
+                    ..*voice
+                }
+            });
+            if player.update(voice.as_ref(), voice_settings, sample_rate) {
+                oscillator.reset(voice_unison);
+                oscillator.shift_phase(drift.phase_offset(drift_settings));
+                filter.reset();
+                // LFOの位相を合わせ直す（ボイスごとのフリーランでは共有のLFOの位相からボイスごとにずらす）
+                match (lfo_settings.voice_mode, lfo_settings.retrigger) {
+                    (LfoVoiceMode::Global, LfoRetrigger::Key) => lfo.retrigger(lfo_settings.phase_offset),
+                    (LfoVoiceMode::Global, LfoRetrigger::FreeRun) => {}
+                    (LfoVoiceMode::PerVoice, LfoRetrigger::Key) => voice_lfo.retrigger(lfo_settings.phase_offset),
+                    (LfoVoiceMode::PerVoice, LfoRetrigger::FreeRun) => {
+                        voice_lfo.retrigger(lfo.phase() + lfo_settings.phase_offset * index as f32)
+                    }
+                }
+            }
+        }
+
+        // マトリクスのエンベロープには最後に発音したボイスのエンベロープを使う
+        let latest_player = (0..players.len())
+            .filter(|&i| players[i].is_audible())
+            .max_by_key(|&i| players[i].age());
+
+        // 鳴っているボイスの数と押されているノートをメーターに通知する
+        params.meter.report_voices(
+            players.iter().filter(|player| player.is_audible()).count(),
+            voices.iter().flatten().filter(|voice| !voice.released).map(|voice| voice.note),
+        );
+        // ボイスを奪う時に音量の小さいボイスや鳴り終わったボイスを選べるように、エンベロープの値を通知する
+        params.voice.report_levels(
+            players
+                .iter()
+                .map(|player| if player.is_audible() { player.envelope(EnvelopeTarget::Amp) } else { 0.0 }),
+        );
+
+        // エフェクトチェーンを取得（ロックなしで読める）
+        // バイパスしたりチェーンから外したエフェクトは遅延バッファや残響を消す
+        let chain = params.effects.state();
+        for kind in EffectKind::ALL {
+            if effect_chain.is_active(kind) && !chain.is_active(kind) {
+                effects[kind.index()].reset();
+            }
+        }
+        *effect_chain = chain;
+        for kind in chain.active() {
+            effects[kind.index()].update(params);
+        }
+
+        // マスター出力設定を取得
+        if let Ok(settings) = params.master.get_settings().try_lock() {
+            *master_settings = *settings;
+        }
+        master_gain.set_target(master_settings.gain());
+        if let Ok(settings) = params.compressor.get_settings().try_lock() {
+            compressor.set_params(&settings);
+        }
+
+        // トランスポートのクロックとストラムのフレーム数を更新する（無音の間も進める）
+        clock.update(&params.transport, sample_rate, frames);
+        params.strum.advance(frames, sample_rate);
+
+        // エフェクトに渡すバッファ（process で最大フレーム数までに分けてある）
+        let effect_buffer = &mut effect_buffer[..frames * 2];
+
+        // 鳴っているボイスがない場合は無音を出力（エフェクトが有効なら残響だけを鳴らし続ける）
+        if latest_player.is_none() {
+            if chain.has_active() {
+                effect_buffer.iter_mut().for_each(|sample| *sample = 0.0);
+                run_effects(effects, chain, effect_buffer);
+                if write_output(data, channels, effect_buffer, master_settings, master_gain, dc_blockers, compressor) {
+                    params.master.report_clip();
+                }
+            } else {
+                for sample in data.iter_mut() {
+                    *sample = 0.0;
+                }
+                compressor.reset();
+                dc_blockers.iter_mut().for_each(DcBlocker::reset);
+            }
+            let (peak, rms) = measure_levels(data);
+            params.meter.report_levels(peak, rms);
+            params.compressor.report_gain_reduction(compressor.take_peak_reduction());
+            clock.advance(frames, &params.transport);
+            return frames;
+        }
+
+        // オシレータ設定を取得（ロックできない場合は前回の設定を使う）
+        if let Ok(settings) = params.oscillator.get_settings().try_lock() {
+            *osc_settings = *settings;
+        }
+        if let Ok(settings) = params.osc_b.get_settings().try_lock() {
+            osc_b_settings.clone_from(&settings);
+        }
+        if let Ok(settings) = params.sub_osc.get_settings().try_lock() {
+            *sub_osc_settings = *settings;
+        }
+        if let Ok(settings) = params.noise.get_settings().try_lock() {
+            *noise_settings = *settings;
+        }
+        if let Ok(settings) = params.mixer.get_settings().try_lock() {
+            *mixer_settings = *settings;
+        }
+
+        // フィルター設定を取得（ロックできない場合は前回の設定を使う）
+        if let Ok(settings) = params.filter.get_settings().try_lock() {
+            *filter_settings = *settings;
+        }
+
+        // モジュレーションマトリクスを評価する
+        if let Ok(controllers) = params.mod_matrix.get_controllers().try_lock() {
+            *mod_controllers = *controllers;
+        }
+        let latest_voice = voices.iter().flatten().filter(|voice| !voice.released).max_by_key(|voice| voice.age);
+        let mod_offsets = mod_settings.evaluate(&ModSources {
+            lfo: *last_lfo_value,
+            envelope: if paraphonic {
+                shared_player.envelope(EnvelopeTarget::Amp)
+            } else {
+                latest_player.map_or(0.0, |i| players[i].envelope(EnvelopeTarget::Amp))
+            },
+            velocity: latest_voice.map_or(0.0, |voice| voice.velocity as f32 / 127.0),
+            mod_wheel: mod_controllers.mod_wheel,
+            aftertouch: mod_controllers.aftertouch,
+            poly_aftertouch: 0.0,
+            slide: 0.0,
+        });
+        // ボイスで共有するカットオフ・デチューンには鳴っているノートの中で一番大きい値を使う
+        let (max_pressure, max_slide) = voices
+            .iter()
+            .flatten()
+            .filter(|voice| !voice.released)
+            .fold((0.0f32, 0.0f32), |(pressure, slide), voice| (pressure.max(voice.pressure), slide.max(voice.slide)));
+        let per_note_offsets = mod_settings.evaluate_per_note(max_pressure, max_slide);
+        let mod_pitch_ratio = mod_offsets.pitch_ratio();
+        let mod_amplitude = mod_offsets.amplitude_gain();
+
+        // マトリクスの変調をこのブロックのデチューン・カットオフ・音量の目標値に反映する
+        detune.set_target((unison_settings.detune + mod_offsets.detune + per_note_offsets.detune).clamp(0.0, 100.0));
+        cutoff.set_target(
+            (filter_settings.cutoff * mod_offsets.cutoff_ratio() * per_note_offsets.cutoff_ratio()).clamp(20.0, 20000.0),
+        );
+        resonance.set_target(filter_settings.resonance);
+        amplitude.set_target(mod_amplitude);
+        let mut block_unison = unison_settings.clone();
+        let mut block_filter = *filter_settings;
+        // ボイスごとの音色（追加のレイヤーとベロシティで硬い音色を混ぜるボイスだけ、メインの音色のボイスは None）
+        // マトリクスのデチューンとカットオフの変調はメインと同じだけかける
+        let main_patch = LayerPatch {
+            unison: unison_settings.clone(),
+            oscillator: *osc_settings,
+            filter: *filter_settings,
+            envelope: *envelope_params,
+        };
+        let voice_patches: [Option<LayerPatch>; MAX_VOICES] = std::array::from_fn(|index| {
+            let player = &players[index];
+            let mut patch = zone_settings.voice_patch(player.layer(), player.velocity(), &main_patch)?;
+            patch.unison.detune = (patch.unison.detune + mod_offsets.detune + per_note_offsets.detune).clamp(0.0, 100.0);
+            patch.filter.cutoff =
+                (patch.filter.cutoff * mod_offsets.cutoff_ratio() * per_note_offsets.cutoff_ratio()).clamp(20.0, 20000.0);
+            Some(patch)
+        });
+
+        // ピッチベンドの目標値を取得（ロックできない場合は前回の値を使う）
+        if let Some(ratio) = params.pitch_bend.try_ratio() {
+            bend_ratio.set_target(ratio);
+        }
+
+        let lfo_to_filter = lfo_settings.cutoff_ratio(1.0) != 1.0;
+        let env_to_filter = filter_settings.enabled && filter_settings.env_amount != 0.0;
+        let envelope_dt = 1.0 / sample_rate;
+        // サブオシレータとノイズの定位による左右の倍率
+        let (sub_left, sub_right) = mixer_settings.sub_gains();
+        let (noise_left, noise_right) = mixer_settings.noise_gains();
+        // テンポ同期したLFOはテンポから周波数を決め、クロックが進んでいる間は位相をスウィングをかけた再生位置に合わせる
+        let block_lfo = LfoSettings {
+            rate: lfo_settings.rate_at(clock.bpm()),
+            ..*lfo_settings
+        };
+        let lfo_beats = (lfo_settings.sync && clock.is_running()).then(|| lfo_settings.division.beats() as f64);
+
+        // 変調を受けたカットオフ・デチューン・パルス幅の範囲（GUIのモジュレーション表示に送る）
+        let mut cutoff_range = ModRange::empty();
+        let mut detune_range = ModRange::empty();
+        let mut pulse_width_range = ModRange::empty();
+
+        // FMの変調指数だけをボイスごとに書き換えるオシレータBの設定
+        let mut voice_osc_b = osc_b_settings.clone();
+
+        // 各フレームを生成（左右のチャンネルを計算し、エフェクトのバッファに書き込む）
+        for (offset, frame) in effect_buffer.chunks_mut(2).enumerate() {
+            // LFOを進める
+            if let Some(beats) = lfo_beats {
+                lfo.lock_phase((clock.swung_beats_at(offset) / beats).fract() as f32);
+            }
+            let lfo_value = lfo.next(&block_lfo, sample_rate);
+            *last_lfo_value = lfo_value;
+
+            // ピッチベンド・マトリクスによるピッチの倍率（ビブラートはボイスごとにかける）
+            let bend = bend_ratio.next_value() * mod_pitch_ratio;
+            block_unison.detune = detune.next_value();
+            detune_range.add(block_unison.detune);
+
+            // カットオフ・レゾナンスを追従させる
+            // （LFO・キートラッキング・フィルターエンベロープはボイスごとにかける）
+            if filter_settings.enabled {
+                block_filter.cutoff = cutoff.next_value();
+                block_filter.resonance = resonance.next_value();
+            }
+            let frame_filter = block_filter;
+            if paraphonic {
+                shared_player.advance_envelopes(envelope_dt);
+            }
+
+            // 各ボイスのUnison音声・サブオシレータ・ノイズを生成し、ミキサーの音量と定位をかけてからフィルターに通して足し合わせる
+            // （ベロシティとエンベロープによる音量をかける。Unisonのボイスはステレオに広げ、サブオシレータとノイズはミキサーの定位に置く）
+            let mut stereo = [0.0f32; 2];
+            let voice_states = players.iter_mut().zip(oscillators.iter_mut()).zip(noises.iter_mut()).zip(drifts.iter_mut());
+            let voice_states = voice_states.zip(filters.iter_mut()).zip(voice_lfos.iter_mut()).zip(voice_patches.iter());
+            for ((((((player, oscillator), noise), drift), filter), voice_lfo), voice_patch) in voice_states {
+                if !player.is_audible() {
+                    continue;
+                }
+                // ボイスごとのLFOを進める（共有のモードでは共有のLFOの値を使う）
+                let voice_lfo_value = if per_voice_lfo {
+                    if let Some(beats) = lfo_beats {
+                        voice_lfo.lock_phase((clock.swung_beats_at(offset) / beats).fract() as f32);
+                    }
+                    voice_lfo.next(&block_lfo, sample_rate)
+                } else {
+                    lfo_value
+                };
+                // エンベロープはサンプルごとに進める（短いアタックやリリースもバッファサイズに関係なく正確な長さになる）
+                player.advance_envelopes(envelope_dt);
+                // ドリフトでボイスごとにピッチと音量を少しずつ揺らす
+                let (drift_ratio, drift_gain) = drift.next(drift_settings, sample_rate);
+                let mut gain = player.next_gain(gain_smoothing, steal_fade_step) * player.envelope(EnvelopeTarget::Amp) * drift_gain;
+                if per_voice_lfo {
+                    gain *= lfo_settings.amplitude_gain(voice_lfo_value);
+                }
+                // FMの変調指数にボイス（パラフォニックモードでは共有）のモジュレーターエンベロープをかける
+                let envelopes = if paraphonic { &*shared_player } else { &*player };
+                voice_osc_b.fm_index = osc_b_settings.fm_index * envelopes.envelope(EnvelopeTarget::Modulator);
+                // グライド・ビブラート・ピッチベンド・ドリフトを含めた周波数で位相を進める
+                let pitch_ratio = lfo_settings.pitch_ratio(voice_lfo_value, mod_controllers.mod_wheel) * bend;
+                // 追加のレイヤーやベロシティで硬い音色を混ぜるボイスは、そのボイスの音色のUnison・オシレータ・フィルターで鳴らす
+                let layer_patch = voice_patch.as_ref();
+                let voice_unison = layer_patch.map_or(&block_unison, |patch| &patch.unison);
+                let voice_filter_settings = layer_patch.map_or(&*filter_settings, |patch| &patch.filter);
+                let mut block_osc = layer_patch.map_or(*osc_settings, |patch| patch.oscillator);
+                block_osc.pulse_width = lfo_settings.pulse_width(voice_lfo_value, block_osc.pulse_width);
+                pulse_width_range.add(block_osc.pulse_width);
+                let freq = player.freq() * pitch_ratio * drift_ratio;
+                let (mut left, mut right) = oscillator.next(freq, voice_unison, sample_rate, &block_osc, &voice_osc_b, mixer_settings);
+                if sub_osc_settings.level > 0.0 {
+                    let sub = oscillator.next_sub(freq, sub_osc_settings, sample_rate, &block_osc);
+                    left += sub * sub_left;
+                    right += sub * sub_right;
+                }
+                if noise_settings.level > 0.0 {
+                    let noise = noise.next(noise_settings.noise_type) * noise_settings.level;
+                    left += noise * noise_left;
+                    right += noise * noise_right;
+                }
+
+                // ボイスのフィルター（カットオフが変わった時だけ係数を計算し直す、パラフォニックモードでは足し合わせてからかける）
+                if voice_filter_settings.enabled && !paraphonic {
+                    let mut voice_filter = if layer_patch.is_some() { *voice_filter_settings } else { frame_filter };
+                    if lfo_to_filter {
+                        voice_filter.cutoff *= lfo_settings.cutoff_ratio(voice_lfo_value);
+                    }
+                    voice_filter.cutoff *= voice_filter_settings.key_track_ratio(player.note());
+                    if voice_filter_settings.env_amount != 0.0 {
+                        voice_filter.cutoff *= voice_filter_settings.env_cutoff_ratio(player.envelope(EnvelopeTarget::Filter));
+                    }
+                    cutoff_range.add(voice_filter.cutoff);
+                    filter.set_params(&voice_filter, sample_rate);
+                    (left, right) = filter.process(left, right);
+                }
+
+                stereo[0] += left * gain;
+                stereo[1] += right * gain;
+                // グライドを進める
+                player.advance();
+            }
+
+            // パラフォニックモードでは足し合わせた音に共有のフィルターと音量エンベロープをかける
+            // （キートラッキングは最後に弾いたノートに合わせる）
+            if paraphonic {
+                if filter_settings.enabled {
+                    let mut shared = frame_filter;
+                    if lfo_to_filter {
+                        shared.cutoff *= lfo_settings.cutoff_ratio(lfo_value);
+                    }
+                    shared.cutoff *= filter_settings.key_track_ratio(shared_player.note());
+                    if env_to_filter {
+                        shared.cutoff *= filter_settings.env_cutoff_ratio(shared_player.envelope(EnvelopeTarget::Filter));
+                    }
+                    cutoff_range.add(shared.cutoff);
+                    shared_filter.set_params(&shared, sample_rate);
+                    (stereo[0], stereo[1]) = shared_filter.process(stereo[0], stereo[1]);
+                }
+                let shared_amp = shared_player.envelope(EnvelopeTarget::Amp);
+                stereo.iter_mut().for_each(|value| *value *= shared_amp);
+            }
+
+            // トレモロ・マトリクスによる音量（エンベロープとボイスごとのLFOのトレモロはボイスごとにかけている）
+            let tremolo = if per_voice_lfo { 1.0 } else { lfo_settings.amplitude_gain(lfo_value) };
+            let amp = tremolo * amplitude.next_value();
+
+            for (value, out) in stereo.iter().zip(frame.iter_mut()) {
+                *out = *value * amp;
+            }
+        }
+
+        params.mod_monitor.report(MonitoredParam::FilterCutoff, &cutoff_range);
+        params.mod_monitor.report(MonitoredParam::UnisonDetune, &detune_range);
+        params.mod_monitor.report(MonitoredParam::PulseWidth, &pulse_width_range);
+
+        // エフェクトをチェーンの順にかけてから出力する
+        run_effects(effects, chain, effect_buffer);
+        if write_output(data, channels, effect_buffer, master_settings, master_gain, dc_blockers, compressor) {
+            params.master.report_clip();
+        }
+        let (peak, rms) = measure_levels(data);
+        params.meter.report_levels(peak, rms);
+        params.compressor.report_gain_reduction(compressor.take_peak_reduction());
+        clock.advance(frames, &params.transport);
+        frames
+    }
+}
+
+/// バイパスされていないエフェクトをチェーンの順にかける
+fn run_effects(effects: &mut [Box<dyn Effect>], chain: ChainState, buffer: &mut [f32]) {
+    for kind in chain.active() {
+        effects[kind.index()].process(buffer);
+    }
+}
+
+/// 左右交互のバッファにDCブロッカー・コンプレッサー・マスターパン・マスター音量とリミッターをかけて出力に書き込む
+///
+/// リミッター前に1.0を超えたら true を返す（クリップとして通知する）
+fn write_output(
+    data: &mut [f32],
+    channels: usize,
+    stereo: &[f32],
+    master_settings: &MasterSettings,
+    master_gain: &mut Smoother,
+    dc_blockers: &mut [DcBlocker; 2],
+    compressor: &mut Compressor,
+) -> bool {
+    let (pan_left, pan_right) = master_settings.balance_gains();
+    let mut clipped = false;
+    for (frame, input) in data.chunks_mut(channels).zip(stereo.chunks(2)) {
+        let gain = master_gain.next_value();
+        let (left, right) = compressor.process(dc_blockers[0].process(input[0]), dc_blockers[1].process(input[1]));
+        let left = left * pan_left;
+        let right = right * pan_right;
+        if (left * gain).abs() > 1.0 || (right * gain).abs() > 1.0 {
+            clipped = true;
+        }
+        write_frame(frame, master_settings.process(left, gain), master_settings.process(right, gain));
+    }
+    clipped
+}
+
+/// 出力バッファのピークとRMS（全チャンネルをまとめて測る）
+fn measure_levels(data: &[f32]) -> (f32, f32) {
+    if data.is_empty() {
+        return (0.0, 0.0);
+    }
+    let (peak, sum) = data
+        .iter()
+        .fold((0.0f32, 0.0f32), |(peak, sum), &x| (peak.max(x.abs()), sum + x * x));
+    (peak, (sum / data.len() as f32).sqrt())
+}
+
+/// 左右の値を出力のチャンネル数に合わせて1フレームに書き込む
+///
+/// モノラル出力では左右を平均し、3チャンネル目以降は無音にする
+fn write_frame(frame: &mut [f32], left: f32, right: f32) {
+    match frame {
+        [mono] => *mono = (left + right) * 0.5,
+        [l, r, rest @ ..] => {
+            *l = left;
+            *r = right;
+            rest.iter_mut().for_each(|sample| *sample = 0.0);
+        }
+        [] => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::NoteHandler;
+
+    const CHANNELS: usize = 2;
+    const FRAMES: usize = 1024;
+
+    fn render(engine: &mut SynthEngine) -> Vec<f32> {
+        let mut data = vec![0.0; FRAMES * CHANNELS];
+        engine.process(&mut data, CHANNELS);
+        data
+    }
+
+    /// ボイスを奪ったノートは、前のノートのフェードアウトが終わったフレームから同じバッファの中で鳴り始める
+    #[test]
+    fn starts_stealing_note_when_fade_ends() {
+        let params = SynthParams::new();
+        params.voice.set_polyphony(1);
+        let notes = NoteHandler::new(Arc::new(Mutex::new(0.0)), params.clone());
+        let mut engine = SynthEngine::new(params, 48000.0, FRAMES);
+        notes.note_on(60, 100);
+        render(&mut engine);
+        render(&mut engine);
+
+        notes.note_on(72, 100);
+        let data = render(&mut engine);
+        let fade_frames = (1.0 / VoicePlayer::steal_fade_step(48000.0)).ceil() as usize;
+        let peak = data[fade_frames * 2 * CHANNELS..].iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!(peak > 0.1);
+    }
+}
@@ -0,0 +1,363 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::denormal::flush_denormal;
+
+/// エンベロープの段階を表す列挙型
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EnvelopeState {
+    Idle,    // 停止中
+    Delay,   // ディレイ（アタックを始めるまで待つ）
+    Attack,  // アタック
+    Hold,    // ホールド（最大レベルを保つ）
+    Decay,   // ディケイ
+    Sustain, // サステイン
+    Release, // リリース
+}
+
+/// DAHDSRエンベロープのパラメータ（ディレイとホールドが0ならADSRと同じ）
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnvelopeParams {
+    /// ディレイ時間（秒、ノートオンからアタックを始めるまで）
+    pub delay: f32,
+    /// アタック時間（秒）
+    pub attack: f32,
+    /// ホールド時間（秒、アタックの後に最大レベルを保つ）
+    pub hold: f32,
+    /// ディケイ時間（秒）
+    pub decay: f32,
+    /// サステインレベル（0.0-1.0）
+    pub sustain: f32,
+    /// リリース時間（秒）
+    pub release: f32,
+    /// アタックの曲線（-1.0-1.0、0で smoothstep、正で立ち上がりが速く、負でゆっくり）
+    pub attack_curve: f32,
+    /// ディケイの曲線（-1.0-1.0、正で最初に速く下がる）
+    pub decay_curve: f32,
+    /// リリースの曲線（-1.0-1.0、正で最初に速く下がる）
+    pub release_curve: f32,
+    /// ループモード（ノートを押している間はアタック→ホールド→ディケイを繰り返す）
+    pub looping: bool,
+}
+
+impl Default for EnvelopeParams {
+    fn default() -> Self {
+        Self {
+            delay: 0.0,
+            attack: 0.01,
+            hold: 0.0,
+            decay: 0.1,
+            sustain: 0.8,
+            release: 0.2,
+            attack_curve: 0.0,
+            decay_curve: 0.0,
+            release_curve: 0.0,
+            looping: false,
+        }
+    }
+}
+
+/// DAHDSRエンベロープ（ループモードではディケイの後にアタックへ戻る）
+pub struct Envelope {
+    pub params: EnvelopeParams,
+    state: EnvelopeState,
+    level: f32,       // 現在のレベル（0.0-1.0）
+    stage_time: f32,  // 現在の段階に入ってからの経過時間（秒）
+    start_level: f32, // 現在の段階に入った時のレベル
+    attack_scale: f32, // アタック時間の倍率（ベロシティで変わる）
+    release_scale: f32, // リリース時間の倍率（ノートオフのベロシティで変わる）
+    onset_delay: f32, // ディレイの前に鳴り始めを待つ時間（秒、ストラムで変わる）
+}
+
+impl Envelope {
+    pub fn new(params: EnvelopeParams) -> Self {
+        Self {
+            params,
+            state: EnvelopeState::Idle,
+            level: 0.0,
+            stage_time: 0.0,
+            start_level: 0.0,
+            attack_scale: 1.0,
+            release_scale: 1.0,
+            onset_delay: 0.0,
+        }
+    }
+
+    /// ノートオンでアタックを開始する（現在のレベルから立ち上げてクリックを防ぐ）
+    pub fn start(&mut self) {
+        self.start_scaled(1.0);
+    }
+
+    /// アタック時間に倍率をかけてアタックを開始する
+    pub fn start_scaled(&mut self, attack_scale: f32) {
+        self.start_delayed(attack_scale, 0.0);
+    }
+
+    /// 鳴り始めを `onset_delay` 秒待ってから、アタック時間に倍率をかけてアタックを開始する
+    pub fn start_delayed(&mut self, attack_scale: f32, onset_delay: f32) {
+        self.attack_scale = attack_scale.max(0.0);
+        self.onset_delay = onset_delay.max(0.0);
+        // ディレイがなければすぐにアタックを始める（1回分の更新を待たない）
+        if self.params.delay + self.onset_delay > 0.0 {
+            self.enter(EnvelopeState::Delay);
+        } else {
+            self.enter(EnvelopeState::Attack);
+        }
+    }
+
+    /// 鳴り始めを待っている間なら、今から `onset_delay` 秒後に鳴り始めるように待ち時間を変える
+    pub fn delay_onset(&mut self, onset_delay: f32) {
+        if self.state == EnvelopeState::Delay && self.stage_time < self.onset_delay {
+            self.onset_delay = self.stage_time + onset_delay.max(0.0);
+        }
+    }
+
+    /// ノートオフでリリースを開始する
+    pub fn end(&mut self) {
+        self.end_scaled(1.0);
+    }
+
+    /// リリース時間に倍率をかけてリリースを開始する
+    pub fn end_scaled(&mut self, release_scale: f32) {
+        self.release_scale = release_scale.max(0.0);
+        if self.state != EnvelopeState::Idle {
+            self.enter(EnvelopeState::Release);
+        }
+    }
+
+    /// リリースを待たずに即座に停止する（パニック）
+    pub fn kill(&mut self) {
+        self.level = 0.0;
+        self.enter(EnvelopeState::Idle);
+    }
+
+    pub fn state(&self) -> EnvelopeState {
+        self.state
+    }
+
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.state != EnvelopeState::Idle
+    }
+
+    /// 時間を dt 秒進めて、新しいレベルを返す
+    pub fn update(&mut self, dt: f32) -> f32 {
+        self.stage_time += dt;
+
+        match self.state {
+            EnvelopeState::Idle => {
+                self.level = 0.0;
+            }
+            EnvelopeState::Delay => {
+                // 再トリガーの場合は前のレベルのまま待つ（クリックを防ぐ）
+                if stage_progress(self.stage_time, self.params.delay + self.onset_delay) >= 1.0 {
+                    self.enter(EnvelopeState::Attack);
+                }
+            }
+            EnvelopeState::Attack => {
+                let progress = stage_progress(self.stage_time, self.params.attack * self.attack_scale);
+                self.level = self.start_level + (1.0 - self.start_level) * shape_curve(progress, self.params.attack_curve);
+                if progress >= 1.0 {
+                    if self.params.hold > 0.0 {
+                        self.enter(EnvelopeState::Hold);
+                    } else {
+                        self.enter(EnvelopeState::Decay);
+                    }
+                }
+            }
+            EnvelopeState::Hold => {
+                self.level = 1.0;
+                if stage_progress(self.stage_time, self.params.hold) >= 1.0 {
+                    self.enter(EnvelopeState::Decay);
+                }
+            }
+            EnvelopeState::Decay => {
+                let sustain = self.params.sustain.clamp(0.0, 1.0);
+                let progress = stage_progress(self.stage_time, self.params.decay);
+                self.level = self.start_level + (sustain - self.start_level) * shape_curve(progress, self.params.decay_curve);
+                if progress >= 1.0 {
+                    // ループモードではサステインに留まらずにアタックへ戻る
+                    if self.params.looping {
+                        self.enter(EnvelopeState::Attack);
+                    } else {
+                        self.enter(EnvelopeState::Sustain);
+                    }
+                }
+            }
+            EnvelopeState::Sustain => {
+                self.level = self.params.sustain.clamp(0.0, 1.0);
+                // サステイン中にループモードにした場合もループを始める
+                if self.params.looping {
+                    self.enter(EnvelopeState::Attack);
+                }
+            }
+            EnvelopeState::Release => {
+                let progress = stage_progress(self.stage_time, self.params.release * self.release_scale);
+                self.level = flush_denormal(self.start_level * (1.0 - shape_curve(progress, self.params.release_curve)));
+                if progress >= 1.0 {
+                    self.level = 0.0;
+                    self.enter(EnvelopeState::Idle);
+                }
+            }
+        }
+
+        self.level
+    }
+
+    /// 次の段階に移る
+    fn enter(&mut self, state: EnvelopeState) {
+        self.state = state;
+        self.stage_time = 0.0;
+        self.start_level = self.level;
+    }
+}
+
+/// 段階の進み具合（0.0-1.0）を計算する
+fn stage_progress(time: f32, duration: f32) -> f32 {
+    if duration <= 0.0 {
+        1.0
+    } else {
+        (time / duration).min(1.0)
+    }
+}
+
+/// 滑らかなカーブ（smoothstep）
+fn smoothstep(x: f32) -> f32 {
+    let x = x.clamp(0.0, 1.0);
+    x * x * (3.0 - 2.0 * x)
+}
+
+/// 段階の進み具合（0.0-1.0）を曲線の設定に従って変形する（エンベロープエディターの描画にも使う）
+///
+/// `curve` が0なら smoothstep、正なら指数的に最初に大きく動く曲線、負なら最後に大きく動く曲線に近づける
+pub fn shape_curve(progress: f32, curve: f32) -> f32 {
+    let x = progress.clamp(0.0, 1.0);
+    let curve = curve.clamp(-1.0, 1.0);
+    let smooth = smoothstep(x);
+    let skewed = if curve >= 0.0 {
+        1.0 - (1.0 - x).powi(4)
+    } else {
+        x.powi(4)
+    };
+    smooth + (skewed - smooth) * curve.abs()
+}
+
+/// エンベロープの行き先（エンベロープごとに別のパラメータを持つ）
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EnvelopeTarget {
+    Amp,    // 音量
+    Filter,    // フィルターのカットオフ
+    Modulator, // FMのモジュレーター（変調指数）
+}
+
+impl EnvelopeTarget {
+    /// 全ての行き先（パラメータの並び順）
+    pub const ALL: [EnvelopeTarget; 3] = [EnvelopeTarget::Amp, EnvelopeTarget::Filter, EnvelopeTarget::Modulator];
+
+    pub fn index(self) -> usize {
+        match self {
+            EnvelopeTarget::Amp => 0,
+            EnvelopeTarget::Filter => 1,
+            EnvelopeTarget::Modulator => 2,
+        }
+    }
+}
+
+/// エンベロープのパラメータを管理する構造体（MIDI・GUI・オーディオスレッドで共有）
+///
+/// 行き先ごとにパラメータを持つ。エンベロープの状態はボイスごとにオーディオスレッドが持ち、
+/// ボイスの割り当て（ノートオン/オフ）に合わせて動かす
+pub struct EnvelopeManager {
+    settings: Arc<Mutex<[EnvelopeParams; 3]>>,
+}
+
+impl EnvelopeManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new([EnvelopeParams::default(); 3])),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<[EnvelopeParams; 3]>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn get_params(&self, target: EnvelopeTarget) -> EnvelopeParams {
+        if let Ok(settings) = self.settings.lock() {
+            settings[target.index()]
+        } else {
+            EnvelopeParams::default()
+        }
+    }
+
+    pub fn set_params(&self, target: EnvelopeTarget, params: EnvelopeParams) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings[target.index()] = params;
+        }
+    }
+
+    pub fn set_delay(&self, target: EnvelopeTarget, delay: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings[target.index()].delay = delay.clamp(0.0, 5.0);
+        }
+    }
+
+    pub fn set_hold(&self, target: EnvelopeTarget, hold: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings[target.index()].hold = hold.clamp(0.0, 5.0);
+        }
+    }
+
+    pub fn set_looping(&self, target: EnvelopeTarget, looping: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings[target.index()].looping = looping;
+        }
+    }
+
+    pub fn set_attack(&self, target: EnvelopeTarget, attack: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings[target.index()].attack = attack.clamp(0.0, 5.0);
+        }
+    }
+
+    pub fn set_decay(&self, target: EnvelopeTarget, decay: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings[target.index()].decay = decay.clamp(0.0, 5.0);
+        }
+    }
+
+    pub fn set_sustain(&self, target: EnvelopeTarget, sustain: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings[target.index()].sustain = sustain.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_release(&self, target: EnvelopeTarget, release: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings[target.index()].release = release.clamp(0.0, 10.0);
+        }
+    }
+
+    pub fn set_attack_curve(&self, target: EnvelopeTarget, curve: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings[target.index()].attack_curve = curve.clamp(-1.0, 1.0);
+        }
+    }
+
+    pub fn set_decay_curve(&self, target: EnvelopeTarget, curve: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings[target.index()].decay_curve = curve.clamp(-1.0, 1.0);
+        }
+    }
+
+    pub fn set_release_curve(&self, target: EnvelopeTarget, curve: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings[target.index()].release_curve = curve.clamp(-1.0, 1.0);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+/// ユークリッドリズムの最大ステップ数
+pub const MAX_EUCLID_STEPS: u8 = 32;
+
+/// ユークリッドリズム（ステップ数の中に発音するステップをできるだけ均等に散らしたリズム）
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EuclideanRhythm {
+    /// リズムで発音するステップを選ぶか（false なら全てのステップで発音する）
+    pub enabled: bool,
+    /// 1周のステップ数（1-32）
+    pub steps: u8,
+    /// 1周で発音するステップ数（0-ステップ数）
+    pub pulses: u8,
+    /// 先頭をずらすステップ数（0-ステップ数-1）
+    pub rotation: u8,
+}
+
+impl Default for EuclideanRhythm {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            steps: 8,
+            pulses: 3,
+            rotation: 0,
+        }
+    }
+}
+
+impl EuclideanRhythm {
+    /// 範囲内に収めた設定
+    pub fn clamped(self) -> Self {
+        let steps = self.steps.clamp(1, MAX_EUCLID_STEPS);
+        Self {
+            enabled: self.enabled,
+            steps,
+            pulses: self.pulses.min(steps),
+            rotation: self.rotation % steps,
+        }
+    }
+
+    /// ステップで発音するかどうか（無効なら常に発音する）
+    ///
+    /// ステップごとにパルス数を足していき、ステップ数を越えて繰り上がる所で発音する（ブレゼンハムの方法）
+    pub fn is_pulse(&self, step: usize) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        let rhythm = self.clamped();
+        let steps = rhythm.steps as usize;
+        let position = (step + rhythm.rotation as usize) % steps;
+        position * rhythm.pulses as usize % steps < rhythm.pulses as usize
+    }
+
+    /// 1周分の発音するかどうかの並び（表示用）
+    pub fn pattern(&self) -> Vec<bool> {
+        let rhythm = Self {
+            enabled: true,
+            ..self.clamped()
+        };
+        (0..rhythm.steps as usize).map(|step| rhythm.is_pulse(step)).collect()
+    }
+}
@@ -0,0 +1,240 @@
+use std::f32::consts::{PI, SQRT_2};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::denormal::flush_denormal;
+
+/// エンベロープ量±1.0の時のカットオフの変調幅（オクターブ）
+pub const MAX_ENV_OCTAVES: f32 = 6.0;
+/// キートラッキングの最大量（2.0で200%、1オクターブ上のノートでカットオフが2オクターブ上がる）
+pub const MAX_KEY_TRACK: f32 = 2.0;
+/// キートラッキングでカットオフが設定値のままになるノート（C4）
+const KEY_TRACK_CENTER: f32 = 60.0;
+
+/// フィルターの種類を表す列挙型
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum FilterMode {
+    LowPass,  // ローパス
+    HighPass, // ハイパス
+    BandPass, // バンドパス
+    Notch,    // ノッチ
+}
+
+impl Default for FilterMode {
+    fn default() -> Self {
+        Self::LowPass
+    }
+}
+
+/// フィルターの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterSettings {
+    /// フィルターを有効にするかどうか
+    pub enabled: bool,
+    /// フィルターの種類
+    pub mode: FilterMode,
+    /// カットオフ周波数（20Hz-20000Hz）
+    pub cutoff: f32,
+    /// レゾナンス（0.0-1.0）
+    pub resonance: f32,
+    /// フィルターエンベロープの量（-1.0〜1.0、負の値でカットオフを下げる）
+    pub env_amount: f32,
+    /// キートラッキングの量（0.0〜2.0、1.0でカットオフがノートの音程と同じだけ動く）
+    pub key_track: f32,
+}
+
+impl Default for FilterSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: FilterMode::LowPass,
+            cutoff: 20000.0,
+            resonance: 0.0,
+            env_amount: 0.0,
+            key_track: 0.0,
+        }
+    }
+}
+
+impl FilterSettings {
+    /// フィルターエンベロープのレベル（0.0-1.0）からカットオフの倍率を計算する
+    pub fn env_cutoff_ratio(&self, level: f32) -> f32 {
+        2.0f32.powf(self.env_amount.clamp(-1.0, 1.0) * level * MAX_ENV_OCTAVES)
+    }
+
+    /// ボイスのノート番号からキートラッキングによるカットオフの倍率を計算する（C4で1.0）
+    pub fn key_track_ratio(&self, note: u8) -> f32 {
+        if self.key_track == 0.0 {
+            return 1.0;
+        }
+        2.0f32.powf((note as f32 - KEY_TRACK_CENTER) / 12.0 * self.key_track.clamp(0.0, MAX_KEY_TRACK))
+    }
+}
+
+/// ステートバリアブルフィルター（TPT方式、カットオフを動かしても安定）
+pub struct StateVariableFilter {
+    // 積分器の状態
+    ic1eq: f32,
+    ic2eq: f32,
+    // 係数（set_params で更新）
+    k: f32,
+    a1: f32,
+    a2: f32,
+    a3: f32,
+    mode: FilterMode,
+}
+
+impl StateVariableFilter {
+    pub fn new() -> Self {
+        let mut filter = Self {
+            ic1eq: 0.0,
+            ic2eq: 0.0,
+            k: SQRT_2,
+            a1: 0.0,
+            a2: 0.0,
+            a3: 0.0,
+            mode: FilterMode::LowPass,
+        };
+        filter.set_params(&FilterSettings::default(), 44100.0);
+        filter
+    }
+
+    /// 内部状態をクリアする
+    pub fn reset(&mut self) {
+        self.ic1eq = 0.0;
+        self.ic2eq = 0.0;
+    }
+
+    /// 設定から係数を計算する（バッファごとに1回呼ぶ）
+    pub fn set_params(&mut self, settings: &FilterSettings, sample_rate: f32) {
+        // カットオフはナイキスト周波数より少し下までに制限
+        let cutoff = settings.cutoff.clamp(20.0, sample_rate * 0.49);
+        let g = (PI * cutoff / sample_rate).tan();
+        // レゾナンス0でバターワース（Q=0.707）、1で自己発振の手前まで
+        self.k = SQRT_2 * (1.0 - settings.resonance.clamp(0.0, 1.0) * 0.97);
+        self.a1 = 1.0 / (1.0 + g * (g + self.k));
+        self.a2 = g * self.a1;
+        self.a3 = g * self.a2;
+        self.mode = settings.mode;
+    }
+
+    /// 1サンプルを処理する
+    pub fn process(&mut self, input: f32) -> f32 {
+        let v3 = input - self.ic2eq;
+        let v1 = self.a1 * self.ic1eq + self.a2 * v3;
+        let v2 = self.ic2eq + self.a2 * self.ic1eq + self.a3 * v3;
+        self.ic1eq = flush_denormal(2.0 * v1 - self.ic1eq);
+        self.ic2eq = flush_denormal(2.0 * v2 - self.ic2eq);
+
+        let low = v2;
+        let band = v1;
+        let high = input - self.k * v1 - v2;
+
+        match self.mode {
+            FilterMode::LowPass => low,
+            FilterMode::HighPass => high,
+            FilterMode::BandPass => band,
+            FilterMode::Notch => low + high,
+        }
+    }
+}
+
+/// 左右2チャンネルのフィルター（ボイスごとに持つ）
+///
+/// カットオフ・レゾナンス・種類が変わった時だけ係数を計算し直す
+pub struct StereoFilter {
+    filters: [StateVariableFilter; 2],
+    cutoff: f32,
+    resonance: f32,
+    mode: FilterMode,
+}
+
+impl StereoFilter {
+    pub fn new() -> Self {
+        Self {
+            filters: [StateVariableFilter::new(), StateVariableFilter::new()],
+            // 最初の set_params で必ず係数を計算する
+            cutoff: -1.0,
+            resonance: -1.0,
+            mode: FilterMode::LowPass,
+        }
+    }
+
+    /// 内部状態をクリアする（ボイスが無音から鳴り始めた時に呼ぶ）
+    pub fn reset(&mut self) {
+        self.filters.iter_mut().for_each(StateVariableFilter::reset);
+    }
+
+    /// 設定から係数を計算する（前回と同じ設定なら何もしない）
+    pub fn set_params(&mut self, settings: &FilterSettings, sample_rate: f32) {
+        if settings.cutoff == self.cutoff && settings.resonance == self.resonance && settings.mode == self.mode {
+            return;
+        }
+        self.cutoff = settings.cutoff;
+        self.resonance = settings.resonance;
+        self.mode = settings.mode;
+        for filter in self.filters.iter_mut() {
+            filter.set_params(settings, sample_rate);
+        }
+    }
+
+    /// 左右1サンプルずつ処理する
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        (self.filters[0].process(left), self.filters[1].process(right))
+    }
+}
+
+/// フィルターの設定を管理する構造体
+pub struct FilterManager {
+    settings: Arc<Mutex<FilterSettings>>,
+}
+
+impl FilterManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(FilterSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<FilterSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.enabled = enabled;
+        }
+    }
+
+    pub fn set_mode(&self, mode: FilterMode) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.mode = mode;
+        }
+    }
+
+    pub fn set_cutoff(&self, cutoff: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.cutoff = cutoff.clamp(20.0, 20000.0);
+        }
+    }
+
+    pub fn set_resonance(&self, resonance: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.resonance = resonance.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_env_amount(&self, env_amount: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.env_amount = env_amount.clamp(-1.0, 1.0);
+        }
+    }
+
+    pub fn set_key_track(&self, key_track: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.key_track = key_track.clamp(0.0, MAX_KEY_TRACK);
+        }
+    }
+}
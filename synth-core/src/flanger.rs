@@ -0,0 +1,128 @@
+use std::f32::consts::TAU;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::denormal::flush_denormal;
+
+/// ディレイの最短時間（秒）
+const MIN_DELAY: f32 = 0.0005;
+
+/// 深さ1.0の時のスイープ幅（秒、最短時間から上に動かす）
+const MAX_SWEEP: f32 = 0.006;
+
+/// フランジャーの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlangerSettings {
+    /// 変調の速さ（Hz、0.05-5.0）
+    pub rate: f32,
+    /// 変調の深さ（0.0-1.0）
+    pub depth: f32,
+    /// フィードバック（-0.95〜0.95、大きいほど金属的な響きになる）
+    pub feedback: f32,
+    /// ウェットの割合（0.0-1.0）
+    pub mix: f32,
+}
+
+impl Default for FlangerSettings {
+    fn default() -> Self {
+        Self {
+            rate: 0.25,
+            depth: 0.7,
+            feedback: 0.6,
+            mix: 0.5,
+        }
+    }
+}
+
+/// 短い変調ディレイにフィードバックをかけるフランジャー（オーディオスレッドが所有する）
+pub struct Flanger {
+    buffer: Vec<f32>,
+    write_index: usize,
+    phase: f32, // 変調LFOの位相（0.0-1.0）
+    sample_rate: f32,
+}
+
+impl Flanger {
+    /// サンプルレートに合わせて遅延バッファを確保する
+    ///
+    /// `phase_offset` は変調LFOの初期位相（0.0-1.0、左右で変えると広がりが出る）
+    pub fn new(sample_rate: f32, phase_offset: f32) -> Self {
+        let size = ((MIN_DELAY + MAX_SWEEP) * sample_rate) as usize + 2;
+        Self {
+            buffer: vec![0.0; size],
+            write_index: 0,
+            phase: phase_offset.fract(),
+            sample_rate,
+        }
+    }
+
+    /// 遅延バッファをクリアする
+    pub fn reset(&mut self) {
+        self.buffer.iter_mut().for_each(|x| *x = 0.0);
+    }
+
+    /// 1サンプル処理する
+    pub fn process(&mut self, input: f32, settings: &FlangerSettings) -> f32 {
+        let len = self.buffer.len();
+
+        // 遅延時間を最短時間から上へ揺らし、線形補間で読む
+        let lfo = 0.5 + 0.5 * (TAU * self.phase).sin();
+        let delay = (MIN_DELAY + settings.depth.clamp(0.0, 1.0) * MAX_SWEEP * lfo) * self.sample_rate;
+        let read = self.write_index as f32 - delay + len as f32;
+        let index = read.floor() as usize;
+        let frac = read - read.floor();
+        let a = self.buffer[index % len];
+        let b = self.buffer[(index + 1) % len];
+        let wet = a + (b - a) * frac;
+
+        self.buffer[self.write_index] = flush_denormal(input + wet * settings.feedback.clamp(-0.95, 0.95));
+        self.write_index = (self.write_index + 1) % len;
+        self.phase = (self.phase + settings.rate.max(0.0) / self.sample_rate).fract();
+
+        let mix = settings.mix.clamp(0.0, 1.0);
+        input * (1.0 - mix) + wet * mix
+    }
+}
+
+/// フランジャーの設定を管理する構造体
+pub struct FlangerManager {
+    settings: Arc<Mutex<FlangerSettings>>,
+}
+
+impl FlangerManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(FlangerSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<FlangerSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_rate(&self, rate: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.rate = rate.clamp(0.05, 5.0);
+        }
+    }
+
+    pub fn set_depth(&self, depth: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.depth = depth.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_feedback(&self, feedback: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.feedback = feedback.clamp(-0.95, 0.95);
+        }
+    }
+
+    pub fn set_mix(&self, mix: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.mix = mix.clamp(0.0, 1.0);
+        }
+    }
+}
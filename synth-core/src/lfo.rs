@@ -0,0 +1,366 @@
+use std::f32::consts::PI;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::oscillator::{MAX_PULSE_WIDTH, MIN_PULSE_WIDTH};
+use crate::transport::NoteDivision;
+
+/// ビブラートの最大深さ（半音）
+pub const MAX_PITCH_DEPTH_SEMITONES: f32 = 2.0;
+/// フィルターモジュレーションの最大深さ（オクターブ）
+pub const MAX_FILTER_DEPTH_OCTAVES: f32 = 4.0;
+/// パルス幅モジュレーションの最大深さ（デューティ比）
+pub const MAX_PWM_DEPTH: f32 = 0.45;
+
+/// LFOの波形を表す列挙型
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum LfoShape {
+    Sine,          // サイン波
+    Triangle,      // 三角波
+    Square,        // 矩形波
+    Sawtooth,      // ノコギリ波
+    SampleAndHold, // サンプル&ホールド（1周期ごとにランダムな値）
+    SmoothRandom,  // なめらかなランダム（1周期ごとのランダムな値の間をなめらかにつなぐ）
+}
+
+impl Default for LfoShape {
+    fn default() -> Self {
+        Self::Sine
+    }
+}
+
+/// LFOのモジュレーション先を表す列挙型
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum LfoTarget {
+    Pitch,        // ビブラート
+    Amplitude,    // トレモロ
+    FilterCutoff, // フィルターのカットオフ
+    PulseWidth,   // 矩形波のパルス幅（PWM）
+}
+
+impl Default for LfoTarget {
+    fn default() -> Self {
+        Self::Pitch
+    }
+}
+
+/// LFOをボイスで共有するか、ボイスごとに持つかを表す列挙型
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum LfoVoiceMode {
+    Global,   // 全ボイスで1つのLFOを共有する
+    PerVoice, // ボイスごとにLFOを持つ
+}
+
+impl Default for LfoVoiceMode {
+    fn default() -> Self {
+        Self::Global
+    }
+}
+
+/// ノートを弾いた時にLFOの位相をどうするかを表す列挙型
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum LfoRetrigger {
+    FreeRun, // 位相をリセットせずに回し続ける
+    Key,     // ノートを弾くたびに位相を合わせ直す
+}
+
+impl Default for LfoRetrigger {
+    fn default() -> Self {
+        Self::FreeRun
+    }
+}
+
+/// LFOの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LfoSettings {
+    /// LFOを有効にするかどうか
+    pub enabled: bool,
+    /// 波形
+    pub shape: LfoShape,
+    /// 周波数（0.01Hz-20Hz、テンポ同期しない時）
+    pub rate: f32,
+    /// テンポに同期するかどうか（true なら division の長さで1周する）
+    pub sync: bool,
+    /// テンポ同期する時の1周の長さ
+    pub division: NoteDivision,
+    /// 深さ（0.0-1.0、モジュレーション先ごとの最大値に対する割合）
+    pub depth: f32,
+    /// 値の変化をなまらせる量（0.0でそのまま、1.0で1周期かけて追いつく）
+    pub slew: f32,
+    /// モジュレーション先
+    pub target: LfoTarget,
+    /// 全ボイスで共有するか、ボイスごとに持つか
+    pub voice_mode: LfoVoiceMode,
+    /// ノートを弾いた時の位相の扱い
+    pub retrigger: LfoRetrigger,
+    /// 位相のずれ（0.0-1.0、キーリトリガーでは始まる位相、ボイスごとのフリーランではボイスごとのずれ）
+    pub phase_offset: f32,
+    /// モジュレーションホイール（CC1）を最大にした時に加わるビブラートの深さ（0.0-1.0、LFOが無効でもかかる）
+    pub mod_wheel_vibrato: f32,
+}
+
+impl Default for LfoSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            shape: LfoShape::Sine,
+            rate: 5.0,
+            sync: false,
+            division: NoteDivision::Quarter,
+            depth: 0.1,
+            slew: 0.0,
+            target: LfoTarget::Pitch,
+            voice_mode: LfoVoiceMode::Global,
+            retrigger: LfoRetrigger::FreeRun,
+            phase_offset: 0.0,
+            mod_wheel_vibrato: 0.5,
+        }
+    }
+}
+
+impl LfoSettings {
+    /// テンポ（BPM）での周波数（Hz、同期しない時は rate）
+    pub fn rate_at(&self, bpm: f32) -> f32 {
+        if self.sync { 1.0 / self.division.seconds(bpm) } else { self.rate }
+    }
+
+    /// LFOの値（-1.0〜1.0）とモジュレーションホイールの値（0.0〜1.0）からピッチの倍率を計算する
+    pub fn pitch_ratio(&self, value: f32, mod_wheel: f32) -> f32 {
+        let mut depth = mod_wheel * self.mod_wheel_vibrato;
+        if self.enabled && self.target == LfoTarget::Pitch {
+            depth += self.depth;
+        }
+        if depth > 0.0 {
+            2.0f32.powf(value * depth.min(1.0) * MAX_PITCH_DEPTH_SEMITONES / 12.0)
+        } else {
+            1.0
+        }
+    }
+
+    /// LFOの値（-1.0〜1.0）から音量の倍率を計算する
+    pub fn amplitude_gain(&self, value: f32) -> f32 {
+        if self.enabled && self.target == LfoTarget::Amplitude {
+            // 深さ1.0で 0.0〜1.0 の範囲を揺れる
+            1.0 - self.depth * (0.5 - 0.5 * value)
+        } else {
+            1.0
+        }
+    }
+
+    /// LFOの値（-1.0〜1.0）からカットオフの倍率を計算する
+    pub fn cutoff_ratio(&self, value: f32) -> f32 {
+        if self.enabled && self.target == LfoTarget::FilterCutoff {
+            2.0f32.powf(value * self.depth * MAX_FILTER_DEPTH_OCTAVES)
+        } else {
+            1.0
+        }
+    }
+
+    /// LFOの値（-1.0〜1.0）で基準のパルス幅を変調する
+    pub fn pulse_width(&self, value: f32, base: f32) -> f32 {
+        if self.enabled && self.target == LfoTarget::PulseWidth {
+            (base + value * self.depth * MAX_PWM_DEPTH).clamp(MIN_PULSE_WIDTH, MAX_PULSE_WIDTH)
+        } else {
+            base
+        }
+    }
+}
+
+/// LFO本体（オーディオスレッドが保持して毎サンプル進める）
+pub struct Lfo {
+    phase: f32,        // 位相（0.0-1.0）
+    held_value: f32,   // サンプル&ホールドの現在値
+    prev_value: f32,   // 1つ前のランダムな値（なめらかなランダムの始点）
+    slewed_value: f32, // スルーをかけた後の出力
+    rng_state: u32,    // 乱数の状態（xorshift）
+}
+
+impl Lfo {
+    pub fn new() -> Self {
+        Self::with_seed(0x1234_5678)
+    }
+
+    /// 乱数の種を指定して作る（ボイスごとのLFOでサンプル&ホールドの値をばらけさせる）
+    pub fn with_seed(seed: u32) -> Self {
+        Self {
+            phase: 0.0,
+            held_value: 0.0,
+            prev_value: 0.0,
+            slewed_value: 0.0,
+            rng_state: seed.max(1),
+        }
+    }
+
+    /// 位相をリセットする
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+    }
+
+    /// 現在の位相（0.0-1.0）
+    pub fn phase(&self) -> f32 {
+        self.phase
+    }
+
+    /// ノートを弾いた時に位相を合わせ直す（ランダムな波形は新しい値から始める）
+    pub fn retrigger(&mut self, phase: f32) {
+        self.phase = phase.rem_euclid(1.0);
+        self.advance_random();
+    }
+
+    /// 位相をトランスポートの再生位置に合わせる（1周したらサンプル&ホールドの値を更新）
+    pub fn lock_phase(&mut self, phase: f32) {
+        let phase = phase.rem_euclid(1.0);
+        if phase < self.phase {
+            self.advance_random();
+        }
+        self.phase = phase;
+    }
+
+    /// 現在の値（-1.0〜1.0）を返して1サンプル進める
+    pub fn next(&mut self, settings: &LfoSettings, sample_rate: f32) -> f32 {
+        let value = match settings.shape {
+            LfoShape::Sine => (2.0 * PI * self.phase).sin(),
+            LfoShape::Triangle => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+            LfoShape::Square => {
+                if self.phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            LfoShape::Sawtooth => 2.0 * self.phase - 1.0,
+            LfoShape::SampleAndHold => self.held_value,
+            LfoShape::SmoothRandom => {
+                // 1つ前の値から今の値へコサインでつなぐ
+                let t = 0.5 - 0.5 * (PI * self.phase).cos();
+                self.prev_value + (self.held_value - self.prev_value) * t
+            }
+        };
+
+        // スルー（1周期に対する割合の時定数で追いかける）
+        let rate = settings.rate.max(0.0);
+        let slew = settings.slew.clamp(0.0, 1.0);
+        self.slewed_value = if slew > 0.0 && rate > 0.0 {
+            let coeff = 1.0 - (-rate / (slew * sample_rate)).exp();
+            self.slewed_value + (value - self.slewed_value) * coeff
+        } else {
+            value
+        };
+
+        // 位相を進める（1周したらランダムな値を更新）
+        self.phase += rate / sample_rate;
+        if self.phase >= 1.0 {
+            self.phase -= self.phase.floor();
+            self.advance_random();
+        }
+
+        self.slewed_value
+    }
+
+    /// 次のランダムな値に進める（今の値はなめらかなランダムの始点になる）
+    fn advance_random(&mut self) {
+        self.prev_value = self.held_value;
+        self.held_value = self.next_random();
+    }
+
+    /// -1.0〜1.0 の乱数を生成する（オーディオスレッドで使えるようにロックなし）
+    fn next_random(&mut self) -> f32 {
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng_state = x;
+        (x as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+}
+
+/// LFOの設定を管理する構造体
+pub struct LfoManager {
+    settings: Arc<Mutex<LfoSettings>>,
+}
+
+impl LfoManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(LfoSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<LfoSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.enabled = enabled;
+        }
+    }
+
+    pub fn set_shape(&self, shape: LfoShape) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.shape = shape;
+        }
+    }
+
+    pub fn set_rate(&self, rate: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.rate = rate.clamp(0.01, 20.0);
+        }
+    }
+
+    pub fn set_sync(&self, sync: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.sync = sync;
+        }
+    }
+
+    pub fn set_division(&self, division: NoteDivision) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.division = division;
+        }
+    }
+
+    pub fn set_depth(&self, depth: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.depth = depth.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_slew(&self, slew: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.slew = slew.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_target(&self, target: LfoTarget) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.target = target;
+        }
+    }
+
+    pub fn set_voice_mode(&self, voice_mode: LfoVoiceMode) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.voice_mode = voice_mode;
+        }
+    }
+
+    pub fn set_retrigger(&self, retrigger: LfoRetrigger) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.retrigger = retrigger;
+        }
+    }
+
+    pub fn set_phase_offset(&self, phase_offset: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.phase_offset = phase_offset.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_mod_wheel_vibrato(&self, amount: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.mod_wheel_vibrato = amount.clamp(0.0, 1.0);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! シンセの音声生成エンジン
+//!
+//! オシレータ・フィルター・エンベロープ・エフェクトなどのDSPと、
+//! ノートやパラメータの管理をまとめたライブラリ。
+//! GUI（egui）・オーディオデバイス（cpal）・MIDIデバイス（midir）には依存しないので、
+//! GUIのないホストやオフラインのレンダリングからも使える。
+
+pub mod arpeggiator;
+pub mod bandlimited;
+pub mod bitcrusher;
+pub mod chord;
+pub mod chorus;
+pub mod compressor;
+pub mod decimator;
+pub mod delay;
+pub mod denormal;
+pub mod distortion;
+pub mod drift;
+pub mod effects;
+pub mod engine;
+pub mod envelope;
+pub mod euclid;
+pub mod filter;
+pub mod flanger;
+pub mod lfo;
+pub mod lock;
+pub mod logger;
+pub mod macros;
+pub mod master;
+pub mod meter;
+pub mod midi_activity;
+pub mod midi_config;
+pub mod midi_map;
+pub mod midi_queue;
+pub mod midi_message;
+pub mod mixer;
+pub mod mod_monitor;
+pub mod modmatrix;
+pub mod note;
+pub mod oscillator;
+pub mod params;
+pub mod part;
+pub mod phaser;
+pub mod pitch_bend;
+pub mod preset;
+pub mod program;
+pub mod randomize;
+pub mod render;
+pub mod reverb;
+pub mod scale_lock;
+pub mod simd;
+pub mod smf;
+pub mod smoother;
+pub mod strum;
+pub mod transport;
+pub mod transpose;
+pub mod tuning;
+pub mod unison;
+pub mod velocity;
+pub mod voice;
+pub mod wavetable;
+pub mod xy_pad;
+pub mod zone;
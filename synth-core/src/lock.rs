@@ -0,0 +1,8 @@
+use std::sync::{Mutex, MutexGuard};
+
+/// Mutex をロックする（`wait` が false なら待たずに試し、他のスレッドがロックしていれば None）
+///
+/// オーディオスレッドのMIDI処理はロックを待たずに試し、取れなければメッセージを次のバッファまで後回しにする
+pub(crate) fn lock_or_try<T>(mutex: &Mutex<T>, wait: bool) -> Option<MutexGuard<'_, T>> {
+    if wait { mutex.lock().ok() } else { mutex.try_lock().ok() }
+}
@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use ringbuf::{HeapConsumer, HeapProducer, HeapRb};
+
+use crate::midi_map::ParamId;
+
+/// 1つのログ口のリングバッファの容量（イベント数）
+const TAP_CAPACITY: usize = 1024;
+
+/// GUIのログパネルに残す行数
+const HISTORY_LINES: usize = 200;
+
+/// ログスレッドがリングバッファを見に行く間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// リアルタイムのコールバックから送るログイベント（固定サイズでアロケーションしない）
+#[derive(Clone, Copy, Debug)]
+pub enum LogEvent {
+    NoteOn { channel: u8, note: u8, velocity: u8 },
+    NoteOff { channel: u8, note: u8 },
+    SustainPedal(bool),
+    AllSoundOff,
+    AllNotesOff,
+    MidiLearn { controller: u8, param: ParamId },
+    StreamError(&'static str),
+    MidiTransport(&'static str),
+    ProgramChange { channel: u8, bank: u16, program: u8 },
+}
+
+impl fmt::Display for LogEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogEvent::NoteOn { channel, note, velocity } => {
+                write!(f, "MIDI note on: ch={}, note={}, velocity={}", channel + 1, note, velocity)
+            }
+            LogEvent::NoteOff { channel, note } => write!(f, "MIDI note off: ch={}, note={}", channel + 1, note),
+            LogEvent::SustainPedal(down) => write!(f, "Sustain pedal: {}", if *down { "on" } else { "off" }),
+            LogEvent::AllSoundOff => write!(f, "All sound off"),
+            LogEvent::AllNotesOff => write!(f, "All notes off"),
+            LogEvent::MidiLearn { controller, param } => write!(f, "MIDI learn: CC{} -> {}", controller, param.label()),
+            LogEvent::StreamError(message) => write!(f, "Error in output stream: {}", message),
+            LogEvent::MidiTransport(message) => write!(f, "MIDI transport: {}", message),
+            LogEvent::ProgramChange { channel, bank, program } => {
+                write!(f, "MIDI program change: ch={}, bank={}, program={}", channel + 1, bank, program)
+            }
+        }
+    }
+}
+
+/// コールバックがログを送る口（1つのスレッドだけが持つ、ブロックしない）
+pub struct LogTap {
+    producer: HeapProducer<LogEvent>,
+}
+
+impl LogTap {
+    /// イベントをリングバッファに積む（溢れた分は捨てる）
+    pub fn push(&mut self, event: LogEvent) {
+        let _ = self.producer.push(event);
+    }
+}
+
+/// ログを文字列にして出力するスレッドを管理する構造体
+pub struct Logger {
+    consumer_tx: Option<Sender<HeapConsumer<LogEvent>>>,
+    history: Arc<Mutex<VecDeque<String>>>,
+    log_thread: Option<JoinHandle<()>>,
+}
+
+impl Logger {
+    pub fn new() -> Self {
+        let (consumer_tx, consumer_rx) = mpsc::channel::<HeapConsumer<LogEvent>>();
+        let history = Arc::new(Mutex::new(VecDeque::with_capacity(HISTORY_LINES)));
+
+        // 文字列の整形と出力は別スレッドで行う（スレッドを作れない環境ではログを出力しない）
+        let thread_history = Arc::clone(&history);
+        let log_thread = thread::Builder::new().name("logger".to_string()).spawn(move || {
+            let mut consumers = Vec::new();
+            loop {
+                // 新しいログ口の登録を待ちつつ、一定間隔でリングバッファを空にする
+                let closed = match consumer_rx.recv_timeout(POLL_INTERVAL) {
+                    Ok(consumer) => {
+                        consumers.push(consumer);
+                        false
+                    }
+                    Err(RecvTimeoutError::Timeout) => false,
+                    Err(RecvTimeoutError::Disconnected) => true,
+                };
+                for consumer in consumers.iter_mut() {
+                    while let Some(event) = consumer.pop() {
+                        let line = event.to_string();
+                        println!("{}", line);
+                        if let Ok(mut history) = thread_history.lock() {
+                            if history.len() >= HISTORY_LINES {
+                                history.pop_front();
+                            }
+                            history.push_back(line);
+                        }
+                    }
+                }
+                if closed {
+                    break;
+                }
+            }
+        });
+        let log_thread = log_thread.inspect_err(|err| println!("Failed to start log thread: {}", err)).ok();
+
+        Self {
+            consumer_tx: Some(consumer_tx),
+            history,
+            log_thread,
+        }
+    }
+
+    /// 新しいログ口を作る（コールバックを作る時に呼び、コールバックに持たせる）
+    pub fn tap(&self) -> LogTap {
+        let (producer, consumer) = HeapRb::<LogEvent>::new(TAP_CAPACITY).split();
+        if let Some(tx) = &self.consumer_tx {
+            let _ = tx.send(consumer);
+        }
+        LogTap { producer }
+    }
+
+    /// 最近のログ（古い順）
+    pub fn history(&self) -> Vec<String> {
+        self.history.lock().map(|history| history.iter().cloned().collect()).unwrap_or_default()
+    }
+}
+
+impl Drop for Logger {
+    fn drop(&mut self) {
+        // 送信側を閉じると残りを出力してからログスレッドが終了する
+        self.consumer_tx = None;
+        if let Some(handle) = self.log_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::midi_map::ParamId;
+use crate::params::SynthParams;
+
+/// マクロツマミの数
+pub const MACRO_COUNT: usize = 4;
+
+/// マクロからパラメータへの割り当て
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MacroAssignment {
+    /// 動かすパラメータ
+    pub param: ParamId,
+    /// マクロが0の時のパラメータの位置（0.0-1.0、パラメータの範囲に対する割合）
+    pub min: f32,
+    /// マクロが1の時のパラメータの位置（0.0-1.0）
+    pub max: f32,
+    /// 逆向きにするかどうか（true ならマクロを上げるとパラメータが max から min へ下がる）
+    pub inverted: bool,
+}
+
+impl Default for MacroAssignment {
+    fn default() -> Self {
+        Self {
+            param: ParamId::FilterCutoff,
+            min: 0.0,
+            max: 1.0,
+            inverted: false,
+        }
+    }
+}
+
+impl MacroAssignment {
+    /// マクロの値（0.0-1.0）からパラメータの位置（0.0-1.0）を計算する
+    pub fn map(&self, value: f32) -> f32 {
+        let x = value.clamp(0.0, 1.0);
+        let x = if self.inverted { 1.0 - x } else { x };
+        self.min.clamp(0.0, 1.0) + (self.max.clamp(0.0, 1.0) - self.min.clamp(0.0, 1.0)) * x
+    }
+}
+
+/// 1つのマクロツマミ
+#[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MacroSlot {
+    /// ツマミの値（0.0-1.0）
+    pub value: f32,
+    /// 割り当てたパラメータ（1つのツマミで複数のパラメータを動かせる）
+    pub assignments: Vec<MacroAssignment>,
+}
+
+/// マクロツマミの設定を表す構造体
+#[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MacroSettings {
+    pub slots: [MacroSlot; MACRO_COUNT],
+}
+
+/// マクロツマミを管理する構造体
+pub struct MacroManager {
+    settings: Arc<Mutex<MacroSettings>>,
+}
+
+impl MacroManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(MacroSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<MacroSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    /// ツマミの値を設定し、割り当てたパラメータに反映する
+    pub fn set_value(&self, index: usize, value: f32, params: &SynthParams) {
+        let assignments = if let Ok(mut settings) = self.settings.lock() {
+            let Some(slot) = settings.slots.get_mut(index) else {
+                return;
+            };
+            slot.value = value.clamp(0.0, 1.0);
+            slot.assignments.clone()
+        } else {
+            return;
+        };
+        // パラメータ側のロックを取るので、マクロのロックを離してから反映する
+        for assignment in assignments {
+            assignment.param.apply(params, assignment.map(value));
+        }
+    }
+
+    /// パラメータを割り当てる（同じパラメータがすでに割り当てられていれば何もしない）
+    pub fn assign(&self, index: usize, param: ParamId) {
+        if let Ok(mut settings) = self.settings.lock()
+            && let Some(slot) = settings.slots.get_mut(index)
+        {
+            if param.is_macro() || slot.assignments.iter().any(|assignment| assignment.param == param) {
+                return;
+            }
+            slot.assignments.push(MacroAssignment {
+                param,
+                ..Default::default()
+            });
+        }
+    }
+
+    /// 割り当ての範囲と向きを変更する
+    pub fn set_assignment(&self, index: usize, assignment_index: usize, assignment: MacroAssignment) {
+        if let Ok(mut settings) = self.settings.lock()
+            && let Some(current) = settings
+                .slots
+                .get_mut(index)
+                .and_then(|slot| slot.assignments.get_mut(assignment_index))
+        {
+            // マクロからマクロへの割り当ては循環するので許さない
+            if assignment.param.is_macro() {
+                return;
+            }
+            *current = MacroAssignment {
+                min: assignment.min.clamp(0.0, 1.0),
+                max: assignment.max.clamp(0.0, 1.0),
+                ..assignment
+            };
+        }
+    }
+
+    /// 割り当てを外す
+    pub fn unassign(&self, index: usize, assignment_index: usize) {
+        if let Ok(mut settings) = self.settings.lock()
+            && let Some(slot) = settings.slots.get_mut(index)
+            && assignment_index < slot.assignments.len()
+        {
+            slot.assignments.remove(assignment_index);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::f32::consts::{FRAC_PI_4, SQRT_2, TAU};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::denormal::flush_denormal;
+
+/// ソフトクリップが効き始めるレベル
+const LIMITER_THRESHOLD: f32 = 0.8;
+/// DCブロッカーのカットオフ周波数（Hz、聞こえる低音には影響しない高さ）
+const DC_BLOCKER_CUTOFF: f32 = 10.0;
+
+/// マスター出力の設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MasterSettings {
+    /// マスター音量（dB、-60〜+12）
+    pub gain_db: f32,
+    /// ソフトリミッターを有効にするかどうか
+    pub limiter: bool,
+    /// マスターパン（-1.0で左、0.0で中央、1.0で右）
+    pub pan: f32,
+}
+
+impl Default for MasterSettings {
+    fn default() -> Self {
+        Self {
+            gain_db: 0.0,
+            limiter: true,
+            pan: 0.0,
+        }
+    }
+}
+
+impl MasterSettings {
+    /// dBを線形の倍率に変換する
+    pub fn gain(&self) -> f32 {
+        if self.gain_db <= -60.0 {
+            0.0
+        } else {
+            10.0f32.powf(self.gain_db / 20.0)
+        }
+    }
+
+    /// マスターパンによる左右の倍率（バランス方式、中央で左右とも1.0）
+    pub fn balance_gains(&self) -> (f32, f32) {
+        let pan = self.pan.clamp(-1.0, 1.0);
+        ((1.0 - pan).min(1.0), (1.0 + pan).min(1.0))
+    }
+
+    /// マスター音量とリミッターを1サンプルに適用する
+    pub fn process(&self, input: f32, gain: f32) -> f32 {
+        let x = input * gain;
+        if self.limiter { soft_clip(x) } else { x }
+    }
+}
+
+/// 定位（-1.0〜1.0）から左右の倍率を計算する（等パワー、中央で左右とも1.0）
+pub fn pan_gains(pan: f32) -> (f32, f32) {
+    let angle = (pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+    (angle.cos() * SQRT_2, angle.sin() * SQRT_2)
+}
+
+/// ソフトクリッパー（しきい値を超えた分を tanh で滑らかに ±1.0 に収める）
+pub fn soft_clip(x: f32) -> f32 {
+    let magnitude = x.abs();
+    if magnitude <= LIMITER_THRESHOLD {
+        return x;
+    }
+    let headroom = 1.0 - LIMITER_THRESHOLD;
+    let over = (magnitude - LIMITER_THRESHOLD) / headroom;
+    (LIMITER_THRESHOLD + headroom * over.tanh()).copysign(x)
+}
+
+/// 直流成分を取り除く1次のハイパスフィルター（マスター出力の左右それぞれに1つ）
+///
+/// 非対称な波形整形などで生じた直流のずれが、コンプレッサーやリミッターに届かないようにする
+pub struct DcBlocker {
+    prev_input: f32,
+    prev_output: f32,
+    coefficient: f32, // 極の位置（1.0に近いほどカットオフが低い）
+}
+
+impl DcBlocker {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            prev_input: 0.0,
+            prev_output: 0.0,
+            coefficient: (-TAU * DC_BLOCKER_CUTOFF / sample_rate).exp(),
+        }
+    }
+
+    /// 内部状態をクリアする
+    pub fn reset(&mut self) {
+        self.prev_input = 0.0;
+        self.prev_output = 0.0;
+    }
+
+    /// 1サンプルを処理する
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = flush_denormal(input - self.prev_input + self.coefficient * self.prev_output);
+        self.prev_input = input;
+        self.prev_output = output;
+        output
+    }
+}
+
+/// マスター出力の設定を管理する構造体
+pub struct MasterManager {
+    settings: Arc<Mutex<MasterSettings>>,
+    clipped: Arc<AtomicBool>, // オーディオスレッドがクリップを検出したら立てる
+}
+
+impl MasterManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(MasterSettings::default())),
+            clipped: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<MasterSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_gain_db(&self, gain_db: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.gain_db = gain_db.clamp(-60.0, 12.0);
+        }
+    }
+
+    pub fn set_limiter(&self, limiter: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.limiter = limiter;
+        }
+    }
+
+    pub fn set_pan(&self, pan: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.pan = pan.clamp(-1.0, 1.0);
+        }
+    }
+
+    /// クリップを通知する（オーディオスレッドから呼ぶ）
+    pub fn report_clip(&self) {
+        self.clipped.store(true, Ordering::Relaxed);
+    }
+
+    /// クリップが発生していたかを取得してリセットする（GUIから呼ぶ）
+    pub fn take_clip(&self) -> bool {
+        self.clipped.swap(false, Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,159 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+/// ノート名（オクターブ番号は C4 = 60 とする）
+const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+/// MIDIノート番号を表示用のノート名にする（例: 60 → "C4"）
+pub fn note_name(note: u8) -> String {
+    format!("{}{}", NOTE_NAMES[note as usize % 12], note as i32 / 12 - 1)
+}
+
+/// 出力レベルと発音状態のメーター（オーディオスレッドが書き、GUIが読む）
+///
+/// 全てアトミック変数でやり取りするので、オーディオスレッドはロックを取らない
+pub struct MeterManager {
+    peak: AtomicU32,            // 前回GUIが読んでからの最大のピーク（f32のビット列）
+    rms: AtomicU32,             // 最後のブロックのRMS（f32のビット列）
+    active_voices: AtomicU32,   // 鳴っているボイスの数（リリース中を含む）
+    held_notes: [AtomicU64; 2], // 押されているノートのビット列（ノート番号 0-63 と 64-127）
+    dsp_load: AtomicU32,        // 前回GUIが読んでからの最大のDSP負荷（1.0でバッファの長さと同じ時間、f32のビット列）
+    xruns: AtomicU32,           // バッファの生成が間に合わなかったり出力が途切れたりした回数
+    buffer_frames: AtomicU32,   // 最後のコールバックのバッファの長さ（フレーム数、0はまだ呼ばれていない）
+    sample_rate: AtomicU32,     // ストリームのサンプルレート（Hz）
+    output_latency: AtomicU32,  // コールバックから音が出るまでの時間（秒、f32のビット列、負の値は測れていない）
+    device_lost: AtomicBool,    // 出力デバイスが使えなくなった（GUIがストリームを作り直す）
+}
+
+impl MeterManager {
+    pub fn new() -> Self {
+        Self {
+            peak: AtomicU32::new(0.0f32.to_bits()),
+            rms: AtomicU32::new(0.0f32.to_bits()),
+            active_voices: AtomicU32::new(0),
+            held_notes: [AtomicU64::new(0), AtomicU64::new(0)],
+            dsp_load: AtomicU32::new(0.0f32.to_bits()),
+            xruns: AtomicU32::new(0),
+            buffer_frames: AtomicU32::new(0),
+            sample_rate: AtomicU32::new(0),
+            output_latency: AtomicU32::new((-1.0f32).to_bits()),
+            device_lost: AtomicBool::new(false),
+        }
+    }
+
+    /// ブロックの出力レベルを通知する（オーディオスレッドから呼ぶ）
+    pub fn report_levels(&self, peak: f32, rms: f32) {
+        // 0以上の f32 はビット列の大小と値の大小が一致するので、整数の最大値で比べられる
+        self.peak.fetch_max(peak.max(0.0).to_bits(), Ordering::Relaxed);
+        self.rms.store(rms.max(0.0).to_bits(), Ordering::Relaxed);
+    }
+
+    /// 鳴っているボイスの数と押されているノートを通知する（オーディオスレッドから呼ぶ）
+    pub fn report_voices(&self, active_voices: usize, held_notes: impl Iterator<Item = u8>) {
+        let mut bits = [0u64; 2];
+        for note in held_notes {
+            let note = note.min(127) as usize;
+            bits[note / 64] |= 1 << (note % 64);
+        }
+        self.active_voices.store(active_voices as u32, Ordering::Relaxed);
+        for (stored, bits) in self.held_notes.iter().zip(bits) {
+            stored.store(bits, Ordering::Relaxed);
+        }
+    }
+
+    /// コールバックにかかった時間のバッファの長さに対する割合を通知する（オーディオスレッドから呼ぶ）
+    ///
+    /// 1.0を超えたら出力が間に合わなかったものとしてアンダーランに数える
+    pub fn report_load(&self, load: f32) {
+        self.dsp_load.fetch_max(load.max(0.0).to_bits(), Ordering::Relaxed);
+        if load > 1.0 {
+            self.report_xrun();
+        }
+    }
+
+    /// アンダーランを1回数える（バックエンドが検出したアンダーランやコールバックの間隔の途切れからも呼ぶ）
+    pub fn report_xrun(&self) {
+        self.xruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 前回呼んでからの最大のDSP負荷を取得してリセットする（GUIから呼ぶ）
+    pub fn take_dsp_load(&self) -> f32 {
+        f32::from_bits(self.dsp_load.swap(0.0f32.to_bits(), Ordering::Relaxed))
+    }
+
+    /// これまでのアンダーランの回数
+    pub fn xruns(&self) -> u32 {
+        self.xruns.load(Ordering::Relaxed)
+    }
+
+    /// アンダーランの回数を0に戻す
+    pub fn reset_xruns(&self) {
+        self.xruns.store(0, Ordering::Relaxed);
+    }
+
+    /// コールバックに来たバッファの長さを通知する（オーディオスレッドから呼ぶ）
+    pub fn report_buffer(&self, frames: usize, sample_rate: u32) {
+        self.buffer_frames.store(frames as u32, Ordering::Relaxed);
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    /// バックエンドが測ったコールバックから再生までの時間（秒）を通知する（オーディオスレッドから呼ぶ）
+    pub fn report_output_latency(&self, seconds: f32) {
+        self.output_latency.store(seconds.to_bits(), Ordering::Relaxed);
+    }
+
+    /// 最後のコールバックのバッファの長さ（フレーム数）とサンプルレート（Hz）
+    pub fn buffer(&self) -> Option<(u32, u32)> {
+        let frames = self.buffer_frames.load(Ordering::Relaxed);
+        let sample_rate = self.sample_rate.load(Ordering::Relaxed);
+        (frames > 0 && sample_rate > 0).then_some((frames, sample_rate))
+    }
+
+    /// バックエンドが測ったコールバックから再生までの時間（秒、測れないバックエンドでは None）
+    pub fn output_latency(&self) -> Option<f32> {
+        let seconds = f32::from_bits(self.output_latency.load(Ordering::Relaxed));
+        (seconds >= 0.0).then_some(seconds)
+    }
+
+    /// 出力デバイスが使えなくなったことを通知する（ストリームのエラーのコールバックから呼ぶ）
+    pub fn report_device_lost(&self) {
+        self.device_lost.store(true, Ordering::Relaxed);
+    }
+
+    /// 出力デバイスが使えなくなっていたかを取得してリセットする（GUIから呼ぶ）
+    pub fn take_device_lost(&self) -> bool {
+        self.device_lost.swap(false, Ordering::Relaxed)
+    }
+
+    /// ストリームを作り直した時に、前のストリームで測った値を消す
+    pub fn reset_stream_info(&self) {
+        self.buffer_frames.store(0, Ordering::Relaxed);
+        self.sample_rate.store(0, Ordering::Relaxed);
+        self.output_latency.store((-1.0f32).to_bits(), Ordering::Relaxed);
+    }
+
+    /// 前回呼んでからの最大のピーク（リニア）を取得してリセットする（GUIから呼ぶ）
+    pub fn take_peak(&self) -> f32 {
+        f32::from_bits(self.peak.swap(0.0f32.to_bits(), Ordering::Relaxed))
+    }
+
+    /// 最後のブロックのRMS（リニア）
+    pub fn rms(&self) -> f32 {
+        f32::from_bits(self.rms.load(Ordering::Relaxed))
+    }
+
+    /// 鳴っているボイスの数
+    pub fn active_voices(&self) -> usize {
+        self.active_voices.load(Ordering::Relaxed) as usize
+    }
+
+    /// 押されているノート（低い順）
+    pub fn held_notes(&self) -> Vec<u8> {
+        let bits = [
+            self.held_notes[0].load(Ordering::Relaxed),
+            self.held_notes[1].load(Ordering::Relaxed),
+        ];
+        (0..128u8)
+            .filter(|&note| bits[note as usize / 64] & (1 << (note % 64)) != 0)
+            .collect()
+    }
+}
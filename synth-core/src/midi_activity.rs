@@ -0,0 +1,74 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// 受信をGUIに表示するMIDIメッセージの種類
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MidiActivityKind {
+    Note,          // ノートオン・ノートオフ
+    ControlChange, // コントロールチェンジ
+    PitchBend,     // ピッチベンド
+    Clock,         // MIDIクロックとスタート・ストップなどのトランスポート
+}
+
+impl MidiActivityKind {
+    /// 全ての種類（表示の並び順）
+    pub const ALL: [Self; 4] = [Self::Note, Self::ControlChange, Self::PitchBend, Self::Clock];
+
+    /// 配列でのインデックス
+    pub fn index(self) -> usize {
+        match self {
+            Self::Note => 0,
+            Self::ControlChange => 1,
+            Self::PitchBend => 2,
+            Self::Clock => 3,
+        }
+    }
+
+    /// 表示名
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Note => "Note",
+            Self::ControlChange => "CC",
+            Self::PitchBend => "Bend",
+            Self::Clock => "Clock",
+        }
+    }
+
+    /// メッセージの種類（表示しないメッセージは None）
+    pub fn from_message(message: &[u8]) -> Option<Self> {
+        match *message.first()? {
+            0xF8 | 0xFA | 0xFB | 0xFC | 0xF2 => Some(Self::Clock),
+            status if status >= 0xF0 => None,
+            status => match status & 0xF0 {
+                0x80 | 0x90 => Some(Self::Note),
+                0xB0 => Some(Self::ControlChange),
+                0xE0 => Some(Self::PitchBend),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// MIDIポートで受信したメッセージの種類ごとの数（MIDIコールバックが数え、GUIが変化を見て点滅させる）
+pub struct MidiActivity {
+    counts: [AtomicU32; MidiActivityKind::ALL.len()],
+}
+
+impl MidiActivity {
+    pub fn new() -> Self {
+        Self {
+            counts: std::array::from_fn(|_| AtomicU32::new(0)),
+        }
+    }
+
+    /// 受信したメッセージを数える（MIDIコールバックから呼ぶ、溢れたら0に戻る）
+    pub fn record(&self, message: &[u8]) {
+        if let Some(kind) = MidiActivityKind::from_message(message) {
+            self.counts[kind.index()].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// これまでに受信した数（前回から変わっていれば受信があった）
+    pub fn count(&self, kind: MidiActivityKind) -> u32 {
+        self.counts[kind.index()].load(Ordering::Relaxed)
+    }
+}
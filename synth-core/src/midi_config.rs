@@ -0,0 +1,141 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+/// 受信するMIDIチャンネル
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum MidiChannel {
+    Omni,        // 全てのチャンネルを受信
+    Channel(u8), // 指定したチャンネルだけを受信（0始まり、表示は1〜16）
+}
+
+impl Default for MidiChannel {
+    fn default() -> Self {
+        Self::Omni
+    }
+}
+
+impl MidiChannel {
+    /// 表示名
+    pub fn label(&self) -> String {
+        match self {
+            MidiChannel::Omni => "Omni".to_string(),
+            MidiChannel::Channel(channel) => format!("Ch {}", channel + 1),
+        }
+    }
+}
+
+/// MIDI入力の設定を表す構造体
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MidiConfig {
+    /// 受信するチャンネル
+    pub channel: MidiChannel,
+    /// 最後に接続していたMIDIポート名（起動時とポートが挿し直された時に自動で接続する）
+    pub ports: Vec<String>,
+}
+
+/// MIDI入力の設定を管理する構造体
+pub struct MidiConfigManager {
+    config: Arc<Mutex<MidiConfig>>,
+}
+
+impl MidiConfigManager {
+    pub fn new() -> Self {
+        Self {
+            config: Arc::new(Mutex::new(MidiConfig::default())),
+        }
+    }
+
+    /// 自動で接続するMIDIポート名
+    pub fn ports(&self) -> Vec<String> {
+        self.config.lock().map(|config| config.ports.clone()).unwrap_or_default()
+    }
+
+    /// 自動で接続するMIDIポートを追加する
+    pub fn add_port(&self, port_name: &str) {
+        if let Ok(mut config) = self.config.lock()
+            && !config.ports.iter().any(|name| name == port_name)
+        {
+            config.ports.push(port_name.to_string());
+        }
+    }
+
+    /// 自動で接続するMIDIポートから外す
+    pub fn remove_port(&self, port_name: &str) {
+        if let Ok(mut config) = self.config.lock() {
+            config.ports.retain(|name| name != port_name);
+        }
+    }
+
+    /// 自動で接続するMIDIポートを全て外す
+    pub fn clear_ports(&self) {
+        if let Ok(mut config) = self.config.lock() {
+            config.ports.clear();
+        }
+    }
+
+    pub fn channel(&self) -> MidiChannel {
+        self.config.lock().map(|config| config.channel).unwrap_or_default()
+    }
+
+    pub fn set_channel(&self, channel: MidiChannel) {
+        if let Ok(mut config) = self.config.lock() {
+            config.channel = match channel {
+                MidiChannel::Channel(channel) => MidiChannel::Channel(channel.min(15)),
+                MidiChannel::Omni => MidiChannel::Omni,
+            };
+        }
+    }
+
+    /// チャンネルメッセージを受信するかどうか（`channel` はステータスバイトの下位4bit）
+    pub fn accepts(&self, channel: u8) -> bool {
+        match self.channel() {
+            MidiChannel::Omni => true,
+            MidiChannel::Channel(selected) => selected == channel,
+        }
+    }
+
+    /// 設定をファイルから読み込む（ファイルがなければ何もしない）
+    pub fn load(&self) -> io::Result<()> {
+        let path = midi_config_path();
+        if !path.exists() {
+            return Ok(());
+        }
+        let json = fs::read_to_string(path)?;
+        let loaded: MidiConfig = serde_json::from_str(&json)?;
+        if let Ok(mut config) = self.config.lock() {
+            *config = loaded;
+        }
+        Ok(())
+    }
+
+    /// 設定をファイルに保存する
+    pub fn save(&self) -> io::Result<()> {
+        let config = self.config.lock().map(|config| config.clone()).unwrap_or_default();
+        let path = midi_config_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&config)?)?;
+        Ok(())
+    }
+
+    /// 設定を保存し、失敗したらログに出す
+    pub fn save_or_log(&self) {
+        if let Err(err) = self.save() {
+            println!("Failed to save MIDI config: {}", err);
+        }
+    }
+}
+
+/// MIDI入力の設定を保存するファイルのパス
+pub fn midi_config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("rust_synth")
+        .join("midi_config.json")
+}
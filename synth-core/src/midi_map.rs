@@ -0,0 +1,680 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::arpeggiator::{MAX_ARP_OCTAVES, MAX_HUMANIZE_TIME};
+use crate::bitcrusher::{MAX_BITS, MAX_CRUSH_RATE, MIN_BITS, MIN_CRUSH_RATE};
+use crate::chorus::MAX_CHORUS_VOICES;
+use crate::delay::{MAX_DELAY_TIME, MIN_DELAY_TIME};
+use crate::envelope::EnvelopeTarget;
+use crate::filter::MAX_KEY_TRACK;
+use crate::macros::MACRO_COUNT;
+use crate::oscillator::{MAX_FM_INDEX, MAX_PULSE_WIDTH, MIN_PULSE_WIDTH, OscRouting};
+use crate::params::SynthParams;
+use crate::phaser::{MAX_PHASER_STAGES, MIN_PHASER_STAGES};
+use crate::pitch_bend::MAX_BEND_RANGE;
+use crate::strum::MAX_STRUM_TIME;
+use crate::transport::{MAX_BPM, MAX_SWING, MIN_BPM, MIN_SWING};
+use crate::transpose::MAX_TRANSPOSE;
+use crate::tuning::{MAX_A4, MAX_FINE_TUNE, MIN_A4};
+use crate::unison::{MAX_UNISON_COARSE, MAX_UNISON_VOICES};
+use crate::voice::{MAX_MPE_BEND_RANGE, MAX_VOICES};
+
+/// MIDIコントローラーで操作できるパラメータ
+///
+/// 音色と演奏に関わる数値のスライダーはすべて割り当てられる。次のものは含まない。
+/// - キーゾーンのレイヤーごとの音色、鍵盤範囲、ベロシティの境目（ゾーンとレイヤーの数が変わるため）
+/// - パートごとの音量と定位（パートの数が変わるため）
+/// - モジュレーションマトリクスの量、マクロとXYパッドの割り当ての範囲（割り当て先の設定なので）
+/// - ユークリッドリズムのステップ数・パルス数・回転（パターンの形なので）
+/// - エンベロープのカーブ、オシレータの品質設定（Filter・Smoothing）、テスト用の周波数、ランダマイズの量
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ParamId {
+    // Unison
+    UnisonDetune,
+    UnisonVoices,
+    UnisonCoarse,
+    UnisonWidth,
+    UnisonBlend,
+    UnisonTaper,
+
+    // オシレータ
+    OscBMix,
+    OscBOctave,
+    OscBSemitone,
+    OscBFine,
+    PulseWidth,
+    FmRatio,
+    FmIndex,
+    Drift,
+
+    // ミキサー
+    OscALevel,
+    OscAPan,
+    OscBLevel,
+    OscBPan,
+    SubLevel,
+    SubPan,
+    NoiseLevel,
+    NoisePan,
+
+    // フィルター
+    FilterCutoff,
+    FilterResonance,
+    FilterKeyTrack,
+    FilterEnvAmount,
+
+    // エンベロープ（音量・フィルター・FMモジュレーター）
+    AmpDelay,
+    AmpAttack,
+    AmpHold,
+    AmpDecay,
+    AmpSustain,
+    AmpRelease,
+    FilterEnvDelay,
+    FilterEnvAttack,
+    FilterEnvHold,
+    FilterEnvDecay,
+    FilterEnvSustain,
+    FilterEnvRelease,
+    ModEnvDelay,
+    ModEnvAttack,
+    ModEnvHold,
+    ModEnvDecay,
+    ModEnvSustain,
+    ModEnvRelease,
+
+    // ベロシティ感度
+    VelocityAmp,
+    VelocityAttack,
+    VelocityRelease,
+
+    // LFO
+    LfoRate,
+    LfoDepth,
+    LfoSlew,
+    LfoPhaseOffset,
+    ModWheelVibrato,
+
+    // 発音とピッチベンド
+    GlideTime,
+    Polyphony,
+    BendRange,
+    MpeBendRange,
+
+    // テンポ・アルペジエーター・ストラム
+    Tempo,
+    Swing,
+    ArpRate,
+    ArpGate,
+    ArpOctaves,
+    ArpHumanizeTime,
+    ArpHumanizeVelocity,
+    StrumTime,
+
+    // エフェクト
+    ChorusVoices,
+    ChorusRate,
+    ChorusDepth,
+    ChorusMix,
+    ReverbRoomSize,
+    ReverbDamping,
+    ReverbMix,
+    DistortionDrive,
+    DistortionTone,
+    DistortionOutput,
+    BitcrusherBits,
+    BitcrusherRate,
+    BitcrusherMix,
+    PhaserStages,
+    PhaserRate,
+    PhaserDepth,
+    PhaserFeedback,
+    PhaserMix,
+    FlangerRate,
+    FlangerDepth,
+    FlangerFeedback,
+    FlangerMix,
+    DelayTime,
+    DelayFeedback,
+    DelayMix,
+
+    // マスター・チューニング・コンプレッサー
+    MasterVolume,
+    MasterPan,
+    MasterTune,
+    FineTune,
+    Transpose,
+    CompressorThreshold,
+    CompressorRatio,
+    CompressorAttack,
+    CompressorRelease,
+    CompressorMakeup,
+
+    Macro(u8), // マクロツマミ（0始まり、表示は1〜4）
+}
+
+impl ParamId {
+    /// マクロやXYパッドの割り当て先に選べるパラメータ（マクロ自身は含まない）
+    pub const ALL: [ParamId; 99] = [
+        ParamId::UnisonDetune,
+        ParamId::UnisonVoices,
+        ParamId::UnisonCoarse,
+        ParamId::UnisonWidth,
+        ParamId::UnisonBlend,
+        ParamId::UnisonTaper,
+        ParamId::OscBMix,
+        ParamId::OscBOctave,
+        ParamId::OscBSemitone,
+        ParamId::OscBFine,
+        ParamId::PulseWidth,
+        ParamId::FmRatio,
+        ParamId::FmIndex,
+        ParamId::Drift,
+        ParamId::OscALevel,
+        ParamId::OscAPan,
+        ParamId::OscBLevel,
+        ParamId::OscBPan,
+        ParamId::SubLevel,
+        ParamId::SubPan,
+        ParamId::NoiseLevel,
+        ParamId::NoisePan,
+        ParamId::FilterCutoff,
+        ParamId::FilterResonance,
+        ParamId::FilterKeyTrack,
+        ParamId::FilterEnvAmount,
+        ParamId::AmpDelay,
+        ParamId::AmpAttack,
+        ParamId::AmpHold,
+        ParamId::AmpDecay,
+        ParamId::AmpSustain,
+        ParamId::AmpRelease,
+        ParamId::FilterEnvDelay,
+        ParamId::FilterEnvAttack,
+        ParamId::FilterEnvHold,
+        ParamId::FilterEnvDecay,
+        ParamId::FilterEnvSustain,
+        ParamId::FilterEnvRelease,
+        ParamId::ModEnvDelay,
+        ParamId::ModEnvAttack,
+        ParamId::ModEnvHold,
+        ParamId::ModEnvDecay,
+        ParamId::ModEnvSustain,
+        ParamId::ModEnvRelease,
+        ParamId::VelocityAmp,
+        ParamId::VelocityAttack,
+        ParamId::VelocityRelease,
+        ParamId::LfoRate,
+        ParamId::LfoDepth,
+        ParamId::LfoSlew,
+        ParamId::LfoPhaseOffset,
+        ParamId::ModWheelVibrato,
+        ParamId::GlideTime,
+        ParamId::Polyphony,
+        ParamId::BendRange,
+        ParamId::MpeBendRange,
+        ParamId::Tempo,
+        ParamId::Swing,
+        ParamId::ArpRate,
+        ParamId::ArpGate,
+        ParamId::ArpOctaves,
+        ParamId::ArpHumanizeTime,
+        ParamId::ArpHumanizeVelocity,
+        ParamId::StrumTime,
+        ParamId::ChorusVoices,
+        ParamId::ChorusRate,
+        ParamId::ChorusDepth,
+        ParamId::ChorusMix,
+        ParamId::ReverbRoomSize,
+        ParamId::ReverbDamping,
+        ParamId::ReverbMix,
+        ParamId::DistortionDrive,
+        ParamId::DistortionTone,
+        ParamId::DistortionOutput,
+        ParamId::BitcrusherBits,
+        ParamId::BitcrusherRate,
+        ParamId::BitcrusherMix,
+        ParamId::PhaserStages,
+        ParamId::PhaserRate,
+        ParamId::PhaserDepth,
+        ParamId::PhaserFeedback,
+        ParamId::PhaserMix,
+        ParamId::FlangerRate,
+        ParamId::FlangerDepth,
+        ParamId::FlangerFeedback,
+        ParamId::FlangerMix,
+        ParamId::DelayTime,
+        ParamId::DelayFeedback,
+        ParamId::DelayMix,
+        ParamId::MasterVolume,
+        ParamId::MasterPan,
+        ParamId::MasterTune,
+        ParamId::FineTune,
+        ParamId::Transpose,
+        ParamId::CompressorThreshold,
+        ParamId::CompressorRatio,
+        ParamId::CompressorAttack,
+        ParamId::CompressorRelease,
+        ParamId::CompressorMakeup,
+    ];
+
+    /// マクロツマミかどうか
+    pub fn is_macro(&self) -> bool {
+        matches!(self, ParamId::Macro(_))
+    }
+
+    /// 表示名
+    pub fn label(&self) -> &'static str {
+        match self {
+            ParamId::UnisonDetune => "Unison Detune",
+            ParamId::UnisonVoices => "Unison Voices",
+            ParamId::UnisonCoarse => "Unison Detune Coarse",
+            ParamId::UnisonWidth => "Unison Width",
+            ParamId::UnisonBlend => "Unison Blend",
+            ParamId::UnisonTaper => "Unison Taper",
+            ParamId::OscBMix => "Osc A/B Mix",
+            ParamId::OscBOctave => "Osc B Octave",
+            ParamId::OscBSemitone => "Osc B Semi",
+            ParamId::OscBFine => "Osc B Fine",
+            ParamId::PulseWidth => "Pulse Width",
+            ParamId::FmRatio => "FM Ratio",
+            ParamId::FmIndex => "FM Index",
+            ParamId::Drift => "Analog Drift",
+            ParamId::OscALevel => "Osc A Level",
+            ParamId::OscAPan => "Osc A Pan",
+            ParamId::OscBLevel => "Osc B Level",
+            ParamId::OscBPan => "Osc B Pan",
+            ParamId::SubLevel => "Sub Level",
+            ParamId::SubPan => "Sub Pan",
+            ParamId::NoiseLevel => "Noise Level",
+            ParamId::NoisePan => "Noise Pan",
+            ParamId::FilterCutoff => "Filter Cutoff",
+            ParamId::FilterResonance => "Filter Resonance",
+            ParamId::FilterKeyTrack => "Filter Key Track",
+            ParamId::FilterEnvAmount => "Filter Env Amount",
+            ParamId::AmpDelay => "Delay",
+            ParamId::AmpAttack => "Attack",
+            ParamId::AmpHold => "Hold",
+            ParamId::AmpDecay => "Decay",
+            ParamId::AmpSustain => "Sustain",
+            ParamId::AmpRelease => "Release",
+            ParamId::FilterEnvDelay => "Filter Env Delay",
+            ParamId::FilterEnvAttack => "Filter Env Attack",
+            ParamId::FilterEnvHold => "Filter Env Hold",
+            ParamId::FilterEnvDecay => "Filter Env Decay",
+            ParamId::FilterEnvSustain => "Filter Env Sustain",
+            ParamId::FilterEnvRelease => "Filter Env Release",
+            ParamId::ModEnvDelay => "Mod Env Delay",
+            ParamId::ModEnvAttack => "Mod Env Attack",
+            ParamId::ModEnvHold => "Mod Env Hold",
+            ParamId::ModEnvDecay => "Mod Env Decay",
+            ParamId::ModEnvSustain => "Mod Env Sustain",
+            ParamId::ModEnvRelease => "Mod Env Release",
+            ParamId::VelocityAmp => "Velocity → Amp",
+            ParamId::VelocityAttack => "Velocity → Attack",
+            ParamId::VelocityRelease => "Release Velocity → Release",
+            ParamId::LfoRate => "LFO Rate",
+            ParamId::LfoDepth => "LFO Depth",
+            ParamId::LfoSlew => "LFO Slew",
+            ParamId::LfoPhaseOffset => "LFO Phase Offset",
+            ParamId::ModWheelVibrato => "Mod Wheel → Vibrato",
+            ParamId::GlideTime => "Glide",
+            ParamId::Polyphony => "Polyphony",
+            ParamId::BendRange => "Bend Range",
+            ParamId::MpeBendRange => "MPE Note Bend Range",
+            ParamId::Tempo => "Tempo",
+            ParamId::Swing => "Swing",
+            ParamId::ArpRate => "Arp Rate",
+            ParamId::ArpGate => "Arp Gate",
+            ParamId::ArpOctaves => "Arp Octaves",
+            ParamId::ArpHumanizeTime => "Arp Humanize Timing",
+            ParamId::ArpHumanizeVelocity => "Arp Humanize Velocity",
+            ParamId::StrumTime => "Strum Time",
+            ParamId::ChorusVoices => "Chorus Voices",
+            ParamId::ChorusRate => "Chorus Rate",
+            ParamId::ChorusDepth => "Chorus Depth",
+            ParamId::ChorusMix => "Chorus Mix",
+            ParamId::ReverbRoomSize => "Reverb Room Size",
+            ParamId::ReverbDamping => "Reverb Damping",
+            ParamId::ReverbMix => "Reverb Mix",
+            ParamId::DistortionDrive => "Distortion Drive",
+            ParamId::DistortionTone => "Distortion Tone",
+            ParamId::DistortionOutput => "Distortion Output",
+            ParamId::BitcrusherBits => "Bitcrusher Bits",
+            ParamId::BitcrusherRate => "Bitcrusher Rate",
+            ParamId::BitcrusherMix => "Bitcrusher Mix",
+            ParamId::PhaserStages => "Phaser Stages",
+            ParamId::PhaserRate => "Phaser Rate",
+            ParamId::PhaserDepth => "Phaser Depth",
+            ParamId::PhaserFeedback => "Phaser Feedback",
+            ParamId::PhaserMix => "Phaser Mix",
+            ParamId::FlangerRate => "Flanger Rate",
+            ParamId::FlangerDepth => "Flanger Depth",
+            ParamId::FlangerFeedback => "Flanger Feedback",
+            ParamId::FlangerMix => "Flanger Mix",
+            ParamId::DelayTime => "Delay Time",
+            ParamId::DelayFeedback => "Delay Feedback",
+            ParamId::DelayMix => "Delay Mix",
+            ParamId::MasterVolume => "Master Volume",
+            ParamId::MasterPan => "Master Pan",
+            ParamId::MasterTune => "A4 Tuning",
+            ParamId::FineTune => "Fine Tune",
+            ParamId::Transpose => "Transpose",
+            ParamId::CompressorThreshold => "Compressor Threshold",
+            ParamId::CompressorRatio => "Compressor Ratio",
+            ParamId::CompressorAttack => "Compressor Attack",
+            ParamId::CompressorRelease => "Compressor Release",
+            ParamId::CompressorMakeup => "Compressor Makeup",
+            ParamId::Macro(0) => "Macro 1",
+            ParamId::Macro(1) => "Macro 2",
+            ParamId::Macro(2) => "Macro 3",
+            ParamId::Macro(_) => "Macro 4",
+        }
+    }
+
+    /// 0.0〜1.0 の値をパラメータの範囲に変換して設定する（範囲とカーブはGUIのスライダーに合わせる）
+    pub fn apply(&self, params: &SynthParams, normalized: f32) {
+        let x = normalized.clamp(0.0, 1.0);
+        // 周波数は指数、時間は2乗のカーブにしてツマミの下側を細かく操作できるようにする
+        let exp = |min: f32, max: f32| min * (max / min).powf(x);
+        let linear = |min: f32, max: f32| min + (max - min) * x;
+        let time = |max: f32| max * x * x;
+
+        match self {
+            ParamId::UnisonDetune => params.unison.set_detune(linear(0.0, 100.0)),
+            ParamId::UnisonVoices => params.unison.set_voices(linear(1.0, MAX_UNISON_VOICES as f32).round() as u8),
+            ParamId::UnisonCoarse => params.unison.set_detune_coarse(linear(0.0, MAX_UNISON_COARSE as f32).round() as u8),
+            ParamId::UnisonWidth => params.unison.set_width(x),
+            ParamId::UnisonBlend => params.unison.set_blend(x),
+            ParamId::UnisonTaper => params.unison.set_taper(x),
+            ParamId::OscBMix => {
+                params.osc_b.set_mix(x);
+                // ミックスの時はミキサーのAとBの音量に振り分ける
+                let is_mix = params.osc_b.get_settings().lock().is_ok_and(|settings| settings.routing == OscRouting::Mix);
+                if is_mix {
+                    params.mixer.set_osc_a_level(1.0 - x);
+                    params.mixer.set_osc_b_level(x);
+                }
+            }
+            ParamId::OscBOctave => params.osc_b.set_octave(linear(-3.0, 3.0).round() as i32),
+            ParamId::OscBSemitone => params.osc_b.set_semitone(linear(-12.0, 12.0).round() as i32),
+            ParamId::OscBFine => params.osc_b.set_fine(linear(-100.0, 100.0)),
+            ParamId::PulseWidth => params.oscillator.set_pulse_width(linear(MIN_PULSE_WIDTH, MAX_PULSE_WIDTH)),
+            ParamId::FmRatio => params.osc_b.set_fm_ratio(exp(0.25, 16.0)),
+            ParamId::FmIndex => params.osc_b.set_fm_index(linear(0.0, MAX_FM_INDEX)),
+            ParamId::Drift => params.drift.set_amount(x),
+            ParamId::OscALevel => params.mixer.set_osc_a_level(x),
+            ParamId::OscAPan => params.mixer.set_osc_a_pan(linear(-1.0, 1.0)),
+            ParamId::OscBLevel => params.mixer.set_osc_b_level(x),
+            ParamId::OscBPan => params.mixer.set_osc_b_pan(linear(-1.0, 1.0)),
+            ParamId::SubLevel => params.sub_osc.set_level(x),
+            ParamId::SubPan => params.mixer.set_sub_pan(linear(-1.0, 1.0)),
+            ParamId::NoiseLevel => params.noise.set_level(x),
+            ParamId::NoisePan => params.mixer.set_noise_pan(linear(-1.0, 1.0)),
+            ParamId::FilterCutoff => params.filter.set_cutoff(exp(20.0, 20000.0)),
+            ParamId::FilterResonance => params.filter.set_resonance(x),
+            ParamId::FilterKeyTrack => params.filter.set_key_track(linear(0.0, MAX_KEY_TRACK)),
+            ParamId::FilterEnvAmount => params.filter.set_env_amount(linear(-1.0, 1.0)),
+            ParamId::AmpDelay => params.envelope.set_delay(EnvelopeTarget::Amp, time(5.0)),
+            ParamId::AmpAttack => params.envelope.set_attack(EnvelopeTarget::Amp, time(5.0)),
+            ParamId::AmpHold => params.envelope.set_hold(EnvelopeTarget::Amp, time(5.0)),
+            ParamId::AmpDecay => params.envelope.set_decay(EnvelopeTarget::Amp, time(5.0)),
+            ParamId::AmpSustain => params.envelope.set_sustain(EnvelopeTarget::Amp, x),
+            ParamId::AmpRelease => params.envelope.set_release(EnvelopeTarget::Amp, time(10.0)),
+            ParamId::FilterEnvDelay => params.envelope.set_delay(EnvelopeTarget::Filter, time(5.0)),
+            ParamId::FilterEnvAttack => params.envelope.set_attack(EnvelopeTarget::Filter, time(5.0)),
+            ParamId::FilterEnvHold => params.envelope.set_hold(EnvelopeTarget::Filter, time(5.0)),
+            ParamId::FilterEnvDecay => params.envelope.set_decay(EnvelopeTarget::Filter, time(5.0)),
+            ParamId::FilterEnvSustain => params.envelope.set_sustain(EnvelopeTarget::Filter, x),
+            ParamId::FilterEnvRelease => params.envelope.set_release(EnvelopeTarget::Filter, time(10.0)),
+            ParamId::ModEnvDelay => params.envelope.set_delay(EnvelopeTarget::Modulator, time(5.0)),
+            ParamId::ModEnvAttack => params.envelope.set_attack(EnvelopeTarget::Modulator, time(5.0)),
+            ParamId::ModEnvHold => params.envelope.set_hold(EnvelopeTarget::Modulator, time(5.0)),
+            ParamId::ModEnvDecay => params.envelope.set_decay(EnvelopeTarget::Modulator, time(5.0)),
+            ParamId::ModEnvSustain => params.envelope.set_sustain(EnvelopeTarget::Modulator, x),
+            ParamId::ModEnvRelease => params.envelope.set_release(EnvelopeTarget::Modulator, time(10.0)),
+            ParamId::VelocityAmp => params.velocity.set_sensitivity(x),
+            ParamId::VelocityAttack => params.velocity.set_attack_sensitivity(x),
+            ParamId::VelocityRelease => params.velocity.set_release_sensitivity(x),
+            ParamId::LfoRate => params.lfo.set_rate(exp(0.01, 20.0)),
+            ParamId::LfoDepth => params.lfo.set_depth(x),
+            ParamId::LfoSlew => params.lfo.set_slew(x),
+            ParamId::LfoPhaseOffset => params.lfo.set_phase_offset(x),
+            ParamId::ModWheelVibrato => params.lfo.set_mod_wheel_vibrato(x),
+            ParamId::GlideTime => params.voice.set_glide_time(time(2.0)),
+            ParamId::Polyphony => params.voice.set_polyphony(linear(1.0, MAX_VOICES as f32).round() as u8),
+            ParamId::BendRange => params.pitch_bend.set_range(linear(0.0, MAX_BEND_RANGE).round()),
+            ParamId::MpeBendRange => params.voice.set_mpe_bend_range(linear(1.0, MAX_MPE_BEND_RANGE)),
+            ParamId::Tempo => params.transport.set_bpm(linear(MIN_BPM, MAX_BPM)),
+            ParamId::Swing => params.transport.set_swing(linear(MIN_SWING, MAX_SWING)),
+            ParamId::ArpRate => params.arp.set_rate(exp(0.5, 20.0)),
+            ParamId::ArpGate => params.arp.set_gate(linear(0.05, 1.0)),
+            ParamId::ArpOctaves => params.arp.set_octaves(linear(1.0, MAX_ARP_OCTAVES as f32).round() as u8),
+            ParamId::ArpHumanizeTime => params.arp.set_humanize_time(linear(0.0, MAX_HUMANIZE_TIME)),
+            ParamId::ArpHumanizeVelocity => params.arp.set_humanize_velocity(x),
+            ParamId::StrumTime => params.strum.set_time(linear(0.0, MAX_STRUM_TIME)),
+            ParamId::ChorusVoices => params.chorus.set_voices(linear(2.0, MAX_CHORUS_VOICES as f32).round() as u8),
+            ParamId::ChorusRate => params.chorus.set_rate(exp(0.05, 5.0)),
+            ParamId::ChorusDepth => params.chorus.set_depth(x),
+            ParamId::ChorusMix => params.chorus.set_mix(x),
+            ParamId::ReverbRoomSize => params.reverb.set_room_size(x),
+            ParamId::ReverbDamping => params.reverb.set_damping(x),
+            ParamId::ReverbMix => params.reverb.set_mix(x),
+            ParamId::DistortionDrive => params.distortion.set_drive(linear(0.0, 40.0)),
+            ParamId::DistortionTone => params.distortion.set_tone(x),
+            ParamId::DistortionOutput => params.distortion.set_output(linear(-24.0, 12.0)),
+            ParamId::BitcrusherBits => params.bitcrusher.set_bits(linear(MIN_BITS as f32, MAX_BITS as f32).round() as u8),
+            ParamId::BitcrusherRate => params.bitcrusher.set_rate(exp(MIN_CRUSH_RATE, MAX_CRUSH_RATE)),
+            ParamId::BitcrusherMix => params.bitcrusher.set_mix(x),
+            ParamId::PhaserStages => params.phaser.set_stages(linear(MIN_PHASER_STAGES as f32, MAX_PHASER_STAGES as f32).round() as u8),
+            ParamId::PhaserRate => params.phaser.set_rate(exp(0.05, 5.0)),
+            ParamId::PhaserDepth => params.phaser.set_depth(x),
+            ParamId::PhaserFeedback => params.phaser.set_feedback(linear(-0.9, 0.9)),
+            ParamId::PhaserMix => params.phaser.set_mix(x),
+            ParamId::FlangerRate => params.flanger.set_rate(exp(0.05, 5.0)),
+            ParamId::FlangerDepth => params.flanger.set_depth(x),
+            ParamId::FlangerFeedback => params.flanger.set_feedback(linear(-0.95, 0.95)),
+            ParamId::FlangerMix => params.flanger.set_mix(x),
+            ParamId::DelayTime => params.delay.set_time(exp(MIN_DELAY_TIME, MAX_DELAY_TIME)),
+            ParamId::DelayFeedback => params.delay.set_feedback(linear(0.0, 0.95)),
+            ParamId::DelayMix => params.delay.set_mix(x),
+            ParamId::MasterVolume => params.master.set_gain_db(linear(-60.0, 12.0)),
+            ParamId::MasterPan => params.master.set_pan(linear(-1.0, 1.0)),
+            ParamId::MasterTune => params.tuning.set_a4(linear(MIN_A4, MAX_A4)),
+            ParamId::FineTune => params.tuning.set_fine(linear(-MAX_FINE_TUNE, MAX_FINE_TUNE)),
+            ParamId::Transpose => params.transpose.set_semitones(linear(-MAX_TRANSPOSE as f32, MAX_TRANSPOSE as f32).round() as i32),
+            ParamId::CompressorThreshold => params.compressor.set_threshold(linear(-60.0, 0.0)),
+            ParamId::CompressorRatio => params.compressor.set_ratio(exp(1.0, 20.0)),
+            ParamId::CompressorAttack => params.compressor.set_attack(exp(0.1, 100.0)),
+            ParamId::CompressorRelease => params.compressor.set_release(exp(10.0, 1000.0)),
+            ParamId::CompressorMakeup => params.compressor.set_makeup(linear(0.0, 24.0)),
+            ParamId::Macro(index) => params.macros.set_value((*index as usize).min(MACRO_COUNT - 1), x, params),
+        }
+    }
+}
+
+/// CC番号とパラメータの対応表
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MidiMap {
+    /// （CC番号, パラメータ）の組
+    pub bindings: Vec<(u8, ParamId)>,
+}
+
+impl MidiMap {
+    /// CCをパラメータに割り当てる（同じCCや同じパラメータへの古い割り当ては外す）
+    pub fn bind(&mut self, controller: u8, param: ParamId) {
+        self.bindings.retain(|&(cc, p)| cc != controller && p != param);
+        self.bindings.push((controller, param));
+    }
+
+    /// パラメータの割り当てを外す
+    pub fn unbind(&mut self, param: ParamId) {
+        self.bindings.retain(|&(_, p)| p != param);
+    }
+
+    /// パラメータに割り当てられているCC番号
+    pub fn controller_for(&self, param: ParamId) -> Option<u8> {
+        self.bindings.iter().find(|&&(_, p)| p == param).map(|&(cc, _)| cc)
+    }
+
+    /// CC番号ごとの割り当て（1つのCCには1つのパラメータしか割り当てない）
+    fn controllers(&self) -> [Option<ParamId>; 128] {
+        let mut controllers = [None; 128];
+        for &(cc, param) in &self.bindings {
+            controllers[cc.min(127) as usize] = Some(param);
+        }
+        controllers
+    }
+}
+
+/// 受け取ったCCをどう処理したか
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CcAction {
+    Learned(ParamId), // MIDIラーンでパラメータに割り当てた
+    Applied,          // 割り当て済みのパラメータに値を反映した
+    Unassigned,       // どのパラメータにも割り当てられていない
+    Busy,             // 他のスレッドが割り当てをロックしていたので何もしていない（後でもう一度渡す）
+}
+
+/// MIDIラーンの状態とCCの割り当てを管理する構造体（GUIとMIDIスレッドで共有）
+///
+/// オーディオスレッドはCC番号ごとの表だけを待たずに引き、ラーンで割り当てたCCの保存はGUIスレッドが行う
+pub struct MidiMapManager {
+    map: Arc<Mutex<MidiMap>>,                        // 保存する割り当て（GUIスレッドが編集する）
+    controllers: Arc<Mutex<[Option<ParamId>; 128]>>, // CC番号ごとの割り当て（オーディオスレッドが引く）
+    learning: Arc<Mutex<Option<ParamId>>>,           // 次に来たCCを割り当てるパラメータ
+    learned: Arc<Mutex<Option<(u8, ParamId)>>>,      // ラーンで割り当てたがまだ保存していないCC
+}
+
+impl MidiMapManager {
+    pub fn new() -> Self {
+        Self {
+            map: Arc::new(Mutex::new(MidiMap::default())),
+            controllers: Arc::new(Mutex::new([None; 128])),
+            learning: Arc::new(Mutex::new(None)),
+            learned: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// MIDIラーンを開始する（次に動かしたCCがこのパラメータに割り当てられる）
+    pub fn start_learn(&self, param: ParamId) {
+        if let Ok(mut learning) = self.learning.lock() {
+            *learning = Some(param);
+        }
+    }
+
+    /// MIDIラーンを中止する
+    pub fn cancel_learn(&self) {
+        if let Ok(mut learning) = self.learning.lock() {
+            *learning = None;
+        }
+    }
+
+    /// MIDIラーン中のパラメータ
+    pub fn learning(&self) -> Option<ParamId> {
+        self.learning.lock().ok().and_then(|learning| *learning)
+    }
+
+    /// パラメータに割り当てられているCC番号
+    pub fn controller_for(&self, param: ParamId) -> Option<u8> {
+        self.map.lock().ok().and_then(|map| map.controller_for(param))
+    }
+
+    /// パラメータの割り当てを外して保存する（GUIスレッドから呼ぶ）
+    pub fn unbind(&self, param: ParamId) -> io::Result<()> {
+        if let Ok(mut map) = self.map.lock() {
+            map.unbind(param);
+        }
+        if let Ok(mut controllers) = self.controllers.lock() {
+            for slot in controllers.iter_mut().filter(|slot| **slot == Some(param)) {
+                *slot = None;
+            }
+        }
+        self.save()
+    }
+
+    /// 受け取ったCCを処理する（オーディオスレッドから呼ぶので、ロックは待たずに試す）
+    ///
+    /// MIDIラーン中ならCCを割り当てる（保存は `save_learned` でGUIスレッドが行う）。そうでなければ割り当て済みのパラメータに値を反映する。
+    pub fn handle_cc(&self, controller: u8, value: u8, params: &SynthParams) -> CcAction {
+        let (Ok(mut learning), Ok(mut controllers)) = (self.learning.try_lock(), self.controllers.try_lock()) else {
+            return CcAction::Busy;
+        };
+        let index = controller.min(127) as usize;
+        if let Some(param) = *learning {
+            let Ok(mut learned) = self.learned.try_lock() else {
+                return CcAction::Busy;
+            };
+            // 同じパラメータへの古い割り当ては外す（同じCCへの割り当ては上書きする）
+            for slot in controllers.iter_mut().filter(|slot| **slot == Some(param)) {
+                *slot = None;
+            }
+            controllers[index] = Some(param);
+            *learned = Some((controller, param));
+            *learning = None;
+            return CcAction::Learned(param);
+        }
+
+        let target = controllers[index];
+        drop(controllers);
+        drop(learning);
+        match target {
+            Some(param) => {
+                param.apply(params, value.min(127) as f32 / 127.0);
+                CcAction::Applied
+            }
+            None => CcAction::Unassigned,
+        }
+    }
+
+    /// MIDIラーンで割り当てたCCを対応表に入れて保存する（GUIスレッドから繰り返し呼ぶ、割り当てがなければ何もしない）
+    pub fn save_learned(&self) -> io::Result<()> {
+        let Some((controller, param)) = self.learned.lock().ok().and_then(|mut learned| learned.take()) else {
+            return Ok(());
+        };
+        if let Ok(mut map) = self.map.lock() {
+            map.bind(controller, param);
+        }
+        self.save()
+    }
+
+    /// 割り当てをファイルから読み込む（ファイルがなければ何もしない）
+    pub fn load(&self) -> io::Result<()> {
+        let path = midi_map_path();
+        if !path.exists() {
+            return Ok(());
+        }
+        let json = fs::read_to_string(path)?;
+        let loaded: MidiMap = serde_json::from_str(&json)?;
+        if let Ok(mut controllers) = self.controllers.lock() {
+            *controllers = loaded.controllers();
+        }
+        if let Ok(mut map) = self.map.lock() {
+            *map = loaded;
+        }
+        Ok(())
+    }
+
+    /// 割り当てをファイルに保存する
+    pub fn save(&self) -> io::Result<()> {
+        let map = self.map.lock().map(|map| map.clone()).unwrap_or_default();
+        let path = midi_map_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&map)?)?;
+        Ok(())
+    }
+}
+
+/// CCの割り当てを保存するファイルのパス
+pub fn midi_map_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("rust_synth")
+        .join("midi_map.json")
+}
@@ -0,0 +1,175 @@
+use crate::logger::LogEvent;
+use crate::midi_map::CcAction;
+use crate::note::NoteHandler;
+use crate::params::SynthParams;
+
+/// 受信したMIDIメッセージをノートやパラメータに反映する（MIDI入力とMIDIファイルのレンダリングで共通）
+///
+/// `log` には記録したいイベントを渡す（オーディオスレッドから呼ばれてもブロックしない口にすること）。
+/// `notes` が `NoteHandler::realtime` のハンドラーで、ノートの状態が他のスレッドにロックされていた時は何もせずに false を返す
+/// （呼び出し側は同じメッセージを後でもう一度渡す）
+pub fn handle_midi_message(message: &[u8], notes: &NoteHandler, params: &SynthParams, log: &mut impl FnMut(LogEvent)) -> bool {
+    if message.is_empty() {
+        return true;
+    }
+    // リアルタイムメッセージとソングポジションはトランスポートへ（外部同期していなければ捨てられる）
+    match message[0] {
+        0xF8 => {
+            params.transport.midi_clock();
+            return true;
+        }
+        0xFA => {
+            log(LogEvent::MidiTransport("start"));
+            params.transport.midi_start();
+            return true;
+        }
+        0xFB => {
+            log(LogEvent::MidiTransport("continue"));
+            params.transport.midi_continue();
+            return true;
+        }
+        0xFC => {
+            log(LogEvent::MidiTransport("stop"));
+            params.transport.midi_stop();
+            return true;
+        }
+        0xF2 if message.len() >= 3 => {
+            params.transport.midi_song_position(message[1] as u16 | (message[2] as u16) << 7);
+            return true;
+        }
+        _ => {}
+    }
+    let channel = message[0] & 0x0F;
+    let Some(voice_settings) = notes.voice_settings() else {
+        return false;
+    };
+    let mpe = voice_settings.mpe;
+    // 選択したチャンネル以外のチャンネルメッセージは無視する
+    // （MPEモードではゾーン全体のチャンネルを使うので絞り込まない）
+    if !mpe && message[0] < 0xF0 && !params.midi_config.accepts(channel) {
+        return true;
+    }
+    // MPEモードではチャンネル2〜16（0始まりで1〜15）をノートごとのチャンネルとして扱う
+    // （チャンネル1はマネージャーチャンネルとして全体に効く）
+    let member_channel = mpe && channel != 0;
+
+    // Channel Pressure メッセージ（0xD0）はデータが1バイトだけ
+    if message.len() >= 2 && message[0] & 0xF0 == 0xD0 {
+        if member_channel {
+            return notes.with_voices(|voices| voices.set_channel_pressure(channel, message[1]));
+        }
+        params.mod_matrix.set_aftertouch(message[1]);
+        return true;
+    }
+
+    // Program Change メッセージ（0xC0）もデータが1バイトだけ（直前のバンクセレクトと合わせてプリセットを選ぶ）
+    if message.len() >= 2 && message[0] & 0xF0 == 0xC0 {
+        let number = params.program.program_change(message[1]);
+        log(LogEvent::ProgramChange {
+            channel,
+            bank: number.bank,
+            program: number.program,
+        });
+        return true;
+    }
+
+    // MIDIメッセージの長さが3バイト以上あることを確認
+    if message.len() >= 3 {
+        let status = message[0];
+        let note = message[1];
+        let velocity = message[2];
+
+        // Note On メッセージ（0x90）の場合
+        if status & 0xF0 == 0x90 && velocity > 0 {
+            let applied = if member_channel {
+                notes.note_on_channel(note, velocity, channel)
+            } else {
+                notes.note_on(note, velocity)
+            };
+            if !applied {
+                return false;
+            }
+            log(LogEvent::NoteOn { channel, note, velocity });
+        }
+        // Note Off メッセージ（0x80）の場合（ノートオフのベロシティでリリース時間を変える）
+        else if status & 0xF0 == 0x80 {
+            if !notes.note_off_velocity(note, velocity) {
+                return false;
+            }
+            log(LogEvent::NoteOff { channel, note });
+        }
+        // Note On with velocity 0 の場合（ノートオフのベロシティを持たない）
+        else if status & 0xF0 == 0x90 {
+            if !notes.note_off(note) {
+                return false;
+            }
+            log(LogEvent::NoteOff { channel, note });
+        }
+        // Polyphonic Aftertouch メッセージ（0xA0）の場合（ノートごとの押し込み）
+        else if status & 0xF0 == 0xA0 {
+            return notes.with_voices(|voices| voices.set_pressure(note, message[2]));
+        }
+        // Pitch Bend メッセージ（0xE0）の場合（データは LSB, MSB の順）
+        else if status & 0xF0 == 0xE0 {
+            if member_channel {
+                return notes.with_voices(|voices| voices.set_channel_bend(channel, message[1], message[2]));
+            } else {
+                params.pitch_bend.set_from_midi(message[1], message[2]);
+            }
+        }
+        // Control Change メッセージ（0xB0）の場合
+        else if status & 0xF0 == 0xB0 {
+            let controller = message[1];
+            let value = message[2];
+            // MPE: CC74 はノートごとのスライド
+            if member_channel && controller == 74 {
+                return notes.with_voices(|voices| voices.set_channel_slide(channel, value));
+            }
+            // CC120: オールサウンドオフ（即座に消音）、CC123: オールノートオフ（リリースする）
+            if controller == 120 {
+                if !notes.all_sound_off() {
+                    return false;
+                }
+                log(LogEvent::AllSoundOff);
+                return true;
+            }
+            if controller == 123 {
+                if !notes.all_notes_off() {
+                    return false;
+                }
+                log(LogEvent::AllNotesOff);
+                return true;
+            }
+            // CC0/CC32: バンクセレクト（次のプログラムチェンジで使う）
+            if controller == 0 {
+                params.program.set_bank_msb(value);
+                return true;
+            }
+            if controller == 32 {
+                params.program.set_bank_lsb(value);
+                return true;
+            }
+            // MIDIラーン中のCCは割り当てに使い、割り当て済みのCCはパラメータに反映する
+            match params.midi_map.handle_cc(controller, value, params) {
+                CcAction::Learned(param) => {
+                    log(LogEvent::MidiLearn { controller, param });
+                    return true;
+                }
+                CcAction::Busy => return false,
+                CcAction::Applied | CcAction::Unassigned => {}
+            }
+            // CC1: モジュレーションホイール
+            if controller == 1 {
+                params.mod_matrix.set_mod_wheel(value);
+            }
+            // CC64: サステインペダル（64以上で踏まれている）
+            else if controller == 64 {
+                if !notes.set_sustain(value >= 64) {
+                    return false;
+                }
+                log(LogEvent::SustainPedal(value >= 64));
+            }
+        }
+    }
+    true
+}
@@ -0,0 +1,111 @@
+use std::sync::{Arc, Mutex};
+
+use ringbuf::{HeapConsumer, HeapProducer, HeapRb};
+use web_time::{Duration, Instant};
+
+/// キューに積めるメッセージ数（溢れた分は捨てる）
+const QUEUE_CAPACITY: usize = 1024;
+
+/// キューに積めるメッセージの最大長（チャンネルメッセージは3バイト以下）
+const MAX_MESSAGE_LEN: usize = 3;
+
+/// 受信時刻つきのMIDIメッセージ（固定サイズでアロケーションしない）
+#[derive(Clone, Copy, Debug)]
+pub struct TimedMidiMessage {
+    /// 受信した時刻
+    pub time: Instant,
+    len: u8,
+    data: [u8; MAX_MESSAGE_LEN],
+}
+
+impl TimedMidiMessage {
+    /// ステータスバイトを含むメッセージ
+    pub fn message(&self) -> &[u8] {
+        &self.data[..self.len as usize]
+    }
+}
+
+/// MIDI入力からオーディオスレッドへメッセージを送るキュー
+///
+/// MIDI入力のコールバックは受信時刻をつけて積むだけにし、
+/// オーディオスレッドが時刻をバッファ内の位置に直してサンプル単位で反映する。
+/// 複数のMIDIポートとストリームの作り直しに備えて、両端を共有できるようにしている
+#[derive(Clone)]
+pub struct MidiQueue {
+    producer: Arc<Mutex<HeapProducer<TimedMidiMessage>>>,
+    consumer: Arc<Mutex<HeapConsumer<TimedMidiMessage>>>,
+}
+
+impl MidiQueue {
+    pub fn new() -> Self {
+        let (producer, consumer) = HeapRb::<TimedMidiMessage>::new(QUEUE_CAPACITY).split();
+        Self {
+            producer: Arc::new(Mutex::new(producer)),
+            consumer: Arc::new(Mutex::new(consumer)),
+        }
+    }
+
+    /// 受信したメッセージを現在時刻で積む
+    ///
+    /// システムエクスクルーシブなど長いメッセージはシンセで使わないので積まない
+    pub fn push(&self, message: &[u8]) {
+        if message.is_empty() || message.len() > MAX_MESSAGE_LEN {
+            return;
+        }
+        let mut data = [0; MAX_MESSAGE_LEN];
+        data[..message.len()].copy_from_slice(message);
+        let event = TimedMidiMessage {
+            time: Instant::now(),
+            len: message.len() as u8,
+            data,
+        };
+        if let Ok(mut producer) = self.producer.lock() {
+            let _ = producer.push(event);
+        }
+    }
+
+    /// オーディオスレッドでメッセージを取り出す口を作る
+    pub fn scheduler(&self) -> MidiScheduler {
+        MidiScheduler {
+            consumer: Arc::clone(&self.consumer),
+            pending: None,
+        }
+    }
+}
+
+/// キューのメッセージをバッファ内の位置に割り当てる（オーディオスレッドが持つ）
+///
+/// バッファの長さ分だけ過去の区間を今回のバッファに対応させるので、
+/// 1バッファ分の遅延と引き換えにメッセージの間隔がバッファサイズに関係なく保たれる
+pub struct MidiScheduler {
+    consumer: Arc<Mutex<HeapConsumer<TimedMidiMessage>>>,
+    pending: Option<TimedMidiMessage>, // まだこのバッファの区間に入らないメッセージ
+}
+
+impl MidiScheduler {
+    /// `now` に始まるバッファで反映するメッセージを1つ取り出し、バッファ内のフレーム位置と一緒に返す
+    ///
+    /// ロックできない場合や、残りが `now` より後に届いたメッセージだけの場合は None を返す（次のバッファで反映する）
+    pub fn next_event(&mut self, now: Instant, frames: usize, sample_rate: f32) -> Option<(usize, TimedMidiMessage)> {
+        let event = match self.pending.take() {
+            Some(event) => event,
+            None => self.consumer.try_lock().ok()?.pop()?,
+        };
+        if event.time > now {
+            self.pending = Some(event);
+            return None;
+        }
+
+        let buffer_duration = Duration::from_secs_f32(frames as f32 / sample_rate.max(1.0));
+        let offset = match now.checked_sub(buffer_duration) {
+            Some(window_start) => (event.time.saturating_duration_since(window_start).as_secs_f32() * sample_rate) as usize,
+            None => 0,
+        };
+        Some((offset.min(frames.saturating_sub(1)), event))
+    }
+
+    /// 取り出したメッセージを反映できなかった時に戻す（次の `next_event` で最初に取り出す）
+    pub fn defer(&mut self, event: TimedMidiMessage) {
+        self.pending = Some(event);
+    }
+}
@@ -0,0 +1,139 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::master::pan_gains;
+use crate::oscillator::{OscBSettings, OscRouting};
+
+/// ミキサーのチャンネル（音源ごとの音量と定位）
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MixerChannel {
+    /// 音量（0.0-1.0、0.0で無効）
+    pub level: f32,
+    /// 定位（-1.0で左、0.0で中央、1.0で右）
+    pub pan: f32,
+}
+
+impl Default for MixerChannel {
+    fn default() -> Self {
+        Self { level: 1.0, pan: 0.0 }
+    }
+}
+
+impl MixerChannel {
+    /// ステレオの音源にかける左右の倍率（バランス方式、中央で左右とも音量のまま）
+    ///
+    /// オシレータAとBはUnisonで左右に広がっているので、片側を絞って広がりを保ったまま寄せる
+    pub fn balance_gains(&self) -> (f32, f32) {
+        let level = self.level.clamp(0.0, 1.0);
+        let pan = self.pan.clamp(-1.0, 1.0);
+        ((1.0 - pan).min(1.0) * level, (1.0 + pan).min(1.0) * level)
+    }
+}
+
+/// 音源ごとのミキサーの設定を表す構造体
+///
+/// サブオシレータとノイズの音量はそれぞれの設定の `level` を使い、ここでは定位だけを持つ
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MixerSettings {
+    /// オシレータAのチャンネル（FM・リングモジュレーション・ハードシンクの時はAとBを組み合わせた音に使う）
+    pub osc_a: MixerChannel,
+    /// オシレータBのチャンネル（ルーティングがミックスの時だけ使う）
+    pub osc_b: MixerChannel,
+    /// サブオシレータの定位（-1.0〜1.0）
+    pub sub_pan: f32,
+    /// ノイズの定位（-1.0〜1.0）
+    pub noise_pan: f32,
+}
+
+impl Default for MixerSettings {
+    fn default() -> Self {
+        Self {
+            osc_a: MixerChannel::default(),
+            // 以前の既定（A↔Bのミックスが0.0）と同じく、Bは鳴らさない
+            osc_b: MixerChannel { level: 0.0, pan: 0.0 },
+            sub_pan: 0.0,
+            noise_pan: 0.0,
+        }
+    }
+}
+
+impl MixerSettings {
+    /// ミキサーのなかった頃のプリセットのために、オシレータBの A↔B のミックス量から作る
+    pub fn legacy(osc_b: &OscBSettings) -> Self {
+        if osc_b.routing != OscRouting::Mix {
+            return Self::default();
+        }
+        let mix = osc_b.mix.clamp(0.0, 1.0);
+        Self {
+            osc_a: MixerChannel { level: 1.0 - mix, pan: 0.0 },
+            osc_b: MixerChannel { level: mix, pan: 0.0 },
+            ..Default::default()
+        }
+    }
+
+    /// サブオシレータにかける左右の倍率（モノラルの音源なので等パワーで振る）
+    pub fn sub_gains(&self) -> (f32, f32) {
+        pan_gains(self.sub_pan)
+    }
+
+    /// ノイズにかける左右の倍率（モノラルの音源なので等パワーで振る）
+    pub fn noise_gains(&self) -> (f32, f32) {
+        pan_gains(self.noise_pan)
+    }
+}
+
+/// ミキサーの設定を管理する構造体
+pub struct MixerManager {
+    settings: Arc<Mutex<MixerSettings>>,
+}
+
+impl MixerManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(MixerSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<MixerSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_osc_a_level(&self, level: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.osc_a.level = level.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_osc_a_pan(&self, pan: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.osc_a.pan = pan.clamp(-1.0, 1.0);
+        }
+    }
+
+    pub fn set_osc_b_level(&self, level: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.osc_b.level = level.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_osc_b_pan(&self, pan: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.osc_b.pan = pan.clamp(-1.0, 1.0);
+        }
+    }
+
+    pub fn set_sub_pan(&self, pan: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.sub_pan = pan.clamp(-1.0, 1.0);
+        }
+    }
+
+    pub fn set_noise_pan(&self, pan: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.noise_pan = pan.clamp(-1.0, 1.0);
+        }
+    }
+}
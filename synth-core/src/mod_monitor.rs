@@ -0,0 +1,103 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// 変調の様子をGUIに表示するパラメータ
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MonitoredParam {
+    FilterCutoff, // フィルターのカットオフ（Hz）
+    UnisonDetune, // Unisonのデチューンの細かい側（セント）
+    PulseWidth,   // 矩形波のパルス幅（デューティ比）
+}
+
+impl MonitoredParam {
+    /// 全てのパラメータ（表示用の配列の並び順）
+    pub const ALL: [Self; 3] = [Self::FilterCutoff, Self::UnisonDetune, Self::PulseWidth];
+
+    /// 配列でのインデックス
+    pub fn index(self) -> usize {
+        match self {
+            Self::FilterCutoff => 0,
+            Self::UnisonDetune => 1,
+            Self::PulseWidth => 2,
+        }
+    }
+}
+
+/// 1つのパラメータの変調を受けた値の範囲と最後の値
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ModRange {
+    pub min: f32,
+    pub max: f32,
+    pub value: f32,
+}
+
+impl ModRange {
+    /// まだ値のない範囲（オーディオスレッドがブロックごとに作り直す）
+    pub fn empty() -> Self {
+        Self {
+            min: f32::INFINITY,
+            max: 0.0,
+            value: 0.0,
+        }
+    }
+
+    /// 値を1つ加える
+    pub fn add(&mut self, value: f32) {
+        let value = value.max(0.0);
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.value = value;
+    }
+
+    /// 値が1つもないかどうか
+    pub fn is_empty(&self) -> bool {
+        self.min > self.max
+    }
+}
+
+/// 1つのパラメータの通知先（f32のビット列）
+struct MonitorSlot {
+    min: AtomicU32,
+    max: AtomicU32,
+    value: AtomicU32,
+}
+
+/// 変調を受けたパラメータの値のモニター（オーディオスレッドがブロックごとに書き、GUIが読む）
+///
+/// 全て0以上の値なので、メーターのピークと同じくビット列の大小で最小値と最大値をまとめられる
+pub struct ModMonitor {
+    slots: [MonitorSlot; MonitoredParam::ALL.len()],
+}
+
+impl ModMonitor {
+    pub fn new() -> Self {
+        Self {
+            slots: std::array::from_fn(|_| MonitorSlot {
+                min: AtomicU32::new(f32::INFINITY.to_bits()),
+                max: AtomicU32::new(0.0f32.to_bits()),
+                value: AtomicU32::new(0.0f32.to_bits()),
+            }),
+        }
+    }
+
+    /// ブロックの中で変調を受けた値の範囲を通知する（オーディオスレッドから呼ぶ）
+    pub fn report(&self, param: MonitoredParam, range: &ModRange) {
+        if range.is_empty() {
+            return;
+        }
+        let slot = &self.slots[param.index()];
+        slot.min.fetch_min(range.min.to_bits(), Ordering::Relaxed);
+        slot.max.fetch_max(range.max.to_bits(), Ordering::Relaxed);
+        slot.value.store(range.value.to_bits(), Ordering::Relaxed);
+    }
+
+    /// 前回呼んでからの範囲と最後の値を取得してリセットする（通知がなければ None、GUIから呼ぶ）
+    pub fn take(&self, param: MonitoredParam) -> Option<ModRange> {
+        let slot = &self.slots[param.index()];
+        let range = ModRange {
+            min: f32::from_bits(slot.min.swap(f32::INFINITY.to_bits(), Ordering::Relaxed)),
+            max: f32::from_bits(slot.max.swap(0.0f32.to_bits(), Ordering::Relaxed)),
+            value: f32::from_bits(slot.value.load(Ordering::Relaxed)),
+        };
+        (!range.is_empty()).then_some(range)
+    }
+}
@@ -0,0 +1,211 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+/// モジュレーションマトリクスのスロット数
+pub const MOD_SLOTS: usize = 8;
+
+/// 量1.0の時のピッチの変調幅（半音）
+pub const MAX_PITCH_SEMITONES: f32 = 12.0;
+
+/// 量1.0の時のカットオフの変調幅（オクターブ）
+pub const MAX_CUTOFF_OCTAVES: f32 = 4.0;
+
+/// 量1.0の時のデチューンの変調幅（セント）
+pub const MAX_DETUNE_CENTS: f32 = 50.0;
+
+/// モジュレーションソースを表す列挙型
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ModSource {
+    None,       // 未使用
+    Lfo,        // LFO（-1.0〜1.0）
+    Envelope,   // 音量エンベロープ（0.0〜1.0）
+    Velocity,   // 最後に弾いたノートのベロシティ（0.0〜1.0）
+    ModWheel,   // モジュレーションホイール CC1（0.0〜1.0）
+    Aftertouch, // チャンネルアフタータッチ（0.0〜1.0）
+    PolyAftertouch, // ノートごとのポリフォニックアフタータッチ（0.0〜1.0、MPEではノートごとのプレッシャー）
+    Slide,          // MPEのノートごとのスライド CC74（0.0〜1.0）
+}
+
+impl Default for ModSource {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+/// モジュレーションの行き先を表す列挙型
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ModDestination {
+    Pitch,        // ピッチ（±12半音）
+    Amplitude,    // 音量
+    FilterCutoff, // フィルターのカットオフ（±4オクターブ）
+    Detune,       // Unisonのデチューン（±50セント）
+}
+
+impl Default for ModDestination {
+    fn default() -> Self {
+        Self::Pitch
+    }
+}
+
+/// ソースから行き先への1本の接続
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModRoute {
+    /// ソース
+    pub source: ModSource,
+    /// 行き先
+    pub destination: ModDestination,
+    /// 量（-1.0〜1.0）
+    pub amount: f32,
+}
+
+/// モジュレーションマトリクスの設定を表す構造体
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModMatrixSettings {
+    /// 接続のスロット
+    pub routes: [ModRoute; MOD_SLOTS],
+}
+
+/// ブロックの先頭での各ソースの値
+#[derive(Clone, Copy, Default)]
+pub struct ModSources {
+    pub lfo: f32,
+    pub envelope: f32,
+    pub velocity: f32,
+    pub mod_wheel: f32,
+    pub aftertouch: f32,
+    pub poly_aftertouch: f32,
+    pub slide: f32,
+}
+
+impl ModSources {
+    fn value(&self, source: ModSource) -> f32 {
+        match source {
+            ModSource::None => 0.0,
+            ModSource::Lfo => self.lfo,
+            ModSource::Envelope => self.envelope,
+            ModSource::Velocity => self.velocity,
+            ModSource::ModWheel => self.mod_wheel,
+            ModSource::Aftertouch => self.aftertouch,
+            ModSource::PolyAftertouch => self.poly_aftertouch,
+            ModSource::Slide => self.slide,
+        }
+    }
+}
+
+/// 行き先ごとに合計した変調量
+#[derive(Clone, Copy, Default)]
+pub struct ModOffsets {
+    /// ピッチ（半音）
+    pub pitch: f32,
+    /// 音量（倍率への加算量）
+    pub amplitude: f32,
+    /// カットオフ（オクターブ）
+    pub cutoff: f32,
+    /// デチューン（セント）
+    pub detune: f32,
+}
+
+impl ModOffsets {
+    /// ピッチの変調を周波数の倍率に変換する
+    pub fn pitch_ratio(&self) -> f32 {
+        2.0f32.powf(self.pitch / 12.0)
+    }
+
+    /// 音量の変調を倍率に変換する
+    pub fn amplitude_gain(&self) -> f32 {
+        (1.0 + self.amplitude).max(0.0)
+    }
+
+    /// カットオフの変調を倍率に変換する
+    pub fn cutoff_ratio(&self) -> f32 {
+        2.0f32.powf(self.cutoff)
+    }
+}
+
+impl ModMatrixSettings {
+    /// ノートごとのソース（ポリフォニックアフタータッチ・スライド）の接続だけを評価する（ボイスごとに呼ぶ）
+    pub fn evaluate_per_note(&self, pressure: f32, slide: f32) -> ModOffsets {
+        self.evaluate(&ModSources {
+            poly_aftertouch: pressure,
+            slide,
+            ..Default::default()
+        })
+    }
+
+    /// 全てのスロットを評価して行き先ごとの変調量を求める（ブロックごとに1回呼ぶ）
+    pub fn evaluate(&self, sources: &ModSources) -> ModOffsets {
+        let mut offsets = ModOffsets::default();
+        for route in self.routes.iter() {
+            if route.source == ModSource::None || route.amount == 0.0 {
+                continue;
+            }
+            let value = sources.value(route.source) * route.amount.clamp(-1.0, 1.0);
+            match route.destination {
+                ModDestination::Pitch => offsets.pitch += value * MAX_PITCH_SEMITONES,
+                ModDestination::Amplitude => offsets.amplitude += value,
+                ModDestination::FilterCutoff => offsets.cutoff += value * MAX_CUTOFF_OCTAVES,
+                ModDestination::Detune => offsets.detune += value * MAX_DETUNE_CENTS,
+            }
+        }
+        offsets
+    }
+}
+
+/// MIDIコントローラーから受け取ったソースの値
+#[derive(Clone, Copy, Default)]
+pub struct ModControllers {
+    pub mod_wheel: f32,
+    pub aftertouch: f32,
+}
+
+/// モジュレーションマトリクスの設定とMIDIコントローラーの値を管理する構造体
+pub struct ModMatrixManager {
+    settings: Arc<Mutex<ModMatrixSettings>>,
+    controllers: Arc<Mutex<ModControllers>>, // MIDIスレッドが書き込み、オーディオスレッドが読む
+}
+
+impl ModMatrixManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(ModMatrixSettings::default())),
+            controllers: Arc::new(Mutex::new(ModControllers::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<ModMatrixSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn get_controllers(&self) -> Arc<Mutex<ModControllers>> {
+        Arc::clone(&self.controllers)
+    }
+
+    /// スロットの接続を設定する
+    pub fn set_route(&self, slot: usize, route: ModRoute) {
+        if let Ok(mut settings) = self.settings.lock()
+            && let Some(target) = settings.routes.get_mut(slot)
+        {
+            *target = ModRoute {
+                amount: route.amount.clamp(-1.0, 1.0),
+                ..route
+            };
+        }
+    }
+
+    /// モジュレーションホイール（CC1）の値を設定する
+    pub fn set_mod_wheel(&self, value: u8) {
+        if let Ok(mut controllers) = self.controllers.lock() {
+            controllers.mod_wheel = value.min(127) as f32 / 127.0;
+        }
+    }
+
+    /// チャンネルアフタータッチの値を設定する
+    pub fn set_aftertouch(&self, value: u8) {
+        if let Ok(mut controllers) = self.controllers.lock() {
+            controllers.aftertouch = value.min(127) as f32 / 127.0;
+        }
+    }
+}
@@ -0,0 +1,404 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::arpeggiator::ArpKeys;
+use crate::chord::{ChordKeys, ChordNotes};
+use crate::lock::lock_or_try;
+use crate::params::SynthParams;
+use crate::scale_lock::ScaleLockSettings;
+use crate::strum::StrumScheduler;
+use crate::tuning::{CurrentTuning, Tuning};
+use crate::velocity::VelocitySettings;
+use crate::voice::{VoiceAllocator, VoiceMode, VoiceSettings};
+use crate::zone::ZoneSettings;
+
+/// 押されているノートとサステインペダルの状態
+///
+/// オーディオスレッドのMIDI処理からも書き換えるので、作成時に全ての鍵盤の分を確保してアロケーションしない
+struct NoteState {
+    held: Vec<u8>,        // 押されているノート（押した順、ペダルで保持中のものを含む）
+    sustained: Vec<u8>,   // 鍵盤は離されたがペダルで保持されているノート
+    sustain_pedal: bool,  // サステインペダル（CC64）が踏まれているか
+    velocities: [u8; 128], // ノートごとの最後のベロシティ
+    sounding: [ChordNotes; 128], // 鍵盤ごとに鳴らしているノート（押した時のトランスポーズ・コード・スケールロックを適用したもの）
+}
+
+impl Default for NoteState {
+    fn default() -> Self {
+        Self {
+            held: Vec::with_capacity(128),
+            sustained: Vec::with_capacity(128),
+            sustain_pedal: false,
+            velocities: [0; 128],
+            sounding: [ChordNotes::default(); 128],
+        }
+    }
+}
+
+/// ノートの処理中にロックしておく、ノートとペダルの状態・ボイスの割り当て・演奏の設定
+///
+/// 処理の最初に全てをロックしてから書き換えるので、ロックが取れずに後回しにする時は何も変わっていない
+struct Locked<'a> {
+    state: MutexGuard<'a, NoteState>,
+    voices: VoiceAllocator<'a>,
+    tuning: CurrentTuning<'a>,
+    velocity: VelocitySettings,
+    zones: MutexGuard<'a, ZoneSettings>,
+    chord: ChordKeys<'a>,
+    scale_lock: ScaleLockSettings,
+    arp: ArpKeys<'a>,
+    strum: StrumScheduler<'a>,
+}
+
+/// ノートオン/オフを処理する共通ロジック（MIDIとPCキーボードの両方から使う）
+///
+/// オーディオスレッドでは `realtime` で作ったハンドラーを使う。ロックを待たずに試し、
+/// 他のスレッドが使っていれば何もせずに false を返すので、呼び出し側はメッセージを次のバッファで処理し直す
+#[derive(Clone)]
+pub struct NoteHandler {
+    current_freq: Arc<Mutex<f32>>, // 最後に鳴らしたノートの周波数（表示用）
+    params: SynthParams,           // ボイス・エンベロープ・ベロシティなどのパラメータ
+    state: Arc<Mutex<NoteState>>,  // ノートとペダルの状態
+    realtime: bool,                // ロックを待たない（オーディオスレッド用）
+}
+
+impl NoteHandler {
+    pub fn new(current_freq: Arc<Mutex<f32>>, params: SynthParams) -> Self {
+        Self {
+            current_freq,
+            params,
+            state: Arc::new(Mutex::new(NoteState::default())),
+            realtime: false,
+        }
+    }
+
+    /// 同じノートの状態を共有し、ロックを待たないハンドラー（オーディオスレッドのMIDI処理で使う）
+    pub fn realtime(&self) -> Self {
+        Self {
+            realtime: true,
+            ..self.clone()
+        }
+    }
+
+    /// ノートオン：アルペジエーターが有効なら鍵盤として渡し、そうでなければ発音する
+    ///
+    /// トランスポーズ・コード・スケールロックは押した時点の値で適用し、離す時は同じノートを止める（押している間に変えても音が途切れない）。
+    /// コードモードでは1つの鍵盤でコードの全ての音を鳴らす（スケールロックはコードの各音に適用するので、スケールに沿ったコードになる）。
+    /// ロックが取れなかった時は false を返す
+    pub fn note_on(&self, note: u8, velocity: u8) -> bool {
+        let Some(mut locked) = self.lock() else {
+            return false;
+        };
+        self.key_down(&mut locked, note, velocity);
+        true
+    }
+
+    /// MPE: ノートオンし、発音したボイスをノートのチャンネルに結び付ける（ロックが取れなかった時は false）
+    pub fn note_on_channel(&self, note: u8, velocity: u8, channel: u8) -> bool {
+        let Some(mut locked) = self.lock() else {
+            return false;
+        };
+        self.key_down(&mut locked, note, velocity);
+        locked.voices.assign_channel(note, channel);
+        true
+    }
+
+    /// ノートを発音する：発音モードに従ってボイスを割り当てる（ロックが取れなかった時は false）
+    pub fn start_note(&self, note: u8, velocity: u8) -> bool {
+        let Some(mut locked) = self.lock() else {
+            return false;
+        };
+        self.start_locked(&mut locked, note, velocity);
+        true
+    }
+
+    /// ノートを発音し、`delay` 秒後に鳴り始めるようにする（エンジンがサンプル単位で遅らせる）
+    pub fn start_note_delayed(&self, note: u8, velocity: u8, delay: f32) -> bool {
+        let Some(mut locked) = self.lock() else {
+            return false;
+        };
+        self.start_locked(&mut locked, note, velocity);
+        if delay > 0.0 {
+            locked.voices.set_onset(note.min(127), self.params.strum.frame_after(delay));
+        }
+        true
+    }
+
+    /// ノートオフ：ペダルが踏まれていればペダルを離すまで保留し、そうでなければリリースする
+    pub fn note_off(&self, note: u8) -> bool {
+        self.release_key(note, None)
+    }
+
+    /// ノートオフのベロシティ付きのノートオフ（設定に応じて速く離すほどリリースを短くする）
+    pub fn note_off_velocity(&self, note: u8, velocity: u8) -> bool {
+        self.release_key(note, Some(velocity))
+    }
+
+    /// サステインペダルの状態を設定する（離した時に保留中のノートをリリースする）
+    pub fn set_sustain(&self, down: bool) -> bool {
+        let Some(mut locked) = self.lock() else {
+            return false;
+        };
+        locked.state.sustain_pedal = down;
+        if !down {
+            // 保留中のノートを確保済みのバッファのまま取り出す（ノートは重複しないので128個に収まる）
+            let mut released = [0; 128];
+            let count = locked.state.sustained.len().min(released.len());
+            released[..count].copy_from_slice(&locked.state.sustained[..count]);
+            locked.state.sustained.clear();
+            for &note in &released[..count] {
+                self.key_up(&mut locked, note);
+            }
+        }
+        true
+    }
+
+    /// サステインペダルが踏まれているかどうか
+    pub fn sustain_pedal(&self) -> bool {
+        self.state.lock().map(|state| state.sustain_pedal).unwrap_or(false)
+    }
+
+    /// 全てのノートをリリースする（ペダルで保持中のノートも含む）
+    pub fn all_notes_off(&self) -> bool {
+        let Some(mut locked) = self.lock() else {
+            return false;
+        };
+        locked.state.held.clear();
+        locked.state.sustained.clear();
+        locked.state.sounding.iter_mut().for_each(ChordNotes::clear);
+        locked.arp.clear();
+        locked.voices.release_all();
+        true
+    }
+
+    /// 全ての音を即座に止める（パニック、ペダルの状態も解除する）
+    pub fn all_sound_off(&self) -> bool {
+        let Some(mut locked) = self.lock() else {
+            return false;
+        };
+        locked.state.held.clear();
+        locked.state.sustained.clear();
+        locked.state.sounding.iter_mut().for_each(ChordNotes::clear);
+        locked.state.sustain_pedal = false;
+        locked.arp.clear();
+        locked.voices.clear();
+        true
+    }
+
+    /// 発音中のノート番号を取得する
+    pub fn current_note(&self) -> Option<u8> {
+        self.state.lock().ok().and_then(|state| state.held.last().copied())
+    }
+
+    /// ノートを解放する（ロックが取れなかった時は false）
+    ///
+    /// モノモードでまだ他のノートが押されている場合は、優先順位で選ばれたノートに戻る
+    pub fn stop_note(&self, note: u8) -> bool {
+        let Some(mut locked) = self.lock() else {
+            return false;
+        };
+        self.stop_locked(&mut locked, note);
+        true
+    }
+
+    /// 周波数を直接設定する（最後に割り当てたボイスの周波数も変更する）
+    pub fn set_current_freq(&self, freq: f32) {
+        self.set_freq(freq);
+        if let Some(mut voices) = self.params.voice.lock_voices(!self.realtime) {
+            voices.retune_latest(freq);
+        }
+    }
+
+    /// ボイスを直接操作する（アフタータッチやMPEのチャンネルの値、ロックが取れなかった時は false）
+    pub fn with_voices(&self, update: impl FnOnce(&mut VoiceAllocator)) -> bool {
+        let Some(mut voices) = self.params.voice.lock_voices(!self.realtime) else {
+            return false;
+        };
+        update(&mut voices);
+        true
+    }
+
+    /// 発音モードの設定（ロックが取れなかった時は None）
+    pub fn voice_settings(&self) -> Option<VoiceSettings> {
+        lock_or_try(&self.params.voice.get_settings(), !self.realtime).map(|settings| *settings)
+    }
+
+    /// ノートとペダルの状態、ボイスの割り当て、演奏の設定の順にロックする（`realtime` なら待たずに試す）
+    fn lock(&self) -> Option<Locked<'_>> {
+        let wait = !self.realtime;
+        Some(Locked {
+            state: lock_or_try(&self.state, wait)?,
+            voices: self.params.voice.lock_voices(wait)?,
+            tuning: self.params.tuning.lock(wait)?,
+            velocity: self.params.velocity.snapshot(wait)?,
+            zones: self.params.zones.lock(wait)?,
+            chord: self.params.chord.lock(wait)?,
+            scale_lock: self.params.scale_lock.snapshot(wait)?,
+            arp: self.params.arp.lock_keys(wait)?,
+            strum: self.params.strum.lock(wait)?,
+        })
+    }
+
+    /// 鍵盤が押された時の処理（押した鍵盤から鳴らすノートを決めて発音する）
+    fn key_down(&self, locked: &mut Locked, note: u8, velocity: u8) {
+        let key = note.min(127);
+        let Some(note) = self.params.transpose.apply(key) else {
+            return;
+        };
+        // コードを覚えている間は弾いた音をそのまま鳴らす
+        let chord = if locked.chord.learn_key_down(note) {
+            ChordNotes::single(note)
+        } else {
+            locked.chord.notes(note)
+        };
+        let mut notes = ChordNotes::default();
+        for &note in chord.iter() {
+            notes.push(locked.scale_lock.apply(note));
+        }
+        locked.state.sustained.retain(|n| !notes.contains(n));
+        locked.state.sounding[key as usize] = notes;
+
+        if locked.arp.is_enabled() {
+            for &note in notes.iter() {
+                locked.arp.key_down(note, velocity);
+            }
+            return;
+        }
+        for &note in notes.iter() {
+            self.start_locked(locked, note, velocity);
+        }
+        // ストラム：同時に弾いたノートの鳴り始めを音の高さの順にずらす（モノモードでは1音しか鳴らないのでずらさない）
+        if locked.strum.is_enabled() && !locked.voices.settings().is_mono() {
+            let voices = &mut locked.voices;
+            locked.strum.schedule(&notes, |note, onset| voices.set_onset(note, onset));
+        }
+    }
+
+    /// ノートを発音する：発音モードに従ってボイスを割り当てる
+    fn start_locked(&self, locked: &mut Locked, note: u8, velocity: u8) {
+        let note = note.min(127);
+        let settings = *locked.voices.settings();
+        let state = &mut locked.state;
+        let was_empty = state.held.is_empty();
+        state.held.retain(|&n| n != note);
+        state.held.push(note);
+        state.velocities[note as usize] = velocity;
+        let selected = settings.priority.select(&state.held);
+
+        // モノモードでは優先順位で選ばれたノートだけを鳴らす
+        if settings.is_mono() && selected != Some(note) {
+            return;
+        }
+
+        // MIDIノート番号から周波数を計算（マスターチューニングの基準周波数とファインチューンを使う）
+        let freq = locked.tuning.note_to_freq(note);
+        self.set_freq(freq);
+
+        // ベロシティで音量とアタック時間を決める
+        let (gain, attack_scale) = (locked.velocity.amplitude(velocity), locked.velocity.attack_scale(velocity));
+
+        // ボイスが割り当てられるとオーディオスレッドがそのボイスのエンベロープを開始する
+        // キーボードスプリット・レイヤーでは、ノートが範囲に入るレイヤーごとにボイスを割り当てる
+        let layers = locked.zones.layers_for(note);
+        if settings.is_mono() {
+            // モノモードでは範囲に入る一番番号の小さいレイヤーだけを鳴らす
+            // レガートモードではノートが重なっている間は再トリガーしない
+            let Some(&layer) = layers.first() else {
+                return;
+            };
+            let retrigger = settings.mode == VoiceMode::Mono || was_empty;
+            locked.voices.set_mono(note, layer, freq, velocity, gain, attack_scale, retrigger);
+        } else {
+            for &layer in layers.iter() {
+                locked.voices.allocate(note, layer, freq, velocity, gain, attack_scale);
+            }
+        }
+    }
+
+    /// 鍵盤を離した時の処理（ノートオフのベロシティがあれば、リリースする時にリリース時間に倍率をかける）
+    ///
+    /// 他の鍵盤のコードやスケールロックで同じノートを鳴らしている間は、そのノートは止めない
+    fn release_key(&self, note: u8, release_velocity: Option<u8>) -> bool {
+        let Some(mut locked) = self.lock() else {
+            return false;
+        };
+        let release_scale = release_velocity.map_or(1.0, |velocity| locked.velocity.release_scale(velocity));
+        let arp_enabled = locked.arp.is_enabled();
+        // 押した時に鳴らしたノートを止める（範囲外で鳴らさなかった鍵盤は何もしない）
+        let state = &mut locked.state;
+        let notes = std::mem::take(&mut state.sounding[note.min(127) as usize]);
+        let mut released = ChordNotes::default();
+        for &note in notes.iter() {
+            if state.sounding.iter().any(|sounding| sounding.contains(&note)) {
+                continue;
+            }
+            if state.sustain_pedal && (arp_enabled || state.held.contains(&note)) {
+                if !state.sustained.contains(&note) {
+                    state.sustained.push(note);
+                }
+                continue;
+            }
+            released.push(note);
+        }
+        let still_held = state.sounding.iter().any(|sounding| !sounding.is_empty());
+        // コードを覚えている間は、全ての鍵盤が離された時に押されていた音を覚える
+        locked.chord.learn_key_up(still_held);
+        for &note in released.iter() {
+            locked.voices.set_release_scale(note, release_scale);
+            self.key_up(&mut locked, note);
+        }
+        true
+    }
+
+    /// 鍵盤を離す（アルペジエーターが有効なら鍵盤から外し、そうでなければノートを解放する）
+    fn key_up(&self, locked: &mut Locked, note: u8) {
+        if locked.arp.is_enabled() {
+            locked.arp.key_up(note);
+        } else {
+            self.stop_locked(locked, note);
+        }
+    }
+
+    /// ノートを解放する
+    fn stop_locked(&self, locked: &mut Locked, note: u8) {
+        let settings = *locked.voices.settings();
+        let state = &mut locked.state;
+        if !state.held.contains(&note) {
+            return;
+        }
+        let was_selected = settings.priority.select(&state.held) == Some(note);
+        state.held.retain(|&n| n != note);
+        let next_note = settings
+            .priority
+            .select(&state.held)
+            .map(|n| (n, state.velocities[n as usize]));
+
+        match next_note {
+            // 全てのノートが離されたらボイスをリリースする（エンベロープのリリースが終わるまで鳴らす）
+            None => locked.voices.release_all(),
+            Some((next, velocity)) if settings.is_mono() => {
+                if !was_selected {
+                    return;
+                }
+                let freq = locked.tuning.note_to_freq(next);
+                self.set_freq(freq);
+                // 戻ったノートのベロシティで音量を合わせる（レガートモードではエンベロープはそのまま）
+                let (gain, attack_scale) = (locked.velocity.amplitude(velocity), locked.velocity.attack_scale(velocity));
+                // 戻ったノートがどのレイヤーの範囲にも入らなければ鳴らさない
+                let Some(&layer) = locked.zones.layers_for(next).first() else {
+                    locked.voices.release_all();
+                    return;
+                };
+                locked.voices.set_mono(next, layer, freq, velocity, gain, attack_scale, settings.mode == VoiceMode::Mono);
+            }
+            // ポリモードでは離したノートのボイスだけをリリースする
+            Some(_) => locked.voices.release(note),
+        }
+    }
+
+    /// 表示用の周波数を更新する（表示用なので、GUIが読んでいる間は待たずに諦める）
+    fn set_freq(&self, freq: f32) {
+        if let Ok(mut freq_lock) = self.current_freq.try_lock() {
+            *freq_lock = freq;
+        }
+    }
+}
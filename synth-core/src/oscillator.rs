@@ -0,0 +1,807 @@
+use std::f32::consts::PI;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::bandlimited::{self, TableWaveform};
+use crate::decimator::{self, Decimator, MAX_OVERSAMPLE_RATIO};
+use crate::simd::{F32x8, sin_turns};
+use crate::wavetable::WaveTable;
+
+/// パルス幅の最小値（デューティ比）
+pub const MIN_PULSE_WIDTH: f32 = 0.05;
+/// パルス幅の最大値（デューティ比）
+pub const MAX_PULSE_WIDTH: f32 = 0.95;
+
+/// オシレータの波形タイプを表す列挙型
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Waveform {
+    Sine,    // サイン波
+    Triangle, // 三角波
+    Square,   // 矩形波（パルス幅を変えられる）
+    Sawtooth, // ノコギリ波
+    Custom(WaveTable), // 読み込んだ単一周期のWAV
+}
+
+impl Default for Waveform {
+    fn default() -> Self {
+        Self::Sine
+    }
+}
+
+/// 波形の生成方式を表す列挙型
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum OscillatorMode {
+    Raw,      // 従来の素朴な波形（エイリアスあり）
+    PolyBlep, // PolyBLEP/PolyBLAMPによる帯域制限波形
+    Table,    // オクターブごとに作っておいた帯域制限テーブルを読む（計算が軽い）
+}
+
+impl Default for OscillatorMode {
+    fn default() -> Self {
+        Self::PolyBlep
+    }
+}
+
+/// オシレータの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OscillatorSettings {
+    /// 波形の生成方式
+    pub mode: OscillatorMode,
+    /// オーバーサンプリング倍率（1, 2, 4, 8, 16）
+    pub oversample_ratio: u32,
+    /// ローパスフィルターの係数（0.0-0.5）
+    pub filter_alpha: f32,
+    /// スムージングの強さ（0.0-0.5）
+    pub smoothing_strength: f32,
+    /// 矩形波のパルス幅（デューティ比、0.05-0.95）
+    pub pulse_width: f32,
+}
+
+impl Default for OscillatorSettings {
+    fn default() -> Self {
+        Self {
+            mode: OscillatorMode::PolyBlep,
+            oversample_ratio: 1,
+            filter_alpha: 0.5,
+            smoothing_strength: 0.0,
+            pulse_width: 0.5,
+        }
+    }
+}
+
+impl OscillatorSettings {
+    /// 実際に使うオーバーサンプリング倍率
+    ///
+    /// Tableモードの波形はテーブルの時点で帯域制限してあり折り返しが起きないので、オーバーサンプリングしない
+    pub fn effective_oversample_ratio(&self) -> u32 {
+        if self.mode == OscillatorMode::Table {
+            1
+        } else {
+            decimator::oversample_ratio(self.oversample_ratio)
+        }
+    }
+}
+
+/// 位相を積算して波形を生成するオシレータ（オーディオスレッドがボイスごとに保持する）
+///
+/// 毎サンプル 周波数 / サンプルレート だけ位相を進めるので、周波数を変えても位相が飛ばない
+#[derive(Clone, Copy)]
+pub struct Oscillator {
+    phase: f32,           // 現在の位相（0.0-1.0）
+    decimator: Decimator, // オーバーサンプリングした波形を間引くフィルター
+}
+
+impl Oscillator {
+    pub fn new() -> Self {
+        Self {
+            phase: 0.0,
+            decimator: Decimator::new(),
+        }
+    }
+
+    /// 位相を設定する（発音開始時に呼ぶ）
+    pub fn reset(&mut self, phase: f32) {
+        self.phase = phase.rem_euclid(1.0);
+        self.decimator.reset();
+    }
+
+    /// 1サンプル分の波形を生成して位相を進める
+    ///
+    /// `pulse_width` は矩形波のデューティ比（LFOで変調した値を渡せるように設定とは別に受け取る）
+    pub fn next(
+        &mut self,
+        waveform: &Waveform,
+        frequency: f32,
+        sample_rate: f32,
+        settings: &OscillatorSettings,
+        pulse_width: f32,
+    ) -> f32 {
+        self.next_modulated(waveform, frequency, sample_rate, settings, pulse_width, 0.0)
+    }
+
+    /// 位相をずらして（位相変調して）1サンプル分の波形を生成し、位相を進める
+    ///
+    /// `phase_offset` は周期単位のずれ（1.0で1周期）
+    pub fn next_modulated(
+        &mut self,
+        waveform: &Waveform,
+        frequency: f32,
+        sample_rate: f32,
+        settings: &OscillatorSettings,
+        pulse_width: f32,
+        phase_offset: f32,
+    ) -> f32 {
+        let phase = (self.phase + phase_offset).rem_euclid(1.0);
+        let value = generate_waveform(waveform, frequency, phase, sample_rate, settings, pulse_width, &mut self.decimator);
+        self.advance(frequency, sample_rate);
+        value
+    }
+
+    /// 現在の位相（0.0-1.0）
+    pub fn phase(&self) -> f32 {
+        self.phase
+    }
+
+    /// 位相だけを書き換える（レーンでまとめて進めた位相を書き戻す、間引きフィルターの履歴は消さない）
+    pub(crate) fn set_phase(&mut self, phase: f32) {
+        self.phase = phase;
+    }
+
+    /// 波形を生成せずに位相だけを1サンプル分進める
+    pub fn advance(&mut self, frequency: f32, sample_rate: f32) {
+        self.phase = (self.phase + frequency / sample_rate).rem_euclid(1.0);
+    }
+}
+
+/// 指定された位相の波形を生成する関数（オーバーサンプリング、フィルター、スムージング付き）
+///
+/// `phase` はこのサンプルの先頭の位相（0.0-1.0）。
+/// オーバーサンプリングした波形は `decimator` で帯域制限してから間引く
+fn generate_waveform(
+    waveform: &Waveform,
+    frequency: f32,
+    phase: f32,
+    sample_rate: f32,
+    settings: &OscillatorSettings,
+    pulse_width: f32,
+    decimator: &mut Decimator,
+) -> f32 {
+    let pulse_width = pulse_width.clamp(MIN_PULSE_WIDTH, MAX_PULSE_WIDTH);
+    let ratio = settings.effective_oversample_ratio();
+    // オーバーサンプリング用の時間刻み
+    let dt = 1.0 / (sample_rate * ratio as f32);
+    let mut samples = [0.0; MAX_OVERSAMPLE_RATIO as usize];
+    let mut prev_sample = 0.0;
+
+    // オーバーサンプリングによる波形生成
+    for (i, sample) in samples.iter_mut().take(ratio as usize).enumerate() {
+        let phase = (phase + i as f32 * dt * frequency).rem_euclid(1.0);
+
+        let raw_sample = match settings.mode {
+            OscillatorMode::Raw => generate_raw(waveform, phase, pulse_width),
+            OscillatorMode::PolyBlep => generate_polyblep(waveform, phase, frequency * dt, pulse_width),
+            OscillatorMode::Table => generate_table(waveform, phase, frequency * dt, pulse_width),
+        };
+
+        // フィルターとスムージングを適用
+        let filtered = apply_lowpass_filter(raw_sample, prev_sample, settings.filter_alpha);
+        *sample = apply_smoothing(filtered, settings.smoothing_strength);
+        prev_sample = filtered;
+    }
+
+    if ratio == 1 {
+        return samples[0];
+    }
+    // 出力のナイキスト周波数より上を取り除いてから間引く
+    decimator.process(&mut samples[..ratio as usize])
+}
+
+/// 8つのオシレータをレーンでまとめて生成できる設定かどうか
+///
+/// オーバーサンプリングの間引きフィルターはオシレータごとに状態を持ち、
+/// 読み込んだ波形はテーブルを読むので、どちらも1つずつ生成する
+pub(crate) fn supports_lanes(waveform: &Waveform, settings: &OscillatorSettings) -> bool {
+    settings.effective_oversample_ratio() == 1 && !matches!(waveform, Waveform::Custom(_))
+}
+
+/// 8つのオシレータの1サンプル分の波形をまとめて生成し、位相を進める（`Oscillator::next` のSIMD版）
+///
+/// `phases` は各レーンの位相（0.0-1.0）、`phase_incs` は1サンプルあたりの位相の進み。
+/// `supports_lanes` が true の設定でだけ呼ぶ
+pub(crate) fn next_lanes(
+    phases: &mut F32x8,
+    phase_incs: F32x8,
+    waveform: &Waveform,
+    settings: &OscillatorSettings,
+    pulse_width: f32,
+) -> F32x8 {
+    let pulse_width = F32x8::splat(pulse_width.clamp(MIN_PULSE_WIDTH, MAX_PULSE_WIDTH));
+    let phase = *phases;
+    let raw = match settings.mode {
+        OscillatorMode::Raw => raw_lanes(waveform, phase, pulse_width),
+        OscillatorMode::PolyBlep => polyblep_lanes(waveform, phase, phase_incs, pulse_width),
+        OscillatorMode::Table => phase.zip(phase_incs, |phase, phase_inc| {
+            // テーブルの読み出しはレーンごとに位置が違うので1つずつ読む
+            generate_table(waveform, phase, phase_inc, pulse_width.0[0])
+        }),
+    };
+    *phases = (phase + phase_incs).fract_positive();
+
+    // オーバーサンプリングなしの時の apply_lowpass_filter と apply_smoothing と同じ計算
+    let filtered = raw * (settings.filter_alpha * 2.0);
+    let strength = settings.smoothing_strength * 2.0;
+    let x = filtered.max(F32x8::splat(-1.0)).min(F32x8::splat(1.0));
+    x * (F32x8::splat(1.0) - x.abs() * strength)
+}
+
+/// 素朴な波形をレーンでまとめて生成する（`generate_raw` のSIMD版）
+fn raw_lanes(waveform: &Waveform, phase: F32x8, pulse_width: F32x8) -> F32x8 {
+    let value = match waveform {
+        Waveform::Sine => return sin_turns(phase),
+        Waveform::Triangle => {
+            let x = phase * 2.0 - F32x8::splat(1.0);
+            (x.abs() * 2.0 - F32x8::splat(1.0)).map(f32::signum)
+        }
+        Waveform::Square => phase.lt(pulse_width).select(F32x8::splat(1.0), F32x8::splat(-1.0)),
+        Waveform::Sawtooth => {
+            let x = phase * 2.0 - F32x8::splat(1.0);
+            x - (x.abs() * 2.0 - F32x8::splat(1.0)).map(f32::signum) * 0.5
+        }
+        // 読み込んだ波形は supports_lanes で除いている
+        Waveform::Custom(_) => F32x8::splat(0.0),
+    };
+    value * 0.8
+}
+
+/// 帯域制限された波形をレーンでまとめて生成する（`generate_polyblep` のSIMD版）
+fn polyblep_lanes(waveform: &Waveform, phase: F32x8, phase_inc: F32x8, pulse_width: F32x8) -> F32x8 {
+    let value = match waveform {
+        Waveform::Sine => sin_turns(phase),
+        Waveform::Triangle => {
+            let naive = F32x8::splat(1.0) - (phase - F32x8::splat(0.5)).abs() * 4.0;
+            let half = (phase + F32x8::splat(0.5)).fract_positive();
+            naive + phase_inc * 4.0 * (poly_blamp_lanes(phase, phase_inc) - poly_blamp_lanes(half, phase_inc))
+        }
+        Waveform::Square => {
+            let naive = phase.lt(pulse_width).select(F32x8::splat(1.0), F32x8::splat(-1.0));
+            let falling = (phase + F32x8::splat(1.0) - pulse_width).fract_positive();
+            naive + poly_blep_lanes(phase, phase_inc) - poly_blep_lanes(falling, phase_inc)
+        }
+        Waveform::Sawtooth => phase * 2.0 - F32x8::splat(1.0) - poly_blep_lanes(phase, phase_inc),
+        Waveform::Custom(_) => F32x8::splat(0.0),
+    };
+
+    // ナイキスト周波数を超えるレーンは補正区間が破綻するので無音にする
+    let valid = phase_inc.gt(F32x8::splat(0.0)).and(phase_inc.lt(F32x8::splat(0.5)));
+    valid.select(value * 0.8, F32x8::splat(0.0))
+}
+
+/// PolyBLEPの残差をレーンでまとめて計算する（両側の式を計算してから選ぶ）
+fn poly_blep_lanes(phase: F32x8, phase_inc: F32x8) -> F32x8 {
+    phase.zip(phase_inc, |phase, phase_inc| {
+        let rising = phase / phase_inc;
+        let falling = (phase - 1.0) / phase_inc;
+        let before = rising + rising - rising * rising - 1.0;
+        let after = falling * falling + falling + falling + 1.0;
+        if phase < phase_inc {
+            before
+        } else if phase > 1.0 - phase_inc {
+            after
+        } else {
+            0.0
+        }
+    })
+}
+
+/// PolyBLAMPの残差をレーンでまとめて計算する
+fn poly_blamp_lanes(phase: F32x8, phase_inc: F32x8) -> F32x8 {
+    phase.zip(phase_inc, |phase, phase_inc| {
+        let rising = phase / phase_inc - 1.0;
+        let falling = (phase - 1.0) / phase_inc + 1.0;
+        if phase < phase_inc {
+            -rising * rising * rising / 3.0
+        } else if phase > 1.0 - phase_inc {
+            falling * falling * falling / 3.0
+        } else {
+            0.0
+        }
+    })
+}
+
+/// 素朴な波形を生成する（Rawモード）
+fn generate_raw(waveform: &Waveform, phase: f32, pulse_width: f32) -> f32 {
+    match waveform {
+        Waveform::Sine => {
+            // サイン波の計算
+            (2.0 * PI * phase).sin()
+        }
+        Waveform::Triangle => {
+            // 三角波の計算（より滑らかな実装）
+            let x = phase * 2.0 - 1.0;
+            let smoothed = (x.abs() * 2.0 - 1.0).signum();
+            smoothed * 0.8 // 振幅を少し抑える
+        }
+        Waveform::Square => {
+            // パルス波の計算（位相がパルス幅より前なら+1）
+            let pulse = if phase < pulse_width { 1.0 } else { -1.0 };
+            pulse * 0.8 // 振幅を少し抑える
+        }
+        Waveform::Sawtooth => {
+            // ノコギリ波の計算（より滑らかな実装）
+            let x = phase * 2.0 - 1.0;
+            let smoothed = x - (x.abs() * 2.0 - 1.0).signum() * 0.5;
+            smoothed * 0.8 // 振幅を少し抑える
+        }
+        Waveform::Custom(table) => table.sample(phase) * 0.8,
+    }
+}
+
+/// 帯域制限された波形を生成する（PolyBLEPモード）
+///
+/// `phase_inc` は1サンプルあたりの位相の進み（周波数 / サンプルレート）
+fn generate_polyblep(waveform: &Waveform, phase: f32, phase_inc: f32, pulse_width: f32) -> f32 {
+    // ナイキスト周波数を超える場合は補正区間が破綻するので無音にする
+    if phase_inc <= 0.0 || phase_inc >= 0.5 {
+        return 0.0;
+    }
+
+    let value = match waveform {
+        Waveform::Sine => (2.0 * PI * phase).sin(),
+        Waveform::Triangle => {
+            // 頂点（位相0と0.5）での傾きの不連続をPolyBLAMPで丸める
+            let naive = 1.0 - 4.0 * (phase - 0.5).abs();
+            let half = (phase + 0.5).fract();
+            naive + 4.0 * phase_inc * (poly_blamp(phase, phase_inc) - poly_blamp(half, phase_inc))
+        }
+        Waveform::Square => {
+            // 立ち上がり（位相0）と立ち下がり（位相=パルス幅）の段差をPolyBLEPで補正
+            let naive = if phase < pulse_width { 1.0 } else { -1.0 };
+            let falling = (phase + 1.0 - pulse_width).fract();
+            naive + poly_blep(phase, phase_inc) - poly_blep(falling, phase_inc)
+        }
+        Waveform::Sawtooth => {
+            // 位相0での段差をPolyBLEPで補正
+            let naive = 2.0 * phase - 1.0;
+            naive - poly_blep(phase, phase_inc)
+        }
+        // 任意波形は段差の位置が分からないので補正せずにテーブルを読む
+        Waveform::Custom(table) => table.sample(phase),
+    };
+
+    value * 0.8 // Rawモードと音量を揃える
+}
+
+/// 帯域制限テーブルから波形を読む（Tableモード）
+///
+/// `phase_inc` は1サンプルあたりの位相の進み（周波数 / サンプルレート）
+fn generate_table(waveform: &Waveform, phase: f32, phase_inc: f32, pulse_width: f32) -> f32 {
+    let value = match waveform {
+        Waveform::Sine => bandlimited::sample(TableWaveform::Sine, phase, phase_inc),
+        Waveform::Triangle => bandlimited::sample(TableWaveform::Triangle, phase, phase_inc),
+        Waveform::Square => bandlimited::pulse(phase, phase_inc, pulse_width),
+        Waveform::Sawtooth => bandlimited::sample(TableWaveform::Sawtooth, phase, phase_inc),
+        // 任意波形は倍音が分からないので帯域制限せずにテーブルを読む
+        Waveform::Custom(table) => table.sample(phase),
+    };
+
+    value * 0.8 // Rawモードと音量を揃える
+}
+
+/// PolyBLEPの残差（高さ2の段差を1サンプル幅の多項式で補間する）
+fn poly_blep(phase: f32, phase_inc: f32) -> f32 {
+    if phase < phase_inc {
+        let x = phase / phase_inc;
+        x + x - x * x - 1.0
+    } else if phase > 1.0 - phase_inc {
+        let x = (phase - 1.0) / phase_inc;
+        x * x + x + x + 1.0
+    } else {
+        0.0
+    }
+}
+
+/// PolyBLAMPの残差（PolyBLEPを積分したもの、傾きの不連続を補正する）
+fn poly_blamp(phase: f32, phase_inc: f32) -> f32 {
+    if phase < phase_inc {
+        let x = phase / phase_inc - 1.0;
+        -x * x * x / 3.0
+    } else if phase > 1.0 - phase_inc {
+        let x = (phase - 1.0) / phase_inc + 1.0;
+        x * x * x / 3.0
+    } else {
+        0.0
+    }
+}
+
+/// FMの変調指数の最大値（ラジアン）
+pub const MAX_FM_INDEX: f32 = 10.0;
+
+/// オシレータAとBの組み合わせ方を表す列挙型
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum OscRouting {
+    Mix, // AとBをミックス
+    Fm,      // BでAの位相を変調する（2オペレーターFM）
+    RingMod, // AとBを掛け合わせる（リングモジュレーション）
+    Sync,    // Aが1周するたびにBの位相をリセットする（ハードシンク）
+}
+
+impl Default for OscRouting {
+    fn default() -> Self {
+        Self::Mix
+    }
+}
+
+/// オシレータBの設定を表す構造体
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OscBSettings {
+    /// 波形タイプ
+    pub waveform: Waveform,
+    /// オクターブ（-3〜+3）
+    pub octave: i32,
+    /// 半音単位のデチューン（-12〜+12）
+    pub semitone: i32,
+    /// セント単位のデチューン（-100〜+100）
+    pub fine: f32,
+    /// ハードシンク時のオシレータAとBのミックス（0.0でAのみ、1.0でBのみ。リングモジュレーション時は1.0でA×Bのみ）
+    ///
+    /// ルーティングがミックスの時はミキサーのAとBの音量を使う
+    pub mix: f32,
+    /// AとBの組み合わせ方
+    pub routing: OscRouting,
+    /// FM時のモジュレーター（B）の周波数比（0.25-16）
+    pub fm_ratio: f32,
+    /// FM時の変調指数（0.0-MAX_FM_INDEX、モジュレーターエンベロープをかける前の値）
+    pub fm_index: f32,
+}
+
+impl Default for OscBSettings {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::Sawtooth,
+            octave: 0,
+            semitone: 0,
+            fine: 0.0,
+            mix: 0.0,
+            routing: OscRouting::Mix,
+            fm_ratio: 1.0,
+            fm_index: 2.0,
+        }
+    }
+}
+
+impl OscBSettings {
+    /// オシレータAに対する周波数の倍率を計算する
+    pub fn freq_ratio(&self) -> f32 {
+        let semitones = (self.octave * 12 + self.semitone) as f32 + self.fine / 100.0;
+        2.0f32.powf(semitones / 12.0)
+    }
+}
+
+/// オシレータBの設定を管理する構造体
+pub struct OscBManager {
+    settings: Arc<Mutex<OscBSettings>>,
+}
+
+impl OscBManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(OscBSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<OscBSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_waveform(&self, waveform: Waveform) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.waveform = waveform;
+        }
+    }
+
+    pub fn set_octave(&self, octave: i32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.octave = octave.clamp(-3, 3);
+        }
+    }
+
+    pub fn set_semitone(&self, semitone: i32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.semitone = semitone.clamp(-12, 12);
+        }
+    }
+
+    pub fn set_fine(&self, fine: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.fine = fine.clamp(-100.0, 100.0);
+        }
+    }
+
+    pub fn set_mix(&self, mix: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.mix = mix.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_routing(&self, routing: OscRouting) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.routing = routing;
+        }
+    }
+
+    pub fn set_fm_ratio(&self, ratio: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.fm_ratio = ratio.clamp(0.25, 16.0);
+        }
+    }
+
+    pub fn set_fm_index(&self, index: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.fm_index = index.clamp(0.0, MAX_FM_INDEX);
+        }
+    }
+}
+
+/// サブオシレータの波形を表す列挙型
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum SubWaveform {
+    Sine,   // サイン波
+    Square, // 矩形波
+}
+
+impl Default for SubWaveform {
+    fn default() -> Self {
+        Self::Sine
+    }
+}
+
+/// サブオシレータの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SubOscSettings {
+    /// 波形
+    pub waveform: SubWaveform,
+    /// 何オクターブ下げるか（1-2）
+    pub octave: u8,
+    /// 音量（0.0-1.0、0.0で無効）
+    pub level: f32,
+}
+
+impl Default for SubOscSettings {
+    fn default() -> Self {
+        Self {
+            waveform: SubWaveform::Sine,
+            octave: 1,
+            level: 0.0,
+        }
+    }
+}
+
+impl SubOscSettings {
+    /// ボイスの周波数からサブオシレータの周波数を計算する
+    pub fn freq(&self, base_freq: f32) -> f32 {
+        base_freq / (1 << self.octave.clamp(1, 2)) as f32
+    }
+
+    /// オシレータで鳴らす波形
+    pub fn oscillator_waveform(&self) -> Waveform {
+        match self.waveform {
+            SubWaveform::Sine => Waveform::Sine,
+            SubWaveform::Square => Waveform::Square,
+        }
+    }
+}
+
+/// サブオシレータの設定を管理する構造体
+pub struct SubOscManager {
+    settings: Arc<Mutex<SubOscSettings>>,
+}
+
+impl SubOscManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(SubOscSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<SubOscSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_waveform(&self, waveform: SubWaveform) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.waveform = waveform;
+        }
+    }
+
+    pub fn set_octave(&self, octave: u8) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.octave = octave.clamp(1, 2);
+        }
+    }
+
+    pub fn set_level(&self, level: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.level = level.clamp(0.0, 1.0);
+        }
+    }
+}
+
+/// ノイズの種類を表す列挙型
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum NoiseType {
+    White, // ホワイトノイズ（全帯域で平坦）
+    Pink,  // ピンクノイズ（-3dB/オクターブ）
+    Brown, // ブラウンノイズ（-6dB/オクターブ）
+}
+
+impl Default for NoiseType {
+    fn default() -> Self {
+        Self::White
+    }
+}
+
+/// ノイズ源の設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoiseSettings {
+    /// ノイズの種類
+    pub noise_type: NoiseType,
+    /// ノイズの音量（0.0-1.0、0.0で無効）
+    pub level: f32,
+}
+
+impl Default for NoiseSettings {
+    fn default() -> Self {
+        Self {
+            noise_type: NoiseType::White,
+            level: 0.0,
+        }
+    }
+}
+
+/// ノイズジェネレーター（ボイスごとに乱数とフィルターの状態を持つ）
+pub struct NoiseGenerator {
+    rng_state: u32,
+    pink: [f32; 7], // ピンクノイズ用のフィルターの状態（Paul Kellet の方式）
+    brown: f32,     // ブラウンノイズ用の積分器の状態
+}
+
+impl NoiseGenerator {
+    /// ボイスごとに異なる種を渡して作る（0は使えないので1に置き換える）
+    pub fn new(seed: u32) -> Self {
+        Self {
+            rng_state: seed.wrapping_mul(0x9E37_79B9).max(1),
+            pink: [0.0; 7],
+            brown: 0.0,
+        }
+    }
+
+    /// -1.0〜1.0 のホワイトノイズ（xorshift32）
+    fn white(&mut self) -> f32 {
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 17;
+        self.rng_state ^= self.rng_state << 5;
+        self.rng_state as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+
+    /// 1サンプル分のノイズを生成する
+    pub fn next(&mut self, noise_type: NoiseType) -> f32 {
+        let white = self.white();
+        match noise_type {
+            NoiseType::White => white * 0.8,
+            NoiseType::Pink => {
+                // 6個の1次ローパスを重ねて -3dB/オクターブの傾きを近似する
+                let b = &mut self.pink;
+                b[0] = 0.99886 * b[0] + white * 0.0555179;
+                b[1] = 0.99332 * b[1] + white * 0.0750759;
+                b[2] = 0.96900 * b[2] + white * 0.153852;
+                b[3] = 0.86650 * b[3] + white * 0.3104856;
+                b[4] = 0.55000 * b[4] + white * 0.5329522;
+                b[5] = -0.7616 * b[5] - white * 0.0168980;
+                let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+                b[6] = white * 0.115926;
+                pink * 0.11 * 0.8 // 振幅をホワイトノイズと揃える
+            }
+            NoiseType::Brown => {
+                // わずかに漏れのある積分器（直流がたまらないようにする）
+                self.brown = (self.brown + white * 0.02) * 0.998;
+                (self.brown * 3.5).clamp(-1.0, 1.0) * 0.8
+            }
+        }
+    }
+}
+
+/// ノイズ源の設定を管理する構造体
+pub struct NoiseManager {
+    settings: Arc<Mutex<NoiseSettings>>,
+}
+
+impl NoiseManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(NoiseSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<NoiseSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_noise_type(&self, noise_type: NoiseType) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.noise_type = noise_type;
+        }
+    }
+
+    pub fn set_level(&self, level: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.level = level.clamp(0.0, 1.0);
+        }
+    }
+}
+
+/// オシレータの設定を管理する構造体
+pub struct OscillatorManager {
+    settings: Arc<Mutex<OscillatorSettings>>,
+}
+
+impl OscillatorManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(OscillatorSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<OscillatorSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_mode(&self, mode: OscillatorMode) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.mode = mode;
+        }
+    }
+
+    pub fn set_oversample_ratio(&self, ratio: u32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.oversample_ratio = decimator::oversample_ratio(ratio);
+        }
+    }
+
+    pub fn set_filter_alpha(&self, alpha: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.filter_alpha = alpha.clamp(0.0, 0.5);
+        }
+    }
+
+    pub fn set_smoothing_strength(&self, strength: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.smoothing_strength = strength.clamp(0.0, 0.5);
+        }
+    }
+
+    pub fn set_pulse_width(&self, pulse_width: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.pulse_width = pulse_width.clamp(MIN_PULSE_WIDTH, MAX_PULSE_WIDTH);
+        }
+    }
+}
+
+/// 簡単なローパスフィルター
+fn apply_lowpass_filter(input: f32, prev_output: f32, filter_alpha: f32) -> f32 {
+    // フィルターの効果を強化
+    let alpha = filter_alpha * 2.0; // フィルターの強度を2倍に
+    prev_output + alpha * (input - prev_output)
+}
+
+/// スムージング処理を適用
+fn apply_smoothing(input: f32, smoothing_strength: f32) -> f32 {
+    // スムージングの効果を強化
+    let strength = smoothing_strength * 2.0; // スムージングの強度を2倍に
+    let x = input.clamp(-1.0, 1.0);
+    x * (1.0 - x.abs() * strength)
+}
\ No newline at end of file
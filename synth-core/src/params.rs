@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use crate::arpeggiator::ArpManager;
+use crate::bitcrusher::BitcrusherManager;
+use crate::chord::ChordManager;
+use crate::chorus::ChorusManager;
+use crate::compressor::CompressorManager;
+use crate::delay::DelayManager;
+use crate::distortion::DistortionManager;
+use crate::drift::DriftManager;
+use crate::effects::EffectsManager;
+use crate::envelope::EnvelopeManager;
+use crate::filter::FilterManager;
+use crate::flanger::FlangerManager;
+use crate::lfo::LfoManager;
+use crate::macros::MacroManager;
+use crate::master::MasterManager;
+use crate::meter::MeterManager;
+use crate::midi_config::MidiConfigManager;
+use crate::midi_map::MidiMapManager;
+use crate::mixer::MixerManager;
+use crate::mod_monitor::ModMonitor;
+use crate::modmatrix::ModMatrixManager;
+use crate::oscillator::{NoiseManager, OscBManager, OscillatorManager, SubOscManager};
+use crate::part::PartManager;
+use crate::phaser::PhaserManager;
+use crate::pitch_bend::PitchBendManager;
+use crate::program::ProgramSelect;
+use crate::reverb::ReverbManager;
+use crate::scale_lock::ScaleLockManager;
+use crate::strum::StrumManager;
+use crate::transport::TransportManager;
+use crate::transpose::TransposeManager;
+use crate::tuning::TuningManager;
+use crate::unison::UnisonManager;
+use crate::velocity::VelocityManager;
+use crate::voice::VoiceManager;
+use crate::xy_pad::XyPadManager;
+use crate::zone::ZoneManager;
+
+/// シンセの全パラメータ（GUI・MIDI・オーディオスレッドで共有する）
+#[derive(Clone)]
+pub struct SynthParams {
+    pub unison: Arc<UnisonManager>,         // Unison設定
+    pub oscillator: Arc<OscillatorManager>, // オシレータ設定
+    pub osc_b: Arc<OscBManager>,            // オシレータB設定
+    pub sub_osc: Arc<SubOscManager>,        // サブオシレータ設定
+    pub noise: Arc<NoiseManager>,           // ノイズ源設定
+    pub drift: Arc<DriftManager>,           // アナログVCOの不安定さ（ドリフト）
+    pub mixer: Arc<MixerManager>,           // 音源ごとの音量と定位
+    pub filter: Arc<FilterManager>,         // フィルター設定
+    pub envelope: Arc<EnvelopeManager>,     // エンベロープ
+    pub lfo: Arc<LfoManager>,               // LFO設定
+    pub pitch_bend: Arc<PitchBendManager>,  // ピッチベンド
+    pub transpose: Arc<TransposeManager>,   // 入力ノートのトランスポーズ（オクターブシフトを含む）
+    pub scale_lock: Arc<ScaleLockManager>,  // 入力ノートをスケールの音に寄せるスケールロック
+    pub chord: Arc<ChordManager>,           // 1つのノートでコードを鳴らすコードモード
+    pub strum: Arc<StrumManager>,           // 同時に弾いたノートの鳴り始めをずらすストラム
+    pub tuning: Arc<TuningManager>,         // マスターチューニング（A4の基準周波数とファインチューン）
+    pub master: Arc<MasterManager>,         // マスター音量とリミッター
+    pub compressor: Arc<CompressorManager>, // マスターバスのコンプレッサー
+    pub meter: Arc<MeterManager>,           // 出力レベルと発音状態のメーター
+    pub mod_monitor: Arc<ModMonitor>,       // 変調を受けたパラメータの値のモニター
+    pub velocity: Arc<VelocityManager>,     // ベロシティ感度
+    pub voice: Arc<VoiceManager>,           // 発音モードとボイスの割り当て
+    pub zones: Arc<ZoneManager>,            // キーボードスプリットとレイヤー（鍵盤の範囲ごとの音色）
+    pub reverb: Arc<ReverbManager>,         // リバーブ設定
+    pub chorus: Arc<ChorusManager>,         // コーラス設定
+    pub distortion: Arc<DistortionManager>, // ディストーション設定
+    pub bitcrusher: Arc<BitcrusherManager>, // ビットクラッシャー設定
+    pub phaser: Arc<PhaserManager>,         // フェイザー設定
+    pub flanger: Arc<FlangerManager>,       // フランジャー設定
+    pub delay: Arc<DelayManager>,           // ディレイ設定
+    pub effects: Arc<EffectsManager>,       // エフェクトチェーンの並びとバイパス
+    pub mod_matrix: Arc<ModMatrixManager>,  // モジュレーションマトリクス
+    pub macros: Arc<MacroManager>,          // マクロツマミと割り当て
+    pub xy_pad: Arc<XyPadManager>,          // XYパッドの位置と割り当て
+    pub midi_map: Arc<MidiMapManager>,      // MIDI CCの割り当て（MIDIラーン）
+    pub arp: Arc<ArpManager>,               // アルペジエーター
+    pub transport: Arc<TransportManager>,   // テンポとクロック
+    pub midi_config: Arc<MidiConfigManager>, // MIDI入力の設定（受信チャンネル）
+    pub program: Arc<ProgramSelect>,        // バンクセレクトとプログラムチェンジ
+    pub parts: Arc<PartManager>,            // MIDIチャンネルごとの追加のパート（マルチティンバー）
+}
+
+impl SynthParams {
+    pub fn new() -> Self {
+        Self {
+            unison: Arc::new(UnisonManager::new()),
+            oscillator: Arc::new(OscillatorManager::new()),
+            osc_b: Arc::new(OscBManager::new()),
+            sub_osc: Arc::new(SubOscManager::new()),
+            noise: Arc::new(NoiseManager::new()),
+            drift: Arc::new(DriftManager::new()),
+            mixer: Arc::new(MixerManager::new()),
+            filter: Arc::new(FilterManager::new()),
+            envelope: Arc::new(EnvelopeManager::new()),
+            lfo: Arc::new(LfoManager::new()),
+            pitch_bend: Arc::new(PitchBendManager::new()),
+            transpose: Arc::new(TransposeManager::new()),
+            scale_lock: Arc::new(ScaleLockManager::new()),
+            chord: Arc::new(ChordManager::new()),
+            strum: Arc::new(StrumManager::new()),
+            tuning: Arc::new(TuningManager::new()),
+            master: Arc::new(MasterManager::new()),
+            compressor: Arc::new(CompressorManager::new()),
+            meter: Arc::new(MeterManager::new()),
+            mod_monitor: Arc::new(ModMonitor::new()),
+            velocity: Arc::new(VelocityManager::new()),
+            voice: Arc::new(VoiceManager::new()),
+            zones: Arc::new(ZoneManager::new()),
+            reverb: Arc::new(ReverbManager::new()),
+            chorus: Arc::new(ChorusManager::new()),
+            distortion: Arc::new(DistortionManager::new()),
+            bitcrusher: Arc::new(BitcrusherManager::new()),
+            phaser: Arc::new(PhaserManager::new()),
+            flanger: Arc::new(FlangerManager::new()),
+            delay: Arc::new(DelayManager::new()),
+            effects: Arc::new(EffectsManager::new()),
+            mod_matrix: Arc::new(ModMatrixManager::new()),
+            macros: Arc::new(MacroManager::new()),
+            xy_pad: Arc::new(XyPadManager::new()),
+            midi_map: Arc::new(MidiMapManager::new()),
+            arp: Arc::new(ArpManager::new()),
+            transport: Arc::new(TransportManager::new()),
+            midi_config: Arc::new(MidiConfigManager::new()),
+            program: Arc::new(ProgramSelect::new()),
+            parts: Arc::new(PartManager::new()),
+        }
+    }
+}
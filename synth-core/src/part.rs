@@ -0,0 +1,378 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::SynthEngine;
+use crate::master::pan_gains;
+use crate::note::NoteHandler;
+use crate::params::SynthParams;
+
+/// メインのパートのほかに追加できるパートの数
+pub const MAX_PARTS: usize = 3;
+
+/// パートの音量の上限（1.0で元の音量）
+pub const MAX_PART_VOLUME: f32 = 2.0;
+
+/// 追加のパートの設定（受信するチャンネルと、出力に混ぜる時の音量と定位）
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PartSettings {
+    /// このパートを鳴らすか
+    pub enabled: bool,
+    /// 受信するMIDIチャンネル（0始まり）
+    pub channel: u8,
+    /// 音量（0.0-2.0）
+    pub volume: f32,
+    /// 定位（-1.0: 左、0.0: 中央、1.0: 右）
+    pub pan: f32,
+}
+
+impl Default for PartSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            channel: 1,
+            volume: 1.0,
+            pan: 0.0,
+        }
+    }
+}
+
+impl PartSettings {
+    /// 範囲外の値を収めた設定（読み込んだセッションの値をそのまま使わないため）
+    pub fn clamped(self) -> Self {
+        Self {
+            enabled: self.enabled,
+            channel: self.channel.min(15),
+            volume: self.volume.clamp(0.0, MAX_PART_VOLUME),
+            pan: self.pan.clamp(-1.0, 1.0),
+        }
+    }
+}
+
+/// マルチティンバーの設定（MIDIチャンネルごとに別の音色を鳴らす）
+///
+/// メインのパート（各パネルで編集する音色）はMIDI入力の受信チャンネルに従い、
+/// 追加のパートに割り当てたチャンネルのメッセージは追加のパートだけが受け取る
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MultiTimbralSettings {
+    /// 追加のパート
+    pub parts: [PartSettings; MAX_PARTS],
+}
+
+impl Default for MultiTimbralSettings {
+    fn default() -> Self {
+        // チャンネル2から順に割り当てておく
+        let mut parts = [PartSettings::default(); MAX_PARTS];
+        for (index, part) in parts.iter_mut().enumerate() {
+            part.channel = index as u8 + 1;
+        }
+        Self { parts }
+    }
+}
+
+impl MultiTimbralSettings {
+    /// チャンネルを受信する追加のパート（同じチャンネルのパートが複数あれば番号の小さい方）
+    pub fn part_for(&self, channel: u8) -> Option<usize> {
+        self.parts.iter().position(|part| part.enabled && part.channel == channel)
+    }
+}
+
+/// マルチティンバーの設定を管理する構造体
+///
+/// トランスポーズなどと同じく演奏の設定なので、プリセットには含めない
+pub struct PartManager {
+    settings: Arc<Mutex<MultiTimbralSettings>>,
+}
+
+impl PartManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(MultiTimbralSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<MultiTimbralSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_enabled(&self, index: usize, enabled: bool) {
+        if let Ok(mut settings) = self.settings.lock()
+            && let Some(part) = settings.parts.get_mut(index)
+        {
+            part.enabled = enabled;
+        }
+    }
+
+    pub fn set_channel(&self, index: usize, channel: u8) {
+        if let Ok(mut settings) = self.settings.lock()
+            && let Some(part) = settings.parts.get_mut(index)
+        {
+            part.channel = channel.min(15);
+        }
+    }
+
+    pub fn set_volume(&self, index: usize, volume: f32) {
+        if let Ok(mut settings) = self.settings.lock()
+            && let Some(part) = settings.parts.get_mut(index)
+        {
+            part.volume = volume.clamp(0.0, MAX_PART_VOLUME);
+        }
+    }
+
+    pub fn set_pan(&self, index: usize, pan: f32) {
+        if let Ok(mut settings) = self.settings.lock()
+            && let Some(part) = settings.parts.get_mut(index)
+        {
+            part.pan = pan.clamp(-1.0, 1.0);
+        }
+    }
+
+    /// 全てのパートの設定をまとめて置き換える（セッションの復元に使う）
+    pub fn set_all(&self, parts: [PartSettings; MAX_PARTS]) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.parts = parts.map(PartSettings::clamped);
+        }
+    }
+}
+
+/// 追加のパート（メインのパートとは別の音色とノートの状態を持つ）
+///
+/// テンポとクロックはメインのパートと共有し、テンポ同期のLFOやアルペジエーターの拍を揃える。
+/// チューニング・トランスポーズ・スケールロック・MIDI CCの割り当ても演奏の設定なのでメインのパートと共有し、
+/// 全てのパートが同じ音律と調で鳴るようにする
+#[derive(Clone)]
+pub struct Part {
+    pub params: SynthParams,
+    pub notes: NoteHandler,
+}
+
+impl Part {
+    pub fn new(main: &SynthParams) -> Self {
+        let params = SynthParams {
+            transport: Arc::clone(&main.transport),
+            tuning: Arc::clone(&main.tuning),
+            transpose: Arc::clone(&main.transpose),
+            scale_lock: Arc::clone(&main.scale_lock),
+            midi_map: Arc::clone(&main.midi_map),
+            ..SynthParams::new()
+        };
+        Self {
+            notes: NoteHandler::new(Arc::new(Mutex::new(0.0)), params.clone()),
+            params,
+        }
+    }
+}
+
+/// 追加のパートを生成してメインのパートの出力に混ぜる（オーディオスレッドが持つ）
+pub struct PartMixer {
+    parts: Vec<(Part, SynthEngine)>,
+    settings: Arc<Mutex<MultiTimbralSettings>>,
+    current: MultiTimbralSettings, // 最後に読めた設定（ロックが取れない時に使う）
+    buffer: Vec<f32>,              // パートの出力を一旦書き込むバッファ（作成時に確保しておく）
+}
+
+impl PartMixer {
+    /// `channels` と `max_block_frames` はストリームのチャンネル数と1回のバッファの最大フレーム数
+    pub fn new(parts: Vec<Part>, manager: &PartManager, sample_rate: f32, channels: usize, max_block_frames: usize) -> Self {
+        Self {
+            parts: parts
+                .into_iter()
+                .take(MAX_PARTS)
+                .map(|mut part| {
+                    let engine = SynthEngine::new(part.params.clone(), sample_rate, max_block_frames);
+                    // オーディオスレッドでMIDIを反映するのでロックを待たないハンドラーにする
+                    part.notes = part.notes.realtime();
+                    (part, engine)
+                })
+                .collect(),
+            settings: manager.get_settings(),
+            current: MultiTimbralSettings::default(),
+            buffer: vec![0.0; max_block_frames.max(1) * channels.max(1)],
+        }
+    }
+
+    /// チャンネルメッセージを受け取る追加のパート（割り当てがなければ None でメインのパートが受け取る）
+    pub fn route(&mut self, message: &[u8]) -> Option<&Part> {
+        let status = *message.first()?;
+        if !(0x80..0xF0).contains(&status) {
+            return None;
+        }
+        if let Ok(settings) = self.settings.try_lock() {
+            self.current = *settings;
+        }
+        let index = self.current.part_for(status & 0x0F)?;
+        self.parts.get(index).map(|(part, _)| part)
+    }
+
+    /// 鳴らしているパートを生成し、インターリーブされた `data` に音量と定位をつけて足す
+    pub fn process(&mut self, data: &mut [f32], channels: usize) {
+        let channels = channels.max(1);
+        if let Ok(settings) = self.settings.try_lock() {
+            self.current = *settings;
+        }
+        // 確保したバッファより長いバッファは、確保した長さずつに分けて混ぜる（オーディオスレッドで確保し直さない）
+        let block_len = self.buffer.len() / channels * channels;
+        if block_len == 0 {
+            return;
+        }
+        for block in data.chunks_mut(block_len) {
+            self.mix_block(block, channels);
+        }
+    }
+
+    /// 確保したバッファの長さまでの `data` に、鳴らしているパートを足す
+    fn mix_block(&mut self, data: &mut [f32], channels: usize) {
+        let buffer = &mut self.buffer[..data.len()];
+        for ((_, engine), settings) in self.parts.iter_mut().zip(self.current.parts.iter()) {
+            if !settings.enabled {
+                continue;
+            }
+            engine.process(buffer, channels);
+            let (left, right) = if channels >= 2 { pan_gains(settings.pan) } else { (1.0, 1.0) };
+            for (frame, input) in data.chunks_mut(channels).zip(buffer.chunks(channels)) {
+                for (channel, (sample, value)) in frame.iter_mut().zip(input).enumerate() {
+                    let gain = match channel {
+                        0 => left,
+                        1 => right,
+                        _ => 1.0,
+                    };
+                    *sample += value * gain * settings.volume;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi_message::handle_midi_message;
+
+    const CHANNELS: usize = 2;
+    const FRAMES: usize = 256;
+
+    /// メインのパートと、追加のパートとそのミキサーを作る（ミキサーはオーディオスレッド側）
+    fn mixer_with_parts(main: &SynthParams) -> (Vec<Part>, PartMixer) {
+        let parts: Vec<Part> = (0..MAX_PARTS).map(|_| Part::new(main)).collect();
+        let mixer = PartMixer::new(parts.clone(), &main.parts, 48000.0, CHANNELS, FRAMES);
+        (parts, mixer)
+    }
+
+    /// 無音のバッファに追加のパートを足した出力
+    fn render(mixer: &mut PartMixer, frames: usize) -> Vec<f32> {
+        let mut data = vec![0.0; frames * CHANNELS];
+        mixer.process(&mut data, CHANNELS);
+        data
+    }
+
+    fn peak(samples: impl Iterator<Item = f32>) -> f32 {
+        samples.fold(0.0f32, |peak, sample| peak.max(sample.abs()))
+    }
+
+    /// 有効なパートに割り当てたチャンネルのメッセージだけがそのパートに届く
+    #[test]
+    fn routes_channel_messages_to_parts() {
+        let main = SynthParams::new();
+        main.parts.set_enabled(0, true);
+        main.parts.set_channel(0, 1);
+        main.parts.set_enabled(2, true);
+        main.parts.set_channel(2, 9);
+        let (parts, mut mixer) = mixer_with_parts(&main);
+        let routed = |mixer: &mut PartMixer, message: &[u8]| {
+            mixer
+                .route(message)
+                .map(|part| parts.iter().position(|other| Arc::ptr_eq(&part.params.unison, &other.params.unison)))
+        };
+
+        assert_eq!(routed(&mut mixer, &[0x91, 60, 100]), Some(Some(0)));
+        assert_eq!(routed(&mut mixer, &[0xB9, 1, 64]), Some(Some(2)));
+        // メインの受信チャンネル・無効なパートのチャンネル・システムメッセージはメインのパートが受け取る
+        assert_eq!(routed(&mut mixer, &[0x90, 60, 100]), None);
+        assert_eq!(routed(&mut mixer, &[0x92, 60, 100]), None);
+        assert_eq!(routed(&mut mixer, &[0xF8]), None);
+
+        // 同じチャンネルのパートが複数あれば番号の小さい方が受け取る
+        main.parts.set_enabled(1, true);
+        main.parts.set_channel(1, 9);
+        main.parts.set_channel(2, 1);
+        assert_eq!(routed(&mut mixer, &[0x81, 60, 0]), Some(Some(0)));
+        main.parts.set_enabled(0, false);
+        assert_eq!(routed(&mut mixer, &[0x81, 60, 0]), Some(Some(2)));
+    }
+
+    /// 有効なパートの音だけを音量と定位をつけて足す
+    #[test]
+    fn mixes_enabled_parts_with_volume_and_pan() {
+        let main = SynthParams::new();
+        let (parts, mut mixer) = mixer_with_parts(&main);
+        parts[0].notes.note_on(60, 100);
+
+        // 無効なパートはノートを弾いていても混ぜない
+        assert_eq!(peak(render(&mut mixer, FRAMES).into_iter()), 0.0);
+
+        main.parts.set_enabled(0, true);
+        let centered = render(&mut mixer, FRAMES);
+        assert!(peak(centered.iter().copied()) > 0.0);
+
+        // 左いっぱいに振ると右には出ない
+        main.parts.set_pan(0, -1.0);
+        let left = render(&mut mixer, FRAMES);
+        assert!(peak(left.iter().step_by(CHANNELS).copied()) > 0.0);
+        assert!(peak(left.iter().skip(1).step_by(CHANNELS).copied()) < 1e-6);
+
+        // 音量が0なら何も足さない
+        main.parts.set_volume(0, 0.0);
+        assert_eq!(peak(render(&mut mixer, FRAMES).into_iter()), 0.0);
+    }
+
+    /// 確保したバッファより長いバッファも分けて全体に混ぜる
+    #[test]
+    fn mixes_blocks_longer_than_the_buffer() {
+        let main = SynthParams::new();
+        let (parts, mut mixer) = mixer_with_parts(&main);
+        main.parts.set_enabled(0, true);
+        parts[0].notes.note_on(60, 100);
+
+        let data = render(&mut mixer, FRAMES * 3);
+        assert!(peak(data[FRAMES * 2 * CHANNELS..].iter().copied()) > 0.0);
+    }
+
+    /// オーディオスレッドのパートは、ボイスや演奏の設定を他のスレッドがロックしている間はメッセージを反映せずに後回しにする
+    #[test]
+    fn defers_messages_while_voices_are_locked() {
+        let main = SynthParams::new();
+        main.parts.set_enabled(0, true);
+        main.parts.set_channel(0, 1);
+        let (parts, mut mixer) = mixer_with_parts(&main);
+        let part = mixer.route(&[0x91, 60, 100]).unwrap();
+
+        let voices = parts[0].params.voice.lock_voices(true);
+        assert!(!handle_midi_message(&[0x91, 60, 100], &part.notes, &part.params, &mut |_| {}));
+        assert_eq!(part.notes.current_note(), None);
+        drop(voices);
+
+        let tuning = main.tuning.get_settings();
+        let tuning = tuning.lock().unwrap();
+        assert!(!handle_midi_message(&[0x91, 60, 100], &part.notes, &part.params, &mut |_| {}));
+        assert_eq!(part.notes.current_note(), None);
+        drop(tuning);
+
+        assert!(handle_midi_message(&[0x91, 60, 100], &part.notes, &part.params, &mut |_| {}));
+        assert_eq!(part.notes.current_note(), Some(60));
+    }
+
+    /// 追加のパートはチューニング・トランスポーズ・スケールロック・MIDI CCの割り当てをメインのパートと共有する
+    #[test]
+    fn parts_share_performance_settings() {
+        let main = SynthParams::new();
+        let part = Part::new(&main);
+
+        assert!(Arc::ptr_eq(&part.params.tuning, &main.tuning));
+        assert!(Arc::ptr_eq(&part.params.transpose, &main.transpose));
+        assert!(Arc::ptr_eq(&part.params.scale_lock, &main.scale_lock));
+        assert!(Arc::ptr_eq(&part.params.midi_map, &main.midi_map));
+        assert!(!Arc::ptr_eq(&part.params.unison, &main.unison));
+    }
+}
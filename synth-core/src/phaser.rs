@@ -0,0 +1,138 @@
+use std::f32::consts::{PI, TAU};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::denormal::flush_denormal;
+
+/// オールパスの段数の範囲
+pub const MIN_PHASER_STAGES: u8 = 4;
+pub const MAX_PHASER_STAGES: u8 = 8;
+
+/// スイープする周波数の範囲（Hz、深さ1.0で全域を動く）
+const MIN_SWEEP_FREQ: f32 = 200.0;
+const MAX_SWEEP_FREQ: f32 = 6000.0;
+
+/// フェイザーの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PhaserSettings {
+    /// オールパスの段数（4-8、多いほどノッチが増える）
+    pub stages: u8,
+    /// スイープの速さ（Hz、0.05-5.0）
+    pub rate: f32,
+    /// スイープの深さ（0.0-1.0）
+    pub depth: f32,
+    /// フィードバック（-0.9〜0.9、大きいほどノッチの間が強調される）
+    pub feedback: f32,
+    /// ウェットの割合（0.0-1.0、0.5で一番深いノッチになる）
+    pub mix: f32,
+}
+
+impl Default for PhaserSettings {
+    fn default() -> Self {
+        Self {
+            stages: 4,
+            rate: 0.5,
+            depth: 0.7,
+            feedback: 0.5,
+            mix: 0.5,
+        }
+    }
+}
+
+/// 1次オールパスを重ねてLFOでスイープするフェイザー（オーディオスレッドが所有する）
+pub struct Phaser {
+    states: [f32; MAX_PHASER_STAGES as usize], // 各段の遅延状態
+    last_output: f32,                          // フィードバック用の最後の出力
+    phase: f32,                                // LFOの位相（0.0-1.0）
+    sample_rate: f32,
+}
+
+impl Phaser {
+    /// `phase_offset` はLFOの初期位相（0.0-1.0、左右で変えると広がりが出る）
+    pub fn new(sample_rate: f32, phase_offset: f32) -> Self {
+        Self {
+            states: [0.0; MAX_PHASER_STAGES as usize],
+            last_output: 0.0,
+            phase: phase_offset.fract(),
+            sample_rate,
+        }
+    }
+
+    /// オールパスの状態をクリアする
+    pub fn reset(&mut self) {
+        self.states = [0.0; MAX_PHASER_STAGES as usize];
+        self.last_output = 0.0;
+    }
+
+    /// 1サンプル処理する
+    pub fn process(&mut self, input: f32, settings: &PhaserSettings) -> f32 {
+        // LFOでオールパスの折れ点周波数を指数的に動かす
+        let lfo = 0.5 + 0.5 * (TAU * self.phase).sin();
+        let sweep = settings.depth.clamp(0.0, 1.0) * lfo;
+        let freq = (MIN_SWEEP_FREQ * (MAX_SWEEP_FREQ / MIN_SWEEP_FREQ).powf(sweep)).min(self.sample_rate * 0.45);
+        let t = (PI * freq / self.sample_rate).tan();
+        let coeff = (t - 1.0) / (t + 1.0);
+
+        let stages = settings.stages.clamp(MIN_PHASER_STAGES, MAX_PHASER_STAGES) as usize;
+        let mut wet = input + self.last_output * settings.feedback.clamp(-0.9, 0.9);
+        for state in self.states.iter_mut().take(stages) {
+            let output = coeff * wet + *state;
+            *state = flush_denormal(wet - coeff * output);
+            wet = output;
+        }
+        self.last_output = flush_denormal(wet);
+        self.phase = (self.phase + settings.rate.max(0.0) / self.sample_rate).fract();
+
+        let mix = settings.mix.clamp(0.0, 1.0);
+        input * (1.0 - mix) + wet * mix
+    }
+}
+
+/// フェイザーの設定を管理する構造体
+pub struct PhaserManager {
+    settings: Arc<Mutex<PhaserSettings>>,
+}
+
+impl PhaserManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(PhaserSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<PhaserSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_stages(&self, stages: u8) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.stages = stages.clamp(MIN_PHASER_STAGES, MAX_PHASER_STAGES);
+        }
+    }
+
+    pub fn set_rate(&self, rate: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.rate = rate.clamp(0.05, 5.0);
+        }
+    }
+
+    pub fn set_depth(&self, depth: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.depth = depth.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_feedback(&self, feedback: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.feedback = feedback.clamp(-0.9, 0.9);
+        }
+    }
+
+    pub fn set_mix(&self, mix: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.mix = mix.clamp(0.0, 1.0);
+        }
+    }
+}
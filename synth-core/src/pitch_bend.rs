@@ -0,0 +1,81 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+/// ピッチベンドの最大レンジ（半音）
+pub const MAX_BEND_RANGE: f32 = 24.0;
+
+/// ピッチベンドの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PitchBendSettings {
+    /// ベンドレンジ（±半音、0-24）
+    pub range: f32,
+}
+
+impl Default for PitchBendSettings {
+    fn default() -> Self {
+        Self { range: 2.0 }
+    }
+}
+
+/// ピッチベンドを管理する構造体（MIDIスレッドが書き込み、オーディオスレッドが読む）
+pub struct PitchBendManager {
+    settings: Arc<Mutex<PitchBendSettings>>,
+    value: Arc<Mutex<f32>>, // 現在のベンド量（-1.0〜1.0）
+}
+
+impl PitchBendManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(PitchBendSettings::default())),
+            value: Arc::new(Mutex::new(0.0)),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<PitchBendSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_range(&self, range: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.range = range.clamp(0.0, MAX_BEND_RANGE);
+        }
+    }
+
+    /// 現在のベンド量（-1.0〜1.0）を取得する
+    pub fn value(&self) -> f32 {
+        self.value.lock().map(|value| *value).unwrap_or(0.0)
+    }
+
+    /// ベンド量（-1.0〜1.0）を設定する
+    pub fn set_value(&self, value: f32) {
+        if let Ok(mut current) = self.value.lock() {
+            *current = value.clamp(-1.0, 1.0);
+        }
+    }
+
+    /// MIDIのピッチベンドメッセージ（14bit値）からベンド量を設定する
+    pub fn set_from_midi(&self, lsb: u8, msb: u8) {
+        self.set_value(midi_bend_value(lsb, msb));
+    }
+
+    /// 現在のベンドを周波数の倍率に変換する（ロックできない場合は None）
+    pub fn try_ratio(&self) -> Option<f32> {
+        let value = *self.value.try_lock().ok()?;
+        let range = self.settings.try_lock().ok()?.range;
+        Some(2.0f32.powf(value * range / 12.0))
+    }
+}
+
+/// MIDIのピッチベンド（14bit）を -1.0〜1.0 に変換する
+pub fn midi_bend_value(lsb: u8, msb: u8) -> f32 {
+    let raw = ((msb as i32 & 0x7F) << 7) | (lsb as i32 & 0x7F);
+    // 中央（8192）を0として、上側は8191、下側は8192で正規化する
+    let centered = raw - 8192;
+    if centered >= 0 {
+        centered as f32 / 8191.0
+    } else {
+        centered as f32 / 8192.0
+    }
+}
@@ -0,0 +1,606 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::arpeggiator::ArpSettings;
+use crate::bitcrusher::BitcrusherSettings;
+use crate::chorus::ChorusSettings;
+use crate::compressor::CompressorSettings;
+use crate::delay::DelaySettings;
+use crate::distortion::DistortionSettings;
+use crate::drift::DriftSettings;
+use crate::effects::{EffectChain, EffectKind};
+use crate::envelope::{EnvelopeParams, EnvelopeTarget};
+use crate::filter::FilterSettings;
+use crate::flanger::FlangerSettings;
+use crate::lfo::LfoSettings;
+use crate::macros::MacroSettings;
+use crate::master::MasterSettings;
+use crate::mixer::MixerSettings;
+use crate::modmatrix::ModMatrixSettings;
+use crate::oscillator::{NoiseSettings, OscBSettings, OscillatorSettings, SubOscSettings};
+use crate::params::SynthParams;
+use crate::phaser::PhaserSettings;
+use crate::pitch_bend::PitchBendSettings;
+use crate::reverb::ReverbSettings;
+use crate::unison::UnisonSettings;
+use crate::velocity::VelocitySettings;
+use crate::voice::VoiceSettings;
+use crate::zone::ZoneSettings;
+
+/// プリセットファイルの拡張子
+const PRESET_EXTENSION: &str = "json";
+
+/// プリセット形式のバージョン（形式を変えたら上げて、`Preset::migrate` に古い形式からの変換を足す）
+///
+/// - 0: バージョンのなかった頃の形式（ミキサーとエフェクトチェーンがない場合がある）
+/// - 1: バージョンを付けた形式（ミキサーとエフェクトチェーンを必ず書く）
+pub const PRESET_VERSION: u32 = 1;
+
+/// プリセットブラウザーで選べるカテゴリーのタグ
+pub const PRESET_CATEGORIES: [&str; 8] = ["Bass", "Lead", "Pad", "Keys", "Pluck", "Arp", "FX", "Drum"];
+
+/// シンセの全設定を保存するプリセット構造体
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preset {
+    /// 保存した時のプリセット形式のバージョン（ない場合は0）
+    pub version: u32,
+    /// プリセット名
+    pub name: String,
+    /// カテゴリーのタグ（Bass・Lead・Pad など、プリセットブラウザーの絞り込みに使う）
+    pub tags: Vec<String>,
+    /// Unison設定（波形を含む）
+    pub unison: UnisonSettings,
+    /// オシレータ設定
+    pub oscillator: OscillatorSettings,
+    /// オシレータB設定
+    pub osc_b: OscBSettings,
+    /// サブオシレータ設定
+    pub sub_osc: SubOscSettings,
+    /// ノイズ源設定
+    pub noise: NoiseSettings,
+    /// ドリフト（アナログVCOの不安定さ）設定
+    pub drift: DriftSettings,
+    /// 音源ごとの音量と定位（ない場合はオシレータBの A↔B のミックス量から作る）
+    pub mixer: Option<MixerSettings>,
+    /// フィルター設定
+    pub filter: FilterSettings,
+    /// ADSRエンベロープ設定（音量）
+    pub envelope: EnvelopeParams,
+    /// フィルターエンベロープ設定
+    pub filter_envelope: EnvelopeParams,
+    /// FMモジュレーターエンベロープ設定
+    pub mod_envelope: EnvelopeParams,
+    /// LFO設定
+    pub lfo: LfoSettings,
+    /// ピッチベンド設定
+    pub pitch_bend: PitchBendSettings,
+    /// マスター出力設定
+    pub master: MasterSettings,
+    /// マスターバスのコンプレッサー設定
+    pub compressor: CompressorSettings,
+    /// ベロシティ設定
+    pub velocity: VelocitySettings,
+    /// 発音モード設定
+    pub voice: VoiceSettings,
+    /// キーボードスプリットとレイヤー（鍵盤の範囲ごとの音色）
+    pub zones: ZoneSettings,
+    /// リバーブ設定
+    pub reverb: ReverbSettings,
+    /// コーラス設定
+    pub chorus: ChorusSettings,
+    /// ディストーション設定
+    pub distortion: DistortionSettings,
+    /// ビットクラッシャー設定
+    pub bitcrusher: BitcrusherSettings,
+    /// フェイザー設定
+    pub phaser: PhaserSettings,
+    /// フランジャー設定
+    pub flanger: FlangerSettings,
+    /// ディレイ設定
+    pub delay: DelaySettings,
+    /// エフェクトチェーンの並びとバイパス（ない場合はコーラス→リバーブの順で、それぞれの有効/無効から作る）
+    pub effects: Option<EffectChain>,
+    /// モジュレーションマトリクス設定
+    pub mod_matrix: ModMatrixSettings,
+    /// マクロツマミの値と割り当て
+    pub macros: MacroSettings,
+    /// アルペジエーター設定
+    pub arp: ArpSettings,
+}
+
+/// プリセットの読み書きで発生するエラー
+#[derive(Debug)]
+pub enum PresetError {
+    Io(io::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for PresetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresetError::Io(err) => write!(f, "I/O error: {}", err),
+            PresetError::Json(err) => write!(f, "JSON error: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for PresetError {
+    fn from(err: io::Error) -> Self {
+        PresetError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for PresetError {
+    fn from(err: serde_json::Error) -> Self {
+        PresetError::Json(err)
+    }
+}
+
+impl Preset {
+    /// 現在のシンセ設定からプリセットを作成する
+    pub fn capture(name: &str, params: &SynthParams) -> Self {
+        let mut preset = Preset {
+            version: PRESET_VERSION,
+            name: name.to_string(),
+            ..Default::default()
+        };
+        if let Ok(settings) = params.unison.get_settings().lock() {
+            preset.unison = settings.clone();
+        }
+        if let Ok(settings) = params.oscillator.get_settings().lock() {
+            preset.oscillator = *settings;
+        }
+        if let Ok(settings) = params.osc_b.get_settings().lock() {
+            preset.osc_b = settings.clone();
+        }
+        if let Ok(settings) = params.sub_osc.get_settings().lock() {
+            preset.sub_osc = *settings;
+        }
+        if let Ok(settings) = params.noise.get_settings().lock() {
+            preset.noise = *settings;
+        }
+        if let Ok(settings) = params.drift.get_settings().lock() {
+            preset.drift = *settings;
+        }
+        if let Ok(settings) = params.mixer.get_settings().lock() {
+            preset.mixer = Some(*settings);
+        }
+        if let Ok(settings) = params.filter.get_settings().lock() {
+            preset.filter = *settings;
+        }
+        preset.envelope = params.envelope.get_params(EnvelopeTarget::Amp);
+        preset.filter_envelope = params.envelope.get_params(EnvelopeTarget::Filter);
+        preset.mod_envelope = params.envelope.get_params(EnvelopeTarget::Modulator);
+        if let Ok(settings) = params.lfo.get_settings().lock() {
+            preset.lfo = *settings;
+        }
+        if let Ok(settings) = params.pitch_bend.get_settings().lock() {
+            preset.pitch_bend = *settings;
+        }
+        if let Ok(settings) = params.master.get_settings().lock() {
+            preset.master = *settings;
+        }
+        if let Ok(settings) = params.compressor.get_settings().lock() {
+            preset.compressor = *settings;
+        }
+        if let Ok(settings) = params.velocity.get_settings().lock() {
+            preset.velocity = *settings;
+        }
+        if let Ok(settings) = params.voice.get_settings().lock() {
+            preset.voice = *settings;
+        }
+        if let Ok(settings) = params.zones.get_settings().lock() {
+            preset.zones = settings.clone();
+        }
+        if let Ok(settings) = params.reverb.get_settings().lock() {
+            preset.reverb = *settings;
+        }
+        if let Ok(settings) = params.chorus.get_settings().lock() {
+            preset.chorus = *settings;
+        }
+        if let Ok(settings) = params.distortion.get_settings().lock() {
+            preset.distortion = *settings;
+        }
+        if let Ok(settings) = params.bitcrusher.get_settings().lock() {
+            preset.bitcrusher = *settings;
+        }
+        if let Ok(settings) = params.phaser.get_settings().lock() {
+            preset.phaser = *settings;
+        }
+        if let Ok(settings) = params.flanger.get_settings().lock() {
+            preset.flanger = *settings;
+        }
+        if let Ok(settings) = params.delay.get_settings().lock() {
+            preset.delay = *settings;
+        }
+        // 並びに対応していない読み込み側のために、有効/無効もチェーンに合わせて書いておく
+        let chain = params.effects.get_chain();
+        preset.chorus.enabled = chain.is_active(EffectKind::Chorus);
+        preset.reverb.enabled = chain.is_active(EffectKind::Reverb);
+        preset.effects = Some(chain);
+        if let Ok(settings) = params.mod_matrix.get_settings().lock() {
+            preset.mod_matrix = *settings;
+        }
+        if let Ok(settings) = params.macros.get_settings().lock() {
+            preset.macros = settings.clone();
+        }
+        if let Ok(settings) = params.arp.get_settings().lock() {
+            preset.arp = *settings;
+        }
+        preset
+    }
+
+    /// プリセットの内容をシンセ設定に反映する
+    pub fn apply(&self, params: &SynthParams) {
+        if let Ok(mut settings) = params.unison.get_settings().lock() {
+            *settings = self.unison.clone();
+        }
+        if let Ok(mut settings) = params.oscillator.get_settings().lock() {
+            *settings = self.oscillator;
+        }
+        if let Ok(mut settings) = params.osc_b.get_settings().lock() {
+            *settings = self.osc_b.clone();
+        }
+        if let Ok(mut settings) = params.sub_osc.get_settings().lock() {
+            *settings = self.sub_osc;
+        }
+        if let Ok(mut settings) = params.noise.get_settings().lock() {
+            *settings = self.noise;
+        }
+        if let Ok(mut settings) = params.drift.get_settings().lock() {
+            *settings = self.drift;
+        }
+        if let Ok(mut settings) = params.mixer.get_settings().lock() {
+            *settings = self.mixer.unwrap_or_else(|| MixerSettings::legacy(&self.osc_b));
+        }
+        if let Ok(mut settings) = params.filter.get_settings().lock() {
+            *settings = self.filter;
+        }
+        params.envelope.set_params(EnvelopeTarget::Amp, self.envelope);
+        params.envelope.set_params(EnvelopeTarget::Filter, self.filter_envelope);
+        params.envelope.set_params(EnvelopeTarget::Modulator, self.mod_envelope);
+        if let Ok(mut settings) = params.lfo.get_settings().lock() {
+            *settings = self.lfo;
+        }
+        if let Ok(mut settings) = params.pitch_bend.get_settings().lock() {
+            *settings = self.pitch_bend;
+        }
+        if let Ok(mut settings) = params.master.get_settings().lock() {
+            *settings = self.master;
+        }
+        if let Ok(mut settings) = params.compressor.get_settings().lock() {
+            *settings = self.compressor;
+        }
+        if let Ok(mut settings) = params.velocity.get_settings().lock() {
+            *settings = self.velocity;
+        }
+        if let Ok(mut settings) = params.voice.get_settings().lock() {
+            *settings = self.voice;
+        }
+        if let Ok(mut settings) = params.zones.get_settings().lock() {
+            *settings = self.zones.clone();
+        }
+        if let Ok(mut settings) = params.reverb.get_settings().lock() {
+            *settings = self.reverb;
+        }
+        if let Ok(mut settings) = params.chorus.get_settings().lock() {
+            *settings = self.chorus;
+        }
+        if let Ok(mut settings) = params.distortion.get_settings().lock() {
+            *settings = self.distortion;
+        }
+        if let Ok(mut settings) = params.bitcrusher.get_settings().lock() {
+            *settings = self.bitcrusher;
+        }
+        if let Ok(mut settings) = params.phaser.get_settings().lock() {
+            *settings = self.phaser;
+        }
+        if let Ok(mut settings) = params.flanger.get_settings().lock() {
+            *settings = self.flanger;
+        }
+        if let Ok(mut settings) = params.delay.get_settings().lock() {
+            *settings = self.delay;
+        }
+        let chain = self.effects.clone().unwrap_or_else(|| EffectChain::legacy(&self.chorus, &self.reverb));
+        params.effects.set_chain(chain);
+        if let Ok(mut settings) = params.mod_matrix.get_settings().lock() {
+            *settings = self.mod_matrix;
+        }
+        // ツマミの値は他のパラメータと一緒に保存されているので、割り当て先には反映し直さない
+        if let Ok(mut settings) = params.macros.get_settings().lock() {
+            *settings = self.macros.clone();
+        }
+        if let Ok(mut settings) = params.arp.get_settings().lock() {
+            *settings = self.arp;
+        }
+    }
+
+    /// プリセットをJSONファイルから読み込む（読み込めずにデフォルトにした設定の名前も返す）
+    pub fn load(path: &Path) -> Result<(Self, Vec<String>), PresetError> {
+        let json = fs::read_to_string(path)?;
+        Self::from_json(&json)
+    }
+
+    /// JSONからプリセットを読み込み、古い形式なら今の形式に変換する
+    ///
+    /// ない設定はデフォルトになり、知らない設定は読み飛ばす。型の合わない設定（壊れた値や形の変わった設定）は
+    /// その設定だけデフォルトにして読み込み、デフォルトにした設定の名前を返す（呼び出し側で知らせる）
+    pub fn from_json(json: &str) -> Result<(Self, Vec<String>), PresetError> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let (preset, skipped) = match serde_json::from_value::<Preset>(value.clone()) {
+            Ok(preset) => (preset, Vec::new()),
+            Err(err) => match value {
+                serde_json::Value::Object(fields) => Self::from_valid_fields(fields)?,
+                _ => return Err(err.into()),
+            },
+        };
+        Ok((preset.migrate(), skipped))
+    }
+
+    /// 読み込める設定だけをデフォルトのプリセットに重ねる（読み込めなかった設定の名前も返す）
+    fn from_valid_fields(fields: serde_json::Map<String, serde_json::Value>) -> Result<(Self, Vec<String>), PresetError> {
+        let mut merged = serde_json::to_value(Preset::default())?;
+        let mut skipped = Vec::new();
+        for (key, field) in fields {
+            let mut candidate = merged.clone();
+            candidate[key.as_str()] = field;
+            if serde_json::from_value::<Preset>(candidate.clone()).is_ok() {
+                merged = candidate;
+            } else {
+                skipped.push(key);
+            }
+        }
+        Ok((serde_json::from_value(merged)?, skipped))
+    }
+
+    /// 古い形式のプリセットを今の形式にする
+    fn migrate(mut self) -> Self {
+        if self.version < 1 {
+            // ミキサーとエフェクトチェーンがなければ、それまでの設定から作る
+            self.mixer.get_or_insert_with(|| MixerSettings::legacy(&self.osc_b));
+            self.effects.get_or_insert_with(|| EffectChain::legacy(&self.chorus, &self.reverb));
+        }
+        self.version = PRESET_VERSION;
+        self
+    }
+
+    /// プリセットをJSONファイルに保存する
+    pub fn save(&self, path: &Path) -> Result<(), PresetError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+/// ユーザープリセットの保存ディレクトリを取得する
+pub fn presets_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("rust_synth")
+        .join("presets")
+}
+
+/// プリセット名から保存先のパスを作成する
+pub fn preset_path(name: &str) -> PathBuf {
+    presets_dir().join(format!("{}.{}", name, PRESET_EXTENSION))
+}
+
+/// プリセットブラウザーに並べる1つのプリセット
+#[derive(Clone, Debug, PartialEq)]
+pub struct PresetEntry {
+    /// プリセットファイルのパス
+    pub path: PathBuf,
+    /// 表示名（ファイル名）
+    pub name: String,
+    /// プリセットディレクトリからのフォルダー（直下なら空）
+    pub folder: String,
+    /// カテゴリーのタグ
+    pub tags: Vec<String>,
+}
+
+impl PresetEntry {
+    /// 検索文字列（名前・フォルダー・タグのどれかに含まれる、大文字小文字は区別しない）とタグで絞り込む
+    pub fn matches(&self, query: &str, tag: Option<&str>) -> bool {
+        if tag.is_some_and(|tag| !self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))) {
+            return false;
+        }
+        let query = query.trim().to_lowercase();
+        query.is_empty()
+            || self.name.to_lowercase().contains(&query)
+            || self.folder.to_lowercase().contains(&query)
+            || self.tags.iter().any(|t| t.to_lowercase().contains(&query))
+    }
+}
+
+/// タグだけを読むためのプリセット（他の設定は読み飛ばす）
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct PresetTags {
+    tags: Vec<String>,
+}
+
+/// プリセットディレクトリ内のプリセットをサブフォルダーも含めてフォルダー順・名前順で列挙する
+///
+/// 全てのファイルを読むので、GUIでは別スレッドから呼ぶ
+pub fn scan_presets() -> Vec<PresetEntry> {
+    let root = presets_dir();
+    let mut presets = Vec::new();
+    let mut dirs = vec![root.clone()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().and_then(|ext| ext.to_str()) == Some(PRESET_EXTENSION) {
+                let tags = fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|json| serde_json::from_str::<PresetTags>(&json).ok())
+                    .unwrap_or_default()
+                    .tags;
+                let folder = path
+                    .parent()
+                    .and_then(|parent| parent.strip_prefix(&root).ok())
+                    .map(|folder| folder.to_string_lossy().replace('\\', "/"))
+                    .unwrap_or_default();
+                presets.push(PresetEntry {
+                    name: preset_display_name(&path),
+                    path,
+                    folder,
+                    tags,
+                });
+            }
+        }
+    }
+    presets.sort_by(|a, b| (&a.folder, a.name.to_lowercase()).cmp(&(&b.folder, b.name.to_lowercase())));
+    presets
+}
+
+/// プリセットのフォルダーを並び順にバンクとして並べる（直下にプリセットがあれば直下がバンク0）
+///
+/// `presets` は `scan_presets` の結果のようにフォルダー順に並んでいること
+pub fn preset_banks(presets: &[PresetEntry]) -> Vec<&str> {
+    let mut banks: Vec<&str> = Vec::new();
+    for entry in presets {
+        if banks.last() != Some(&entry.folder.as_str()) {
+            banks.push(&entry.folder);
+        }
+    }
+    banks
+}
+
+/// バンクセレクトとプログラムチェンジで選ばれたプリセット（バンクはフォルダー、プログラムはフォルダー内の名前順）
+pub fn find_program(presets: &[PresetEntry], bank: u16, program: u8) -> Option<&PresetEntry> {
+    let folder = *preset_banks(presets).get(bank as usize)?;
+    presets.iter().filter(|entry| entry.folder == folder).nth(program as usize)
+}
+
+/// プリセットファイルのパスから表示名を取得する
+pub fn preset_display_name(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("Unknown")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lfo::LfoTarget;
+    use crate::voice::VoiceMode;
+
+    /// 保存して読み込み直しても全ての設定が変わらない
+    #[test]
+    fn round_trip_keeps_all_settings() {
+        let params = SynthParams::new();
+        params.filter.set_cutoff(1234.0);
+        params.lfo.set_target(LfoTarget::FilterCutoff);
+        params.unison.set_voices(5);
+        params.voice.set_mode(VoiceMode::Legato);
+        let mut preset = Preset::capture("Round Trip", &params);
+        preset.tags = vec!["Bass".to_string()];
+
+        let json = serde_json::to_string_pretty(&preset).unwrap();
+        let (loaded, _) = Preset::from_json(&json).unwrap();
+
+        assert_eq!(loaded.version, PRESET_VERSION);
+        assert_eq!(loaded.name, "Round Trip");
+        assert_eq!(loaded.tags, vec!["Bass".to_string()]);
+        assert_eq!(loaded.filter.cutoff, 1234.0);
+        assert_eq!(loaded.unison.voices, 5);
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), serde_json::to_value(&preset).unwrap());
+    }
+
+    /// 読み込んだプリセットを反映してから取り直しても同じになる
+    #[test]
+    fn round_trip_through_params() {
+        let params = SynthParams::new();
+        params.unison.set_detune(17.0);
+        params.lfo.set_rate(3.0);
+        let preset = Preset::capture("Params", &params);
+
+        let other = SynthParams::new();
+        Preset::from_json(&serde_json::to_string(&preset).unwrap()).unwrap().0.apply(&other);
+        let recaptured = Preset::capture("Params", &other);
+
+        assert_eq!(serde_json::to_value(&recaptured).unwrap(), serde_json::to_value(&preset).unwrap());
+    }
+
+    /// バージョンのない古いプリセットはミキサーとエフェクトチェーンを補って読み込む
+    #[test]
+    fn legacy_preset_is_migrated() {
+        let json = r#"{ "name": "Old", "osc_b": { "routing": "Mix", "mix": 0.25 } }"#;
+        let (preset, skipped) = Preset::from_json(json).unwrap();
+
+        assert!(skipped.is_empty());
+        assert_eq!(preset.version, PRESET_VERSION);
+        assert_eq!(preset.name, "Old");
+        let mixer = preset.mixer.expect("mixer should be migrated");
+        assert_eq!(mixer.osc_a.level, 0.75);
+        assert_eq!(mixer.osc_b.level, 0.25);
+        assert!(preset.effects.is_some());
+    }
+
+    /// 型の合わない設定はその設定だけデフォルトにして、他の設定は読み込む
+    #[test]
+    fn invalid_field_falls_back_to_default() {
+        let json = r#"{ "name": "Broken", "filter": "oops", "lfo": { "rate": 2.0 } }"#;
+        let (preset, skipped) = Preset::from_json(json).unwrap();
+
+        assert_eq!(skipped, vec!["filter".to_string()]);
+        assert_eq!(preset.name, "Broken");
+        assert_eq!(preset.lfo.rate, 2.0);
+        assert_eq!(preset.filter.cutoff, FilterSettings::default().cutoff);
+    }
+
+    /// 新しいバージョンで足された知らない設定は読み飛ばす
+    #[test]
+    fn unknown_fields_are_ignored() {
+        let json = r#"{ "version": 99, "name": "Future", "hologram": { "enabled": true } }"#;
+        let (preset, skipped) = Preset::from_json(json).unwrap();
+
+        assert!(skipped.is_empty());
+        assert_eq!(preset.name, "Future");
+    }
+
+    /// JSONのオブジェクトでないものは読み込めない
+    #[test]
+    fn non_object_is_an_error() {
+        assert!(Preset::from_json("[1, 2, 3]").is_err());
+        assert!(Preset::from_json("not json").is_err());
+    }
+
+    /// バンクはフォルダーの並び順、プログラムはフォルダー内の並び順で選ばれる
+    #[test]
+    fn program_change_selects_folder_and_index() {
+        let entry = |folder: &str, name: &str| PresetEntry {
+            path: PathBuf::from(format!("{}/{}.json", folder, name)),
+            name: name.to_string(),
+            folder: folder.to_string(),
+            tags: Vec::new(),
+        };
+        let presets = vec![
+            entry("", "Init"),
+            entry("Bass", "Acid"),
+            entry("Bass", "Sub"),
+            entry("Lead", "Saw"),
+        ];
+        assert_eq!(preset_banks(&presets), vec!["", "Bass", "Lead"]);
+        let name = |bank, program| find_program(&presets, bank, program).map(|entry| entry.name.as_str());
+        assert_eq!(name(0, 0), Some("Init"));
+        assert_eq!(name(1, 1), Some("Sub"));
+        assert_eq!(name(2, 0), Some("Saw"));
+        assert_eq!(name(1, 2), None);
+        assert_eq!(name(3, 0), None);
+    }
+}
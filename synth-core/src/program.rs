@@ -0,0 +1,63 @@
+use std::sync::atomic::{AtomicU8, AtomicU32, Ordering};
+
+/// バンクセレクトとプログラムチェンジで選ばれたプログラム
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ProgramNumber {
+    pub bank: u16,   // バンク（CC0 を上位7ビット、CC32 を下位7ビットにした 0-16383）
+    pub program: u8, // バンク内のプログラム（0-127）
+}
+
+/// MIDIのバンクセレクト（CC0/CC32）とプログラムチェンジの受信状態
+///
+/// オーディオスレッドが書き、GUIが読んでプリセットを読み込む（ファイルを読むのでオーディオスレッドでは読み込まない）。
+/// GUIが読む前に続けて届いたプログラムチェンジは最後のものだけを残す
+pub struct ProgramSelect {
+    bank_msb: AtomicU8,  // CC0 で選ばれたバンクの上位7ビット
+    bank_lsb: AtomicU8,  // CC32 で選ばれたバンクの下位7ビット
+    pending: AtomicU32, // まだ読み込んでいないプログラム（バンク << 7 | プログラム に1を足した値、0はなし）
+}
+
+impl ProgramSelect {
+    pub fn new() -> Self {
+        Self {
+            bank_msb: AtomicU8::new(0),
+            bank_lsb: AtomicU8::new(0),
+            pending: AtomicU32::new(0),
+        }
+    }
+
+    /// CC0: バンクの上位7ビットを設定する（次のプログラムチェンジから使う）
+    pub fn set_bank_msb(&self, value: u8) {
+        self.bank_msb.store(value.min(127), Ordering::Relaxed);
+    }
+
+    /// CC32: バンクの下位7ビットを設定する（次のプログラムチェンジから使う）
+    pub fn set_bank_lsb(&self, value: u8) {
+        self.bank_lsb.store(value.min(127), Ordering::Relaxed);
+    }
+
+    /// 現在選ばれているバンク
+    pub fn bank(&self) -> u16 {
+        (self.bank_msb.load(Ordering::Relaxed) as u16) << 7 | self.bank_lsb.load(Ordering::Relaxed) as u16
+    }
+
+    /// プログラムチェンジを受信する（現在のバンクと合わせて読み込みを待つ）
+    pub fn program_change(&self, program: u8) -> ProgramNumber {
+        let number = ProgramNumber {
+            bank: self.bank(),
+            program: program.min(127),
+        };
+        let encoded = ((number.bank as u32) << 7 | number.program as u32) + 1;
+        self.pending.store(encoded, Ordering::Relaxed);
+        number
+    }
+
+    /// 前回呼んでから届いた最後のプログラムを取得してリセットする（届いていなければ None）
+    pub fn take(&self) -> Option<ProgramNumber> {
+        let encoded = self.pending.swap(0, Ordering::Relaxed).checked_sub(1)?;
+        Some(ProgramNumber {
+            bank: (encoded >> 7) as u16,
+            program: (encoded & 0x7F) as u8,
+        })
+    }
+}
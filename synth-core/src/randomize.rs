@@ -0,0 +1,200 @@
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::envelope::EnvelopeParams;
+use crate::filter::FilterMode;
+use crate::mixer::MixerSettings;
+use crate::oscillator::{MAX_FM_INDEX, MAX_PULSE_WIDTH, MIN_PULSE_WIDTH, NoiseType, OscRouting, SubWaveform, Waveform};
+use crate::preset::Preset;
+use crate::unison::MAX_UNISON_VOICES;
+
+/// ランダムに選ぶオシレータの波形（読み込んだ波形テーブルは選ばない）
+const WAVEFORMS: [Waveform; 4] = [Waveform::Sine, Waveform::Triangle, Waveform::Square, Waveform::Sawtooth];
+
+/// オシレータBの周波数比として選ぶ値（整数倍とその逆数にして音程感を残す）
+const FM_RATIOS: [f32; 6] = [0.5, 1.0, 2.0, 3.0, 4.0, 5.0];
+
+/// 音作りのヒントになるように、パッチをランダムに作ったり少しだけ変えたりする
+///
+/// 音が出なくなるような極端な値は選ばないように、それぞれ使いやすい範囲に絞っている。
+/// エフェクト・マスター・アルペジエーターなどの設定はそのまま残す
+pub struct PatchRandomizer {
+    rng_state: u32, // 乱数の状態（xorshift）
+}
+
+impl PatchRandomizer {
+    /// 種を指定して作る（0は使えないので1に置き換える）
+    pub fn new(seed: u32) -> Self {
+        Self {
+            rng_state: seed.wrapping_mul(0x9E37_79B9).max(1),
+        }
+    }
+
+    /// 現在時刻を種にして作る
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.subsec_nanos() ^ elapsed.as_secs() as u32)
+            .unwrap_or(1);
+        Self::new(nanos)
+    }
+
+    /// オシレータ・Unison・エンベロープ・フィルターをランダムな値にする
+    pub fn randomize(&mut self, preset: &mut Preset) {
+        // オシレータA（Unison）
+        preset.unison.waveform = self.pick(&WAVEFORMS);
+        preset.unison.voices = if self.chance(0.5) { 1 } else { self.range(2.0, MAX_UNISON_VOICES as f32 + 1.0) as u8 };
+        preset.unison.detune = self.range(5.0, 40.0);
+        preset.unison.width = self.range(0.0, 1.0);
+        preset.unison.blend = self.range(0.3, 0.7);
+        preset.oscillator.pulse_width = self.range(0.2, 0.8);
+
+        // オシレータB（半分はAだけにする）
+        preset.osc_b.waveform = self.pick(&WAVEFORMS);
+        preset.osc_b.routing = self.pick(&[OscRouting::Mix, OscRouting::Mix, OscRouting::Fm, OscRouting::RingMod, OscRouting::Sync]);
+        preset.osc_b.octave = self.pick(&[-1, 0, 0, 1]);
+        preset.osc_b.semitone = self.pick(&[0, 0, 0, 7, -5, 12]);
+        preset.osc_b.fine = self.range(-10.0, 10.0);
+        preset.osc_b.mix = if self.chance(0.5) { 0.0 } else { self.range(0.2, 0.8) };
+        // ミックスの時はAとBの音量をミキサーで振り分ける（定位はそのまま）
+        let mixer = preset.mixer.get_or_insert_with(|| MixerSettings::legacy(&preset.osc_b));
+        mixer.osc_a.level = if preset.osc_b.routing == OscRouting::Mix { 1.0 - preset.osc_b.mix } else { 1.0 };
+        mixer.osc_b.level = preset.osc_b.mix;
+        preset.osc_b.fm_ratio = self.pick(&FM_RATIOS);
+        preset.osc_b.fm_index = self.range(0.0, MAX_FM_INDEX * 0.4);
+
+        // サブオシレータとノイズ（どちらも控えめに）
+        preset.sub_osc.waveform = self.pick(&[SubWaveform::Sine, SubWaveform::Square]);
+        preset.sub_osc.octave = self.pick(&[1, 2]);
+        preset.sub_osc.level = if self.chance(0.6) { 0.0 } else { self.range(0.1, 0.5) };
+        preset.noise.noise_type = self.pick(&[NoiseType::White, NoiseType::Pink, NoiseType::Brown]);
+        preset.noise.level = if self.chance(0.7) { 0.0 } else { self.range(0.02, 0.15) };
+
+        // エンベロープ
+        self.randomize_envelope(&mut preset.envelope);
+        self.randomize_envelope(&mut preset.filter_envelope);
+        self.randomize_envelope(&mut preset.mod_envelope);
+        // 音量エンベロープはサステインが小さすぎると音が聞こえにくいので下限を上げる
+        preset.envelope.sustain = self.range(0.3, 1.0);
+
+        // フィルター（ほとんどはローパス）
+        preset.filter.enabled = true;
+        preset.filter.mode = self.pick(&[FilterMode::LowPass, FilterMode::LowPass, FilterMode::LowPass, FilterMode::BandPass, FilterMode::HighPass]);
+        preset.filter.cutoff = self.log_range(300.0, 12000.0);
+        preset.filter.resonance = self.range(0.0, 0.7);
+        preset.filter.env_amount = self.range(-0.3, 0.8);
+    }
+
+    /// 今のパッチを少しだけ変える（`amount` は0.0〜1.0、それぞれの範囲に対する変化の大きさ）
+    ///
+    /// 波形や組み合わせ方などの選択肢は、amount に応じた確率でまれに切り替える
+    pub fn mutate(&mut self, preset: &mut Preset, amount: f32) {
+        let amount = amount.clamp(0.0, 1.0);
+        let switch = amount * 0.3; // 選択肢を切り替える確率
+
+        // オシレータA（Unison）
+        if self.chance(switch) {
+            preset.unison.waveform = self.pick(&WAVEFORMS);
+        }
+        if self.chance(switch) {
+            preset.unison.voices = self.range(1.0, MAX_UNISON_VOICES as f32 + 1.0) as u8;
+        }
+        preset.unison.detune = self.nudge(preset.unison.detune, 0.0, 100.0, amount);
+        preset.unison.width = self.nudge(preset.unison.width, 0.0, 1.0, amount);
+        preset.unison.blend = self.nudge(preset.unison.blend, 0.0, 1.0, amount);
+        preset.oscillator.pulse_width = self.nudge(preset.oscillator.pulse_width, MIN_PULSE_WIDTH, MAX_PULSE_WIDTH, amount);
+
+        // オシレータB
+        if self.chance(switch) {
+            preset.osc_b.waveform = self.pick(&WAVEFORMS);
+        }
+        preset.osc_b.fine = self.nudge(preset.osc_b.fine, -100.0, 100.0, amount);
+        preset.osc_b.mix = self.nudge(preset.osc_b.mix, 0.0, 1.0, amount);
+        let mixer = preset.mixer.get_or_insert_with(|| MixerSettings::legacy(&preset.osc_b));
+        mixer.osc_a.level = self.nudge(mixer.osc_a.level, 0.0, 1.0, amount);
+        mixer.osc_b.level = self.nudge(mixer.osc_b.level, 0.0, 1.0, amount);
+        preset.osc_b.fm_index = self.nudge(preset.osc_b.fm_index, 0.0, MAX_FM_INDEX, amount);
+
+        // サブオシレータとノイズ
+        preset.sub_osc.level = self.nudge(preset.sub_osc.level, 0.0, 1.0, amount);
+        preset.noise.level = self.nudge(preset.noise.level, 0.0, 1.0, amount * 0.5);
+
+        // エンベロープ
+        self.mutate_envelope(&mut preset.envelope, amount);
+        self.mutate_envelope(&mut preset.filter_envelope, amount);
+        self.mutate_envelope(&mut preset.mod_envelope, amount);
+
+        // フィルター
+        if self.chance(switch) {
+            preset.filter.mode = self.pick(&[FilterMode::LowPass, FilterMode::HighPass, FilterMode::BandPass, FilterMode::Notch]);
+        }
+        preset.filter.cutoff = self.nudge_log(preset.filter.cutoff, 20.0, 20000.0, amount);
+        preset.filter.resonance = self.nudge(preset.filter.resonance, 0.0, 1.0, amount);
+        preset.filter.env_amount = self.nudge(preset.filter.env_amount, -1.0, 1.0, amount);
+    }
+
+    /// エンベロープをランダムな値にする（ディレイとホールドは使わない）
+    fn randomize_envelope(&mut self, envelope: &mut EnvelopeParams) {
+        *envelope = EnvelopeParams {
+            delay: 0.0,
+            attack: self.log_range(0.001, 1.0),
+            hold: 0.0,
+            decay: self.log_range(0.05, 2.0),
+            sustain: self.range(0.0, 1.0),
+            release: self.log_range(0.01, 2.0),
+            attack_curve: self.range(-0.5, 0.5),
+            decay_curve: self.range(-0.5, 0.5),
+            release_curve: self.range(-0.5, 0.5),
+            looping: false,
+        };
+    }
+
+    /// エンベロープを少しだけ変える（時間は対数で動かす）
+    fn mutate_envelope(&mut self, envelope: &mut EnvelopeParams, amount: f32) {
+        envelope.attack = self.nudge_log(envelope.attack, 0.001, 5.0, amount);
+        envelope.decay = self.nudge_log(envelope.decay, 0.001, 5.0, amount);
+        envelope.sustain = self.nudge(envelope.sustain, 0.0, 1.0, amount);
+        envelope.release = self.nudge_log(envelope.release, 0.001, 5.0, amount);
+    }
+
+    /// 0.0〜1.0 の乱数（xorshift32）
+    fn next(&mut self) -> f32 {
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng_state = x;
+        x as f32 / u32::MAX as f32
+    }
+
+    /// min〜max の一様な乱数
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next()
+    }
+
+    /// min〜max を対数で一様に選ぶ（時間や周波数用）
+    fn log_range(&mut self, min: f32, max: f32) -> f32 {
+        min * (max / min).powf(self.next())
+    }
+
+    /// 確率 p で true
+    fn chance(&mut self, p: f32) -> bool {
+        self.next() < p
+    }
+
+    /// 候補から1つ選ぶ
+    fn pick<T: Clone>(&mut self, choices: &[T]) -> T {
+        let index = (self.next() * choices.len() as f32) as usize;
+        choices[index.min(choices.len() - 1)].clone()
+    }
+
+    /// 範囲の幅 × amount まで上下に動かす
+    fn nudge(&mut self, value: f32, min: f32, max: f32, amount: f32) -> f32 {
+        (value + (max - min) * amount * self.range(-1.0, 1.0)).clamp(min, max)
+    }
+
+    /// 対数で範囲の幅 × amount まで上下に動かす
+    fn nudge_log(&mut self, value: f32, min: f32, max: f32, amount: f32) -> f32 {
+        let octaves = (max / min).log2() * amount * self.range(-1.0, 1.0);
+        (value.clamp(min, max) * 2.0f32.powf(octaves)).clamp(min, max)
+    }
+}
@@ -0,0 +1,135 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::engine::SynthEngine;
+use crate::midi_message::handle_midi_message;
+use crate::note::NoteHandler;
+use crate::params::SynthParams;
+use crate::smf::MidiFile;
+
+/// オフラインで書き出すチャンネル数（ステレオ）
+pub const RENDER_CHANNELS: u16 = 2;
+
+/// 一度にエンジンへ渡すフレーム数（エンベロープはこの単位で進む）
+const RENDER_BLOCK_SIZE: usize = 256;
+
+/// ノートを離した後に余韻を書き出す最大時間（秒）
+const MAX_TAIL_SECONDS: f32 = 10.0;
+
+/// オーディオデバイスを使わずにエンジンを回して音声を書き出す
+///
+/// 実時間を待たずにできるだけ速く生成する。
+/// 時間で動くアルペジエーターは使えないので、作成時に無効にする。
+pub struct OfflineRenderer {
+    params: SynthParams,
+    notes: NoteHandler,
+    engine: SynthEngine,
+    sample_rate: u32,
+    output: Vec<f32>, // 生成したサンプル（左右交互）
+}
+
+impl OfflineRenderer {
+    pub fn new(params: SynthParams, sample_rate: u32) -> Self {
+        params.arp.set_enabled(false);
+        Self {
+            notes: NoteHandler::new(Arc::new(Mutex::new(0.0)), params.clone()),
+            engine: SynthEngine::new(params.clone(), sample_rate as f32, RENDER_BLOCK_SIZE),
+            params,
+            sample_rate,
+            output: Vec::new(),
+        }
+    }
+
+    /// ノートオン・オフやペダルを送る口
+    pub fn notes(&self) -> &NoteHandler {
+        &self.notes
+    }
+
+    /// MIDIメッセージを反映する（MIDI入力と同じ処理をする）
+    pub fn send_midi(&self, message: &[u8]) {
+        handle_midi_message(message, &self.notes, &self.params, &mut |_| {});
+    }
+
+    /// これまでに生成したフレーム数
+    pub fn frames(&self) -> usize {
+        self.output.len() / RENDER_CHANNELS as usize
+    }
+
+    /// 指定したフレーム数だけ音声を生成する
+    pub fn render(&mut self, frames: usize) {
+        let channels = RENDER_CHANNELS as usize;
+        let mut remaining = frames;
+        while remaining > 0 {
+            let block = remaining.min(RENDER_BLOCK_SIZE);
+            let start = self.output.len();
+            self.output.resize(start + block * channels, 0.0);
+            self.engine.process(&mut self.output[start..], channels);
+            remaining -= block;
+        }
+    }
+
+    /// 指定した秒数だけ音声を生成する
+    pub fn render_seconds(&mut self, seconds: f32) {
+        self.render((seconds.max(0.0) * self.sample_rate as f32).round() as usize);
+    }
+
+    /// 全てのボイスのアンプエンベロープのリリースが終わるまで余韻を生成する（最大 `MAX_TAIL_SECONDS` 秒）
+    ///
+    /// 直前に送ったノートオン/オフはエンジンが次のブロックで反映するので、最低1ブロックは生成する
+    pub fn render_tail(&mut self) {
+        let max_frames = (MAX_TAIL_SECONDS * self.sample_rate as f32) as usize;
+        let mut rendered = 0;
+        loop {
+            self.render(RENDER_BLOCK_SIZE);
+            rendered += RENDER_BLOCK_SIZE;
+            if rendered >= max_frames || !self.engine.is_active() {
+                break;
+            }
+        }
+    }
+
+    /// 生成したサンプル（左右交互）を取り出す
+    pub fn finish(self) -> Vec<f32> {
+        self.output
+    }
+}
+
+/// 1つのノートを指定した長さだけ鳴らし、リリースの余韻まで含めて生成する
+pub fn render_note(params: SynthParams, note: u8, velocity: u8, duration: f32, sample_rate: u32) -> Vec<f32> {
+    let mut renderer = OfflineRenderer::new(params, sample_rate);
+    renderer.notes().note_on(note, velocity);
+    renderer.render_seconds(duration);
+    renderer.notes().note_off(note);
+    renderer.render_tail();
+    renderer.finish()
+}
+
+/// MIDIファイルのイベントをサンプル単位のタイミングで送り、最後の余韻まで含めて生成する
+pub fn render_midi_file(params: SynthParams, file: &MidiFile, sample_rate: u32) -> Vec<f32> {
+    let mut renderer = OfflineRenderer::new(params, sample_rate);
+    for event in file.events() {
+        // イベントの時刻までを生成してからイベントを反映する
+        let frame = (event.time * sample_rate as f64).round() as usize;
+        renderer.render(frame.saturating_sub(renderer.frames()));
+        renderer.send_midi(&event.message);
+    }
+    // ノートオフがないまま終わったノートもリリースさせる
+    renderer.notes().all_notes_off();
+    renderer.render_tail();
+    renderer.finish()
+}
+
+/// 生成したサンプルを32bit floatのステレオWAVファイルに書き出す
+pub fn write_wav(path: &Path, samples: &[f32], sample_rate: u32) -> Result<(), hound::Error> {
+    let spec = hound::WavSpec {
+        channels: RENDER_CHANNELS,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for &sample in samples {
+        writer.write_sample(sample)?;
+    }
+    writer.finalize()
+}
@@ -0,0 +1,200 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::denormal::flush_denormal;
+
+/// コムフィルターの遅延時間（44.1kHz でのサンプル数、Freeverb の値）
+const COMB_TUNINGS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+
+/// オールパスフィルターの遅延時間（44.1kHz でのサンプル数、Freeverb の値）
+const ALLPASS_TUNINGS: [usize; 4] = [556, 441, 341, 225];
+
+/// 右チャンネルの遅延時間を伸ばす量（44.1kHz でのサンプル数、Freeverb の値）
+pub const STEREO_SPREAD: usize = 23;
+
+/// コムフィルターへの入力ゲイン
+const FIXED_GAIN: f32 = 0.015;
+
+/// ルームサイズからフィードバック量への変換
+const SCALE_ROOM: f32 = 0.28;
+const OFFSET_ROOM: f32 = 0.7;
+
+/// ダンピングからローパス係数への変換
+const SCALE_DAMP: f32 = 0.4;
+
+/// オールパスフィルターのフィードバック量
+const ALLPASS_FEEDBACK: f32 = 0.5;
+
+/// リバーブの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReverbSettings {
+    /// リバーブを有効にするかどうか（旧形式のプリセット用、エンジンはエフェクトチェーンのバイパスを使う）
+    pub enabled: bool,
+    /// ルームサイズ（0.0-1.0、大きいほど残響が長い）
+    pub room_size: f32,
+    /// ダンピング（0.0-1.0、大きいほど高域が早く減衰する）
+    pub damping: f32,
+    /// ウェットの割合（0.0で原音のみ、1.0でリバーブ音のみ）
+    pub mix: f32,
+}
+
+impl Default for ReverbSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            room_size: 0.5,
+            damping: 0.5,
+            mix: 0.25,
+        }
+    }
+}
+
+/// ローパス付きフィードバックコムフィルター
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    filter_store: f32, // ダンピング用ローパスの状態
+}
+
+impl Comb {
+    fn new(size: usize) -> Self {
+        Self {
+            buffer: vec![0.0; size.max(1)],
+            index: 0,
+            filter_store: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damp: f32) -> f32 {
+        let output = self.buffer[self.index];
+        self.filter_store = flush_denormal(output * (1.0 - damp) + self.filter_store * damp);
+        self.buffer[self.index] = flush_denormal(input + self.filter_store * feedback);
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+
+    fn clear(&mut self) {
+        self.buffer.iter_mut().for_each(|x| *x = 0.0);
+        self.filter_store = 0.0;
+    }
+}
+
+/// シュレーダー型オールパスフィルター
+struct Allpass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl Allpass {
+    fn new(size: usize) -> Self {
+        Self {
+            buffer: vec![0.0; size.max(1)],
+            index: 0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let buffered = self.buffer[self.index];
+        let output = buffered - input;
+        self.buffer[self.index] = flush_denormal(input + buffered * ALLPASS_FEEDBACK);
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+
+    fn clear(&mut self) {
+        self.buffer.iter_mut().for_each(|x| *x = 0.0);
+    }
+}
+
+/// Freeverb 方式のリバーブ（並列コムフィルター＋直列オールパスフィルター）
+///
+/// 遅延バッファを持つので、オーディオスレッドがストリームごとに1つ所有する
+pub struct Reverb {
+    combs: Vec<Comb>,
+    allpasses: Vec<Allpass>,
+    feedback: f32,
+    damp: f32,
+    mix: f32,
+}
+
+impl Reverb {
+    /// サンプルレートに合わせて遅延バッファを確保する
+    ///
+    /// `spread` だけ遅延時間を伸ばす（左右で変えるとステレオに広がる）
+    pub fn new(sample_rate: f32, spread: usize) -> Self {
+        let scale = sample_rate / 44100.0;
+        let scaled = |tuning: usize| (((tuning + spread) as f32) * scale) as usize;
+        let mut reverb = Self {
+            combs: COMB_TUNINGS.iter().map(|&t| Comb::new(scaled(t))).collect(),
+            allpasses: ALLPASS_TUNINGS.iter().map(|&t| Allpass::new(scaled(t))).collect(),
+            feedback: 0.0,
+            damp: 0.0,
+            mix: 0.0,
+        };
+        reverb.set_params(&ReverbSettings::default());
+        reverb
+    }
+
+    /// 設定から係数を計算する（バッファごとに1回呼ぶ）
+    pub fn set_params(&mut self, settings: &ReverbSettings) {
+        self.feedback = settings.room_size.clamp(0.0, 1.0) * SCALE_ROOM + OFFSET_ROOM;
+        self.damp = settings.damping.clamp(0.0, 1.0) * SCALE_DAMP;
+        self.mix = settings.mix.clamp(0.0, 1.0);
+    }
+
+    /// 残響をすべて消す
+    pub fn reset(&mut self) {
+        self.combs.iter_mut().for_each(Comb::clear);
+        self.allpasses.iter_mut().for_each(Allpass::clear);
+    }
+
+    /// 1サンプル処理する
+    pub fn process(&mut self, input: f32) -> f32 {
+        let scaled = input * FIXED_GAIN;
+        let mut wet = 0.0;
+        for comb in self.combs.iter_mut() {
+            wet += comb.process(scaled, self.feedback, self.damp);
+        }
+        for allpass in self.allpasses.iter_mut() {
+            wet = allpass.process(wet);
+        }
+        input * (1.0 - self.mix) + wet * self.mix
+    }
+}
+
+/// リバーブの設定を管理する構造体
+pub struct ReverbManager {
+    settings: Arc<Mutex<ReverbSettings>>,
+}
+
+impl ReverbManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(ReverbSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<ReverbSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_room_size(&self, room_size: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.room_size = room_size.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_damping(&self, damping: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.damping = damping.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_mix(&self, mix: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.mix = mix.clamp(0.0, 1.0);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::sync::{Arc, Mutex};
+
+use crate::lock::lock_or_try;
+
+/// スケールロックで使うスケールの種類
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScaleKind {
+    Major,      // メジャー（イオニアン）
+    Minor,      // ナチュラルマイナー（エオリアン）
+    Dorian,     // ドリアン
+    Pentatonic, // メジャーペンタトニック
+    Custom,     // 鍵盤で選んだ音
+}
+
+impl Default for ScaleKind {
+    fn default() -> Self {
+        Self::Major
+    }
+}
+
+impl ScaleKind {
+    /// 全ての種類（GUIの並び順）
+    pub const ALL: [Self; 5] = [Self::Major, Self::Minor, Self::Dorian, Self::Pentatonic, Self::Custom];
+
+    /// 表示名
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Major => "Major",
+            Self::Minor => "Minor",
+            Self::Dorian => "Dorian",
+            Self::Pentatonic => "Pentatonic",
+            Self::Custom => "Custom",
+        }
+    }
+
+    /// ルートからの半音ごとにスケールに含まれるかを表すビット列（ビット0がルート、カスタムは None）
+    pub fn mask(self) -> Option<u16> {
+        let degrees: &[u8] = match self {
+            Self::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Self::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            Self::Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            Self::Pentatonic => &[0, 2, 4, 7, 9],
+            Self::Custom => return None,
+        };
+        Some(degrees.iter().fold(0, |mask, &degree| mask | 1 << degree))
+    }
+}
+
+/// 音名（ルートの表示に使う）
+pub const PITCH_CLASS_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+/// スケールロックの設定を表す構造体
+#[derive(Clone, Copy)]
+pub struct ScaleLockSettings {
+    /// 入力されたノートをスケールの音に寄せるか
+    pub enabled: bool,
+    /// スケールのルート（0: C 〜 11: B）
+    pub root: u8,
+    /// スケールの種類
+    pub scale: ScaleKind,
+    /// カスタムスケールの音（ルートからの半音ごとのビット列）
+    pub custom: u16,
+}
+
+impl Default for ScaleLockSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            root: 0,
+            scale: ScaleKind::Major,
+            custom: ScaleKind::Major.mask().unwrap_or_default(),
+        }
+    }
+}
+
+impl ScaleLockSettings {
+    /// 使っているスケールのビット列（ルートからの半音ごと）
+    pub fn mask(&self) -> u16 {
+        self.scale.mask().unwrap_or(self.custom) & 0x0FFF
+    }
+
+    /// ノートがスケールの音かどうか
+    pub fn contains(&self, note: u8) -> bool {
+        let degree = (note as i32 - self.root as i32).rem_euclid(12);
+        self.mask() & 1 << degree != 0
+    }
+
+    /// ノートを一番近いスケールの音に寄せる（同じ距離なら下の音、無効かスケールが空なら変えない）
+    pub fn apply(&self, note: u8) -> u8 {
+        let note = note.min(127);
+        if !self.enabled || self.mask() == 0 {
+            return note;
+        }
+        (0..12u8)
+            .flat_map(|distance| [note.checked_sub(distance), note.checked_add(distance).filter(|&n| n <= 127)])
+            .flatten()
+            .find(|&candidate| self.contains(candidate))
+            .unwrap_or(note)
+    }
+}
+
+/// スケールロックの設定を管理する構造体
+///
+/// トランスポーズと同じく演奏の設定なので、プリセットには含めない
+pub struct ScaleLockManager {
+    settings: Arc<Mutex<ScaleLockSettings>>,
+}
+
+impl ScaleLockManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(ScaleLockSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<ScaleLockSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.enabled = enabled;
+        }
+    }
+
+    pub fn set_root(&self, root: u8) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.root = root % 12;
+        }
+    }
+
+    pub fn set_scale(&self, scale: ScaleKind) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.scale = scale;
+        }
+    }
+
+    pub fn set_custom(&self, custom: u16) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.custom = custom & 0x0FFF;
+        }
+    }
+
+    /// 現在の設定（`wait` が false なら待たずに試し、他のスレッドがロックしていれば None）
+    pub fn snapshot(&self, wait: bool) -> Option<ScaleLockSettings> {
+        lock_or_try(&self.settings, wait).map(|settings| *settings)
+    }
+}
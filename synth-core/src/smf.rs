@@ -0,0 +1,237 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// テンポ指定がない場合のテンポ（4分音符あたりのマイクロ秒、120BPM）
+const DEFAULT_TEMPO: u32 = 500_000;
+
+/// MIDIファイル中のチャンネルメッセージ（曲の先頭からの時間つき）
+#[derive(Clone, Debug)]
+pub struct MidiFileEvent {
+    /// 曲の先頭からの時間（秒）
+    pub time: f64,
+    /// ステータスバイトを含むメッセージ（ランニングステータスは展開済み）
+    pub message: Vec<u8>,
+}
+
+/// 標準MIDIファイル（SMF）を読み込んだもの
+///
+/// 全トラックのチャンネルメッセージを時間順に並べ、テンポ変更を反映した秒単位の時間を持つ。
+/// システムエクスクルーシブとテンポ以外のメタイベントは読み飛ばす。
+pub struct MidiFile {
+    events: Vec<MidiFileEvent>,
+}
+
+/// MIDIファイルの読み込みで発生するエラー
+#[derive(Debug)]
+pub enum SmfError {
+    Io(io::Error),
+    Invalid(&'static str),
+}
+
+impl fmt::Display for SmfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SmfError::Io(err) => write!(f, "I/O error: {}", err),
+            SmfError::Invalid(reason) => write!(f, "Invalid MIDI file: {}", reason),
+        }
+    }
+}
+
+impl From<io::Error> for SmfError {
+    fn from(err: io::Error) -> Self {
+        SmfError::Io(err)
+    }
+}
+
+/// トラックから読み出したイベント（時間はティック単位）
+enum TrackEvent {
+    Tempo(u32),
+    Message(Vec<u8>),
+}
+
+/// バイト列を先頭から読み進める
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn peek(&self) -> Result<u8, SmfError> {
+        self.data.get(self.pos).copied().ok_or(SmfError::Invalid("unexpected end of data"))
+    }
+
+    fn u8(&mut self) -> Result<u8, SmfError> {
+        let value = self.peek()?;
+        self.pos += 1;
+        Ok(value)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], SmfError> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.data.len());
+        let end = end.ok_or(SmfError::Invalid("unexpected end of data"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, SmfError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// 可変長数値（7bitずつ、最上位ビットが継続フラグ）
+    fn varlen(&mut self) -> Result<u32, SmfError> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = self.u8()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(SmfError::Invalid("variable-length value too long"))
+    }
+}
+
+impl MidiFile {
+    /// MIDIファイルを読み込む
+    pub fn load(path: &Path) -> Result<Self, SmfError> {
+        let data = std::fs::read(path)?;
+        Self::parse(&data)
+    }
+
+    /// MIDIファイルの内容を解釈する
+    pub fn parse(data: &[u8]) -> Result<Self, SmfError> {
+        let mut reader = Reader::new(data);
+        if reader.bytes(4)? != b"MThd" {
+            return Err(SmfError::Invalid("missing MThd header"));
+        }
+        let header_len = reader.u32()? as usize;
+        let header = reader.bytes(header_len)?;
+        if header.len() < 6 {
+            return Err(SmfError::Invalid("header too short"));
+        }
+        let division = u16::from_be_bytes([header[4], header[5]]);
+
+        // 全トラックのイベントを（ティック, トラック番号, トラック内の順番）で並べる
+        let mut events = Vec::new();
+        let mut track_index = 0;
+        while !reader.is_empty() {
+            let id = reader.bytes(4)?;
+            let len = reader.u32()? as usize;
+            let chunk = reader.bytes(len)?;
+            // 未知のチャンクは読み飛ばす
+            if id == b"MTrk" {
+                for (order, (tick, event)) in parse_track(chunk)?.into_iter().enumerate() {
+                    events.push((tick, track_index, order, event));
+                }
+                track_index += 1;
+            }
+        }
+        events.sort_by_key(|&(tick, track, order, _)| (tick, track, order));
+
+        // ティックを秒に変換する（テンポ変更はそれ以降のイベントにだけ効く）
+        let mut tempo = DEFAULT_TEMPO;
+        let mut last_tick = 0u64;
+        let mut time = 0.0f64;
+        let mut file_events = Vec::new();
+        for (tick, _, _, event) in events {
+            time += (tick - last_tick) as f64 * seconds_per_tick(division, tempo);
+            last_tick = tick;
+            match event {
+                TrackEvent::Tempo(value) => tempo = value,
+                TrackEvent::Message(message) => file_events.push(MidiFileEvent { time, message }),
+            }
+        }
+
+        Ok(Self { events: file_events })
+    }
+
+    /// 時間順に並んだチャンネルメッセージ
+    pub fn events(&self) -> &[MidiFileEvent] {
+        &self.events
+    }
+
+    /// 最後のイベントまでの時間（秒）
+    pub fn duration(&self) -> f64 {
+        self.events.last().map_or(0.0, |event| event.time)
+    }
+}
+
+/// 1ティックの長さ（秒）
+///
+/// 4分音符あたりのティック数で指定されている場合はテンポに従い、
+/// SMPTEフレームで指定されている場合はテンポに関係なく一定になる
+fn seconds_per_tick(division: u16, tempo: u32) -> f64 {
+    if division & 0x8000 != 0 {
+        let fps = match -((division >> 8) as i8) {
+            29 => 29.97,
+            fps => fps as f64,
+        };
+        let ticks_per_frame = (division & 0xFF).max(1) as f64;
+        1.0 / (fps * ticks_per_frame)
+    } else {
+        tempo as f64 / 1_000_000.0 / division.max(1) as f64
+    }
+}
+
+/// 1つのトラックのイベントを（ティック, イベント）の列として読み出す
+fn parse_track(data: &[u8]) -> Result<Vec<(u64, TrackEvent)>, SmfError> {
+    let mut reader = Reader::new(data);
+    let mut events = Vec::new();
+    let mut tick = 0u64;
+    let mut running_status = None;
+
+    while !reader.is_empty() {
+        tick += reader.varlen()? as u64;
+        let status = if reader.peek()? & 0x80 != 0 {
+            reader.u8()?
+        } else {
+            running_status.ok_or(SmfError::Invalid("data byte without running status"))?
+        };
+
+        match status {
+            // メタイベント（テンポだけを使い、トラックの終わりで打ち切る）
+            0xFF => {
+                running_status = None;
+                let kind = reader.u8()?;
+                let len = reader.varlen()? as usize;
+                let data = reader.bytes(len)?;
+                match kind {
+                    0x51 if len == 3 => {
+                        let tempo = u32::from_be_bytes([0, data[0], data[1], data[2]]);
+                        events.push((tick, TrackEvent::Tempo(tempo.max(1))));
+                    }
+                    0x2F => break,
+                    _ => {}
+                }
+            }
+            // システムエクスクルーシブは読み飛ばす
+            0xF0 | 0xF7 => {
+                running_status = None;
+                let len = reader.varlen()? as usize;
+                reader.bytes(len)?;
+            }
+            0xF1..=0xFE => return Err(SmfError::Invalid("unexpected system message")),
+            // チャンネルメッセージ（プログラムチェンジとチャンネルプレッシャーはデータが1バイト）
+            _ => {
+                running_status = Some(status);
+                let len = if matches!(status & 0xF0, 0xC0 | 0xD0) { 1 } else { 2 };
+                let mut message = vec![status];
+                message.extend_from_slice(reader.bytes(len)?);
+                events.push((tick, TrackEvent::Message(message)));
+            }
+        }
+    }
+
+    Ok(events)
+}
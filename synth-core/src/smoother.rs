@@ -0,0 +1,53 @@
+/// パラメータを滑らかに変化させる時の時定数（秒）
+pub const PARAM_SMOOTHING_TIME: f32 = 0.01;
+
+/// 時定数から1サンプルごとの追従係数を計算する
+pub fn smoothing_coefficient(time_constant: f32, sample_rate: f32) -> f32 {
+    1.0 - (-1.0 / (time_constant * sample_rate).max(1.0)).exp()
+}
+
+/// 1次のローパスで目標値に追従するパラメータ（オーディオスレッドが保持する）
+///
+/// スライダーの値がバッファごとに飛ぶことで生じるジッパーノイズを防ぐ
+pub struct Smoother {
+    value: f32,
+    target: f32,
+    coefficient: f32,
+}
+
+impl Smoother {
+    pub fn new(value: f32, time_constant: f32, sample_rate: f32) -> Self {
+        Self {
+            value,
+            target: value,
+            coefficient: smoothing_coefficient(time_constant, sample_rate),
+        }
+    }
+
+    /// 目標値を設定する（バッファの先頭で呼ぶ）
+    pub fn set_target(&mut self, target: f32) {
+        self.target = target;
+    }
+
+    /// 追従させずに目標値へすぐ揃える
+    pub fn snap(&mut self) {
+        self.value = self.target;
+    }
+
+    /// 目標値に追従し終わっているかどうか
+    pub fn is_settled(&self) -> bool {
+        self.value == self.target
+    }
+
+    /// 1サンプル分だけ目標値に近づけて返す
+    pub fn next_value(&mut self) -> f32 {
+        if self.value != self.target {
+            self.value += (self.target - self.value) * self.coefficient;
+            // 十分に近づいたら目標値に揃える（係数の計算を止められるように）
+            if (self.target - self.value).abs() <= self.target.abs() * 1.0e-5 + 1.0e-7 {
+                self.value = self.target;
+            }
+        }
+        self.value
+    }
+}
@@ -0,0 +1,176 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::lock::lock_or_try;
+
+/// 1音ごとにずらす時間の上限（秒）
+pub const MAX_STRUM_TIME: f32 = 0.2;
+
+/// 同時に弾いたものとしてまとめる時間（秒、グループの最初のノートはこの分だけ遅れて鳴る）
+pub const STRUM_WINDOW: f32 = 0.02;
+
+/// ストラムで鳴らす順番
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StrumDirection {
+    Up,   // 低い音から（ダウンストローク）
+    Down, // 高い音から（アップストローク）
+}
+
+impl Default for StrumDirection {
+    fn default() -> Self {
+        Self::Up
+    }
+}
+
+/// ストラムの設定を表す構造体
+#[derive(Clone, Copy)]
+pub struct StrumSettings {
+    /// 同時に弾いたノートの鳴り始めをずらすか
+    pub enabled: bool,
+    /// 1音ごとにずらす時間（秒）
+    pub time: f32,
+    /// 鳴らす順番
+    pub direction: StrumDirection,
+}
+
+impl Default for StrumSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            time: 0.03,
+            direction: StrumDirection::Up,
+        }
+    }
+}
+
+/// 同時に弾いたものとしてまとめているノート
+struct StrumGroup {
+    start: u64,       // グループの最初のノートが届いたフレーム
+    notes: [u8; 128], // グループのノート（届いた順、先頭の len 個）
+    len: usize,       // グループのノートの数
+}
+
+/// ストラムの設定と、鳴り始めを決めるためのエンジンのフレーム数を管理する構造体
+///
+/// エンジンが生成したフレーム数を数え、ノート処理側はそれを時刻としてノートをまとめる
+/// （オーディオスレッドではメッセージの位置でバッファを区切るので、フレーム数はノートの位置と一致する）。
+/// トランスポーズなどと同じく演奏の設定なので、プリセットには含めない
+pub struct StrumManager {
+    settings: Arc<Mutex<StrumSettings>>,
+    frame: AtomicU64,       // エンジンがこれまでに生成したフレーム数
+    sample_rate: AtomicU32, // エンジンのサンプルレート（f32のビット列）
+    group: Mutex<StrumGroup>,
+}
+
+impl StrumManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(StrumSettings::default())),
+            frame: AtomicU64::new(0),
+            sample_rate: AtomicU32::new(44100.0f32.to_bits()),
+            group: Mutex::new(StrumGroup {
+                start: 0,
+                notes: [0; 128],
+                len: 0,
+            }),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<StrumSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.enabled = enabled;
+        }
+    }
+
+    pub fn set_time(&self, time: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.time = time.clamp(0.0, MAX_STRUM_TIME);
+        }
+    }
+
+    pub fn set_direction(&self, direction: StrumDirection) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.direction = direction;
+        }
+    }
+
+    /// エンジンが次に生成するフレームの番号
+    pub fn frame(&self) -> u64 {
+        self.frame.load(Ordering::Relaxed)
+    }
+
+    /// 今から `seconds` 秒後のエンジンのフレーム（アルペジエーターのヒューマナイズでも鳴り始めを遅らせるのに使う）
+    pub fn frame_after(&self, seconds: f32) -> u64 {
+        let sample_rate = f32::from_bits(self.sample_rate.load(Ordering::Relaxed));
+        self.frame() + (seconds.max(0.0) * sample_rate) as u64
+    }
+
+    /// エンジンが生成したフレーム数を進める（オーディオスレッドがバッファごとに呼ぶ）
+    pub fn advance(&self, frames: usize, sample_rate: f32) {
+        self.frame.fetch_add(frames as u64, Ordering::Relaxed);
+        self.sample_rate.store(sample_rate.to_bits(), Ordering::Relaxed);
+    }
+
+    /// 設定とノートのグループをロックする（`wait` が false なら待たずに試し、他のスレッドがロックしていれば None）
+    pub fn lock(&self, wait: bool) -> Option<StrumScheduler<'_>> {
+        let settings = *lock_or_try(&self.settings, wait)?;
+        let group = lock_or_try(&self.group, wait)?;
+        Some(StrumScheduler {
+            manager: self,
+            settings,
+            group,
+        })
+    }
+}
+
+/// 設定とノートのグループをロックしている間の操作（`StrumManager::lock` で作る）
+pub struct StrumScheduler<'a> {
+    manager: &'a StrumManager,
+    settings: StrumSettings, // ロックした時の設定
+    group: MutexGuard<'a, StrumGroup>,
+}
+
+impl StrumScheduler<'_> {
+    /// ストラムが有効かどうか
+    pub fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    /// 弾いたノートをグループに加え、グループの全てのノートの鳴り始めのフレームを `set_onset` に渡す
+    ///
+    /// グループの最初のノートから `STRUM_WINDOW` の間に届いたノートを同時に弾いたものとしてまとめ、
+    /// その間は鳴らさずに待つので、後から届いたノートも含めて音の高さの順に並べ直せる。
+    /// オーディオスレッドから呼ばれるので、グループは確保済みのバッファを使い回してアロケーションしない
+    pub fn schedule(&mut self, notes: &[u8], mut set_onset: impl FnMut(u8, u64)) {
+        let settings = self.settings;
+        let sample_rate = f32::from_bits(self.manager.sample_rate.load(Ordering::Relaxed));
+        let now = self.manager.frame();
+        let window = (STRUM_WINDOW * sample_rate) as u64;
+        let group = &mut *self.group;
+        if group.len == 0 || now.saturating_sub(group.start) > window {
+            group.start = now;
+            group.len = 0;
+        }
+        for &note in notes {
+            if !group.notes[..group.len].contains(&note) && group.len < group.notes.len() {
+                group.notes[group.len] = note;
+                group.len += 1;
+            }
+        }
+
+        let mut order = group.notes;
+        let order = &mut order[..group.len];
+        order.sort_unstable();
+        if settings.direction == StrumDirection::Down {
+            order.reverse();
+        }
+        let step = settings.time.clamp(0.0, MAX_STRUM_TIME) * sample_rate;
+        for (rank, &note) in order.iter().enumerate() {
+            set_onset(note, group.start + window + (rank as f32 * step) as u64);
+        }
+    }
+}
@@ -0,0 +1,467 @@
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+/// テンポの範囲（BPM）
+pub const MIN_BPM: f32 = 20.0;
+pub const MAX_BPM: f32 = 300.0;
+
+/// スウィングの範囲（2つの区切りの組のうち、2つ目が始まる位置の割合、%）
+pub const MIN_SWING: f32 = 50.0;
+pub const MAX_SWING: f32 = 75.0;
+
+/// 1小節の拍数（4/4拍子）
+pub const BEATS_PER_BAR: u32 = 4;
+
+/// MIDIクロックの4分音符あたりのパルス数
+const MIDI_CLOCKS_PER_BEAT: f64 = 24.0;
+
+/// 外部クロックのテンポを測る区間（パルス数、1拍分）
+const TEMPO_WINDOW_CLOCKS: u32 = 24;
+
+/// 外部クロックの位置とのずれを1ブロックで詰める割合
+const CLOCK_CORRECTION: f64 = 0.5;
+
+/// 受信したMIDIのスタート・コンティニュー・ストップ（オーディオスレッドが次のブロックで反映する）
+const COMMAND_NONE: u8 = 0;
+const COMMAND_START: u8 = 1;
+const COMMAND_CONTINUE: u8 = 2;
+const COMMAND_STOP: u8 = 3;
+
+/// ソングポジションポインタを受信していないことを表す値
+const NO_SONG_POSITION: u32 = u32::MAX;
+
+/// テンポ同期する時の音符の長さ
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum NoteDivision {
+    Whole,            // 全音符
+    Half,             // 2分音符
+    Quarter,          // 4分音符
+    QuarterDotted,    // 付点4分音符
+    QuarterTriplet,   // 4分3連
+    Eighth,           // 8分音符
+    EighthDotted,     // 付点8分音符
+    EighthTriplet,    // 8分3連
+    Sixteenth,        // 16分音符
+    SixteenthDotted,  // 付点16分音符
+    SixteenthTriplet, // 16分3連
+    ThirtySecond,     // 32分音符
+}
+
+impl Default for NoteDivision {
+    fn default() -> Self {
+        Self::Sixteenth
+    }
+}
+
+impl NoteDivision {
+    /// 全ての長さ（長い順）
+    pub const ALL: [NoteDivision; 12] = [
+        NoteDivision::Whole,
+        NoteDivision::Half,
+        NoteDivision::Quarter,
+        NoteDivision::QuarterDotted,
+        NoteDivision::QuarterTriplet,
+        NoteDivision::Eighth,
+        NoteDivision::EighthDotted,
+        NoteDivision::EighthTriplet,
+        NoteDivision::Sixteenth,
+        NoteDivision::SixteenthDotted,
+        NoteDivision::SixteenthTriplet,
+        NoteDivision::ThirtySecond,
+    ];
+
+    /// 4分音符を1とした時の長さ
+    pub fn beats(&self) -> f32 {
+        match self {
+            NoteDivision::Whole => 4.0,
+            NoteDivision::Half => 2.0,
+            NoteDivision::Quarter => 1.0,
+            NoteDivision::QuarterDotted => 1.5,
+            NoteDivision::QuarterTriplet => 2.0 / 3.0,
+            NoteDivision::Eighth => 0.5,
+            NoteDivision::EighthDotted => 0.75,
+            NoteDivision::EighthTriplet => 1.0 / 3.0,
+            NoteDivision::Sixteenth => 0.25,
+            NoteDivision::SixteenthDotted => 0.375,
+            NoteDivision::SixteenthTriplet => 1.0 / 6.0,
+            NoteDivision::ThirtySecond => 0.125,
+        }
+    }
+
+    /// 表示名
+    pub fn label(&self) -> &'static str {
+        match self {
+            NoteDivision::Whole => "1/1",
+            NoteDivision::Half => "1/2",
+            NoteDivision::Quarter => "1/4",
+            NoteDivision::QuarterDotted => "1/4.",
+            NoteDivision::QuarterTriplet => "1/4T",
+            NoteDivision::Eighth => "1/8",
+            NoteDivision::EighthDotted => "1/8.",
+            NoteDivision::EighthTriplet => "1/8T",
+            NoteDivision::Sixteenth => "1/16",
+            NoteDivision::SixteenthDotted => "1/16.",
+            NoteDivision::SixteenthTriplet => "1/16T",
+            NoteDivision::ThirtySecond => "1/32",
+        }
+    }
+
+    /// テンポ（BPM）での長さ（秒）
+    pub fn seconds(&self, bpm: f32) -> f32 {
+        60.0 / bpm.clamp(MIN_BPM, MAX_BPM) * self.beats()
+    }
+}
+
+/// トランスポート（テンポと再生状態）の設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransportSettings {
+    /// テンポ（BPM、20-300）
+    pub bpm: f32,
+    /// クロックを進めるかどうか（止めている間はテンポ同期したLFOやアルペジエーターがテンポだけに合わせて動く）
+    pub running: bool,
+    /// 外部のMIDIクロックに従うかどうか（true ならテンポと再生・停止を受信したクロックから決める）
+    pub external_sync: bool,
+    /// スウィング（50-75%、50 ならずらさない。2つ目の区切りを組の長さのこの割合の位置まで遅らせる）
+    pub swing: f32,
+    /// スウィングをかける区切りの長さ
+    pub swing_division: NoteDivision,
+}
+
+impl Default for TransportSettings {
+    fn default() -> Self {
+        Self {
+            bpm: 120.0,
+            running: true,
+            external_sync: false,
+            swing: MIN_SWING,
+            swing_division: NoteDivision::Sixteenth,
+        }
+    }
+}
+
+impl TransportSettings {
+    /// スウィングをかける区切りの長さ（拍）と、2つの区切りの組の中で2つ目が始まる位置（拍、ずらさないなら None）
+    fn swing_grid(&self) -> Option<(f64, f64)> {
+        let swing = self.swing.clamp(MIN_SWING, MAX_SWING);
+        if swing <= MIN_SWING {
+            return None;
+        }
+        let grid = self.swing_division.beats() as f64;
+        Some((grid, grid * 2.0 * swing as f64 / 100.0))
+    }
+
+    /// 再生位置（拍）をスウィングをかけた位置に変える
+    ///
+    /// 組の1つ目の区切りを伸ばし、2つ目を縮める（区切りの境目が遅れた位置に来る）。
+    /// アルペジエーターやテンポ同期したLFOはこの位置で区切りを数えるので、同じようにずれる
+    pub fn swung(&self, beats: f64) -> f64 {
+        let Some((grid, split)) = self.swing_grid() else {
+            return beats;
+        };
+        let pair = grid * 2.0;
+        let start = (beats / pair).floor() * pair;
+        let phase = beats - start;
+        if phase < split {
+            start + phase * grid / split
+        } else {
+            start + grid + (phase - split) * grid / (pair - split)
+        }
+    }
+
+    /// スウィングをかけた位置（拍）になる再生位置（`swung` の逆、自分のタイマーで進める時に使う）
+    pub fn unswung(&self, beats: f64) -> f64 {
+        let Some((grid, split)) = self.swing_grid() else {
+            return beats;
+        };
+        let pair = grid * 2.0;
+        let start = (beats / pair).floor() * pair;
+        let phase = beats - start;
+        if phase < grid {
+            start + phase * split / grid
+        } else {
+            start + split + (phase - grid) * (pair - split) / grid
+        }
+    }
+}
+
+/// トランスポートを管理する構造体
+///
+/// 再生位置・テンポ・再生中かどうかはオーディオスレッドがサンプル単位で進め、ブロックごとに書き出す。
+/// GUIやアルペジエーターのスレッドはロックせずに読める。
+/// 受信したMIDIクロックもロックせずに積んでおき、オーディオスレッドが次のブロックで反映する
+pub struct TransportManager {
+    settings: Arc<Mutex<TransportSettings>>,
+    position: AtomicU64, // 再生位置（拍、f64のビット列）
+    tempo: AtomicU32,    // 実際に使っているテンポ（BPM、f32のビット列、外部クロックでは測った値）
+    playing: AtomicBool, // 実際にクロックが進んでいるかどうか
+    rewind: AtomicBool,  // 次のブロックで再生位置を先頭に戻す
+
+    // 受信したMIDIクロック（外部同期用）
+    midi_clocks: AtomicU32,        // 前のブロックから受信したパルス数
+    midi_command: AtomicU8,        // 最後に受信したスタート・コンティニュー・ストップ
+    midi_song_position: AtomicU32, // 最後に受信したソングポジション（16分音符単位）
+}
+
+impl TransportManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(TransportSettings::default())),
+            position: AtomicU64::new(0.0f64.to_bits()),
+            tempo: AtomicU32::new(TransportSettings::default().bpm.to_bits()),
+            playing: AtomicBool::new(TransportSettings::default().running),
+            rewind: AtomicBool::new(false),
+            midi_clocks: AtomicU32::new(0),
+            midi_command: AtomicU8::new(COMMAND_NONE),
+            midi_song_position: AtomicU32::new(NO_SONG_POSITION),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<TransportSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    /// 現在の設定
+    pub fn settings(&self) -> TransportSettings {
+        self.settings.lock().map(|settings| *settings).unwrap_or_default()
+    }
+
+    pub fn set_bpm(&self, bpm: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+        }
+    }
+
+    pub fn set_running(&self, running: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.running = running;
+        }
+    }
+
+    pub fn set_external_sync(&self, external_sync: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.external_sync = external_sync;
+        }
+    }
+
+    pub fn set_swing(&self, swing: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.swing = swing.clamp(MIN_SWING, MAX_SWING);
+        }
+    }
+
+    pub fn set_swing_division(&self, division: NoteDivision) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.swing_division = division;
+        }
+    }
+
+    /// MIDIクロック（0xF8）を受信した
+    pub fn midi_clock(&self) {
+        self.midi_clocks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// MIDIスタート（0xFA）を受信した（ソングポジションか先頭から再生する）
+    pub fn midi_start(&self) {
+        self.midi_command.store(COMMAND_START, Ordering::Relaxed);
+    }
+
+    /// MIDIコンティニュー（0xFB）を受信した（止めた位置から再生する）
+    pub fn midi_continue(&self) {
+        self.midi_command.store(COMMAND_CONTINUE, Ordering::Relaxed);
+    }
+
+    /// MIDIストップ（0xFC）を受信した
+    pub fn midi_stop(&self) {
+        self.midi_command.store(COMMAND_STOP, Ordering::Relaxed);
+    }
+
+    /// ソングポジションポインタ（0xF2、16分音符単位）を受信した
+    pub fn midi_song_position(&self, sixteenths: u16) {
+        self.midi_song_position.store(sixteenths as u32, Ordering::Relaxed);
+    }
+
+    /// 再生位置を先頭に戻す（オーディオスレッドが次のブロックで反映する）
+    pub fn rewind(&self) {
+        self.rewind.store(true, Ordering::Relaxed);
+    }
+
+    /// 最後のブロックの終わりの再生位置（拍）
+    pub fn position(&self) -> f64 {
+        f64::from_bits(self.position.load(Ordering::Relaxed))
+    }
+
+    /// 最後のブロックの終わりの再生位置にスウィングをかけた位置（拍）
+    pub fn swung_position(&self) -> f64 {
+        self.settings().swung(self.position())
+    }
+
+    /// 実際に使っているテンポ（BPM、外部同期中は受信したクロックから測った値）
+    pub fn tempo(&self) -> f32 {
+        f32::from_bits(self.tempo.load(Ordering::Relaxed))
+    }
+
+    /// 実際にクロックが進んでいるかどうか（外部同期中はスタート・ストップに従う）
+    pub fn is_playing(&self) -> bool {
+        self.playing.load(Ordering::Relaxed)
+    }
+
+    /// 再生位置・テンポ・再生中かどうかを通知する（オーディオスレッドから呼ぶ）
+    fn report(&self, beats: f64, tempo: f32, playing: bool) {
+        self.position.store(beats.to_bits(), Ordering::Relaxed);
+        self.tempo.store(tempo.to_bits(), Ordering::Relaxed);
+        self.playing.store(playing, Ordering::Relaxed);
+    }
+
+    fn take_rewind(&self) -> bool {
+        self.rewind.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// オーディオスレッドで進めるクロック（再生位置をサンプル単位で数える）
+///
+/// 外部同期中は受信したパルス数から測ったテンポで進め、
+/// パルスの位置とのずれをブロックごとに詰める（パルスより1つ以上先には進まない）
+pub struct TransportClock {
+    settings: TransportSettings,
+    beats: f64,            // ブロックの先頭の再生位置（拍）
+    beats_per_sample: f64, // 1サンプルで進む拍数
+    sample_rate: f64,
+
+    // 外部クロックの状態
+    external_running: bool,
+    external_beats: f64,  // 受信したパルスの位置（拍）
+    external_tempo: f32,  // 測ったテンポ（BPM）
+    window_clocks: u32,   // テンポを測っている区間のパルス数
+    window_samples: f64,  // テンポを測っている区間のサンプル数
+}
+
+impl TransportClock {
+    pub fn new() -> Self {
+        Self {
+            settings: TransportSettings::default(),
+            beats: 0.0,
+            beats_per_sample: 0.0,
+            sample_rate: 44100.0,
+            external_running: false,
+            external_beats: 0.0,
+            external_tempo: TransportSettings::default().bpm,
+            window_clocks: 0,
+            window_samples: 0.0,
+        }
+    }
+
+    /// ブロックの先頭で設定と受信したMIDIクロックを読む（ロックできない場合は前回の設定を使う）
+    ///
+    /// `frames` はこのブロックのフレーム数（外部クロックとのずれをこのブロックで詰める）
+    pub fn update(&mut self, manager: &TransportManager, sample_rate: f32, frames: usize) {
+        if let Ok(settings) = manager.get_settings().try_lock() {
+            self.settings = *settings;
+        }
+        self.sample_rate = sample_rate.max(1.0) as f64;
+        if manager.take_rewind() {
+            self.beats = 0.0;
+            self.external_beats = 0.0;
+        }
+
+        // 受信したMIDIクロックは外部同期していない時も取り出して捨てる（切り替えた時に古いパルスが残らないように）
+        let clocks = manager.midi_clocks.swap(0, Ordering::Relaxed);
+        let command = manager.midi_command.swap(COMMAND_NONE, Ordering::Relaxed);
+        let song_position = manager.midi_song_position.swap(NO_SONG_POSITION, Ordering::Relaxed);
+        if !self.settings.external_sync {
+            self.beats_per_sample = if self.settings.running {
+                self.settings.bpm.clamp(MIN_BPM, MAX_BPM) as f64 / 60.0 / self.sample_rate
+            } else {
+                0.0
+            };
+            return;
+        }
+
+        if song_position != NO_SONG_POSITION {
+            self.external_beats = song_position as f64 * 0.25;
+            self.beats = self.external_beats;
+        }
+        // 止まっている間の時間をテンポの測定に含めないように、再生を始めたら測り直す
+        if command == COMMAND_START || command == COMMAND_CONTINUE {
+            self.window_clocks = 0;
+            self.window_samples = 0.0;
+        }
+        match command {
+            COMMAND_START => {
+                if song_position == NO_SONG_POSITION {
+                    self.external_beats = 0.0;
+                    self.beats = 0.0;
+                }
+                self.external_running = true;
+            }
+            COMMAND_CONTINUE => self.external_running = true,
+            COMMAND_STOP => self.external_running = false,
+            _ => {}
+        }
+
+        // 1拍分のパルスを受信するごとに、その間のサンプル数からテンポを測る
+        if clocks > 0 {
+            self.window_clocks += clocks;
+            if self.external_running {
+                self.external_beats += clocks as f64 / MIDI_CLOCKS_PER_BEAT;
+            }
+            if self.window_clocks >= TEMPO_WINDOW_CLOCKS && self.window_samples > 0.0 {
+                let beats = self.window_clocks as f64 / MIDI_CLOCKS_PER_BEAT;
+                let bpm = (beats * 60.0 * self.sample_rate / self.window_samples) as f32;
+                self.external_tempo = (self.external_tempo + bpm.clamp(MIN_BPM, MAX_BPM)) * 0.5;
+                self.window_clocks = 0;
+                self.window_samples = 0.0;
+            }
+        }
+
+        self.beats_per_sample = if self.external_running {
+            // 測ったテンポで進めつつ、パルスの位置とのずれを詰める
+            let base = self.external_tempo as f64 / 60.0 / self.sample_rate;
+            let error = self.external_beats - self.beats;
+            (base + error * CLOCK_CORRECTION / frames.max(1) as f64).max(0.0)
+        } else {
+            0.0
+        };
+    }
+
+    /// テンポ（BPM、外部同期中は測った値）
+    pub fn bpm(&self) -> f32 {
+        if self.settings.external_sync {
+            self.external_tempo
+        } else {
+            self.settings.bpm.clamp(MIN_BPM, MAX_BPM)
+        }
+    }
+
+    /// クロックが進んでいるかどうか
+    pub fn is_running(&self) -> bool {
+        if self.settings.external_sync {
+            self.external_running
+        } else {
+            self.settings.running
+        }
+    }
+
+    /// ブロックの先頭から `offset` サンプル目の再生位置（拍）
+    pub fn beats_at(&self, offset: usize) -> f64 {
+        self.beats + self.beats_per_sample * offset as f64
+    }
+
+    /// ブロックの先頭から `offset` サンプル目の再生位置にスウィングをかけた位置（拍）
+    pub fn swung_beats_at(&self, offset: usize) -> f64 {
+        self.settings.swung(self.beats_at(offset))
+    }
+
+    /// ブロックの終わりで `frames` サンプル分進めて、再生位置・テンポ・再生中かどうかを書き出す
+    pub fn advance(&mut self, frames: usize, manager: &TransportManager) {
+        self.beats = self.beats_at(frames);
+        if self.settings.external_sync {
+            self.window_samples += frames as f64;
+            // 次のパルスが来るまではその位置より先に進まない
+            self.beats = self.beats.min(self.external_beats + 1.0 / MIDI_CLOCKS_PER_BEAT);
+        }
+        manager.report(self.beats, self.bpm(), self.is_running());
+    }
+}
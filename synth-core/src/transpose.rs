@@ -0,0 +1,44 @@
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// トランスポーズの範囲（半音、±）
+pub const MAX_TRANSPOSE: i32 = 24;
+
+/// 入力されたノートをずらすトランスポーズを管理する構造体
+///
+/// オクターブシフトは12半音単位のトランスポーズとして同じ値に加える。
+/// 値は1つのアトミック変数で持つので、MIDIスレッドとGUIが同時に触っても途中の値は見えない
+pub struct TransposeManager {
+    semitones: AtomicI32, // トランスポーズ量（半音、-24〜24）
+}
+
+impl TransposeManager {
+    pub fn new() -> Self {
+        Self {
+            semitones: AtomicI32::new(0),
+        }
+    }
+
+    /// トランスポーズ量（半音）
+    pub fn semitones(&self) -> i32 {
+        self.semitones.load(Ordering::Relaxed)
+    }
+
+    pub fn set_semitones(&self, semitones: i32) {
+        self.semitones
+            .store(semitones.clamp(-MAX_TRANSPOSE, MAX_TRANSPOSE), Ordering::Relaxed);
+    }
+
+    /// オクターブ単位でずらす（範囲を超える場合は端で止める）
+    pub fn shift_octave(&self, octaves: i32) {
+        let _ = self.semitones.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |semitones| {
+            Some((semitones + octaves * 12).clamp(-MAX_TRANSPOSE, MAX_TRANSPOSE))
+        });
+    }
+
+    /// 入力されたノートにトランスポーズを適用する（0-127を外れる場合は None）
+    pub fn apply(&self, note: u8) -> Option<u8> {
+        u8::try_from(note as i32 + self.semitones())
+            .ok()
+            .filter(|&note| note <= 127)
+    }
+}
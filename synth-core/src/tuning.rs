@@ -0,0 +1,317 @@
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+
+use crate::lock::lock_or_try;
+
+/// A4の基準周波数の範囲（Hz）
+pub const MIN_A4: f32 = 400.0;
+pub const MAX_A4: f32 = 480.0;
+
+/// ファインチューンの範囲（セント、±）
+pub const MAX_FINE_TUNE: f32 = 100.0;
+
+/// スケールの1度目を置くMIDIノート（C4）
+pub const SCALE_REFERENCE_NOTE: u8 = 60;
+
+/// MIDIノート番号を周波数にする音律（ノートを周波数にする経路は全てこれを通す）
+pub trait Tuning {
+    /// MIDIノート番号から周波数（Hz）を計算する
+    fn note_to_freq(&self, note: u8) -> f32;
+}
+
+/// マスターチューニングの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TuningSettings {
+    /// A4（MIDIノート69）の周波数（Hz、400-480）
+    pub a4: f32,
+    /// ファインチューン（セント、-100〜100）
+    pub fine: f32,
+}
+
+impl Default for TuningSettings {
+    fn default() -> Self {
+        Self { a4: 440.0, fine: 0.0 }
+    }
+}
+
+/// A4の基準周波数とファインチューンを合わせた12平均律
+impl Tuning for TuningSettings {
+    fn note_to_freq(&self, note: u8) -> f32 {
+        let semitones = note as f32 - 69.0 + self.fine.clamp(-MAX_FINE_TUNE, MAX_FINE_TUNE) / 100.0;
+        self.a4.clamp(MIN_A4, MAX_A4) * 2.0f32.powf(semitones / 12.0)
+    }
+}
+
+/// Scala形式（.scl）のスケール
+///
+/// 1度目（0セント）から数えた各度の音程を持ち、最後の度の音程（普通はオクターブ）で繰り返す
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scale {
+    /// スケールの説明（ファイルの1行目）
+    pub description: String,
+    /// 2度目以降の音程（セント、最後が繰り返しの周期）
+    cents: Vec<f32>,
+}
+
+/// Scala形式の読み込みで発生するエラー
+#[derive(Debug, PartialEq)]
+pub enum ScaleError {
+    MissingCount,                                     // 音程の数の行がない
+    InvalidCount(String),                             // 音程の数が数値でない、または0
+    InvalidPitch(String),                             // 音程がセントにも比にも読めない
+    WrongPitchCount { expected: usize, found: usize }, // 音程の数が書かれた数と合わない
+}
+
+impl fmt::Display for ScaleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScaleError::MissingCount => write!(f, "missing note count"),
+            ScaleError::InvalidCount(line) => write!(f, "invalid note count: {}", line),
+            ScaleError::InvalidPitch(line) => write!(f, "invalid pitch: {}", line),
+            ScaleError::WrongPitchCount { expected, found } => {
+                write!(f, "expected {} pitches, found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl Scale {
+    /// Scala形式のテキストを読み込む（"!" で始まる行はコメント）
+    ///
+    /// 音程は "." を含めばセント、"/" を含むか整数なら比として読む
+    pub fn parse_scl(text: &str) -> Result<Self, ScaleError> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.starts_with('!'));
+        let description = lines.next().unwrap_or_default().to_string();
+        let count_line = lines.next().ok_or(ScaleError::MissingCount)?;
+        let count = count_line
+            .split_whitespace()
+            .next()
+            .and_then(|count| count.parse::<usize>().ok())
+            .filter(|&count| count > 0)
+            .ok_or_else(|| ScaleError::InvalidCount(count_line.to_string()))?;
+        let cents = lines
+            .filter(|line| !line.is_empty())
+            .take(count)
+            .map(|line| parse_pitch(line).ok_or_else(|| ScaleError::InvalidPitch(line.to_string())))
+            .collect::<Result<Vec<f32>, ScaleError>>()?;
+        if cents.len() != count {
+            return Err(ScaleError::WrongPitchCount {
+                expected: count,
+                found: cents.len(),
+            });
+        }
+        Ok(Self { description, cents })
+    }
+
+    /// 1周期の度の数
+    pub fn len(&self) -> usize {
+        self.cents.len()
+    }
+
+    /// 度がないかどうか（読み込んだスケールは必ず1つ以上ある）
+    pub fn is_empty(&self) -> bool {
+        self.cents.is_empty()
+    }
+
+    /// 1度目からの音程（セント、1度目の上の周期を越えて数えてよい）
+    pub fn degree_cents(&self, degree: i32) -> f32 {
+        let len = self.cents.len() as i32;
+        let Some(&period) = self.cents.last() else {
+            return 0.0;
+        };
+        let index = degree.rem_euclid(len);
+        let offset = if index == 0 { 0.0 } else { self.cents[index as usize - 1] };
+        degree.div_euclid(len) as f32 * period + offset
+    }
+}
+
+/// Scala形式の音程の行を読む（行の最初の語だけを使う）
+fn parse_pitch(line: &str) -> Option<f32> {
+    let pitch = line.split_whitespace().next()?;
+    if pitch.contains('.') {
+        return pitch.parse().ok();
+    }
+    let (numerator, denominator) = pitch.split_once('/').unwrap_or((pitch, "1"));
+    let ratio = numerator.parse::<f32>().ok()? / denominator.parse::<f32>().ok()?;
+    (ratio > 0.0 && ratio.is_finite()).then(|| 1200.0 * ratio.log2())
+}
+
+/// スケールをマスターチューニングの上に置いた音律（C4をスケールの1度目にする）
+pub struct ScaleTuning<'a> {
+    pub master: TuningSettings,
+    pub scale: &'a Scale,
+}
+
+impl Tuning for ScaleTuning<'_> {
+    fn note_to_freq(&self, note: u8) -> f32 {
+        let degree = note as i32 - SCALE_REFERENCE_NOTE as i32;
+        self.master.note_to_freq(SCALE_REFERENCE_NOTE) * 2.0f32.powf(self.scale.degree_cents(degree) / 1200.0)
+    }
+}
+
+/// マスターチューニングを管理する構造体
+///
+/// スケールはプリセットに含めない（全ての音色に共通の設定）
+pub struct TuningManager {
+    settings: Arc<Mutex<TuningSettings>>,
+    scale: Mutex<Option<Scale>>, // 12平均律の代わりに使うスケール（None なら12平均律）
+}
+
+impl TuningManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(TuningSettings::default())),
+            scale: Mutex::new(None),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<TuningSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_a4(&self, a4: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.a4 = a4.clamp(MIN_A4, MAX_A4);
+        }
+    }
+
+    pub fn set_fine(&self, fine: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.fine = fine.clamp(-MAX_FINE_TUNE, MAX_FINE_TUNE);
+        }
+    }
+
+    /// 12平均律の代わりに使うスケールを設定する（None で12平均律に戻す）
+    pub fn set_scale(&self, scale: Option<Scale>) {
+        if let Ok(mut current) = self.scale.lock() {
+            *current = scale;
+        }
+    }
+
+    /// 使っているスケールの説明（12平均律なら None）
+    pub fn scale_description(&self) -> Option<String> {
+        self.scale.lock().ok()?.as_ref().map(|scale| scale.description.clone())
+    }
+
+    /// 現在のチューニング（スケールがあればスケール）でMIDIノート番号から周波数を計算する
+    pub fn note_to_freq(&self, note: u8) -> f32 {
+        match self.lock(true) {
+            Some(tuning) => tuning.note_to_freq(note),
+            None => TuningSettings::default().note_to_freq(note),
+        }
+    }
+
+    /// 現在のチューニングをロックする（`wait` が false なら待たずに試し、他のスレッドがロックしていれば None）
+    pub fn lock(&self, wait: bool) -> Option<CurrentTuning<'_>> {
+        let master = *lock_or_try(&self.settings, wait)?;
+        let scale = lock_or_try(&self.scale, wait)?;
+        Some(CurrentTuning { master, scale })
+    }
+}
+
+/// ロックしている間の現在のチューニング（スケールがあればスケール、`TuningManager::lock` で作る）
+pub struct CurrentTuning<'a> {
+    master: TuningSettings,
+    scale: MutexGuard<'a, Option<Scale>>,
+}
+
+impl Tuning for CurrentTuning<'_> {
+    fn note_to_freq(&self, note: u8) -> f32 {
+        match self.scale.as_ref() {
+            Some(scale) => ScaleTuning {
+                master: self.master,
+                scale,
+            }
+            .note_to_freq(note),
+            None => self.master.note_to_freq(note),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < expected * 1e-4, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn a4_is_reference_frequency() {
+        assert_close(TuningSettings::default().note_to_freq(69), 440.0);
+        assert_close(TuningSettings::default().note_to_freq(60), 261.6256);
+    }
+
+    #[test]
+    fn octaves_double_frequency() {
+        let tuning = TuningSettings::default();
+        assert_close(tuning.note_to_freq(81), 880.0);
+        assert_close(tuning.note_to_freq(57), 220.0);
+        assert_close(tuning.note_to_freq(0), 8.175799);
+    }
+
+    #[test]
+    fn master_tune_shifts_all_notes() {
+        let tuning = TuningSettings { a4: 432.0, fine: 0.0 };
+        assert_close(tuning.note_to_freq(69), 432.0);
+        assert_close(tuning.note_to_freq(81), 864.0);
+        // 100セント上げると1半音上と同じ
+        let sharp = TuningSettings { a4: 440.0, fine: 100.0 };
+        assert_close(sharp.note_to_freq(69), TuningSettings::default().note_to_freq(70));
+    }
+
+    #[test]
+    fn settings_are_clamped() {
+        let tuning = TuningSettings { a4: 1000.0, fine: 500.0 };
+        assert_close(tuning.note_to_freq(69), MAX_A4 * 2.0f32.powf(MAX_FINE_TUNE / 1200.0));
+    }
+
+    #[test]
+    fn equal_temperament_scale_matches_default() {
+        let text = "! 12tet.scl\n!\n12-tone equal temperament\n 12\n!\n";
+        let steps: String = (1..=12).map(|i| format!(" {}.0\n", i * 100)).collect();
+        let scale = Scale::parse_scl(&(text.to_string() + &steps)).unwrap();
+        assert_eq!(scale.len(), 12);
+        let tuning = ScaleTuning { master: TuningSettings::default(), scale: &scale };
+        for note in [0, 21, 59, 60, 61, 69, 127] {
+            assert_close(tuning.note_to_freq(note), TuningSettings::default().note_to_freq(note));
+        }
+    }
+
+    #[test]
+    fn just_intonation_uses_ratios() {
+        let scale = Scale::parse_scl("Just major\n7\n9/8\n5/4\n4/3\n3/2\n5/3\n15/8\n2\n").unwrap();
+        assert_eq!(scale.description, "Just major");
+        let tuning = ScaleTuning { master: TuningSettings::default(), scale: &scale };
+        let c4 = TuningSettings::default().note_to_freq(60);
+        assert_close(tuning.note_to_freq(60), c4);
+        assert_close(tuning.note_to_freq(64), c4 * 1.5);
+        assert_close(tuning.note_to_freq(67), c4 * 2.0);
+        assert_close(tuning.note_to_freq(59), c4 * 15.0 / 16.0);
+    }
+
+    #[test]
+    fn invalid_scales_are_rejected() {
+        assert_eq!(Scale::parse_scl("Empty\n"), Err(ScaleError::MissingCount));
+        assert!(matches!(Scale::parse_scl("Bad\nmany\n"), Err(ScaleError::InvalidCount(_))));
+        assert!(matches!(Scale::parse_scl("Bad\n1\nfoo\n"), Err(ScaleError::InvalidPitch(_))));
+        assert_eq!(
+            Scale::parse_scl("Short\n3\n100.0\n200.0\n"),
+            Err(ScaleError::WrongPitchCount { expected: 3, found: 2 })
+        );
+    }
+
+    #[test]
+    fn manager_uses_scale_when_set() {
+        let manager = TuningManager::new();
+        let scale = Scale::parse_scl("Fifths\n1\n3/2\n").unwrap();
+        manager.set_scale(Some(scale));
+        assert_eq!(manager.scale_description().as_deref(), Some("Fifths"));
+        assert_close(manager.note_to_freq(61), TuningSettings::default().note_to_freq(60) * 1.5);
+        manager.set_scale(None);
+        assert_close(manager.note_to_freq(69), 440.0);
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::lock::lock_or_try;
+
+/// ベロシティカーブの種類を表す列挙型
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum VelocityCurve {
+    Linear,      // 直線
+    Exponential, // 指数（弱く弾いた時の差が大きい）
+    Fixed,       // 固定（ベロシティを無視）
+}
+
+impl Default for VelocityCurve {
+    fn default() -> Self {
+        Self::Linear
+    }
+}
+
+/// ベロシティの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VelocitySettings {
+    /// ベロシティカーブ
+    pub curve: VelocityCurve,
+    /// 音量へのベロシティ感度（0.0で常に最大音量、1.0でベロシティどおり）
+    pub sensitivity: f32,
+    /// アタック時間へのベロシティ感度（1.0で最強打時にアタックが1/10になる）
+    pub attack_sensitivity: f32,
+    /// リリース時間へのノートオフのベロシティ感度（1.0で最も速く離した時にリリースが1/10になる）
+    pub release_sensitivity: f32,
+}
+
+impl Default for VelocitySettings {
+    fn default() -> Self {
+        Self {
+            curve: VelocityCurve::Linear,
+            sensitivity: 0.5,
+            attack_sensitivity: 0.0,
+            release_sensitivity: 0.0,
+        }
+    }
+}
+
+impl VelocitySettings {
+    /// ベロシティ（0-127）をカーブに通して 0.0〜1.0 に変換する
+    pub fn apply_curve(&self, velocity: u8) -> f32 {
+        let x = (velocity.min(127) as f32) / 127.0;
+        match self.curve {
+            VelocityCurve::Linear => x,
+            VelocityCurve::Exponential => ((4.0 * x).exp() - 1.0) / (4.0f32.exp() - 1.0),
+            VelocityCurve::Fixed => 1.0,
+        }
+    }
+
+    /// ベロシティから音量の倍率を計算する
+    pub fn amplitude(&self, velocity: u8) -> f32 {
+        let sensitivity = self.sensitivity.clamp(0.0, 1.0);
+        1.0 - sensitivity * (1.0 - self.apply_curve(velocity))
+    }
+
+    /// ベロシティからアタック時間の倍率を計算する（強く弾くほど短くなる）
+    pub fn attack_scale(&self, velocity: u8) -> f32 {
+        let sensitivity = self.attack_sensitivity.clamp(0.0, 1.0);
+        1.0 - sensitivity * 0.9 * self.apply_curve(velocity)
+    }
+
+    /// ノートオフのベロシティからリリース時間の倍率を計算する（速く離すほど短くなる）
+    pub fn release_scale(&self, velocity: u8) -> f32 {
+        let sensitivity = self.release_sensitivity.clamp(0.0, 1.0);
+        1.0 - sensitivity * 0.9 * self.apply_curve(velocity)
+    }
+}
+
+/// ベロシティの設定を管理する構造体
+pub struct VelocityManager {
+    settings: Arc<Mutex<VelocitySettings>>,
+}
+
+impl VelocityManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(VelocitySettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<VelocitySettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_curve(&self, curve: VelocityCurve) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.curve = curve;
+        }
+    }
+
+    pub fn set_sensitivity(&self, sensitivity: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.sensitivity = sensitivity.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_attack_sensitivity(&self, sensitivity: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.attack_sensitivity = sensitivity.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_release_sensitivity(&self, sensitivity: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.release_sensitivity = sensitivity.clamp(0.0, 1.0);
+        }
+    }
+
+    /// 現在の設定（`wait` が false なら待たずに試し、他のスレッドがロックしていれば None）
+    pub fn snapshot(&self, wait: bool) -> Option<VelocitySettings> {
+        lock_or_try(&self.settings, wait).map(|settings| *settings)
+    }
+}
@@ -0,0 +1,750 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+
+use crate::envelope::{Envelope, EnvelopeParams, EnvelopeTarget};
+use crate::lock::lock_or_try;
+use crate::pitch_bend::midi_bend_value;
+use crate::smoother::PARAM_SMOOTHING_TIME;
+
+/// 同時発音数の上限
+pub const MAX_VOICES: usize = 16;
+
+/// パラフォニックモードでボイスごとにかけるエンベロープ（音の形は共有のエンベロープで作るので、押している間だけ鳴らすゲートにする）
+pub const PARAPHONIC_GATE: EnvelopeParams = EnvelopeParams {
+    delay: 0.0,
+    attack: 0.003,
+    hold: 0.0,
+    decay: 0.0,
+    sustain: 1.0,
+    release: 0.005,
+    attack_curve: 0.0,
+    decay_curve: 0.0,
+    release_curve: 0.0,
+    looping: false,
+};
+
+/// グライドの最大時間（秒）
+pub const MAX_GLIDE_TIME: f32 = 2.0;
+
+/// 奪われたボイスをフェードアウトする時間（秒）
+const STEAL_FADE_TIME: f32 = 0.003;
+
+/// MPEのノートごとのピッチベンド幅の最大値（半音）
+pub const MAX_MPE_BEND_RANGE: f32 = 96.0;
+
+/// MIDIチャンネル数
+const MIDI_CHANNELS: usize = 16;
+
+/// 発音モードを表す列挙型
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum VoiceMode {
+    Poly,       // ポリフォニック
+    Mono,       // モノフォニック（ノートが変わるたびにエンベロープを再トリガー）
+    Legato,     // モノフォニック（ノートが重なっている間は再トリガーしない）
+    Paraphonic, // ノートごとにオシレータを鳴らし、フィルターと音量エンベロープは全ノートで1つを共有する
+}
+
+impl Default for VoiceMode {
+    fn default() -> Self {
+        Self::Poly
+    }
+}
+
+/// モノモードで複数のノートが押された時にどれを鳴らすか
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum NotePriority {
+    Last, // 最後に押したノート
+    High, // 一番高いノート
+    Low,  // 一番低いノート
+}
+
+impl Default for NotePriority {
+    fn default() -> Self {
+        Self::Last
+    }
+}
+
+impl NotePriority {
+    /// 押されているノート（押した順）から鳴らすノートを選ぶ
+    pub fn select(&self, held: &[u8]) -> Option<u8> {
+        match self {
+            NotePriority::Last => held.last().copied(),
+            NotePriority::High => held.iter().copied().max(),
+            NotePriority::Low => held.iter().copied().min(),
+        }
+    }
+}
+
+/// 同時発音数を超えた時にどのボイスを奪うか
+///
+/// リリース中のボイスがあればその中から選び、なければ押されているボイスから選ぶ
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum StealPolicy {
+    Oldest,   // 一番前に弾いたボイス
+    Newest,   // 一番最後に弾いたボイス
+    Quietest, // 音量エンベロープの値が一番小さいボイス
+    Lowest,   // 一番低いノートのボイス
+}
+
+impl Default for StealPolicy {
+    fn default() -> Self {
+        Self::Oldest
+    }
+}
+
+impl StealPolicy {
+    /// 候補の（スロット, ボイス）から奪うボイスのスロットを選ぶ（`levels` はスロットごとの音量エンベロープの値）
+    fn pick<'a>(&self, candidates: impl Iterator<Item = (usize, &'a Voice)>, levels: &[f32; MAX_VOICES]) -> Option<usize> {
+        let chosen = match self {
+            StealPolicy::Oldest => candidates.min_by_key(|(_, voice)| voice.age),
+            StealPolicy::Newest => candidates.max_by_key(|(_, voice)| voice.age),
+            StealPolicy::Quietest => candidates.min_by(|(a, _), (b, _)| levels[*a].total_cmp(&levels[*b])),
+            StealPolicy::Lowest => candidates.min_by_key(|(_, voice)| (voice.note, voice.age)),
+        };
+        chosen.map(|(index, _)| index)
+    }
+}
+
+/// モノモードでいつグライドするか
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum GlideMode {
+    Always,   // ノートが変わるたびにグライドする
+    Fingered, // 前のノートを押したまま次のノートを弾いた時だけグライドする（離してから弾くと音程が飛ぶ）
+}
+
+impl Default for GlideMode {
+    fn default() -> Self {
+        Self::Always
+    }
+}
+
+/// グライドにかける時間の決め方
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum GlideRate {
+    ConstantTime, // 音程の幅に関係なく同じ時間で到達する
+    ConstantRate, // 同じ速さで動く（1オクターブあたりの時間が一定で、離れたノートほど時間がかかる）
+}
+
+impl Default for GlideRate {
+    fn default() -> Self {
+        Self::ConstantTime
+    }
+}
+
+/// 発音モードの設定を表す構造体
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VoiceSettings {
+    /// 発音モード
+    pub mode: VoiceMode,
+    /// モノモードのノート優先順位
+    pub priority: NotePriority,
+    /// ポリモード・パラフォニックモードの同時発音数（1-16）
+    pub polyphony: u8,
+    /// 同時発音数を超えた時にどのボイスを奪うか
+    pub steal: StealPolicy,
+    /// グライド時間（秒、モノ/レガートモードのみ。一定速度の時は1オクターブあたりの秒数）
+    pub glide_time: f32,
+    /// いつグライドするか
+    pub glide_mode: GlideMode,
+    /// グライドにかける時間の決め方
+    pub glide_rate: GlideRate,
+    /// MPEモード（チャンネル2〜16をノートごとのチャンネルとして扱う）
+    pub mpe: bool,
+    /// MPEのノートごとのピッチベンド幅（半音、1-96）
+    pub mpe_bend_range: f32,
+}
+
+impl Default for VoiceSettings {
+    fn default() -> Self {
+        Self {
+            mode: VoiceMode::Poly,
+            priority: NotePriority::Last,
+            polyphony: 8,
+            steal: StealPolicy::Oldest,
+            glide_time: 0.0,
+            glide_mode: GlideMode::Always,
+            glide_rate: GlideRate::ConstantTime,
+            mpe: false,
+            mpe_bend_range: 48.0,
+        }
+    }
+}
+
+impl VoiceSettings {
+    /// モノフォニックのモードかどうか
+    pub fn is_mono(&self) -> bool {
+        matches!(self.mode, VoiceMode::Mono | VoiceMode::Legato)
+    }
+
+    /// パラフォニックモードかどうか
+    pub fn is_paraphonic(&self) -> bool {
+        self.mode == VoiceMode::Paraphonic
+    }
+
+    /// 周波数 `from` から `to` へのグライドにかける秒数（グライドしない時は0.0）
+    ///
+    /// `legato` は前のノートを押したまま弾かれたかどうか
+    pub fn glide_duration(&self, from: f32, to: f32, legato: bool) -> f32 {
+        if !self.is_mono() || (self.glide_mode == GlideMode::Fingered && !legato) {
+            return 0.0;
+        }
+        match self.glide_rate {
+            GlideRate::ConstantTime => self.glide_time,
+            GlideRate::ConstantRate if from > 0.0 && to > 0.0 => self.glide_time * (to / from).log2().abs(),
+            GlideRate::ConstantRate => 0.0,
+        }
+    }
+}
+
+/// 発音中のボイス（ノート処理側が書き込み、オーディオスレッドが読む）
+#[derive(Clone, Copy, Debug)]
+pub struct Voice {
+    pub note: u8,       // ノート番号
+    pub layer: usize,   // 鳴らすレイヤー（キーボードスプリット・レイヤーの音色、0はメイン）
+    pub freq: f32,      // 周波数（Hz）
+    pub velocity: u8,   // ベロシティ（0-127）
+    pub gain: f32,      // ベロシティによる音量
+    pub attack_scale: f32, // ベロシティによるアタック時間の倍率
+    pub release_scale: f32, // ノートオフのベロシティによるリリース時間の倍率
+    pub onset: u64,     // 鳴り始めるエンジンのフレーム（ストラムで遅らせる、過ぎていればすぐに鳴らす）
+    pub age: u64,       // 割り当てた順番（古いボイスから奪うのに使う、変わったらエンベロープを再トリガーする）
+    pub released: bool, // ノートオフ済み（エンベロープのリリース中だけ鳴らす）
+    pub pressure: f32,  // ポリフォニックアフタータッチ（0.0-1.0、MPEではノートのチャンネルプレッシャー）
+    pub channel: u8,    // MPEでノートを受け持つチャンネル（0は割り当てなし）
+    pub bend: f32,      // MPEのノートごとのピッチベンド（半音）
+    pub slide: f32,     // MPEのスライド CC74（0.0-1.0）
+    pub legato: bool,   // モノモードで前のノートを押したまま弾かれたか（フィンガードのグライドに使う）
+    pub stolen: bool,   // 鳴っていた別のノートから奪ったボイスか（前のノートをフェードアウトしてから鳴らす）
+}
+
+/// MPEのチャンネルごとのコントローラーの値（ノートオンより先に届いた値も保持する）
+#[derive(Clone, Copy, Default)]
+struct MpeChannel {
+    bend: f32,     // ピッチベンド（半音）
+    slide: f32,    // スライド（0.0-1.0）
+    pressure: f32, // プレッシャー（0.0-1.0）
+}
+
+/// 発音モードの設定とボイスの割り当てを管理する構造体
+pub struct VoiceManager {
+    settings: Arc<Mutex<VoiceSettings>>,
+    voices: Arc<Mutex<[Option<Voice>; MAX_VOICES]>>,
+    next_age: AtomicU64, // 次に割り当てるボイスの順番
+    mpe_channels: Arc<Mutex<[MpeChannel; MIDI_CHANNELS]>>, // MPEのチャンネルごとの値（ボイスと一緒にロックする）
+    levels: [AtomicU32; MAX_VOICES], // スロットごとの音量エンベロープの値（オーディオスレッドが書く、f32のビット列）
+}
+
+impl VoiceManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(VoiceSettings::default())),
+            voices: Arc::new(Mutex::new([None; MAX_VOICES])),
+            next_age: AtomicU64::new(0),
+            mpe_channels: Arc::new(Mutex::new([MpeChannel::default(); MIDI_CHANNELS])),
+            levels: std::array::from_fn(|_| AtomicU32::new(0.0f32.to_bits())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<VoiceSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn get_voices(&self) -> Arc<Mutex<[Option<Voice>; MAX_VOICES]>> {
+        Arc::clone(&self.voices)
+    }
+
+    pub fn set_mode(&self, mode: VoiceMode) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.mode = mode;
+        }
+    }
+
+    pub fn set_priority(&self, priority: NotePriority) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.priority = priority;
+        }
+    }
+
+    pub fn set_polyphony(&self, polyphony: u8) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.polyphony = polyphony.clamp(1, MAX_VOICES as u8);
+        }
+    }
+
+    pub fn set_steal(&self, steal: StealPolicy) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.steal = steal;
+        }
+    }
+
+    pub fn set_glide_time(&self, glide_time: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.glide_time = glide_time.clamp(0.0, MAX_GLIDE_TIME);
+        }
+    }
+
+    pub fn set_glide_mode(&self, glide_mode: GlideMode) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.glide_mode = glide_mode;
+        }
+    }
+
+    pub fn set_glide_rate(&self, glide_rate: GlideRate) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.glide_rate = glide_rate;
+        }
+    }
+
+    pub fn set_mpe(&self, mpe: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.mpe = mpe;
+        }
+    }
+
+    pub fn set_mpe_bend_range(&self, range: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.mpe_bend_range = range.clamp(1.0, MAX_MPE_BEND_RANGE);
+        }
+    }
+
+    /// ボイスの割り当てをロックする（ノートの処理はロックしている間にまとめて行う）
+    ///
+    /// `wait` が false なら待たずに試し、他のスレッドが発音モードの設定・ボイス・MPEのチャンネルの値をロックしていれば None を返す
+    /// （オーディオスレッドのMIDI処理ではブロックせずに次のバッファまで後回しにする）
+    pub fn lock_voices(&self, wait: bool) -> Option<VoiceAllocator<'_>> {
+        let settings = *lock_or_try(&self.settings, wait)?;
+        let voices = lock_or_try(&self.voices, wait)?;
+        let channels = lock_or_try(&self.mpe_channels, wait)?;
+        Some(VoiceAllocator {
+            manager: self,
+            settings,
+            voices,
+            channels,
+        })
+    }
+
+    /// スロットごとの音量エンベロープの値を通知する（オーディオスレッドから呼ぶ、鳴っていないボイスは0.0）
+    pub fn report_levels(&self, levels: impl Iterator<Item = f32>) {
+        for (stored, level) in self.levels.iter().zip(levels) {
+            stored.store(level.max(0.0).to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// スロットごとの音量エンベロープの値（最後にオーディオスレッドが通知した値）
+    fn levels(&self) -> [f32; MAX_VOICES] {
+        std::array::from_fn(|i| f32::from_bits(self.levels[i].load(Ordering::Relaxed)))
+    }
+
+    fn next_age(&self) -> u64 {
+        self.next_age.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
+/// ボイスの割り当てをロックしている間の操作（`VoiceManager::lock_voices` で作る）
+///
+/// ロックした時の発音モードの設定を持っているので、操作の途中で他のロックを待たない
+pub struct VoiceAllocator<'a> {
+    manager: &'a VoiceManager,
+    settings: VoiceSettings,
+    voices: MutexGuard<'a, [Option<Voice>; MAX_VOICES]>,
+    channels: MutexGuard<'a, [MpeChannel; MIDI_CHANNELS]>, // MPEのチャンネルごとの値
+}
+
+impl VoiceAllocator<'_> {
+    /// ロックした時の発音モードの設定
+    pub fn settings(&self) -> &VoiceSettings {
+        &self.settings
+    }
+
+    /// ポリモードでノートにボイスを割り当てる
+    ///
+    /// 同じノートの同じレイヤーが鳴っていればそのボイスを使い（レイヤーごとに別のボイスを割り当てる）、空きがなければ鳴り終わったボイス、
+    /// それもなければ設定の方法でリリース中のボイス、押されているボイスの順に選んで奪う。
+    /// 鳴っているボイスを奪った時は、オーディオスレッドが前のノートを短くフェードアウトしてから鳴らす
+    pub fn allocate(&mut self, note: u8, layer: usize, freq: f32, velocity: u8, gain: f32, attack_scale: f32) {
+        let age = self.manager.next_age();
+        let polyphony = (self.settings.polyphony as usize).clamp(1, MAX_VOICES);
+        let policy = self.settings.steal;
+        let levels = self.manager.levels();
+        let voices = &mut *self.voices;
+        for slot in voices[polyphony..].iter_mut() {
+            *slot = None;
+        }
+
+        let occupied = || voices[..polyphony].iter().enumerate().filter_map(|(i, slot)| slot.as_ref().map(|voice| (i, voice)));
+        let index = occupied()
+            .find(|(_, voice)| voice.note == note && voice.layer == layer)
+            .map(|(i, _)| i)
+            .or_else(|| voices[..polyphony].iter().position(|slot| slot.is_none()))
+            // リリースが終わって音の出ていないボイス（割り当てたばかりのボイスはまだ値が届いていないので除く）
+            .or_else(|| occupied().find(|(i, voice)| voice.released && levels[*i] <= 0.0).map(|(i, _)| i))
+            .or_else(|| policy.pick(occupied().filter(|(_, voice)| voice.released), &levels))
+            .or_else(|| policy.pick(occupied(), &levels))
+            .unwrap_or(0);
+        let stolen = voices[index].is_some_and(|voice| voice.note != note || voice.layer != layer) && levels[index] > 0.0;
+
+        voices[index] = Some(Voice {
+            note,
+            layer,
+            freq,
+            velocity,
+            gain,
+            attack_scale,
+            release_scale: 1.0,
+            onset: 0,
+            age,
+            released: false,
+            pressure: 0.0,
+            channel: 0,
+            bend: 0.0,
+            slide: 0.0,
+            legato: false,
+            stolen,
+        });
+    }
+
+    /// ポリモードでノートのボイスをリリース中にする（エンベロープのリリースが終わるまで鳴らす）
+    pub fn release(&mut self, note: u8) {
+        for voice in self.voices.iter_mut().flatten().filter(|voice| voice.note == note) {
+            voice.released = true;
+        }
+    }
+
+    /// ノートオフのベロシティによるリリース時間の倍率をノートのボイスに設定する（リリースする前に呼ぶ）
+    pub fn set_release_scale(&mut self, note: u8, release_scale: f32) {
+        for voice in self.voices.iter_mut().flatten().filter(|voice| voice.note == note && !voice.released) {
+            voice.release_scale = release_scale;
+        }
+    }
+
+    /// ノートのボイスが鳴り始めるエンジンのフレームを設定する（ストラムで鳴り始めをずらす）
+    pub fn set_onset(&mut self, note: u8, onset: u64) {
+        for voice in self.voices.iter_mut().flatten().filter(|voice| voice.note == note && !voice.released) {
+            voice.onset = onset;
+        }
+    }
+
+    /// モノモードで鳴らすノートを設定する（常に先頭のボイスを使う）
+    ///
+    /// `retrigger` が false で前のノートがまだ鳴っている場合は、エンベロープを続けたまま音程だけを変える
+    /// （レイヤーが変わる場合は音色が変わるので再トリガーする）
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_mono(&mut self, note: u8, layer: usize, freq: f32, velocity: u8, gain: f32, attack_scale: f32, retrigger: bool) {
+        let held = self.voices[0].filter(|voice| !voice.released);
+        let age = match held {
+            Some(voice) if !retrigger && voice.layer == layer => voice.age,
+            _ => self.manager.next_age(),
+        };
+        *self.voices = [None; MAX_VOICES];
+        self.voices[0] = Some(Voice {
+            note,
+            layer,
+            freq,
+            velocity,
+            gain,
+            attack_scale,
+            release_scale: 1.0,
+            onset: 0,
+            age,
+            released: false,
+            pressure: 0.0,
+            channel: 0,
+            bend: 0.0,
+            slide: 0.0,
+            legato: held.is_some(),
+            stolen: false,
+        });
+    }
+
+    /// 全てのボイスをリリース中にする（エンベロープのリリースが終わるまで鳴らす）
+    pub fn release_all(&mut self) {
+        for voice in self.voices.iter_mut().flatten() {
+            voice.released = true;
+        }
+    }
+
+    /// ノートのポリフォニックアフタータッチの値を設定する
+    pub fn set_pressure(&mut self, note: u8, value: u8) {
+        for voice in self.voices.iter_mut().flatten().filter(|voice| voice.note == note) {
+            voice.pressure = value.min(127) as f32 / 127.0;
+        }
+    }
+
+    /// MPE: 発音したノートのボイスをチャンネルに結び付ける（チャンネルの現在の値を引き継ぐ）
+    pub fn assign_channel(&mut self, note: u8, channel: u8) {
+        let Some(&state) = self.channels.get(channel as usize) else {
+            return;
+        };
+        let newest = self
+            .voices
+            .iter_mut()
+            .flatten()
+            .filter(|voice| voice.note == note && !voice.released)
+            .max_by_key(|voice| voice.age);
+        if let Some(voice) = newest {
+            voice.channel = channel;
+            voice.bend = state.bend;
+            voice.slide = state.slide;
+            voice.pressure = state.pressure;
+        }
+    }
+
+    /// MPE: チャンネルのピッチベンドを設定する
+    pub fn set_channel_bend(&mut self, channel: u8, lsb: u8, msb: u8) {
+        let bend = midi_bend_value(lsb, msb) * self.settings.mpe_bend_range;
+        self.update_channel(channel, |state| state.bend = bend);
+    }
+
+    /// MPE: チャンネルのスライド（CC74）を設定する
+    pub fn set_channel_slide(&mut self, channel: u8, value: u8) {
+        self.update_channel(channel, |state| state.slide = value.min(127) as f32 / 127.0);
+    }
+
+    /// MPE: チャンネルのプレッシャーを設定する
+    pub fn set_channel_pressure(&mut self, channel: u8, value: u8) {
+        self.update_channel(channel, |state| state.pressure = value.min(127) as f32 / 127.0);
+    }
+
+    /// 全てのボイスを即座に解放する（パニック）
+    pub fn clear(&mut self) {
+        *self.voices = [None; MAX_VOICES];
+    }
+
+    /// 最後に割り当てたボイスの周波数を変更する
+    pub fn retune_latest(&mut self, freq: f32) {
+        if let Some(voice) = self.voices.iter_mut().flatten().max_by_key(|voice| voice.age) {
+            voice.freq = freq;
+        }
+    }
+
+    /// チャンネルの値を更新し、そのチャンネルのボイスに反映する
+    fn update_channel(&mut self, channel: u8, update: impl FnOnce(&mut MpeChannel)) {
+        let Some(state) = self.channels.get_mut(channel as usize) else {
+            return;
+        };
+        update(state);
+        let state = *state;
+        for voice in self.voices.iter_mut().flatten().filter(|voice| voice.channel == channel) {
+            voice.bend = state.bend;
+            voice.slide = state.slide;
+            voice.pressure = state.pressure;
+        }
+    }
+}
+
+/// オーディオスレッド側のボイスの再生状態（グライド・音量・エンベロープ）
+pub struct VoicePlayer {
+    freq: f32,         // 現在の周波数（グライド中は目標に向かって変化する）
+    target_freq: f32,  // 目標の周波数
+    glide_factor: f32, // グライド中に1サンプルごとにかける倍率
+    gain: f32,         // 現在の音量（クリックを防ぐため目標に向かって滑らかに追従させる）
+    target_gain: f32,  // 目標の音量
+    envelopes: [Envelope; 3], // 行き先ごとのエンベロープ（EnvelopeTarget の順）
+    levels: [f32; 3],  // エンベロープの現在のレベル
+    age: u64,          // エンベロープをトリガーしたボイスの割り当て順
+    released: bool,    // エンベロープをリリースしたか
+    note: u8,          // 鳴らしているノート番号（フィルターのキートラッキングに使う）
+    layer: usize,      // 鳴らしているレイヤー（音色を選ぶのに使う）
+    velocity: u8,      // 鳴らしているノートのベロシティ（ベロシティで切り替える音色を選ぶのに使う）
+    steal_fade: f32,   // 奪われた時のフェードアウトの残り（1.0から0.0に減らす、0.0はフェードしていない）
+}
+
+impl VoicePlayer {
+    pub fn new() -> Self {
+        Self {
+            freq: 0.0,
+            target_freq: 0.0,
+            glide_factor: 1.0,
+            gain: 0.0,
+            target_gain: 0.0,
+            envelopes: EnvelopeTarget::ALL.map(|_| Envelope::new(EnvelopeParams::default())),
+            levels: [0.0; 3],
+            age: 0,
+            released: true,
+            note: 0,
+            layer: 0,
+            velocity: 0,
+            steal_fade: 0.0,
+        }
+    }
+
+    /// ボイスの割り当てをエンベロープに反映する
+    ///
+    /// 新しく割り当てられたボイスならアタックを始め、ノートオフされたらリリースに入る。
+    /// `onset_delay` 秒後に鳴り始めるボイスは、それまでエンベロープのディレイで待つ（待っている間は待ち時間を更新する）。
+    /// ボイスが解放された場合（パニック）は即座に止める。
+    /// 鳴らすべきボイス（エンベロープが動いているボイス）を返す
+    pub fn trigger<'a>(&mut self, voice: Option<&'a Voice>, params: &[EnvelopeParams; 3], onset_delay: f32) -> Option<&'a Voice> {
+        for (envelope, params) in self.envelopes.iter_mut().zip(params.iter()) {
+            envelope.params = *params;
+        }
+        let Some(voice) = voice else {
+            self.envelopes.iter_mut().for_each(Envelope::kill);
+            self.released = true;
+            self.steal_fade = 0.0;
+            return None;
+        };
+        // 鳴っているボイスが別のノートに奪われたら、前のノートをフェードアウトしてから新しいノートを始める
+        // （エンジンはフェードアウトが終わるフレームでバッファを区切るので、新しいノートはそこから無音で鳴り始める）
+        if voice.age != self.age && voice.stolen && self.is_audible() {
+            if self.steal_fade <= 0.0 {
+                self.steal_fade = 1.0;
+            }
+            return None;
+        }
+        self.steal_fade = 0.0;
+        // モノモードのレガートでは再トリガーせずにノートだけが変わる
+        self.note = voice.note;
+        self.layer = voice.layer;
+        self.velocity = voice.velocity;
+        if voice.age != self.age {
+            self.age = voice.age;
+            self.released = false;
+            self.envelopes.iter_mut().for_each(|envelope| envelope.start_delayed(voice.attack_scale, onset_delay));
+        } else if !self.released {
+            self.envelopes.iter_mut().for_each(|envelope| envelope.delay_onset(onset_delay));
+        }
+        if voice.released && !self.released {
+            self.released = true;
+            self.envelopes.iter_mut().for_each(|envelope| envelope.end_scaled(voice.release_scale));
+        }
+        self.envelopes[EnvelopeTarget::Amp.index()].is_active().then_some(voice)
+    }
+
+    /// エンベロープを dt 秒（1サンプル分）進める
+    pub fn advance_envelopes(&mut self, dt: f32) {
+        for (level, envelope) in self.levels.iter_mut().zip(self.envelopes.iter_mut()) {
+            *level = envelope.update(dt);
+        }
+    }
+
+    /// エンベロープの現在のレベル
+    pub fn envelope(&self, target: EnvelopeTarget) -> f32 {
+        self.levels[target.index()]
+    }
+
+    /// エンベロープをトリガーしたボイスの割り当て順（新しいノートほど大きい）
+    pub fn age(&self) -> u64 {
+        self.age
+    }
+
+    /// 鳴らしているノート番号
+    pub fn note(&self) -> u8 {
+        self.note
+    }
+
+    /// 鳴らしているレイヤー（0はメイン）
+    pub fn layer(&self) -> usize {
+        self.layer
+    }
+
+    /// 鳴らしているノートのベロシティ
+    pub fn velocity(&self) -> u8 {
+        self.velocity
+    }
+
+    /// 鳴らす必要があるかどうか（停止したボイスもフェードアウトが終わるまでは鳴らす）
+    pub fn is_audible(&self) -> bool {
+        self.target_gain > 0.0 || self.gain > 1.0e-4
+    }
+
+    /// バッファの先頭でボイスの割り当てを反映する
+    ///
+    /// 無音から鳴り始めた場合は true を返す（呼び出し側でオシレータの位相をリセットする）
+    pub fn update(&mut self, voice: Option<&Voice>, settings: &VoiceSettings, sample_rate: f32) -> bool {
+        let Some(voice) = voice else {
+            self.target_gain = 0.0;
+            return false;
+        };
+        let silent = !self.is_audible();
+        self.target_gain = voice.gain;
+
+        // 無音から鳴り始める場合は周波数をリセットする
+        if silent || self.freq <= 0.0 {
+            self.freq = voice.freq;
+            self.target_freq = voice.freq;
+            self.glide_factor = 1.0;
+            return true;
+        }
+
+        if voice.freq == self.target_freq {
+            return false;
+        }
+        self.target_freq = voice.freq;
+
+        // モノモードではグライドのモードと速さから決めた時間をかけて目標の周波数に近づける（対数的に変化させる）
+        // グライドしない場合も周波数が飛ばないように短い時間で近づける
+        let glide_time = settings.glide_duration(self.freq, voice.freq, voice.legato).max(PARAM_SMOOTHING_TIME);
+        let glide_samples = glide_time * sample_rate;
+        if glide_samples >= 1.0 && self.freq > 0.0 {
+            self.glide_factor = (voice.freq / self.freq).powf(1.0 / glide_samples);
+        } else {
+            self.freq = voice.freq;
+            self.glide_factor = 1.0;
+        }
+        false
+    }
+
+    /// 現在の周波数（グライド中の値）
+    pub fn freq(&self) -> f32 {
+        self.freq
+    }
+
+    /// 1サンプル分の音量を進めて返す
+    ///
+    /// 奪われたボイスは `steal_fade_step` ずつフェードアウトし、終わったらエンベロープを止める
+    pub fn next_gain(&mut self, smoothing: f32, steal_fade_step: f32) -> f32 {
+        self.gain += (self.target_gain - self.gain) * smoothing;
+        if self.steal_fade > 0.0 {
+            self.steal_fade -= steal_fade_step;
+            if self.steal_fade <= 0.0 {
+                self.steal_fade = 0.0;
+                self.gain = 0.0;
+                self.target_gain = 0.0;
+                self.envelopes.iter_mut().for_each(Envelope::kill);
+                return 0.0;
+            }
+            return self.gain * self.steal_fade;
+        }
+        self.gain
+    }
+
+    /// 奪われたボイスのフェードアウトが終わるまでのフレーム数（フェードアウトしていなければ None）
+    ///
+    /// `voice` がこのボイスを奪ったノートなら、これから始めるフェードアウトの長さを返す
+    pub fn steal_fade_frames(&self, voice: Option<&Voice>, steal_fade_step: f32) -> Option<usize> {
+        let fade = if self.steal_fade > 0.0 {
+            self.steal_fade
+        } else if voice.is_some_and(|voice| voice.age != self.age && voice.stolen && self.is_audible()) {
+            1.0
+        } else {
+            return None;
+        };
+        Some((fade / steal_fade_step).ceil() as usize)
+    }
+
+    /// 奪われたボイスのフェードアウトで1サンプルごとに減らす量
+    pub fn steal_fade_step(sample_rate: f32) -> f32 {
+        1.0 / (STEAL_FADE_TIME * sample_rate).max(1.0)
+    }
+
+    /// 1サンプル分だけグライドを進める
+    pub fn advance(&mut self) {
+        if self.glide_factor != 1.0 {
+            self.freq *= self.glide_factor;
+            // 目標を通り過ぎたらグライドを終える
+            let reached = if self.glide_factor > 1.0 {
+                self.freq >= self.target_freq
+            } else {
+                self.freq <= self.target_freq
+            };
+            if reached {
+                self.freq = self.target_freq;
+                self.glide_factor = 1.0;
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+/// 波形テーブルの長さ（1周期あたりのサンプル数）
+pub const WAVETABLE_SIZE: usize = 512;
+
+/// 1周期分の波形を保持するテーブル（読み込んだWAVを内部の長さにリサンプリングしたもの）
+///
+/// 設定をコピーする度に2KBを複製しないように、共有のスライスで持つ（複製は参照カウントを増やすだけ）
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(from = "Vec<f32>", into = "Vec<f32>")]
+pub struct WaveTable {
+    samples: Arc<[f32]>,
+}
+
+impl WaveTable {
+    /// 任意の長さの1周期分のサンプルからテーブルを作成する（線形補間でリサンプリングし、DC除去と正規化をする）
+    pub fn from_samples(source: &[f32]) -> Self {
+        let mut samples = [0.0; WAVETABLE_SIZE];
+        if source.is_empty() {
+            return Self { samples: Arc::new(samples) };
+        }
+
+        for (i, sample) in samples.iter_mut().enumerate() {
+            let position = i as f32 * source.len() as f32 / WAVETABLE_SIZE as f32;
+            let index = position as usize;
+            let frac = position - index as f32;
+            let current = source[index % source.len()];
+            let next = source[(index + 1) % source.len()];
+            *sample = current + (next - current) * frac;
+        }
+
+        // 直流成分を取り除く
+        let mean = samples.iter().sum::<f32>() / WAVETABLE_SIZE as f32;
+        samples.iter_mut().for_each(|sample| *sample -= mean);
+
+        // ピークが1.0になるように正規化する
+        let peak = samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        if peak > 0.0 {
+            samples.iter_mut().for_each(|sample| *sample /= peak);
+        }
+
+        Self { samples: Arc::new(samples) }
+    }
+
+    /// 単一周期のWAVファイルを読み込む（複数チャンネルの場合は平均してモノラルにする）
+    pub fn load_wav(path: &Path) -> Result<Self, WaveTableError> {
+        let mut reader = hound::WavReader::open(path)?;
+        let spec = reader.spec();
+        let channels = spec.channels.max(1) as usize;
+
+        let interleaved: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1u32 << (spec.bits_per_sample.clamp(1, 32) - 1)) as f32;
+                reader
+                    .samples::<i32>()
+                    .map(|sample| sample.map(|value| value as f32 * scale))
+                    .collect::<Result<_, _>>()?
+            }
+        };
+
+        let mono: Vec<f32> = interleaved
+            .chunks(channels)
+            .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+            .collect();
+        if mono.is_empty() {
+            return Err(WaveTableError::Empty);
+        }
+
+        Ok(Self::from_samples(&mono))
+    }
+
+    /// 位相（0.0-1.0）の値を線形補間で読み出す
+    pub fn sample(&self, phase: f32) -> f32 {
+        let position = phase.rem_euclid(1.0) * WAVETABLE_SIZE as f32;
+        let index = (position as usize).min(WAVETABLE_SIZE - 1);
+        let frac = position - index as f32;
+        let current = self.samples[index];
+        let next = self.samples[(index + 1) % WAVETABLE_SIZE];
+        current + (next - current) * frac
+    }
+}
+
+impl From<Vec<f32>> for WaveTable {
+    fn from(samples: Vec<f32>) -> Self {
+        Self::from_samples(&samples)
+    }
+}
+
+impl From<WaveTable> for Vec<f32> {
+    fn from(table: WaveTable) -> Self {
+        table.samples.to_vec()
+    }
+}
+
+/// WAVファイルの読み込みで発生するエラー
+#[derive(Debug)]
+pub enum WaveTableError {
+    Wav(hound::Error),
+    Empty,
+}
+
+impl fmt::Display for WaveTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaveTableError::Wav(err) => write!(f, "WAV error: {}", err),
+            WaveTableError::Empty => write!(f, "WAV file has no samples"),
+        }
+    }
+}
+
+impl From<hound::Error> for WaveTableError {
+    fn from(err: hound::Error) -> Self {
+        WaveTableError::Wav(err)
+    }
+}
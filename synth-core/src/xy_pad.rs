@@ -0,0 +1,117 @@
+use std::sync::{Arc, Mutex};
+
+use crate::midi_map::ParamId;
+use crate::params::SynthParams;
+use crate::smoother::Smoother;
+
+/// パッドの位置に追従する時定数（秒、ドラッグで値が飛んでもジッパーノイズが出ないように）
+const XY_SMOOTHING_TIME: f32 = 0.03;
+
+/// XYパッドの設定を表す構造体
+#[derive(Clone, Copy)]
+pub struct XyPadSettings {
+    /// 横軸で動かすパラメータ
+    pub x_param: ParamId,
+    /// 縦軸で動かすパラメータ
+    pub y_param: ParamId,
+    /// 横の位置（0.0-1.0、左が0）
+    pub x: f32,
+    /// 縦の位置（0.0-1.0、下が0）
+    pub y: f32,
+}
+
+impl Default for XyPadSettings {
+    fn default() -> Self {
+        Self {
+            x_param: ParamId::FilterCutoff,
+            y_param: ParamId::FilterResonance,
+            x: 0.5,
+            y: 0.5,
+        }
+    }
+}
+
+/// XYパッドを管理する構造体
+pub struct XyPadManager {
+    settings: Arc<Mutex<XyPadSettings>>,
+}
+
+impl XyPadManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(XyPadSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<XyPadSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    /// パッドの位置を設定する（パラメータにはオーディオスレッドが滑らかに反映する）
+    pub fn set_position(&self, x: f32, y: f32) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.x = x.clamp(0.0, 1.0);
+            settings.y = y.clamp(0.0, 1.0);
+        }
+    }
+
+    /// 軸に割り当てるパラメータを設定する（マクロは選べない）
+    pub fn set_params(&self, x_param: ParamId, y_param: ParamId) {
+        if x_param.is_macro() || y_param.is_macro() {
+            return;
+        }
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.x_param = x_param;
+            settings.y_param = y_param;
+        }
+    }
+}
+
+/// パッドの位置に滑らかに追従してパラメータに反映する（オーディオスレッドが保持する）
+///
+/// パッドを動かしていない間はパラメータに触らないので、スライダーやMIDI CCで動かした値を上書きしない
+pub struct XyPadFollower {
+    settings: XyPadSettings,
+    x: Smoother,
+    y: Smoother,
+}
+
+impl XyPadFollower {
+    pub fn new(sample_rate: f32) -> Self {
+        let settings = XyPadSettings::default();
+        Self {
+            settings,
+            x: Smoother::new(settings.x, XY_SMOOTHING_TIME, sample_rate),
+            y: Smoother::new(settings.y, XY_SMOOTHING_TIME, sample_rate),
+        }
+    }
+
+    /// パッドの位置を読み、ブロックの長さだけ追従させてパラメータに反映する（バッファの先頭で呼ぶ）
+    pub fn update(&mut self, params: &SynthParams, frames: usize) {
+        if let Ok(settings) = params.xy_pad.get_settings().try_lock() {
+            // 割り当てを変えた時は、パッドを次に動かすまで新しいパラメータに反映しない
+            if settings.x_param != self.settings.x_param || settings.y_param != self.settings.y_param {
+                self.x.set_target(settings.x);
+                self.y.set_target(settings.y);
+                self.x.snap();
+                self.y.snap();
+            }
+            self.settings = *settings;
+        }
+
+        for (smoother, target, param) in [
+            (&mut self.x, self.settings.x, self.settings.x_param),
+            (&mut self.y, self.settings.y, self.settings.y_param),
+        ] {
+            smoother.set_target(target);
+            if smoother.is_settled() {
+                continue;
+            }
+            let mut value = target;
+            for _ in 0..frames {
+                value = smoother.next_value();
+            }
+            param.apply(params, value);
+        }
+    }
+}
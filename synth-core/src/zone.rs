@@ -0,0 +1,401 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+
+use crate::envelope::{EnvelopeParams, EnvelopeTarget};
+use crate::filter::FilterSettings;
+use crate::lock::lock_or_try;
+use crate::oscillator::OscillatorSettings;
+use crate::params::SynthParams;
+use crate::unison::UnisonSettings;
+
+/// メインのレイヤーのほかに追加できるレイヤーの数
+pub const MAX_EXTRA_LAYERS: usize = 3;
+
+/// メインのレイヤー（オシレータやフィルターなどの各パネルで編集する音色）の番号
+pub const MAIN_LAYER: usize = 0;
+
+/// ノートを鳴らすレイヤーの番号（固定長なのでオーディオスレッドでもアロケーションしない）
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct NoteLayers {
+    layers: [usize; MAX_EXTRA_LAYERS + 1],
+    len: usize,
+}
+
+impl NoteLayers {
+    /// メインのレイヤーだけ
+    pub fn main() -> Self {
+        let mut layers = Self::default();
+        layers.push(MAIN_LAYER);
+        layers
+    }
+
+    /// レイヤーを加える（番号の小さい順に加えること）
+    fn push(&mut self, layer: usize) {
+        if self.len < self.layers.len() {
+            self.layers[self.len] = layer;
+            self.len += 1;
+        }
+    }
+}
+
+impl std::ops::Deref for NoteLayers {
+    type Target = [usize];
+
+    fn deref(&self) -> &[usize] {
+        &self.layers[..self.len]
+    }
+}
+
+/// レイヤーの音色（ゾーンごとに持つオシレータ・フィルター・エンベロープ）
+///
+/// オシレータB・サブオシレータ・ノイズ・ミキサー・LFO・エフェクトは全てのレイヤーで共有する
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayerPatch {
+    /// Unison設定（波形を含む）
+    pub unison: UnisonSettings,
+    /// オシレータ設定
+    pub oscillator: OscillatorSettings,
+    /// フィルター設定
+    pub filter: FilterSettings,
+    /// エンベロープ（EnvelopeTarget の順）
+    pub envelope: [EnvelopeParams; 3],
+}
+
+impl Default for LayerPatch {
+    fn default() -> Self {
+        Self {
+            unison: UnisonSettings::default(),
+            oscillator: OscillatorSettings::default(),
+            filter: FilterSettings::default(),
+            envelope: [EnvelopeParams::default(); 3],
+        }
+    }
+}
+
+impl LayerPatch {
+    /// メインのレイヤーの今の音色から作る（追加のレイヤーの音色を作り始める時に使う）
+    pub fn capture(params: &SynthParams) -> Self {
+        let mut patch = Self::default();
+        if let Ok(settings) = params.unison.get_settings().lock() {
+            patch.unison = settings.clone();
+        }
+        if let Ok(settings) = params.oscillator.get_settings().lock() {
+            patch.oscillator = *settings;
+        }
+        if let Ok(settings) = params.filter.get_settings().lock() {
+            patch.filter = *settings;
+        }
+        if let Ok(settings) = params.envelope.get_settings().lock() {
+            patch.envelope = *settings;
+        }
+        patch
+    }
+
+    /// 行き先のエンベロープ
+    pub fn envelope(&self, target: EnvelopeTarget) -> EnvelopeParams {
+        self.envelope[target.index()]
+    }
+
+    /// `other` を `amount`（0.0-1.0）の割合で混ぜた音色
+    ///
+    /// 数値は線形に（カットオフは音程として対数的に）混ぜ、波形やフィルターの種類などの切り替えは割合の多い方の音色を使う
+    pub fn blend(&self, other: &LayerPatch, amount: f32) -> LayerPatch {
+        let t = amount.clamp(0.0, 1.0);
+        if t <= 0.0 {
+            return self.clone();
+        }
+        if t >= 1.0 {
+            return other.clone();
+        }
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        let mut patch = if t < 0.5 { self.clone() } else { other.clone() };
+
+        patch.unison.detune = lerp(self.unison.detune, other.unison.detune);
+        patch.unison.width = lerp(self.unison.width, other.unison.width);
+        patch.unison.blend = lerp(self.unison.blend, other.unison.blend);
+        patch.unison.taper = lerp(self.unison.taper, other.unison.taper);
+        patch.oscillator.pulse_width = lerp(self.oscillator.pulse_width, other.oscillator.pulse_width);
+
+        let (from, to) = (self.filter.cutoff.max(1.0), other.filter.cutoff.max(1.0));
+        patch.filter.cutoff = from * (to / from).powf(t);
+        patch.filter.resonance = lerp(self.filter.resonance, other.filter.resonance);
+        patch.filter.env_amount = lerp(self.filter.env_amount, other.filter.env_amount);
+        patch.filter.key_track = lerp(self.filter.key_track, other.filter.key_track);
+
+        for ((env, a), b) in patch.envelope.iter_mut().zip(self.envelope.iter()).zip(other.envelope.iter()) {
+            env.delay = lerp(a.delay, b.delay);
+            env.attack = lerp(a.attack, b.attack);
+            env.hold = lerp(a.hold, b.hold);
+            env.decay = lerp(a.decay, b.decay);
+            env.sustain = lerp(a.sustain, b.sustain);
+            env.release = lerp(a.release, b.release);
+            env.attack_curve = lerp(a.attack_curve, b.attack_curve);
+            env.decay_curve = lerp(a.decay_curve, b.decay_curve);
+            env.release_curve = lerp(a.release_curve, b.release_curve);
+        }
+        patch
+    }
+}
+
+/// ベロシティで柔らかい音色と硬い音色をどう選ぶか
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum VelocitySwitchMode {
+    Switch,    // しきい値以上のベロシティで硬い音色に切り替える
+    Crossfade, // しきい値を中心としたフェード幅の間で2つの音色を混ぜる
+}
+
+impl Default for VelocitySwitchMode {
+    fn default() -> Self {
+        Self::Switch
+    }
+}
+
+/// ベロシティで切り替えるレイヤー内の2つ目の音色（強く弾いた時の硬い音色）
+///
+/// 柔らかい音色はレイヤーの音色（メインのレイヤーでは各パネルの音色）を使う
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VelocityLayer {
+    /// ベロシティで音色を切り替えるか
+    pub enabled: bool,
+    /// 切り替え方
+    pub mode: VelocitySwitchMode,
+    /// 硬い音色に切り替えるベロシティ（1-127、クロスフェードではフェードの中心）
+    pub threshold: u8,
+    /// クロスフェードで2つの音色を混ぜるベロシティの幅（1-127）
+    pub fade: u8,
+    /// 硬い音色
+    pub hard: LayerPatch,
+}
+
+impl Default for VelocityLayer {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: VelocitySwitchMode::Switch,
+            threshold: 100,
+            fade: 32,
+            hard: LayerPatch::default(),
+        }
+    }
+}
+
+impl VelocityLayer {
+    /// 範囲内に収めた設定
+    pub fn clamped(self) -> Self {
+        Self {
+            threshold: self.threshold.clamp(1, 127),
+            fade: self.fade.clamp(1, 127),
+            ..self
+        }
+    }
+
+    /// ベロシティから硬い音色を混ぜる割合（0.0で柔らかい音色だけ、1.0で硬い音色だけ、無効なら常に0.0）
+    pub fn hard_amount(&self, velocity: u8) -> f32 {
+        if !self.enabled {
+            return 0.0;
+        }
+        let velocity = velocity.min(127) as f32;
+        let threshold = self.threshold.clamp(1, 127) as f32;
+        match self.mode {
+            VelocitySwitchMode::Switch => {
+                if velocity >= threshold {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            VelocitySwitchMode::Crossfade => {
+                let fade = self.fade.clamp(1, 127) as f32;
+                ((velocity - (threshold - fade * 0.5)) / fade).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+/// 鍵盤の範囲（両端を含む）
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyRange {
+    /// 一番低いノート
+    pub low: u8,
+    /// 一番高いノート
+    pub high: u8,
+}
+
+impl Default for KeyRange {
+    fn default() -> Self {
+        Self { low: 0, high: 127 }
+    }
+}
+
+impl KeyRange {
+    /// 範囲内に収めた値（下端が上端を超えていたら入れ替える）
+    pub fn clamped(self) -> Self {
+        let low = self.low.min(127);
+        let high = self.high.min(127);
+        Self {
+            low: low.min(high),
+            high: low.max(high),
+        }
+    }
+
+    /// ノートが範囲に含まれるかどうか
+    pub fn contains(&self, note: u8) -> bool {
+        let range = self.clamped();
+        (range.low..=range.high).contains(&note)
+    }
+}
+
+/// 追加のレイヤー（鍵盤の範囲と音色）
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyZone {
+    /// このレイヤーを鳴らすか
+    pub enabled: bool,
+    /// 鳴らす鍵盤の範囲
+    pub range: KeyRange,
+    /// 音色
+    pub patch: LayerPatch,
+    /// ベロシティで切り替える硬い音色
+    pub velocity: VelocityLayer,
+}
+
+/// キーボードスプリットとレイヤーの設定を表す構造体
+///
+/// 範囲が重ならなければスプリット（例：C3より下はベース、上はリード）、重なればレイヤーとして同時に鳴らす
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZoneSettings {
+    /// ゾーンを使うか（false なら全ての鍵盤でメインのレイヤーだけを鳴らす）
+    pub enabled: bool,
+    /// メインのレイヤーを鳴らす鍵盤の範囲
+    pub main_range: KeyRange,
+    /// メインのレイヤーでベロシティで切り替える硬い音色（ゾーンを使わない時も使える）
+    pub main_velocity: VelocityLayer,
+    /// 追加のレイヤー（レイヤー番号はメインの次から1, 2, 3）
+    pub layers: [KeyZone; MAX_EXTRA_LAYERS],
+}
+
+impl ZoneSettings {
+    /// ノートを鳴らすレイヤーの番号（番号の小さい順、どの範囲にも入らなければ空）
+    pub fn layers_for(&self, note: u8) -> NoteLayers {
+        if !self.enabled {
+            return NoteLayers::main();
+        }
+        let mut layers = NoteLayers::default();
+        let main = self.main_range.contains(note).then_some(MAIN_LAYER);
+        let extra = self
+            .layers
+            .iter()
+            .enumerate()
+            .filter(|(_, zone)| zone.enabled && zone.range.contains(note))
+            .map(|(index, _)| index + 1);
+        main.into_iter().chain(extra).for_each(|layer| layers.push(layer));
+        layers
+    }
+
+    /// 追加のレイヤーの音色（メインのレイヤーは None、各パネルの設定を使う）
+    pub fn patch(&self, layer: usize) -> Option<&LayerPatch> {
+        let index = layer.checked_sub(1)?;
+        self.layers.get(index).map(|zone| &zone.patch)
+    }
+
+    /// レイヤーのベロシティで切り替える硬い音色
+    pub fn velocity_layer(&self, layer: usize) -> Option<&VelocityLayer> {
+        match layer.checked_sub(1) {
+            None => Some(&self.main_velocity),
+            Some(index) => self.layers.get(index).map(|zone| &zone.velocity),
+        }
+    }
+
+    /// ボイスを鳴らす音色（レイヤーの音色にベロシティで硬い音色を混ぜたもの）
+    ///
+    /// `main` はメインのレイヤーの音色（各パネルの設定）。メインのレイヤーで硬い音色を混ぜない時は None を返す
+    /// （呼び出し側は各パネルの設定をそのまま使う）
+    pub fn voice_patch(&self, layer: usize, velocity: u8, main: &LayerPatch) -> Option<LayerPatch> {
+        let soft = self.patch(layer);
+        let hard = self
+            .velocity_layer(layer)
+            .map(|velocity_layer| (velocity_layer.hard_amount(velocity), &velocity_layer.hard))
+            .filter(|(amount, _)| *amount > 0.0);
+        match (soft, hard) {
+            (None, None) => None,
+            (Some(soft), None) => Some(soft.clone()),
+            (soft, Some((amount, hard))) => Some(soft.unwrap_or(main).blend(hard, amount)),
+        }
+    }
+}
+
+/// キーボードスプリットとレイヤーの設定を管理する構造体
+pub struct ZoneManager {
+    settings: Arc<Mutex<ZoneSettings>>,
+}
+
+impl ZoneManager {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(ZoneSettings::default())),
+        }
+    }
+
+    pub fn get_settings(&self) -> Arc<Mutex<ZoneSettings>> {
+        Arc::clone(&self.settings)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.enabled = enabled;
+        }
+    }
+
+    pub fn set_main_range(&self, range: KeyRange) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.main_range = range.clamped();
+        }
+    }
+
+    /// メインのレイヤーのベロシティで切り替える硬い音色を設定する
+    pub fn set_main_velocity(&self, velocity: VelocityLayer) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.main_velocity = velocity.clamped();
+        }
+    }
+
+    /// 追加のレイヤー（0から数える）を鳴らすかを設定する
+    pub fn set_layer_enabled(&self, index: usize, enabled: bool) {
+        self.update_layer(index, |zone| zone.enabled = enabled);
+    }
+
+    /// 追加のレイヤー（0から数える）の鍵盤の範囲を設定する
+    pub fn set_layer_range(&self, index: usize, range: KeyRange) {
+        self.update_layer(index, |zone| zone.range = range.clamped());
+    }
+
+    /// 追加のレイヤー（0から数える）の音色を設定する
+    pub fn set_layer_patch(&self, index: usize, patch: LayerPatch) {
+        self.update_layer(index, |zone| zone.patch = patch);
+    }
+
+    /// 追加のレイヤー（0から数える）のベロシティで切り替える硬い音色を設定する
+    pub fn set_layer_velocity(&self, index: usize, velocity: VelocityLayer) {
+        self.update_layer(index, |zone| zone.velocity = velocity.clamped());
+    }
+
+    /// 設定をロックする（`wait` が false なら待たずに試し、他のスレッドがロックしていれば None）
+    ///
+    /// 音色まで含むので、ノートを鳴らすレイヤーを選ぶ間はコピーせずにロックしたまま使う
+    pub fn lock(&self, wait: bool) -> Option<MutexGuard<'_, ZoneSettings>> {
+        lock_or_try(&self.settings, wait)
+    }
+
+    fn update_layer(&self, index: usize, update: impl FnOnce(&mut KeyZone)) {
+        if let Ok(mut settings) = self.settings.lock()
+            && let Some(zone) = settings.layers.get_mut(index)
+        {
+            update(zone);
+        }
+    }
+}